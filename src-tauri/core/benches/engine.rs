@@ -0,0 +1,138 @@
+// Benchmarks of the engines' hot paths. Run with `cargo bench`; compare against a saved
+// baseline with `cargo bench -- --save-baseline main` and `cargo bench -- --baseline main`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rayon::prelude::*;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use large_file_compare_core::spill::{read_partition_into_maps, read_partition_sorted, HashOffset, PartitionWriter};
+use large_file_compare_core::hashing::HashAlgo;
+use large_file_compare_core::records::find_line_ends;
+
+const MB: usize = 1024 * 1024;
+
+/// Lines of `line_len` pseudo-random printable bytes, `total` bytes in all.
+fn text(total: usize, line_len: usize) -> Vec<u8> {
+    let mut state = 0x9e3779b97f4a7c15u64;
+    let mut data = Vec::with_capacity(total);
+    while data.len() < total {
+        for _ in 0..line_len {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            data.push(b'a' + (state % 26) as u8);
+        }
+        data.push(b'\n');
+    }
+    data.truncate(total);
+    data
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lfc_bench_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn bench_hash_line(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hash_line");
+    for algo in [HashAlgo::Gxhash, HashAlgo::Xxh3, HashAlgo::Blake3] {
+        if !algo.is_supported() {
+            continue;
+        }
+        for len in [16, 100, 1000] {
+            let line = text(len, len);
+            group.throughput(Throughput::Bytes(len as u64));
+            group.bench_with_input(BenchmarkId::new(format!("{:?}", algo), len), &line, |b, line| {
+                b.iter(|| algo.hash_line(0, black_box(line)))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_find_line_ends(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_line_ends");
+    group.sample_size(20);
+    for size in [MB, 16 * MB, 64 * MB] {
+        let data = text(size, 80);
+        group.throughput(Throughput::Bytes(size as u64));
+        for chunk in [MB, 16 * MB] {
+            group.bench_with_input(BenchmarkId::new(format!("chunk_{}MB", chunk / MB), size / MB), &data, |b, data| {
+                b.iter(|| find_line_ends(black_box(data), b'\n', chunk, |_| {}))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn items(count: u64) -> Vec<HashOffset> {
+    (0..count)
+        .map(|i| HashOffset(HashAlgo::Xxh3.hash_line(0, &(i % (count / 2).max(1)).to_le_bytes()), i * 80))
+        .collect()
+}
+
+/// Writes `items` to `partitions` files the way the external engine does: from parallel
+/// workers behind one lock per partition, or from a single thread.
+fn write_partitions(dir: &Path, items: &[HashOffset], partitions: u64, parallel: bool) {
+    let writers: Vec<_> = (0..partitions)
+        .map(|i| Mutex::new(PartitionWriter::create(&dir.join(format!("part_{}", i))).unwrap()))
+        .collect();
+    let write = |item: &HashOffset| writers[(item.0 % partitions) as usize].lock().unwrap().write(*item).unwrap();
+    if parallel {
+        items.par_iter().for_each(write);
+    } else {
+        items.iter().for_each(write);
+    }
+    for writer in writers {
+        writer.into_inner().unwrap().finish().unwrap();
+    }
+}
+
+fn bench_partition_writes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("partition_writes");
+    group.sample_size(10);
+    let dir = temp_dir("partitions");
+    for count in [100_000u64, 1_000_000] {
+        let items = items(count);
+        group.throughput(Throughput::Elements(count));
+        for partitions in [64, 1024] {
+            for (strategy, parallel) in [("parallel", true), ("sequential", false)] {
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{}_{}", strategy, partitions), count),
+                    &items,
+                    |b, items| b.iter(|| write_partitions(&dir, items, partitions, parallel)),
+                );
+            }
+        }
+    }
+    group.finish();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn bench_aggregation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aggregation");
+    group.sample_size(10);
+    let dir = temp_dir("aggregation");
+    for count in [10_000u64, 100_000, 1_000_000] {
+        let path = dir.join(format!("part_{}", count));
+        let mut writer = PartitionWriter::create(&path).unwrap();
+        for item in items(count) {
+            writer.write(item).unwrap();
+        }
+        writer.finish().unwrap();
+        group.throughput(Throughput::Elements(count));
+        group.bench_with_input(BenchmarkId::new("maps", count), &path, |b, path| {
+            b.iter(|| read_partition_into_maps(path.clone()).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("sorted", count), &path, |b, path| {
+            b.iter(|| read_partition_sorted(path).unwrap())
+        });
+    }
+    group.finish();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group!(benches, bench_hash_line, bench_find_line_ends, bench_partition_writes, bench_aggregation);
+criterion_main!(benches);
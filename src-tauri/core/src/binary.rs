@@ -0,0 +1,228 @@
+use crate::config::{BinaryBlocks, Chunking};
+use crate::jobs::Job;
+use crate::payloads::{BinaryFinishedPayload, BinaryRange, ComparisonSummary};
+use crate::results::collected::finish_comparison;
+use crate::results::store::ResultStatus;
+use crate::sink::ProgressSink;
+use crate::utils::{HashAlgo, HashMap};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::Error as IoError;
+use std::ops::Range;
+
+/// Chunks compared between two cancellation checks.
+const CHECK_INTERVAL: usize = 1024;
+
+/// Random values for the gear rolling hash, derived with splitmix64.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+fn map_file(path: &str) -> Result<Option<Mmap>, IoError> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(unsafe { Mmap::map(&file)? }))
+}
+
+fn hash_chunk(chunk: &[u8]) -> u64 {
+    HashAlgo::detect().hash_line(0, chunk)
+}
+
+/// Splits `data` at content-defined boundaries (gear hash, FastCDC style) averaging
+/// `average` bytes, with chunks between a quarter and four times that size.
+fn content_defined_chunks(data: &[u8], average: usize) -> Vec<Range<usize>> {
+    let average = average.max(64).next_power_of_two();
+    let mask = (average - 1) as u64;
+    let (min, max) = (average / 4, average * 4);
+    let mut chunks = Vec::with_capacity(data.len() / average + 1);
+    let mut start = 0;
+    while start < data.len() {
+        let limit = (start + max).min(data.len());
+        let mut end = limit;
+        let mut hash = 0u64;
+        for (i, &byte) in data[start..limit].iter().enumerate().skip(min) {
+            hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+            if hash & mask == 0 {
+                end = start + i + 1;
+                break;
+            }
+        }
+        chunks.push(start..end);
+        start = end;
+    }
+    chunks
+}
+
+/// Joins touching ranges into one.
+fn merge_ranges(ranges: impl IntoIterator<Item = Range<usize>>) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Byte ranges where chunk `i` of A differs from chunk `i` of B, including the tail of the longer file.
+fn fixed_differences<'a>(job: &Job, a: &'a [u8], b: &'a [u8], size: usize) -> Result<Vec<Range<usize>>, IoError> {
+    let chunks = a.len().max(b.len()).div_ceil(size);
+    let differing: Vec<Range<usize>> = (0..chunks)
+        .into_par_iter()
+        .filter_map(|i| {
+            if i % CHECK_INTERVAL == 0 && job.is_cancelled() {
+                return None;
+            }
+            let range = i * size..(i + 1) * size;
+            let chunk = |data: &'a [u8]| data.get(range.start..range.end.min(data.len())).unwrap_or_default();
+            (chunk(a) != chunk(b)).then_some(range)
+        })
+        .collect();
+    job.check_cancelled()?;
+    Ok(merge_ranges(differing))
+}
+
+/// Byte ranges of chunks of `data` whose content doesn't occur in `other` as often.
+fn unmatched_chunks(data: &[u8], chunks: &[Range<usize>], other: &HashMap<u64, usize>) -> Vec<Range<usize>> {
+    let mut available = other.clone();
+    merge_ranges(chunks.iter().filter_map(|chunk| {
+        match available.get_mut(&hash_chunk(&data[chunk.clone()])) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                None
+            }
+            _ => Some(chunk.clone()),
+        }
+    }))
+}
+
+fn chunk_counts(data: &[u8], chunks: &[Range<usize>]) -> HashMap<u64, usize> {
+    let mut counts = HashMap::default();
+    for chunk in chunks {
+        *counts.entry(hash_chunk(&data[chunk.clone()])).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Compares two files as raw bytes in chunks and reports the byte ranges of each file that
+/// have no counterpart in the other. In the job summary the unique counts are these ranges.
+pub fn run_binary_comparison<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    file_a_path: &str,
+    file_b_path: &str,
+    blocks: &BinaryBlocks,
+) -> Result<(), IoError> {
+    let (mmap_a, mmap_b) = (map_file(file_a_path)?, map_file(file_b_path)?);
+    let a: &[u8] = mmap_a.as_deref().unwrap_or_default();
+    let b: &[u8] = mmap_b.as_deref().unwrap_or_default();
+    let size = blocks.chunk_size.max(1);
+
+    let (ranges_a, ranges_b) = match blocks.chunking {
+        Chunking::Fixed => {
+            let differing = fixed_differences(job, a, b, size)?;
+            let clip = |len: usize| -> Vec<Range<usize>> {
+                differing.iter().filter(|r| r.start < len).map(|r| r.start..r.end.min(len)).collect()
+            };
+            (clip(a.len()), clip(b.len()))
+        }
+        Chunking::ContentDefined => {
+            let (chunks_a, chunks_b) = rayon::join(|| content_defined_chunks(a, size), || content_defined_chunks(b, size));
+            job.check_cancelled()?;
+            let (counts_a, counts_b) = rayon::join(|| chunk_counts(a, &chunks_a), || chunk_counts(b, &chunks_b));
+            job.check_cancelled()?;
+            (unmatched_chunks(a, &chunks_a, &counts_b), unmatched_chunks(b, &chunks_b, &counts_a))
+        }
+    };
+
+    let to_payload = |file: &str, range: &Range<usize>| BinaryRange {
+        file: file.to_string(),
+        offset: range.start as u64,
+        len: (range.end - range.start) as u64,
+    };
+    let ranges: Vec<BinaryRange> = ranges_a
+        .iter()
+        .map(|r| to_payload("A", r))
+        .chain(ranges_b.iter().map(|r| to_payload("B", r)))
+        .collect();
+    let differing_bytes = |ranges: &[Range<usize>]| ranges.iter().map(|r| (r.end - r.start) as u64).sum();
+    let payload = BinaryFinishedPayload {
+        size_a: a.len() as u64,
+        size_b: b.len() as u64,
+        differing_bytes_a: differing_bytes(&ranges_a),
+        differing_bytes_b: differing_bytes(&ranges_b),
+        ranges: ranges.len(),
+    };
+
+    if !job.is_quiet() {
+        for range in &ranges {
+            sink.emit("binary_range", range);
+        }
+        sink.on_finished("binary_finished", &payload);
+    }
+    let summary = {
+        let mut store = job.results.lock().unwrap();
+        store.binary_ranges = ranges;
+        store.status = ResultStatus::Complete;
+        ComparisonSummary {
+            unique_to_a: ranges_a.len(),
+            unique_to_b: ranges_b.len(),
+            degradations: store.degradations.clone(),
+            inputs: store.inputs.clone(),
+            same_file: store.same_file,
+            ..Default::default()
+        }
+    };
+    finish_comparison(sink, job, summary);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_content_defined_chunks_resync_after_insertion() {
+        let a = pseudo_random(200_000, 7);
+        let mut b = a.clone();
+        b.splice(50_000..50_000, *b"inserted bytes");
+
+        let (chunks_a, chunks_b) = (content_defined_chunks(&a, 4096), content_defined_chunks(&b, 4096));
+        let only_in_a = unmatched_chunks(&a, &chunks_a, &chunk_counts(&b, &chunks_b));
+        let only_in_b = unmatched_chunks(&b, &chunks_b, &chunk_counts(&a, &chunks_a));
+        assert_eq!(only_in_a.len(), 1);
+        assert_eq!(only_in_b.len(), 1);
+        assert!(only_in_b[0].contains(&50_000));
+        assert!(only_in_b[0].len() <= 4 * 4096 * 2);
+    }
+
+    #[test]
+    fn test_merge_ranges_joins_adjacent() {
+        assert_eq!(merge_ranges([0..4, 4..8, 12..16]), vec![0..8, 12..16]);
+    }
+}
@@ -0,0 +1,55 @@
+use crate::config::{CompareConfig, WhitespaceLines};
+use crate::jobs::Job;
+use crate::payloads::LineClassCounts;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A non-empty record made only of spaces, tabs and other ASCII whitespace.
+pub fn is_whitespace_only(record: &[u8]) -> bool {
+    !record.is_empty() && record.iter().all(u8::is_ascii_whitespace)
+}
+
+/// Whether the engines leave `record` out of the comparison. Empty lines always are,
+/// whitespace-only lines when configured to count as blank.
+pub fn is_skipped(record: &[u8], config: &CompareConfig) -> bool {
+    record.is_empty() || (config.whitespace_lines == WhitespaceLines::Blank && is_whitespace_only(record))
+}
+
+/// Counts the blank and whitespace-only lines of one file while it is hashed.
+#[derive(Default)]
+pub struct LineClassCounter {
+    blank: AtomicUsize,
+    whitespace_only: AtomicUsize,
+}
+
+impl LineClassCounter {
+    /// Counts `record` if it is blank or whitespace-only and returns whether it is skipped.
+    pub fn skip(&self, record: &[u8], config: &CompareConfig) -> bool {
+        if record.is_empty() {
+            self.blank.fetch_add(1, Ordering::Relaxed);
+        } else if is_whitespace_only(record) {
+            self.whitespace_only.fetch_add(1, Ordering::Relaxed);
+        }
+        is_skipped(record, config)
+    }
+
+    /// Stores the counts in the job's results for the summary.
+    pub fn record(&self, job: &Job, file_id: &str) {
+        job.results.lock().unwrap().line_classes.push(LineClassCounts {
+            file: file_id.to_string(),
+            blank: self.blank.load(Ordering::Relaxed),
+            whitespace_only: self.whitespace_only.load(Ordering::Relaxed),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whitespace_only_lines_are_told_apart_from_blank_ones() {
+        assert!(is_whitespace_only(b" \t "));
+        assert!(!is_whitespace_only(b""));
+        assert!(!is_whitespace_only(b" x "));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::config::CompareConfig;
+use crate::fields::{record_len, RecordHasher};
+use crate::header::HeaderLayout;
+use crate::utils::{HashMap, HashSet};
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::Error as IoError;
+
+fn map_file(path: &str) -> Result<Option<Mmap>, IoError> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(unsafe { Mmap::map(&file)? }))
+}
+
+/// Re-reads the representative record of each hash found in both files and compares the
+/// two with an independently seeded hash. Only the first record per hash and file is
+/// checked, so a collision between two lines of the same file goes unnoticed.
+pub struct CollisionCheck<'a> {
+    data_a: Option<Mmap>,
+    data_b: Option<Mmap>,
+    hasher_a: RecordHasher<'a>,
+    hasher_b: RecordHasher<'a>,
+    terminator: u8,
+    quote: Option<u8>,
+}
+
+impl<'a> CollisionCheck<'a> {
+    /// Returns `None` unless the config asks for verification. `strip_cr` tells whether the
+    /// records of A and B have their trailing `\r` stripped.
+    pub fn open(
+        file_a_path: &str,
+        file_b_path: &str,
+        compare_config: &'a CompareConfig,
+        header_layout: Option<&HeaderLayout>,
+        strip_cr: [bool; 2],
+    ) -> Result<Option<Self>, IoError> {
+        if !compare_config.verify_hash_matches {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            data_a: map_file(file_a_path)?,
+            data_b: map_file(file_b_path)?,
+            hasher_a: RecordHasher::new(compare_config, header_layout, "A", strip_cr[0])?,
+            hasher_b: RecordHasher::new(compare_config, header_layout, "B", strip_cr[1])?,
+            terminator: compare_config.terminator(),
+            quote: compare_config.record_quote(),
+        }))
+    }
+
+    fn record<'d>(&self, data: &'d Option<Mmap>, offset: u64, hasher: &RecordHasher) -> &'d [u8] {
+        let data = data.as_deref().unwrap_or_default();
+        let rest = data.get(offset as usize..).unwrap_or_default();
+        hasher.content(&rest[..record_len(rest, self.terminator, self.quote)])
+    }
+
+    /// Whether the records at these offsets only share their hash by accident.
+    pub fn collides(&self, offset_a: u64, offset_b: u64) -> bool {
+        let record_a = self.record(&self.data_a, offset_a, &self.hasher_a);
+        let record_b = self.record(&self.data_b, offset_b, &self.hasher_b);
+        self.hasher_a.verification_hash(record_a) != self.hasher_b.verification_hash(record_b)
+    }
+
+    /// The hashes of both indexes whose representative records differ.
+    pub fn collided<V>(
+        &self,
+        index_a: &HashMap<u64, V>,
+        index_b: &HashMap<u64, V>,
+        offset: impl Fn(&V) -> u64,
+    ) -> HashSet<u64> {
+        let mut collided = HashSet::default();
+        for (hash, entry_a) in index_a {
+            if let Some(entry_b) = index_b.get(hash)
+                && self.collides(offset(entry_a), offset(entry_b))
+            {
+                collided.insert(*hash);
+            }
+        }
+        collided
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_different_records_under_one_hash_collide() {
+        let dir = std::env::temp_dir().join(format!("collisions_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path_a, path_b) = (dir.join("a.txt"), dir.join("b.txt"));
+        std::fs::write(&path_a, "x\r\ny\n").unwrap();
+        std::fs::write(&path_b, "y\n").unwrap();
+        let config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": false,
+            "ignoreOccurences": false,
+            "useSingleThread": false,
+            "ignoreLineNumber": false,
+            "verifyHashMatches": true,
+        }))
+        .unwrap();
+
+        let check = CollisionCheck::open(path_a.to_str().unwrap(), path_b.to_str().unwrap(), &config, None, [true, true])
+            .unwrap()
+            .unwrap();
+        assert!(check.collides(0, 0));
+        assert!(!check.collides(3, 0));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
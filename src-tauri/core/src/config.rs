@@ -0,0 +1,333 @@
+use crate::line_endings::{LineEndingStyle, RECORD_SEPARATOR};
+use crate::utils::HashAlgo;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+mod field_format;
+mod line_terminator;
+mod options;
+mod set_operation;
+
+pub use field_format::{FieldFormat, FixedField, NumericTolerance};
+pub use line_terminator::LineTerminator;
+pub use options::{BinaryBlocks, Chunking, RewriteRule, SimilarityPairing, WhitespaceLines};
+pub use set_operation::{SetOperation, Side};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareConfig {
+    pub use_external_sort: bool,
+    pub ignore_occurences: bool,
+    pub use_single_thread: bool,
+    pub ignore_line_number: bool,
+    /// Compute line numbers with a targeted pass over the reported offsets instead of
+    /// persisting the full newline index (external engine only). Saves temp disk space;
+    /// turned off, the index is written while partitioning as before.
+    #[serde(default = "default_on_demand_line_numbers")]
+    pub on_demand_line_numbers: bool,
+    /// When set, lines are parsed as delimited records and compared field by field.
+    #[serde(default)]
+    pub field_format: Option<FieldFormat>,
+    /// Zero-based column indices excluded from the comparison (requires `field_format`).
+    /// With a header, indices refer to the columns of file A.
+    #[serde(default)]
+    pub ignore_columns: Vec<usize>,
+    /// Regex extracting the record key (first capture group, or the whole match).
+    /// Unique lines of A and B sharing a key are reported as modified lines.
+    #[serde(default)]
+    pub primary_key_regex: Option<String>,
+    /// Zero-based key columns used for pairing when no key regex is set (requires `field_format`).
+    #[serde(default)]
+    pub key_columns: Vec<usize>,
+    /// Pair the unique lines left after key pairing by similarity.
+    #[serde(default)]
+    pub similarity_pairing: Option<SimilarityPairing>,
+    /// Treat numbers within this tolerance as equal (requires `field_format`).
+    #[serde(default)]
+    pub numeric_tolerance: Option<NumericTolerance>,
+    /// Also report lines that occur more than once across both files combined.
+    #[serde(default)]
+    pub report_duplicates: bool,
+    /// Report the N most frequent lines of each file and a histogram of how often lines
+    /// repeat, to explain count mismatches such as one row repeated thousands of times.
+    #[serde(default)]
+    pub frequency_report: Option<usize>,
+    /// Compress the text of stored result lines (zstd with a per-job dictionary).
+    #[serde(default)]
+    pub compress_results: bool,
+    /// Rewrites applied in order to each line before hashing.
+    #[serde(default)]
+    pub rewrite_rules: Vec<RewriteRule>,
+    /// Parse lines as JSON and compare their canonical form (sorted keys, normalized numbers).
+    #[serde(default)]
+    pub canonicalize_json: bool,
+    /// Ignore leading and trailing whitespace of each line.
+    #[serde(default)]
+    pub trim_whitespace: bool,
+    /// Compare lines case-insensitively.
+    #[serde(default)]
+    pub ignore_case: bool,
+    /// Rhai script transforming each line before hashing; see `LineScript`.
+    #[serde(default)]
+    pub transform_script: Option<String>,
+    /// Both files are sorted byte-wise; compare them with a streaming merge-join.
+    #[serde(default)]
+    pub presorted: bool,
+    /// Check that both files really are sorted before merging (presorted mode only).
+    #[serde(default)]
+    pub validate_sort_order: bool,
+    /// File of known, accepted differences (exact lines or record keys, one per line).
+    #[serde(default)]
+    pub accepted_differences_path: Option<String>,
+    #[serde(default)]
+    pub whitespace_lines: WhitespaceLines,
+    /// Report the lines unique to either file (default), to one of them, or the lines in both.
+    #[serde(default)]
+    pub set_operation: SetOperation,
+    /// Compare the inputs byte-wise in chunks and report differing byte ranges.
+    #[serde(default)]
+    pub binary_blocks: Option<BinaryBlocks>,
+    /// Only find the first byte at which the files differ, reading them side by side.
+    #[serde(default)]
+    pub first_difference_only: bool,
+    /// Before comparing, check whether the files are byte-identical (size, sampled blocks,
+    /// then whole-file checksums) and finish at once if they are. Costs a full read of both
+    /// files when they differ only outside the samples.
+    #[serde(default)]
+    pub identical_fast_path: bool,
+    /// How lines end: "auto" (default), "\n", "\r\n", "\r" or a custom separator of up to 8 bytes.
+    #[serde(default)]
+    pub line_terminator: LineTerminator,
+    /// Regex matching the first line of each record, e.g. `^\d{4}-\d{2}-\d{2} ` for log
+    /// entries. The lines up to the next match are compared and reported as one record, and
+    /// records are numbered instead of lines. Lines end at `\n` when this is set.
+    #[serde(default)]
+    pub record_start: Option<String>,
+    /// Encoding of the input files: UTF-8 when unset, "auto" to detect a legacy encoding,
+    /// or a label such as "gbk", "shift_jis" or "latin1".
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Per-file overrides of `encoding`, keyed by file id ("A", "B", "BASE", "F1", ...).
+    /// Every side is transcoded to UTF-8, so equal content hashes the same.
+    #[serde(default)]
+    pub file_encodings: HashMap<String, String>,
+    /// Line events larger than this once serialized are truncated before they reach the frontend.
+    #[serde(default = "default_max_event_bytes")]
+    pub max_event_bytes: usize,
+    /// Modified and unique lines of each file emitted as events. The rest stay in the job's
+    /// result store and are fetched with `get_results_page`.
+    #[serde(default = "default_max_streamed_lines")]
+    pub max_streamed_lines: usize,
+    /// Differences emitted as events in total, over all kinds. Counting goes on past it.
+    #[serde(default = "default_max_emitted_differences")]
+    pub max_emitted_differences: usize,
+    /// Line hash algorithm; unset picks gxhash when the CPU supports it and xxh3 otherwise.
+    #[serde(default)]
+    pub hash_algo: Option<HashAlgo>,
+    /// Seed of the line hashes. A random one is picked per run when unset, so crafted inputs
+    /// can't force collisions or skew the partitions; set it to reproduce a run.
+    #[serde(default)]
+    pub hash_seed: Option<u64>,
+    /// Re-read one record per hash found in both files and check it with a second, independent
+    /// hash, so a 64-bit collision is reported as a difference instead of a match.
+    #[serde(default)]
+    pub verify_hash_matches: bool,
+    /// Pick the in-memory or the external engine from the input sizes instead of `use_external_sort`.
+    #[serde(default)]
+    pub auto_engine: bool,
+    /// Never memory-map the inputs: read them in bounded chunks into the external engine's
+    /// partitions and count line numbers on demand. For slow network storage or machines
+    /// with very little RAM; overrides the engine choice except `presorted`.
+    #[serde(default)]
+    pub streaming: bool,
+    /// Combined input size up to which the automatic engine choice stays in memory.
+    /// Unset values below are filled in from the hardware calibration.
+    #[serde(default)]
+    pub in_memory_limit: Option<u64>,
+    /// Memory in bytes the in-memory engine may use. When its hash tables for the inputs are
+    /// estimated to need more, the external engine runs instead. Unset uses the memory
+    /// available when the comparison starts.
+    #[serde(default)]
+    pub max_memory: Option<u64>,
+    /// Directory for partitions and local copies, e.g. on a fast scratch disk. Unset uses the
+    /// temp directory preference, then the system temp dir.
+    #[serde(default)]
+    pub temp_dir: Option<String>,
+    /// Pick up the partitions an external comparison of the same files left behind when the
+    /// app stopped mid-run, instead of partitioning again.
+    #[serde(default = "default_resume_interrupted")]
+    pub resume_interrupted: bool,
+    /// Keep the partitions of each input in the temp directory after the run, and reuse them
+    /// when the unchanged file is compared again, against any counterpart.
+    #[serde(default)]
+    pub reuse_file_hashes: bool,
+    /// Number of hash partitions the external engines write per file.
+    #[serde(default)]
+    pub partition_count: Option<u64>,
+    /// Bytes each thread scans for line ends at a time.
+    #[serde(default)]
+    pub scan_chunk_size: Option<usize>,
+    /// Combined input size up to which both files are diffed directly, which also yields an
+    /// ordered diff. 0 always uses the hashing engines.
+    #[serde(default = "default_trivial_diff_limit")]
+    pub trivial_diff_limit: u64,
+    /// Stop looking for differences once this many are found, for a quick "are these basically
+    /// the same?" answer. The hashing engines still read both files in full. An intersection
+    /// reports common lines, not differences, and lists all of them.
+    #[serde(default)]
+    pub stop_after_differences: Option<usize>,
+}
+
+fn default_on_demand_line_numbers() -> bool {
+    true
+}
+
+fn default_max_event_bytes() -> usize {
+    256 * 1024
+}
+
+fn default_max_streamed_lines() -> usize {
+    10_000
+}
+
+fn default_max_emitted_differences() -> usize {
+    100_000
+}
+
+fn default_resume_interrupted() -> bool {
+    true
+}
+
+fn default_trivial_diff_limit() -> u64 {
+    4 * 1024 * 1024
+}
+
+const DEFAULT_PARTITIONS: u64 = 256;
+const DEFAULT_SCAN_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+const DEFAULT_IN_MEMORY_LIMIT: u64 = 1024 * 1024 * 1024;
+
+impl CompareConfig {
+    /// How many lines the engines report before they stop, see `stop_after_differences`.
+    pub fn stop_after(&self) -> usize {
+        match self.set_operation {
+            SetOperation::Intersection => usize::MAX,
+            _ => self.stop_after_differences.unwrap_or(usize::MAX),
+        }
+    }
+
+    /// Quote byte used to find record boundaries, if quoted fields may span lines.
+    pub fn record_quote(&self) -> Option<u8> {
+        self.field_format.as_ref().and_then(|f| f.quote_byte())
+    }
+
+    /// Byte the newline scanners split records on.
+    pub fn terminator(&self) -> u8 {
+        match self.record_start {
+            Some(_) => RECORD_SEPARATOR,
+            None => self.line_terminator.byte(),
+        }
+    }
+
+    /// Whether a trailing `\r` is stripped from the lines of a file with the given detected
+    /// line ending. Files that weren't inspected keep stripping it.
+    pub fn strips_cr(&self, style: Option<LineEndingStyle>) -> bool {
+        match self.line_terminator {
+            LineTerminator::Auto => style.is_none_or(|style| style == LineEndingStyle::CrLf),
+            LineTerminator::CrLf => true,
+            LineTerminator::Lf | LineTerminator::Cr | LineTerminator::Byte(_) | LineTerminator::Sequence { .. } => false,
+        }
+    }
+
+    /// Picks a random hash seed for this run unless one is set. Seeds stay below 2^53 so
+    /// they round-trip through JavaScript numbers.
+    pub fn seed_hashes(&mut self) {
+        self.hash_seed.get_or_insert_with(|| rand::random::<u64>() >> 11);
+    }
+
+    pub fn hash_algo(&self) -> HashAlgo {
+        self.hash_algo.unwrap_or_else(HashAlgo::detect)
+    }
+
+    pub fn partitions(&self) -> u64 {
+        self.partition_count.filter(|&n| n > 0).unwrap_or(DEFAULT_PARTITIONS)
+    }
+
+    pub fn scan_chunk(&self) -> usize {
+        self.scan_chunk_size.filter(|&n| n > 0).unwrap_or(DEFAULT_SCAN_CHUNK_SIZE)
+    }
+
+    /// Directory the temp files of this comparison are created in.
+    pub fn temp_root(&self) -> PathBuf {
+        self.temp_dir.as_ref().map_or_else(std::env::temp_dir, PathBuf::from)
+    }
+
+    pub fn memory_limit(&self) -> u64 {
+        self.in_memory_limit.unwrap_or(DEFAULT_IN_MEMORY_LIMIT)
+    }
+
+    pub fn has_header(&self) -> bool {
+        self.field_format.as_ref().is_some_and(|f| f.has_header)
+    }
+
+    /// The encoding setting of the file with the given id.
+    pub fn encoding_for(&self, file_id: &str) -> Option<&str> {
+        self.file_encodings.get(file_id).or(self.encoding.as_ref()).map(String::as_str)
+    }
+
+    /// A copy of this config with the given top-level fields replaced. Keys that aren't
+    /// fields, e.g. misspelled ones, are rejected rather than ignored.
+    pub fn with_overrides(&self, overrides: &Value) -> Result<Self, serde_json::Error> {
+        let mut value = serde_json::to_value(self)?;
+        if let (Value::Object(fields), Value::Object(changes)) = (&mut value, overrides) {
+            for (key, change) in changes {
+                let Some(field) = fields.get_mut(key) else {
+                    return Err(serde::de::Error::custom(format!("Unknown config key: {}", key)));
+                };
+                *field = change.clone();
+            }
+        }
+        serde_json::from_value(value)
+    }
+
+    /// The settings that determine line hashes. Partitions hashed under the same rules
+    /// can be reused by another run.
+    pub fn hashing_rules(&self) -> Value {
+        serde_json::json!({
+            "fieldFormat": self.field_format,
+            "ignoreColumns": self.ignore_columns,
+            "numericTolerance": self.numeric_tolerance,
+            "rewriteRules": self.rewrite_rules,
+            "canonicalizeJson": self.canonicalize_json,
+            "trimWhitespace": self.trim_whitespace,
+            "ignoreCase": self.ignore_case,
+            "transformScript": self.transform_script,
+            "whitespaceLines": self.whitespace_lines,
+            "encoding": self.encoding,
+            "fileEncodings": self.file_encodings,
+            "lineTerminator": self.line_terminator,
+            "recordStart": self.record_start,
+            "partitionCount": self.partitions(),
+            "hashSeed": self.hash_seed,
+            "hashAlgo": self.hash_algo(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_replace_fields_and_reject_unknown_keys() {
+        let config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": false, "ignoreOccurences": false, "useSingleThread": false, "ignoreLineNumber": false,
+        }))
+        .unwrap();
+        let changed = config.with_overrides(&serde_json::json!({ "maxStreamedLines": 5, "hashSeed": 7 })).unwrap();
+        assert_eq!((changed.max_streamed_lines, changed.hash_seed), (5, Some(7)));
+        let err = config.with_overrides(&serde_json::json!({ "maxEvents": 5 })).unwrap_err();
+        assert!(err.to_string().contains("Unknown config key: maxEvents"));
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+/// How a line is split into fields for column-aware comparison.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldFormat {
+    /// Field separator, e.g. ",", "\t", "|" or any multi-byte string. Unused with `fixed_width`.
+    #[serde(default)]
+    pub delimiter: String,
+    /// Quote character for RFC 4180 style quoting. `None` disables quoting.
+    #[serde(default)]
+    pub quote_char: Option<char>,
+    /// Treat the first record of each file as a header and match columns by name.
+    #[serde(default)]
+    pub has_header: bool,
+    /// Layout of fixed-width (mainframe style) records: fields are byte ranges instead of
+    /// being delimited, and bytes outside every field, e.g. filler, aren't compared.
+    #[serde(default)]
+    pub fixed_width: Option<Vec<FixedField>>,
+}
+
+/// A field of a fixed-width record: `length` bytes from byte `start`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FixedField {
+    pub start: usize,
+    pub length: usize,
+    /// Shown for the field in place of its column number.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+impl FixedField {
+    /// The field's bytes in `record`, cut short or empty where the record is.
+    pub fn slice<'r>(&self, record: &'r [u8]) -> &'r [u8] {
+        record.get(self.start..(self.start + self.length).min(record.len())).unwrap_or_default()
+    }
+}
+
+impl FieldFormat {
+    pub fn delimiter_bytes(&self) -> &[u8] {
+        self.delimiter.as_bytes()
+    }
+
+    /// How a zero-based column is called in messages: its layout name, or its number.
+    pub fn column_label(&self, column: usize) -> String {
+        let layout = self.fixed_width.as_ref().and_then(|layout| layout.get(column));
+        match layout.and_then(|field| field.name.as_ref()) {
+            Some(name) => name.clone(),
+            None => format!("column {}", column + 1),
+        }
+    }
+
+    /// Fixed-width fields are never quoted, so records can't span lines.
+    pub fn quote_byte(&self) -> Option<u8> {
+        if self.fixed_width.is_some() {
+            return None;
+        }
+        self.quote_char.filter(|c| c.is_ascii()).map(|c| c as u8)
+    }
+}
+
+/// Tolerance for comparing numeric fields. The absolute tolerance takes precedence.
+/// Values are bucketed rather than compared pairwise, so two values closer than the
+/// tolerance still differ when they fall either side of a bucket boundary.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NumericTolerance {
+    #[serde(default)]
+    pub absolute: Option<f64>,
+    #[serde(default)]
+    pub relative: Option<f64>,
+    /// Columns (of file A) compared numerically. Empty means every field that parses as a number.
+    #[serde(default)]
+    pub columns: Vec<usize>,
+}
@@ -0,0 +1,136 @@
+use crate::line_endings::RECORD_SEPARATOR;
+use serde::{Deserialize, Serialize};
+
+/// Longest multi-byte record separator.
+const MAX_SEPARATOR_LEN: usize = 8;
+
+/// Byte ending each line. With `Auto` lines end at `\n`, and files using only bare `\r`
+/// (classic Mac) are converted to `\n` in a local copy before the comparison.
+/// `CrLf` splits like `Lf` and strips the trailing `\r` of every line; `Auto` only strips it
+/// in files whose dominant line ending is `\r\n`, elsewhere a `\r` is data.
+/// In the config it is a keyword (`auto`, `lf`, `crlf`, `cr`, `nul`) or the separator
+/// itself, where `\0`, `\t`, `\n`, `\r`, `\\` and `\xNN` are unescaped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum LineTerminator {
+    #[default]
+    Auto,
+    Lf,
+    CrLf,
+    Cr,
+    /// Any other single byte, e.g. `\0` or `\x1e`.
+    Byte(u8),
+    /// A separator of several bytes, e.g. `<EOR>` or `\n\n`. Inputs are copied with each
+    /// occurrence replaced by `RECORD_SEPARATOR`, which they must not contain.
+    Sequence { bytes: [u8; MAX_SEPARATOR_LEN], len: u8 },
+}
+
+impl LineTerminator {
+    pub fn byte(self) -> u8 {
+        match self {
+            LineTerminator::Auto | LineTerminator::Lf | LineTerminator::CrLf => b'\n',
+            LineTerminator::Cr => b'\r',
+            LineTerminator::Byte(byte) => byte,
+            LineTerminator::Sequence { .. } => RECORD_SEPARATOR,
+        }
+    }
+
+    /// The separator of a `Sequence`, which inputs are converted from.
+    pub fn sequence(&self) -> Option<&[u8]> {
+        match self {
+            LineTerminator::Sequence { bytes, len } => Some(&bytes[..*len as usize]),
+            _ => None,
+        }
+    }
+}
+
+fn unescape(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 2..i + 4).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        let (byte, len) = match (bytes[i], bytes.get(i + 1)) {
+            (b'\\', Some(b'0')) => (0, 2),
+            (b'\\', Some(b't')) => (b'\t', 2),
+            (b'\\', Some(b'n')) => (b'\n', 2),
+            (b'\\', Some(b'r')) => (b'\r', 2),
+            (b'\\', Some(b'\\')) => (b'\\', 2),
+            (b'\\', Some(b'x')) if hex.is_some() => (hex.unwrap_or_default(), 4),
+            (byte, _) => (byte, 1),
+        };
+        unescaped.push(byte);
+        i += len;
+    }
+    unescaped
+}
+
+fn escape(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            b'\\' => "\\\\".to_string(),
+            b' '..=b'~' => char::from(byte).to_string(),
+            _ => format!("\\x{:02x}", byte),
+        })
+        .collect()
+}
+
+impl TryFrom<String> for LineTerminator {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let separator = match value.as_str() {
+            "" | "auto" => return Ok(LineTerminator::Auto),
+            "lf" => b"\n".to_vec(),
+            "crlf" => b"\r\n".to_vec(),
+            "cr" => b"\r".to_vec(),
+            "nul" => vec![0],
+            other => unescape(other),
+        };
+        Ok(match separator.as_slice() {
+            b"\n" => LineTerminator::Lf,
+            b"\r\n" => LineTerminator::CrLf,
+            b"\r" => LineTerminator::Cr,
+            [byte] => LineTerminator::Byte(*byte),
+            separator if separator.len() <= MAX_SEPARATOR_LEN => {
+                let mut bytes = [0; MAX_SEPARATOR_LEN];
+                bytes[..separator.len()].copy_from_slice(separator);
+                LineTerminator::Sequence { bytes, len: separator.len() as u8 }
+            }
+            _ => return Err(format!("Record separators are at most {} bytes: {:?}", MAX_SEPARATOR_LEN, value)),
+        })
+    }
+}
+
+impl From<LineTerminator> for String {
+    fn from(value: LineTerminator) -> Self {
+        match value {
+            LineTerminator::Auto => "auto".to_string(),
+            LineTerminator::Lf => "\n".to_string(),
+            LineTerminator::CrLf => "\r\n".to_string(),
+            LineTerminator::Cr => "\r".to_string(),
+            LineTerminator::Byte(byte) if byte.is_ascii() && byte != b'\\' => char::from(byte).to_string(),
+            LineTerminator::Byte(byte) => escape(&[byte]),
+            LineTerminator::Sequence { .. } => escape(value.sequence().unwrap_or_default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_separators_parse_from_escapes() {
+        let parse = |text: &str| LineTerminator::try_from(text.to_string()).unwrap();
+        assert_eq!(parse("\\0"), LineTerminator::Byte(0));
+        assert_eq!(parse("\\x1e"), LineTerminator::Byte(0x1e));
+        assert_eq!(parse("\\r\\n"), LineTerminator::CrLf);
+        assert_eq!(parse("<EOR>\\n").sequence(), Some(&b"<EOR>\n"[..]));
+        for text in ["\\0", "\\", "\\xff", "<EOR>\\n", "|\\\\|"] {
+            assert_eq!(parse(&String::from(parse(text))), parse(text));
+        }
+        assert!(LineTerminator::try_from("<END OF RECORD>".to_string()).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Chunking {
+    /// Chunk `i` of A is compared with chunk `i` of B.
+    #[default]
+    Fixed,
+    /// Chunk boundaries follow the content, so an insertion only affects the chunks around it.
+    ContentDefined,
+}
+
+/// How lines made only of whitespace are compared. Empty lines are always skipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhitespaceLines {
+    /// Compared like any other line, so "  " and "\t" differ.
+    #[default]
+    Literal,
+    /// Skipped like empty lines.
+    Blank,
+    /// All whitespace-only lines compare equal to each other.
+    Normalize,
+}
+
+/// Compare files as raw bytes in chunks instead of lines, for non-text files.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BinaryBlocks {
+    #[serde(default)]
+    pub chunking: Chunking,
+    /// Chunk size in bytes; the average size for content-defined chunks.
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+}
+
+fn default_chunk_size() -> usize {
+    64 * 1024
+}
+
+/// Regex rewrite applied to every line before hashing. The replacement may reference
+/// capture groups (`$1`, `${name}`).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewriteRule {
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+}
+
+/// Pairs leftover unique lines of A and B by similarity when no record key matched them.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarityPairing {
+    /// Jaccard similarity of the lines' token sets from which they count as one modified line.
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f64,
+    /// Leading characters two lines must share to be compared at all. 0 compares any lines.
+    #[serde(default = "default_prefix_len")]
+    pub prefix_len: usize,
+}
+
+fn default_min_similarity() -> f64 {
+    0.6
+}
+
+fn default_prefix_len() -> usize {
+    4
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+/// One of the two compared files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    A,
+    B,
+}
+
+/// Which lines a comparison reports. The difference modes report the occurrences of a line
+/// beyond those in the other file; the intersection reports, under file A, the occurrences
+/// found in both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SetOperation {
+    /// Lines unique to A and lines unique to B.
+    #[default]
+    SymmetricDifference,
+    AMinusB,
+    BMinusA,
+    Intersection,
+}
+
+impl SetOperation {
+    /// How many occurrences of a record found `count` times in the file on `side` and
+    /// `other_count` times in the other file are reported for `side`. With
+    /// `ignore_occurences` a record present in both files is common once, never a difference.
+    pub fn reported(self, side: Side, count: usize, other_count: usize, ignore_occurences: bool) -> usize {
+        match (self, side) {
+            (SetOperation::Intersection, Side::A) if ignore_occurences => count.min(other_count).min(1),
+            (SetOperation::Intersection, Side::A) => count.min(other_count),
+            (SetOperation::Intersection, Side::B) | (SetOperation::AMinusB, Side::B) | (SetOperation::BMinusA, Side::A) => 0,
+            _ if ignore_occurences && other_count > 0 => 0,
+            _ => count.saturating_sub(other_count),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_operations_pick_the_reported_occurrences() {
+        let reported = |operation: SetOperation, side| operation.reported(side, 3, 1, false);
+        assert_eq!((reported(SetOperation::SymmetricDifference, Side::A), reported(SetOperation::SymmetricDifference, Side::B)), (2, 2));
+        assert_eq!((reported(SetOperation::AMinusB, Side::A), reported(SetOperation::AMinusB, Side::B)), (2, 0));
+        assert_eq!((reported(SetOperation::BMinusA, Side::A), reported(SetOperation::BMinusA, Side::B)), (0, 2));
+        assert_eq!((reported(SetOperation::Intersection, Side::A), reported(SetOperation::Intersection, Side::B)), (1, 0));
+        assert_eq!(SetOperation::Intersection.reported(Side::A, 3, 0, false), 0);
+        assert_eq!(SetOperation::SymmetricDifference.reported(Side::A, 3, 1, true), 0);
+    }
+}
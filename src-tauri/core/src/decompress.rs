@@ -0,0 +1,141 @@
+use crate::jobs::Job;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error as IoError, ErrorKind, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    fn from_magic(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if header.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+
+    fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+/// Detects the compression of a file by its magic bytes, falling back to its extension.
+pub fn detect_compression(path: &str) -> Result<Option<Compression>, IoError> {
+    let mut header = [0u8; 4];
+    let mut file = File::open(path)?;
+    let mut read = 0;
+    while read < header.len() {
+        match file.read(&mut header[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(Compression::from_magic(&header[..read]).or_else(|| Compression::from_extension(Path::new(path))))
+}
+
+/// Streaming decoder shared by all supported formats. Concatenated members are read to the end.
+pub fn decoder(compression: Compression, file: impl Read + 'static) -> Result<Box<dyn Read>, IoError> {
+    let reader = BufReader::new(file);
+    Ok(match compression {
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+        Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+    })
+}
+
+/// Times a read that timed out or was interrupted is retried, e.g. on a flaky network share.
+const MAX_RETRIES: u32 = 4;
+/// Wait before the first retry, doubled for each further one.
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+fn is_transient(e: &IoError) -> bool {
+    matches!(e.kind(), ErrorKind::Interrupted | ErrorKind::TimedOut | ErrorKind::WouldBlock)
+}
+
+/// Streams `reader` into `target`, checking for cancellation between blocks and retrying
+/// reads that fail transiently.
+pub fn stream_to_file(job: &Job, mut reader: impl Read, target: &Path) -> Result<(), IoError> {
+    let mut writer = BufWriter::new(File::create(target)?);
+    let mut buffer = vec![0u8; 1 << 20];
+    let mut retries = 0;
+    loop {
+        job.check_cancelled()?;
+        let n = match reader.read(&mut buffer) {
+            Ok(n) => n,
+            Err(e) if is_transient(&e) && retries < MAX_RETRIES => {
+                thread::sleep(RETRY_DELAY * 2u32.pow(retries));
+                retries += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if n == 0 {
+            break;
+        }
+        retries = 0;
+        writer.write_all(&buffer[..n])?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::JobRegistry;
+    use crate::slow_io::{SlowReader, SlowStorage};
+
+    #[test]
+    fn test_compression_from_magic_bytes_and_extension() {
+        assert_eq!(Compression::from_magic(&[0x1f, 0x8b, 8, 0]), Some(Compression::Gzip));
+        assert_eq!(Compression::from_magic(b"BZh9"), Some(Compression::Bzip2));
+        assert_eq!(Compression::from_magic(b"a,b\n"), None);
+        assert_eq!(Compression::from_extension(Path::new("dump.ZST")), Some(Compression::Zstd));
+        assert_eq!(Compression::from_extension(Path::new("dump.csv")), None);
+    }
+
+    #[test]
+    fn test_copy_from_slow_storage_retries_transient_errors() {
+        let job = JobRegistry::default().create(
+            serde_json::from_value(serde_json::json!({
+                "useExternalSort": false,
+                "ignoreOccurences": false,
+                "useSingleThread": false,
+                "ignoreLineNumber": false,
+            }))
+            .unwrap(),
+        );
+        let dir = std::env::temp_dir().join(format!("slow_copy_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("copy");
+        let data: Vec<u8> = (0..50_000u32).flat_map(|i| format!("line {}\n", i).into_bytes()).collect();
+        let flaky = |failures| SlowStorage {
+            latency: Duration::from_millis(1),
+            max_read: 64 * 1024,
+            fail_at: Some(100_000),
+            failures,
+            ..Default::default()
+        };
+
+        stream_to_file(&job, SlowReader::new(&data[..], flaky(2)), &target).unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), data);
+        let err = stream_to_file(&job, SlowReader::new(&data[..], flaky(usize::MAX)), &target).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
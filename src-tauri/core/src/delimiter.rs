@@ -0,0 +1,93 @@
+use crate::config::{CompareConfig, FieldFormat};
+use crate::fields::{find_record_ends, split_fields};
+use crate::payloads::DelimiterMismatch;
+use std::fs::File;
+use std::io::{Error as IoError, Read};
+
+const SAMPLE_BYTES: u64 = 64 * 1024;
+const CANDIDATES: [&str; 4] = [",", "\t", ";", "|"];
+
+fn read_sample(path: &str) -> Result<Vec<u8>, IoError> {
+    let mut sample = Vec::new();
+    File::open(path)?.take(SAMPLE_BYTES).read_to_end(&mut sample)?;
+    Ok(sample)
+}
+
+/// Number of fields every sampled record splits into, if it is the same for all of them.
+fn consistent_field_count(records: &[&[u8]], format: &FieldFormat) -> Option<usize> {
+    let mut counts = records.iter().map(|r| split_fields(r, format).len());
+    let first = counts.next()?;
+    (first > 1 && counts.all(|c| c == first)).then_some(first)
+}
+
+/// Delimiter a sample parses cleanly with: the configured one if it does, otherwise the
+/// candidate producing the most fields consistently across the sampled records.
+pub fn detect_delimiter(sample: &[u8], format: &FieldFormat, terminator: u8) -> Option<String> {
+    let mut start = 0;
+    let mut records = Vec::new();
+    let ends = match format.quote_byte() {
+        Some(q) => find_record_ends(sample, terminator, q),
+        None => memchr::memchr_iter(terminator, sample).collect(),
+    };
+    // Whatever follows the last terminator may be a record cut off by the sample size.
+    for end in ends {
+        let record = &sample[start..end];
+        records.push(record.strip_suffix(b"\r").unwrap_or(record));
+        start = end + 1;
+    }
+    if records.is_empty() && !sample.is_empty() {
+        records.push(sample);
+    }
+    if format.has_header && records.len() > 1 {
+        records.remove(0);
+    }
+
+    if consistent_field_count(&records, format).is_some() {
+        return Some(format.delimiter.clone());
+    }
+    CANDIDATES
+        .iter()
+        .filter_map(|&candidate| {
+            let candidate_format = FieldFormat { delimiter: candidate.to_string(), ..format.clone() };
+            consistent_field_count(&records, &candidate_format).map(|count| (count, candidate))
+        })
+        .max_by_key(|&(count, _)| count)
+        .map(|(_, candidate)| candidate.to_string())
+}
+
+/// Samples the head of both files and reports when they don't parse with the same delimiter,
+/// which would otherwise make a field-aware comparison report every line as different.
+pub fn check_delimiters(
+    file_a_path: &str,
+    file_b_path: &str,
+    compare_config: &CompareConfig,
+) -> Result<Option<DelimiterMismatch>, IoError> {
+    // Fixed-width records have no delimiter to disagree on.
+    let Some(format) = compare_config.field_format.as_ref().filter(|format| format.fixed_width.is_none()) else {
+        return Ok(None);
+    };
+    let terminator = compare_config.terminator();
+    let detected_a = detect_delimiter(&read_sample(file_a_path)?, format, terminator);
+    let detected_b = detect_delimiter(&read_sample(file_b_path)?, format, terminator);
+    if detected_a == detected_b {
+        return Ok(None);
+    }
+    Ok(Some(DelimiterMismatch {
+        configured: format.delimiter.clone(),
+        detected_a,
+        detected_b,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_other_delimiter() {
+        let format = FieldFormat { delimiter: ",".to_string(), quote_char: Some('"'), has_header: false, fixed_width: None };
+        assert_eq!(detect_delimiter(b"1,\"a,b\",x\n2,c,y\n", &format, b'\n'), Some(",".to_string()));
+        assert_eq!(detect_delimiter(b"1\ta,b\tx\n2\tc\ty\n3\td", &format, b'\n'), Some("\t".to_string()));
+        assert_eq!(detect_delimiter(b"single\ncolumn\n", &format, b'\n'), None);
+    }
+}
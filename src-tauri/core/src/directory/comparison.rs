@@ -0,0 +1,102 @@
+use crate::config::CompareConfig;
+use crate::event_limit::emit_bounded;
+use crate::directory::pairing::{pair_files, DirectoryOptions, FilePair};
+use crate::engine::run_engine;
+use crate::jobs::{Job, JobRegistry};
+use crate::payloads::{DirectoryEntry, DirectoryEntryStatus};
+use crate::results::batch::summarize_batch;
+use crate::results::store::ResultStatus;
+use crate::sink::ProgressSink;
+use std::io::Error as IoError;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+fn new_entry(pair: &FilePair, status: DirectoryEntryStatus) -> DirectoryEntry {
+    DirectoryEntry {
+        relative_path: pair.relative_path.clone(),
+        status,
+        job_id: None,
+        summary: None,
+        error: None,
+    }
+}
+
+/// Compares one pair in a child job whose lines are stored but not emitted.
+/// The child's results stay available for drill-down through its job id.
+fn compare_pair<S: ProgressSink>(
+    sink: &S,
+    registry: &JobRegistry,
+    parent: &Job,
+    pair: &FilePair,
+    compare_config: &CompareConfig,
+) -> DirectoryEntry {
+    let (path_a, path_b) = match (&pair.path_a, &pair.path_b) {
+        (Some(a), Some(b)) => (a.to_string_lossy().into_owned(), b.to_string_lossy().into_owned()),
+        (Some(_), None) => return new_entry(pair, DirectoryEntryStatus::OnlyInA),
+        _ => return new_entry(pair, DirectoryEntryStatus::OnlyInB),
+    };
+    let child = registry.create(compare_config.clone());
+    child.set_quiet();
+    parent.add_child(child.clone());
+    let mut entry = new_entry(pair, DirectoryEntryStatus::Compared);
+    entry.job_id = Some(child.id.clone());
+
+    match run_engine(sink.clone(), child.clone(), path_a, path_b, compare_config.clone(), None) {
+        Ok(()) => entry.summary = child.results.lock().unwrap().summary.clone(),
+        Err(e) => {
+            entry.status = DirectoryEntryStatus::Failed;
+            entry.error = Some(e.to_string());
+        }
+    }
+    // Partitions of a single pair aren't worth keeping for a re-run.
+    child.take_partitions();
+    entry
+}
+
+/// Pairs the files of two directories by relative path and compares every pair, at most
+/// `max_parallel` at a time so many small pairs don't all map their files at once.
+pub fn run_directory_comparison<S: ProgressSink>(
+    sink: S,
+    registry: &JobRegistry,
+    job: Arc<Job>,
+    dir_a: String,
+    dir_b: String,
+    compare_config: CompareConfig,
+    options: DirectoryOptions,
+) -> Result<(), IoError> {
+    let pairs = pair_files(Path::new(&dir_a), Path::new(&dir_b), &options)?;
+    let next = AtomicUsize::new(0);
+    let entries = Mutex::new(Vec::with_capacity(pairs.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..options.max_parallel.max(1) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(pair) = pairs.get(index) else { break };
+                    if job.is_cancelled() {
+                        break;
+                    }
+                    let entry = compare_pair(&sink, registry, &job, pair, &compare_config);
+                    emit_bounded(&sink, "directory_entry", &entry, compare_config.max_event_bytes);
+                    entries.lock().unwrap().push(entry);
+                }
+            });
+        }
+    });
+    job.check_cancelled()?;
+
+    let mut entries = entries.into_inner().unwrap();
+    entries.sort_unstable_by(|x, y| x.relative_path.cmp(&y.relative_path));
+    let payload = summarize_batch(&entries);
+    {
+        let mut store = job.results.lock().unwrap();
+        store.directory = entries;
+        store.batch_summary = Some(payload.clone());
+        store.status = ResultStatus::Complete;
+    }
+    sink.on_finished("directory_finished", &payload);
+    Ok(())
+}
@@ -0,0 +1,220 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Which files of two directories are compared and how many pairs run at once.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryOptions {
+    /// Glob patterns on the relative path (e.g. "**/*.csv"). Empty means every file.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Maximum number of file pairs compared at the same time.
+    #[serde(default = "default_max_parallel")]
+    pub max_parallel: usize,
+    /// Pair files by name pattern instead of identical relative paths.
+    #[serde(default)]
+    pub name_pattern: Option<NamePattern>,
+}
+
+/// Relative path templates for both sides, e.g. `{name}_v1.csv` and `{name}_v2.csv`.
+/// Files whose placeholders capture the same text are paired; files matching neither
+/// template are skipped.
+#[derive(Clone, Debug, Deserialize)]
+pub struct NamePattern {
+    pub a: String,
+    pub b: String,
+}
+
+fn default_max_parallel() -> usize {
+    4
+}
+
+impl Default for DirectoryOptions {
+    fn default() -> Self {
+        Self { include: Vec::new(), exclude: Vec::new(), max_parallel: default_max_parallel(), name_pattern: None }
+    }
+}
+
+/// Files with the same relative path (or pattern key) in both directories. One side is `None`
+/// when the file exists in only one directory. Pattern pairs are named after the A side.
+pub struct FilePair {
+    pub relative_path: String,
+    pub path_a: Option<PathBuf>,
+    pub path_b: Option<PathBuf>,
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, IoError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| IoError::new(ErrorKind::InvalidInput, format!("Invalid glob '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| IoError::new(ErrorKind::InvalidInput, e.to_string()))
+}
+
+/// A relative path template compiled to an anchored regex, with its sorted placeholder names.
+struct Template {
+    regex: Regex,
+    placeholders: Vec<String>,
+}
+
+impl Template {
+    fn new(template: &str) -> Result<Self, IoError> {
+        let invalid = |message: String| IoError::new(ErrorKind::InvalidInput, message);
+        let mut pattern = String::from("^");
+        let mut placeholders = Vec::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| invalid(format!("Unclosed placeholder in '{}'", template)))?;
+            let name = &rest[open + 1..open + close];
+            pattern.push_str(&regex::escape(&rest[..open]));
+            pattern.push_str(&format!("(?P<{}>.+?)", name));
+            placeholders.push(name.to_string());
+            rest = &rest[open + close + 1..];
+        }
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+        let regex = Regex::new(&pattern).map_err(|e| invalid(format!("Invalid name pattern '{}': {}", template, e)))?;
+        placeholders.sort();
+        Ok(Self { regex, placeholders })
+    }
+
+    /// The texts captured by the placeholders, in name order, if `relative_path` matches.
+    fn key(&self, relative_path: &str) -> Option<String> {
+        let captures = self.regex.captures(relative_path)?;
+        let values: Vec<&str> = self.placeholders.iter().map(|name| &captures[name.as_str()]).collect();
+        Some(values.join("\u{0}"))
+    }
+}
+
+/// Re-keys the files of one side by the captures of `template`, dropping files that don't match.
+fn key_by_template(files: BTreeMap<String, PathBuf>, template: &Template) -> BTreeMap<String, (String, PathBuf)> {
+    files
+        .into_iter()
+        .filter_map(|(relative_path, path)| Some((template.key(&relative_path)?, (relative_path, path))))
+        .collect()
+}
+
+/// Relative paths (with `/` separators) of all files below `dir` that pass the filters.
+fn list_files(dir: &Path, include: &GlobSet, exclude: &GlobSet) -> Result<BTreeMap<String, PathBuf>, IoError> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(dir).follow_links(true) {
+        let entry = entry.map_err(IoError::other)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        if (include.is_empty() || include.is_match(&relative)) && !exclude.is_match(&relative) {
+            files.insert(relative, entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+/// Pairs the files of both directories by relative path, or by name pattern when one is set.
+/// Sorted by the relative path the pair is named after.
+pub fn pair_files(dir_a: &Path, dir_b: &Path, options: &DirectoryOptions) -> Result<Vec<FilePair>, IoError> {
+    let include = glob_set(&options.include)?;
+    let exclude = glob_set(&options.exclude)?;
+    let files_a = list_files(dir_a, &include, &exclude)?;
+    let files_b = list_files(dir_b, &include, &exclude)?;
+
+    let (mut files_a, files_b) = match &options.name_pattern {
+        Some(pattern) => {
+            let (template_a, template_b) = (Template::new(&pattern.a)?, Template::new(&pattern.b)?);
+            if template_a.placeholders != template_b.placeholders {
+                return Err(IoError::new(
+                    ErrorKind::InvalidInput,
+                    "Both name patterns must use the same placeholders",
+                ));
+            }
+            (key_by_template(files_a, &template_a), key_by_template(files_b, &template_b))
+        }
+        None => {
+            let by_path = |files: BTreeMap<String, PathBuf>| {
+                files.into_iter().map(|(relative_path, path)| (relative_path.clone(), (relative_path, path))).collect()
+            };
+            (by_path(files_a), by_path(files_b))
+        }
+    };
+
+    let mut pairs: Vec<FilePair> = files_b
+        .into_iter()
+        .map(|(key, (relative_path_b, path_b))| {
+            let (relative_path, path_a) = match files_a.remove(&key) {
+                Some((relative_path_a, path_a)) => (relative_path_a, Some(path_a)),
+                None => (relative_path_b, None),
+            };
+            FilePair { relative_path, path_a, path_b: Some(path_b) }
+        })
+        .collect();
+    pairs.extend(files_a.into_values().map(|(relative_path, path_a)| FilePair {
+        relative_path,
+        path_a: Some(path_a),
+        path_b: None,
+    }));
+    pairs.sort_unstable_by(|x, y| x.relative_path.cmp(&y.relative_path));
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_pairs_by_relative_path_with_filters() {
+        let root = std::env::temp_dir().join(format!("pairing_test_{}", std::process::id()));
+        let (dir_a, dir_b) = (root.join("a"), root.join("b"));
+        for (dir, files) in [(&dir_a, ["x.csv", "sub/y.csv", "skip.log"]), (&dir_b, ["x.csv", "sub/z.csv", "skip.log"])] {
+            for file in files {
+                let path = dir.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, "line\n").unwrap();
+            }
+        }
+        let options = DirectoryOptions { include: vec!["**/*.csv".to_string()], ..Default::default() };
+        let pairs = pair_files(&dir_a, &dir_b, &options).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let summary: Vec<(&str, bool, bool)> =
+            pairs.iter().map(|p| (p.relative_path.as_str(), p.path_a.is_some(), p.path_b.is_some())).collect();
+        assert_eq!(summary, vec![("sub/y.csv", true, false), ("sub/z.csv", false, true), ("x.csv", true, true)]);
+    }
+
+    #[test]
+    fn test_pairs_by_name_pattern() {
+        let root = std::env::temp_dir().join(format!("pattern_pairing_test_{}", std::process::id()));
+        let (dir_a, dir_b) = (root.join("a"), root.join("b"));
+        for (dir, files) in [(&dir_a, ["orders_v1.csv", "users_v1.csv", "notes.txt"]), (&dir_b, ["orders_v2.csv", "items_v2.csv", "notes.txt"])] {
+            fs::create_dir_all(dir).unwrap();
+            for file in files {
+                fs::write(dir.join(file), "line\n").unwrap();
+            }
+        }
+        let pattern = NamePattern { a: "{name}_v1.csv".to_string(), b: "{name}_v2.csv".to_string() };
+        let options = DirectoryOptions { name_pattern: Some(pattern), ..Default::default() };
+        let pairs = pair_files(&dir_a, &dir_b, &options).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let summary: Vec<(&str, bool, bool)> =
+            pairs.iter().map(|p| (p.relative_path.as_str(), p.path_a.is_some(), p.path_b.is_some())).collect();
+        assert_eq!(
+            summary,
+            vec![("items_v2.csv", false, true), ("orders_v1.csv", true, true), ("users_v1.csv", true, false)]
+        );
+    }
+}
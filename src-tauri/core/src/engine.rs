@@ -0,0 +1,95 @@
+use crate::binary;
+use crate::config::{CompareConfig, SetOperation};
+use crate::external::comparison;
+use crate::external::partition_cache::PartitionCache;
+use crate::first_difference;
+use crate::identical;
+use crate::internal::{comparison_in_memory, trivial_diff};
+use crate::input::{fetch_remote, prepare_input};
+use crate::jobs::Job;
+use crate::memory_budget;
+use crate::merge::merge_join;
+use crate::sink::ProgressSink;
+use std::fs;
+use std::io::Error as IoError;
+use std::sync::Arc;
+
+/// Runs a two-file comparison with the engine selected by the config, or on the
+/// partitions of an earlier run when a cache is given. Inputs that can't be mapped
+/// are compared by the external engine, which can read them as a stream. Inputs in S3,
+/// on SFTP servers, behind a URL or in a database are downloaded first.
+pub fn run_engine<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    file_a_path: String,
+    file_b_path: String,
+    compare_config: CompareConfig,
+    cache: Option<PartitionCache>,
+) -> Result<(), IoError> {
+    let file_a_path = fetch_remote(&sink, &job, &file_a_path, "A")?;
+    let file_b_path = fetch_remote(&sink, &job, &file_b_path, "B")?;
+    // Raw bytes are compared as they are: no transcoding or line ending conversion.
+    if let Some(blocks) = &compare_config.binary_blocks {
+        return binary::run_binary_comparison(&sink, &job, &file_a_path, &file_b_path, blocks);
+    }
+    if compare_config.first_difference_only {
+        let terminator = compare_config.terminator();
+        return first_difference::run_first_difference(&sink, &job, &file_a_path, &file_b_path, terminator);
+    }
+    if let Some(cache) = cache {
+        return comparison::rerun_from_cache(sink, job, cache, compare_config);
+    }
+    // Identical files have every line in common, which an intersection has to list.
+    if compare_config.identical_fast_path
+        && compare_config.set_operation != SetOperation::Intersection
+        && identical::files_identical(&sink, &job, &file_a_path, &file_b_path)?
+    {
+        identical::finish_identical(&sink, &job);
+        return Ok(());
+    }
+    let file_a_path = prepare_input(&sink, &job, &file_a_path, "A")?;
+    let file_b_path = prepare_input(&sink, &job, &file_b_path, "B")?;
+    let (size_a, size_b) = (fs::metadata(&file_a_path)?.len(), fs::metadata(&file_b_path)?.len());
+    let mut compare_config = compare_config;
+    if compare_config.auto_engine {
+        compare_config.use_external_sort = size_a + size_b > compare_config.memory_limit();
+    }
+    if compare_config.streaming {
+        // Without a newline index nothing grows with the number of lines.
+        compare_config.use_external_sort = true;
+        compare_config.on_demand_line_numbers = true;
+    }
+    // The merge-join reads each file once; the hashing engines aggregate after hashing.
+    let file_share = if compare_config.presorted { 1.0 } else { 0.5 };
+    job.progress.start([("A".to_string(), size_a), ("B".to_string(), size_b)], file_share);
+    // Duplicate and frequency reports and hash verification need the hashing engines.
+    let trivial = !compare_config.streaming
+        && size_a + size_b <= compare_config.trivial_diff_limit
+        && !compare_config.report_duplicates
+        && compare_config.frequency_report.is_none()
+        && !compare_config.verify_hash_matches;
+    if !compare_config.presorted
+        && !trivial
+        && !compare_config.use_external_sort
+        && let Some(message) = memory_budget::exceeded(&file_a_path, &file_b_path, &compare_config)?
+    {
+        job.record_degradation(&message);
+        compare_config.use_external_sort = true;
+    }
+    if !compare_config.presorted && !trivial && (job.is_streamed() || compare_config.streaming) {
+        compare_config.use_external_sort = true;
+        if compare_config.verify_hash_matches {
+            job.record_degradation("Hash matches weren't verified, the inputs are read as a stream");
+            compare_config.verify_hash_matches = false;
+        }
+    }
+    if compare_config.presorted {
+        merge_join::run_comparison(sink, job, file_a_path, file_b_path, compare_config)
+    } else if trivial {
+        trivial_diff::run_comparison(sink, job, file_a_path, file_b_path, compare_config)
+    } else if compare_config.use_external_sort {
+        comparison::run_comparison(sink, job, file_a_path, file_b_path, compare_config)
+    } else {
+        comparison_in_memory::run_comparison(sink, job, file_a_path, file_b_path, compare_config)
+    }
+}
@@ -0,0 +1,101 @@
+use crate::sink::ProgressSink;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Strings and arrays are never cut below this many bytes or elements.
+const MIN_KEEP: usize = 64;
+
+fn serialized_len(value: &Value) -> usize {
+    serde_json::to_vec(value).map_or(usize::MAX, |bytes| bytes.len())
+}
+
+fn cap_strings(value: &mut Value, cap: usize) {
+    match value {
+        Value::String(text) if text.len() > cap => {
+            let mut end = cap;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            text.truncate(end);
+            text.push('…');
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| cap_strings(item, cap)),
+        Value::Object(fields) => fields.values_mut().for_each(|field| cap_strings(field, cap)),
+        _ => {}
+    }
+}
+
+fn cap_arrays(value: &mut Value, cap: usize) {
+    match value {
+        Value::Array(items) => {
+            items.truncate(cap);
+            items.iter_mut().for_each(|item| cap_arrays(item, cap));
+        }
+        Value::Object(fields) => fields.values_mut().for_each(|field| cap_arrays(field, cap)),
+        _ => {}
+    }
+}
+
+/// Shrinks `value` until it serializes to at most `limit` bytes: first by cutting its longest
+/// strings, then its longest arrays. Returns whether anything had to be cut.
+pub fn bound_payload(value: &mut Value, limit: usize) -> bool {
+    let original_len = serialized_len(value);
+    if original_len <= limit {
+        return false;
+    }
+    let mut cap = limit;
+    while cap > MIN_KEEP && serialized_len(value) > limit {
+        cap /= 2;
+        cap_strings(value, cap.max(MIN_KEEP));
+    }
+    let mut cap = limit;
+    while cap > MIN_KEEP && serialized_len(value) > limit {
+        cap /= 2;
+        cap_arrays(value, cap.max(MIN_KEEP));
+    }
+    true
+}
+
+/// Serializes the payload of `event`, truncated to `limit` serialized bytes so a pathological
+/// line can't stall the IPC bridge. Truncated payloads carry `payload_truncated: true`.
+pub fn bounded<T: Serialize>(event: &str, payload: &T, limit: usize) -> Option<Value> {
+    let mut value = match serde_json::to_value(payload) {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Failed to serialize {} event: {}", event, e);
+            return None;
+        }
+    };
+    if bound_payload(&mut value, limit)
+        && let Value::Object(fields) = &mut value
+    {
+        fields.insert("payload_truncated".to_string(), Value::Bool(true));
+    }
+    Some(value)
+}
+
+/// Emits `payload` to the frontend, bounded to `limit` bytes, see `bounded`.
+pub fn emit_bounded<T: Serialize, S: ProgressSink>(sink: &S, event: &str, payload: &T, limit: usize) {
+    if let Some(value) = bounded(event, payload, limit) {
+        sink.on_event(event, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_long_text_is_cut_to_fit() {
+        let mut value = json!({ "line_number": 7, "text": "é".repeat(10_000) });
+        assert!(bound_payload(&mut value, 1024));
+        assert!(serialized_len(&value) <= 1024);
+        assert_eq!(value["line_number"], 7);
+        assert!(value["text"].as_str().unwrap().ends_with('…'));
+
+        let mut small = json!({ "text": "short" });
+        assert!(!bound_payload(&mut small, 1024));
+        assert_eq!(small["text"], "short");
+    }
+}
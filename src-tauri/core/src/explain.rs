@@ -0,0 +1,215 @@
+use crate::blank_lines::is_skipped;
+use crate::config::CompareConfig;
+use crate::external::spill::read_partition;
+use crate::fields::{find_record_ends, record_len, split_fields, RecordHasher, MAX_QUOTED_RECORD};
+use crate::header::{resolve_header_layout, HeaderLayout};
+use crate::jobs::Job;
+use crate::normalize::{canonicalize_json, LineRewriter};
+use crate::numeric::normalize_number;
+use crate::payloads::{ExplainedField, LineExplanation};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::Arc;
+
+/// Offset where the 1-based record `line_number` starts.
+fn record_start(data: &[u8], line_number: usize, terminator: u8, quote: Option<u8>) -> Option<usize> {
+    if line_number <= 1 {
+        return Some(0);
+    }
+    let mut remaining = line_number - 1;
+    let mut in_quotes = false;
+    let mut start = 0;
+    let positions: Box<dyn Iterator<Item = usize>> = match quote {
+        Some(q) => Box::new(memchr::memchr2_iter(terminator, q, data)),
+        None => Box::new(memchr::memchr_iter(terminator, data)),
+    };
+    for pos in positions {
+        if Some(data[pos]) == quote {
+            in_quotes = !in_quotes;
+        } else if !in_quotes || pos - start >= MAX_QUOTED_RECORD {
+            remaining -= 1;
+            if remaining == 0 {
+                return Some(pos + 1);
+            }
+            in_quotes = false;
+            start = pos + 1;
+        }
+    }
+    None
+}
+
+/// Occurrences of `hash` in a file, from the job's cached partitions if it still has them.
+fn count_hash(
+    job: &Job,
+    path: &str,
+    file_id: &str,
+    hash: u64,
+    hasher: &RecordHasher,
+    config: &CompareConfig,
+) -> Result<usize, IoError> {
+    let partition = job.with_partitions(|cache| {
+        cache.map(|c| c.temp_dir.0.join(file_id.to_lowercase()).join(format!("part_{}", hash % config.partitions())))
+    });
+    if let Some(partition) = partition.filter(|p| p.exists()) {
+        let mut count = 0;
+        read_partition(&partition, |item| count += usize::from(item.0 == hash))?;
+        return Ok(count);
+    }
+
+    let file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(0);
+    }
+    let mmap = unsafe { Mmap::map(&file)? };
+    let terminator = config.terminator();
+    let ends = match config.record_quote() {
+        Some(quote) => find_record_ends(&mmap, terminator, quote),
+        None => memchr::memchr_iter(terminator, &mmap).collect(),
+    };
+    let first_record = if config.has_header() { 1 } else { 0 };
+    Ok((first_record..ends.len())
+        .into_par_iter()
+        .filter(|&i| {
+            let start = if i == 0 { 0 } else { ends[i - 1] + 1 };
+            let record = hasher.content(&mmap[start..ends[i]]);
+            !is_skipped(record, config) && hasher.hash(record) == hash
+        })
+        .count())
+}
+
+fn explain_fields(
+    record: &[u8],
+    file_id: &str,
+    config: &CompareConfig,
+    layout: Option<&HeaderLayout>,
+) -> Vec<ExplainedField> {
+    let Some(format) = &config.field_format else {
+        return Vec::new();
+    };
+    split_fields(record, format)
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let column = match layout {
+                Some(layout) => {
+                    let fields = if file_id == "A" { &layout.projection_a } else { &layout.projection_b };
+                    fields.iter().position(|&f| f == i).map(|p| layout.projection_a[p])
+                }
+                None => Some(i).filter(|i| !config.ignore_columns.contains(i)),
+            };
+            let normalized = match (&config.numeric_tolerance, column) {
+                (Some(tolerance), Some(column)) if tolerance.columns.is_empty() || tolerance.columns.contains(&column) => {
+                    normalize_number(field, tolerance)
+                }
+                _ => None,
+            };
+            ExplainedField { column, text: String::from_utf8_lossy(field).into_owned(), normalized }
+        })
+        .collect()
+}
+
+fn reported_as(job: &Job, file_id: &str, line_number: usize) -> Option<String> {
+    let store = job.results.lock().unwrap();
+    if store.unique_line_numbers(file_id).contains(&line_number) {
+        return Some("unique".to_string());
+    }
+    let modified = store.modified.iter().any(|m| {
+        let line = if file_id == "A" { &m.line_a } else { &m.line_b };
+        line.line_number == line_number
+    });
+    if modified {
+        return Some("modified".to_string());
+    }
+    store
+        .duplicates
+        .iter()
+        .any(|d| d.file == file_id && d.line_number == line_number)
+        .then(|| "duplicate".to_string())
+}
+
+/// Explains how line `line_number` of file `file_id` ("A" or "B") was treated by a job:
+/// how it was normalized and hashed, how often the hash occurs on each side and why it
+/// was or wasn't reported.
+pub fn explain_line(job: &Job, file_id: &str, line_number: usize) -> Result<LineExplanation, IoError> {
+    let config = &job.config;
+    let (path_a, path_b, layout) = {
+        let store = job.results.lock().unwrap();
+        let [a, b] = store.inputs.as_slice() else {
+            return Err(IoError::new(ErrorKind::InvalidInput, "Job has no two input files"));
+        };
+        (job.readable_path(&a.path), job.readable_path(&b.path), store.header_layout.clone())
+    };
+    let layout = match layout {
+        Some(layout) => Some(layout),
+        None => resolve_header_layout(&path_a, &path_b, config)?.map(Arc::new),
+    };
+    let path = if file_id == "A" { &path_a } else { &path_b };
+
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    let quote = config.record_quote();
+    let start = record_start(&mmap, line_number, config.terminator(), quote)
+        .filter(|&start| start < mmap.len())
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, format!("File {} has no line {}", file_id, line_number)))?;
+    let hasher_a = RecordHasher::new(config, layout.as_deref(), "A", job.strips_cr("A"))?;
+    let hasher_b = RecordHasher::new(config, layout.as_deref(), "B", job.strips_cr("B"))?;
+    let hasher = if file_id == "A" { &hasher_a } else { &hasher_b };
+    let record = hasher.content(&mmap[start..start + record_len(&mmap[start..], config.terminator(), quote)]);
+    let normalized = hasher.normalize(record);
+    let hash = hasher.hash(record);
+
+    let canonical = if config.canonicalize_json { canonicalize_json(record) } else { None };
+    let canonical_or_raw = canonical.as_deref().unwrap_or(record);
+    let rewriter = LineRewriter::new(&config.rewrite_rules)?;
+    let count_a = count_hash(job, &path_a, "A", hash, &hasher_a, config)?;
+    let count_b = count_hash(job, &path_b, "B", hash, &hasher_b, config)?;
+    let reported = reported_as(job, file_id, line_number);
+
+    let (count_here, count_other, other) = if file_id == "A" { (count_a, count_b, "B") } else { (count_b, count_a, "A") };
+    let reason = if line_number == 1 && config.has_header() {
+        "Header record, only used to match columns".to_string()
+    } else if record.is_empty() {
+        "Empty lines are skipped".to_string()
+    } else if is_skipped(record, config) {
+        "Whitespace-only lines are skipped like blank lines".to_string()
+    } else if let Some(kind) = &reported {
+        format!("Reported as {}: occurs {}x here and {}x in file {}", kind, count_here, count_other, other)
+    } else if config.ignore_occurences && count_other > 0 {
+        format!("Also present in file {}; occurrence counts are ignored", other)
+    } else if count_here <= count_other {
+        format!("Matched by {} equal line(s) in file {} after normalization", count_other, other)
+    } else {
+        "Occurs more often here than in the other file; reported at its first occurrence".to_string()
+    };
+
+    Ok(LineExplanation {
+        file: file_id.to_string(),
+        line_number,
+        text: String::from_utf8_lossy(record).into_owned(),
+        hash: format!("{:016x}", hash),
+        normalized_text: String::from_utf8_lossy(&normalized).into_owned(),
+        json_canonicalized: canonical.is_some(),
+        rewrite_rules_applied: rewriter.matching_rules(canonical_or_raw),
+        fields: explain_fields(&normalized, file_id, config, layout.as_deref()),
+        count_a,
+        count_b,
+        reported_as: reported,
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_start_skips_quoted_newlines() {
+        let data = b"a\n\"x\ny\"\nb\n";
+        assert_eq!(record_start(data, 2, b'\n', None), Some(2));
+        assert_eq!(record_start(data, 3, b'\n', None), Some(5));
+        assert_eq!(record_start(data, 3, b'\n', Some(b'"')), Some(8));
+        assert_eq!(record_start(data, 9, b'\n', None), None);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::collisions::CollisionCheck;
+use crate::external::spill::{read_partition_into_maps, read_partition_sorted, SortedHashes};
+use crate::jobs::Job;
+use crate::results::duplicates::{duplicate_hashes, DuplicateCandidate};
+use crate::results::frequency::FrequencyCounter;
+use crate::config::{SetOperation, Side};
+use crate::CompareConfig;
+use crate::sink::ProgressSink;
+use crate::utils::{HashMap, HashSet};
+use rayon::prelude::*;
+use std::io::Error as IoError;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Offsets of lines found in only one file, with how many more times they occur there.
+pub type UniqueOffsets = Vec<(u64, usize)>;
+
+fn unique_in(
+    side: Side,
+    counts: &HashMap<u64, usize>,
+    offsets: &HashMap<u64, u64>,
+    other_counts: &HashMap<u64, usize>,
+    collided: &HashSet<u64>,
+    compare_config: &CompareConfig,
+) -> UniqueOffsets {
+    let mut unique = Vec::new();
+    for (hash, &count) in counts {
+        // Records that only share their hash by accident have no counterpart.
+        let other_count = if collided.contains(hash) { 0 } else { other_counts.get(hash).copied().unwrap_or(0) };
+        let reported = compare_config.set_operation.reported(side, count, other_count, compare_config.ignore_occurences);
+        if reported > 0
+            && let Some(&offset) = offsets.get(hash)
+        {
+            unique.push((offset, reported));
+        }
+    }
+    unique
+}
+
+/// Hashes of only one side when occurrence counts are ignored, found by walking both sorted
+/// lists side by side (or for an intersection, the hashes of A also in B). Also returns the
+/// number of collisions found by the check.
+fn unique_in_sorted(
+    a: &SortedHashes,
+    b: &SortedHashes,
+    collision_check: Option<&CollisionCheck>,
+    set_operation: SetOperation,
+) -> (UniqueOffsets, UniqueOffsets, usize) {
+    let (mut unique_a, mut unique_b, mut collisions) = (Vec::new(), Vec::new(), 0);
+    let mut report = |side, (offset, count), other_count| {
+        let reported = set_operation.reported(side, count, other_count, true);
+        if reported > 0 {
+            let unique = if side == Side::A { &mut unique_a } else { &mut unique_b };
+            unique.push((offset, reported));
+        }
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let order = match (a.get(i), b.get(j)) {
+            (Some(x), Some(y)) => x.0.cmp(&y.0),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            _ => std::cmp::Ordering::Greater,
+        };
+        match order {
+            std::cmp::Ordering::Less => {
+                report(Side::A, (a[i].1, a[i].2), 0);
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                report(Side::B, (b[j].1, b[j].2), 0);
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                if collision_check.is_some_and(|check| check.collides(a[i].1, b[j].1)) {
+                    report(Side::A, (a[i].1, a[i].2), 0);
+                    report(Side::B, (b[j].1, b[j].2), 0);
+                    collisions += 1;
+                } else {
+                    report(Side::A, (a[i].1, a[i].2), b[j].2);
+                    report(Side::B, (b[j].1, b[j].2), a[i].2);
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    (unique_a, unique_b, collisions)
+}
+
+pub struct Aggregation {
+    pub unique_to_a: UniqueOffsets,
+    pub unique_to_b: UniqueOffsets,
+    pub duplicates: Vec<DuplicateCandidate>,
+    pub collisions: usize,
+    /// Frequencies of the records of A and B, when `frequency_report` is set.
+    pub frequencies: Option<(FrequencyCounter, FrequencyCounter)>,
+    /// Partitions were skipped because `stop_after_differences` was reached.
+    pub stopped_early: bool,
+}
+
+fn duplicate_candidates(
+    counts_a: &HashMap<u64, usize>,
+    offsets_a: &HashMap<u64, u64>,
+    counts_b: &HashMap<u64, usize>,
+    offsets_b: &HashMap<u64, u64>,
+) -> Vec<DuplicateCandidate> {
+    duplicate_hashes(counts_a, counts_b)
+        .into_iter()
+        .filter_map(|(hash, count_a, count_b)| {
+            let (in_a, offset) = match offsets_a.get(&hash) {
+                Some(&offset) => (true, offset),
+                None => (false, *offsets_b.get(&hash)?),
+            };
+            Some(DuplicateCandidate { in_a, offset, line_number: None, count_a, count_b })
+        })
+        .collect()
+}
+
+/// Compares the matching partitions of both files and returns the offsets unique to A and B.
+/// With a collision check, hashes whose records differ count as unique on both sides.
+pub fn aggregate_partitions<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    temp_dir_a: &Path,
+    temp_dir_b: &Path,
+    compare_config: &CompareConfig,
+    collision_check: Option<&CollisionCheck>,
+) -> Result<Aggregation, IoError> {
+    let progress_counter = AtomicUsize::new(0);
+    let found = AtomicUsize::new(0);
+    let stop_after = compare_config.stop_after();
+    let stopped_early = AtomicBool::new(false);
+
+    let partitions = compare_config.partitions();
+    // Without occurrence counts or duplicates only set membership matters.
+    let set_membership_only = compare_config.ignore_occurences && !compare_config.report_duplicates;
+    let report_progress = |processed_count: usize| {
+        let fraction = (processed_count + 1) as f64 / partitions as f64;
+        job.progress.shared_progress(sink, fraction, fraction * 50.0 + 50.0, "B", "Aggregating partitions...");
+    };
+    let limit = compare_config.frequency_report.unwrap_or(0);
+    let counters = || (FrequencyCounter::new(limit), FrequencyCounter::new(limit));
+    let (unique_to_a, unique_to_b, duplicates, collisions, frequencies) = (0..partitions)
+        .into_par_iter()
+        .map(|i| -> Result<_, IoError> {
+            let mut frequencies = counters();
+            if job.is_cancelled() {
+                return Ok((Vec::new(), Vec::new(), Vec::new(), 0, frequencies));
+            }
+            if found.load(Ordering::Relaxed) >= stop_after {
+                stopped_early.store(true, Ordering::Relaxed);
+                return Ok((Vec::new(), Vec::new(), Vec::new(), 0, frequencies));
+            }
+            let part_a_path = temp_dir_a.join(format!("part_{}", i));
+            let part_b_path = temp_dir_b.join(format!("part_{}", i));
+
+            if set_membership_only {
+                let sorted_a = read_partition_sorted(&part_a_path)?;
+                let sorted_b = read_partition_sorted(&part_b_path)?;
+                if compare_config.frequency_report.is_some() {
+                    sorted_a.iter().for_each(|&(_, offset, count)| frequencies.0.add(count, offset, None));
+                    sorted_b.iter().for_each(|&(_, offset, count)| frequencies.1.add(count, offset, None));
+                }
+                let (unique_a, unique_b, collisions) = unique_in_sorted(&sorted_a, &sorted_b, collision_check, compare_config.set_operation);
+                found.fetch_add(unique_a.len() + unique_b.len(), Ordering::Relaxed);
+                report_progress(progress_counter.fetch_add(1, Ordering::Relaxed));
+                return Ok((unique_a, unique_b, Vec::new(), collisions, frequencies));
+            }
+            let (counts_a, offsets_a) = read_partition_into_maps(part_a_path)?;
+            let (counts_b, offsets_b) = read_partition_into_maps(part_b_path)?;
+            if compare_config.frequency_report.is_some() {
+                for (counts, offsets, counter) in [(&counts_a, &offsets_a, &mut frequencies.0), (&counts_b, &offsets_b, &mut frequencies.1)] {
+                    for (hash, &count) in counts {
+                        counter.add(count, offsets[hash], None);
+                    }
+                }
+            }
+
+            let collided = collision_check
+                .map(|check| check.collided(&offsets_a, &offsets_b, |&offset| offset))
+                .unwrap_or_default();
+            let partition_unique_a = unique_in(Side::A, &counts_a, &offsets_a, &counts_b, &collided, compare_config);
+            let partition_unique_b = unique_in(Side::B, &counts_b, &offsets_b, &counts_a, &collided, compare_config);
+            let partition_duplicates = if compare_config.report_duplicates {
+                duplicate_candidates(&counts_a, &offsets_a, &counts_b, &offsets_b)
+            } else {
+                Vec::new()
+            };
+
+            found.fetch_add(partition_unique_a.len() + partition_unique_b.len(), Ordering::Relaxed);
+            report_progress(progress_counter.fetch_add(1, Ordering::Relaxed));
+            Ok((partition_unique_a, partition_unique_b, partition_duplicates, collided.len(), frequencies))
+        })
+        .try_reduce(
+            || (Vec::new(), Vec::new(), Vec::new(), 0, counters()),
+            |mut a, b| {
+                a.0.extend(b.0);
+                a.1.extend(b.1);
+                a.2.extend(b.2);
+                a.3 += b.3;
+                a.4 = (a.4.0.merge(b.4.0), a.4.1.merge(b.4.1));
+                Ok(a)
+            },
+        )?;
+
+    job.check_cancelled()?;
+    let stopped_early = stopped_early.into_inner();
+    let frequencies = compare_config.frequency_report.map(|_| frequencies);
+    Ok(Aggregation { unique_to_a, unique_to_b, duplicates, collisions, frequencies, stopped_early })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_walk_finds_hashes_of_one_side() {
+        let a = vec![(1, 10, 2), (3, 30, 1), (5, 50, 1)];
+        let b = vec![(2, 20, 1), (3, 31, 4), (6, 60, 1)];
+        let (unique_a, unique_b, collisions) = unique_in_sorted(&a, &b, None, SetOperation::SymmetricDifference);
+        assert_eq!(unique_a, vec![(10, 2), (50, 1)]);
+        assert_eq!(unique_b, vec![(20, 1), (60, 1)]);
+        assert_eq!(collisions, 0);
+        let (common, none, _) = unique_in_sorted(&a, &b, None, SetOperation::Intersection);
+        assert_eq!((common, none), (vec![(30, 1)], vec![]));
+    }
+}
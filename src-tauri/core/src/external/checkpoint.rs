@@ -0,0 +1,228 @@
+use crate::config::CompareConfig;
+use crate::external::file_cache;
+use crate::external::file_processing::{partition_file, NEWLINE_INDEX_FILE};
+use crate::external::multi_file::TEMP_DIR_PREFIX;
+use crate::external::spill::is_finished;
+use crate::header::HeaderLayout;
+use crate::identity::file_identity;
+use crate::jobs::Job;
+use crate::sink::ProgressSink;
+use crate::utils::emit_step_detail;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Error as IoError;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const MANIFEST_FILE: &str = "manifest.json";
+/// Locked exclusively by the process using the directory, for as long as it does.
+const LOCK_FILE: &str = "lock";
+/// Written into a file's partition directory once all of its partitions are.
+const COMPLETE_MARKER: &str = "complete";
+
+/// Lock files of the run directories this process uses, held open until the directory is
+/// dropped. The OS releases them when the process ends, however it ends.
+static HELD_LOCKS: Mutex<Vec<(PathBuf, File)>> = Mutex::new(Vec::new());
+
+/// Locks `dir` for this process. Returns false when another run holds it, in this process
+/// or another one.
+fn claim(dir: &Path) -> Result<bool, IoError> {
+    let mut held = HELD_LOCKS.lock().unwrap();
+    if held.iter().any(|(path, _)| path == dir) {
+        return Ok(true);
+    }
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(dir.join(LOCK_FILE))?;
+    match file.try_lock() {
+        Ok(()) => {
+            held.push((dir.to_path_buf(), file));
+            Ok(true)
+        }
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Whether a run, of this process or another one, is using `dir`.
+fn is_locked(dir: &Path) -> bool {
+    if HELD_LOCKS.lock().unwrap().iter().any(|(path, _)| path == dir) {
+        return true;
+    }
+    let Ok(file) = OpenOptions::new().create(true).truncate(false).write(true).open(dir.join(LOCK_FILE)) else {
+        return true;
+    };
+    matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+/// Unlocks `dir` once it is no longer used, before it is deleted.
+pub fn release(dir: &Path) {
+    HELD_LOCKS.lock().unwrap().retain(|(path, _)| path != dir);
+}
+
+/// What an external run's temp directory was created for, written when the run starts so
+/// a later run of the same comparison can find and resume it.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    file_a_path: String,
+    file_b_path: String,
+    /// Size and modification time of each input, which must not have changed on resume.
+    inputs: Vec<(u64, Option<u64>)>,
+    hashing_rules: Value,
+}
+
+impl Manifest {
+    fn new(file_a_path: &str, file_b_path: &str, config: &CompareConfig) -> Result<Self, IoError> {
+        Ok(Self {
+            file_a_path: file_a_path.to_string(),
+            file_b_path: file_b_path.to_string(),
+            inputs: input_stamps(file_a_path, file_b_path)?,
+            hashing_rules: config.hashing_rules(),
+        })
+    }
+
+    /// Whether the partitions were hashed like a run of `config` would hash them. A config
+    /// without a seed yet matches any seed, which it then adopts.
+    fn matches(&self, other: &Manifest, config: &CompareConfig) -> bool {
+        let mut rules = other.hashing_rules.clone();
+        if config.hash_seed.is_none() {
+            rules["hashSeed"] = self.hashing_rules["hashSeed"].clone();
+        }
+        self.file_a_path == other.file_a_path && self.file_b_path == other.file_b_path && self.inputs == other.inputs && self.hashing_rules == rules
+    }
+}
+
+fn input_stamps(file_a_path: &str, file_b_path: &str) -> Result<Vec<(u64, Option<u64>)>, IoError> {
+    [file_a_path, file_b_path]
+        .iter()
+        .map(|path| file_identity(path).map(|identity| (identity.size, identity.modified_ms)))
+        .collect()
+}
+
+/// Records what `dir` holds partitions of, before any of them are written, and locks it
+/// for this process.
+pub fn write_manifest(dir: &Path, file_a_path: &str, file_b_path: &str, config: &CompareConfig) -> Result<(), IoError> {
+    fs::create_dir_all(dir)?;
+    if !claim(dir)? {
+        return Err(IoError::other(format!("{} is in use by another comparison", dir.display())));
+    }
+    let manifest = Manifest::new(file_a_path, file_b_path, config)?;
+    let temp_path = dir.join(format!("{}.tmp", MANIFEST_FILE));
+    fs::write(&temp_path, serde_json::to_vec(&manifest)?)?;
+    fs::rename(temp_path, dir.join(MANIFEST_FILE))
+}
+
+/// The temp directory an interrupted run of the same comparison left behind, with the manifest
+/// it was started with. Directories of runs still going, or kept for a re-run, are locked
+/// and skipped.
+fn find(file_a_path: &str, file_b_path: &str, config: &CompareConfig) -> Option<(PathBuf, Manifest)> {
+    if !config.resume_interrupted {
+        return None;
+    }
+    let wanted = Manifest::new(file_a_path, file_b_path, config).ok()?;
+    fs::read_dir(config.temp_root())
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(TEMP_DIR_PREFIX))
+        .find_map(|entry| {
+            let manifest: Manifest = serde_json::from_slice(&fs::read(entry.path().join(MANIFEST_FILE)).ok()?).ok()?;
+            (manifest.matches(&wanted, config) && !is_locked(&entry.path())).then(|| (entry.path(), manifest))
+        })
+}
+
+/// The temp directory of an interrupted run of the same comparison, if there is one to
+/// resume, locked for this process.
+pub fn find_checkpoint(file_a_path: &str, file_b_path: &str, config: &CompareConfig) -> Option<PathBuf> {
+    let (dir, _) = find(file_a_path, file_b_path, config)?;
+    // Another process may have taken it since it was found.
+    claim(&dir).ok()?.then_some(dir)
+}
+
+/// Takes over the hash seed of an interrupted run of the same comparison, so its partitions
+/// can be resumed. Call before the config is seeded.
+pub fn adopt_seed(config: &mut CompareConfig, file_a_path: &str, file_b_path: &str) {
+    if config.hash_seed.is_none()
+        && let Some((_, manifest)) = find(file_a_path, file_b_path, config)
+    {
+        config.hash_seed = manifest.hashing_rules["hashSeed"].as_u64();
+    }
+}
+
+/// Whether every partition in `dir` was written in full and marked complete.
+fn is_complete(dir: &Path, partitions: u64) -> bool {
+    dir.join(COMPLETE_MARKER).exists() && (0..partitions).all(|i| is_finished(&dir.join(format!("part_{}", i))))
+}
+
+/// Partitions one file unless an interrupted run or the file cache already did, then marks
+/// its partitions complete so a later resume skips it.
+pub fn partition_file_once<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    input_path: &str,
+    output_dir: &Path,
+    file_id: &str,
+    config: &CompareConfig,
+    header_layout: Option<&HeaderLayout>,
+) -> Result<Option<PathBuf>, IoError> {
+    let cached = !is_complete(output_dir, config.partitions())
+        && file_cache::restore(job, input_path, output_dir, file_id, config);
+    if is_complete(output_dir, config.partitions()) {
+        let step = if cached { "Reused cached partitions of the unchanged file" } else { "Reused partitions of the interrupted run" };
+        emit_step_detail(sink, job, file_id, step, 0);
+        job.progress.file_progress(sink, file_id, 1.0, "Partitioned");
+        let index = output_dir.join(NEWLINE_INDEX_FILE);
+        let wanted = !config.ignore_line_number && !config.on_demand_line_numbers;
+        return Ok((wanted && index.exists()).then_some(index));
+    }
+    let index = partition_file(sink, job, input_path, output_dir, file_id, config, header_layout)?;
+    fs::create_dir_all(output_dir)?;
+    fs::write(output_dir.join(COMPLETE_MARKER), b"")?;
+    if let Err(e) = file_cache::store(job, input_path, output_dir, file_id, config) {
+        eprintln!("Failed to cache partitions of {}: {}", input_path, e);
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unseeded_config_matches_any_seed() {
+        let path = std::env::temp_dir().join(format!("checkpoint_test_{}", std::process::id()));
+        fs::write(&path, "line\n").unwrap();
+        let path = path.to_str().unwrap();
+        let mut config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": true,
+            "ignoreOccurences": false,
+            "useSingleThread": false,
+            "ignoreLineNumber": false,
+            "hashSeed": 42,
+        }))
+        .unwrap();
+        let stored = Manifest::new(path, path, &config).unwrap();
+
+        config.hash_seed = None;
+        assert!(stored.matches(&Manifest::new(path, path, &config).unwrap(), &config));
+        config.hash_seed = Some(7);
+        assert!(!stored.matches(&Manifest::new(path, path, &config).unwrap(), &config));
+        config.hash_seed = None;
+        config.partition_count = Some(3);
+        assert!(!stored.matches(&Manifest::new(path, path, &config).unwrap(), &config));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_locked_directories_are_not_claimed_twice() {
+        let dir = std::env::temp_dir().join(format!("checkpoint_lock_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(!is_locked(&dir));
+        assert!(claim(&dir).unwrap());
+        let other = File::open(dir.join(LOCK_FILE)).unwrap();
+        assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+        assert!(is_locked(&dir));
+        release(&dir);
+        assert!(!is_locked(&dir));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use crate::collisions::CollisionCheck;
+use crate::external::aggregation::aggregate_partitions;
+use crate::external::disk_space::check_temp_space;
+use crate::external::checkpoint::{find_checkpoint, partition_file_once, write_manifest};
+use crate::external::file_processing::{collect_unique_lines, is_storage_full};
+use crate::external::multi_file::TempDir;
+use crate::external::partition_cache::PartitionCache;
+use crate::delimiter::check_delimiters;
+use crate::header::resolve_header_layout;
+use crate::internal::comparison_in_memory;
+use crate::jobs::Job;
+use crate::memory_budget;
+use crate::payloads::ComparisonSummary;
+use crate::results::collected::{finish_comparison, report_differences};
+use crate::results::duplicates::report_duplicates;
+use crate::results::frequency::report_frequencies;
+use crate::sink::ProgressSink;
+use crate::utils::emit_step;
+use crate::CompareConfig;
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::Arc;
+use std::thread;
+
+pub fn run_comparison<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    file_a_path: String,
+    file_b_path: String,
+    mut compare_config: CompareConfig,
+) -> Result<(), IoError> {
+    let start_time = std::time::Instant::now();
+    let temp_dir = match find_checkpoint(&file_a_path, &file_b_path, &compare_config) {
+        Some(dir) => {
+            emit_step(&sink, &job, format!("Resuming the interrupted run in {}", dir.display()), 0);
+            write_manifest(&dir, &file_a_path, &file_b_path, &compare_config)?;
+            TempDir(dir)
+        }
+        None => {
+            check_temp_space(&job, &file_a_path, &file_b_path, &mut compare_config)?;
+            let temp_dir = TempDir::new(&job);
+            write_manifest(&temp_dir.0, &file_a_path, &file_b_path, &compare_config)?;
+            temp_dir
+        }
+    };
+    emit_step(&sink, &job, format!("Temp directory: {}", temp_dir.0.display()), 0);
+
+    let result = partition_both(&sink, &job, temp_dir, &file_a_path, &file_b_path, &compare_config)
+        .and_then(|cache| analyze(&sink, &job, &cache, &compare_config).map(|_| cache));
+    emit_step(&sink, &job, "Total".to_string(), start_time.elapsed().as_millis());
+
+    match result {
+        // Keep the partitions so the job can be re-run with other settings.
+        Ok(cache) => {
+            job.keep_partitions(cache);
+            Ok(())
+        }
+        // Partitions no longer fit on the temp disk: redo the run without temp files, but
+        // only when the in-memory engine fits the memory budget; otherwise it would run out
+        // of memory instead, so the run fails with the disk to free up.
+        Err(e) if is_storage_full(&e) && !job.is_cancelled() => {
+            if memory_budget::exceeded(&file_a_path, &file_b_path, &compare_config)?.is_some() {
+                return Err(IoError::new(
+                    ErrorKind::StorageFull,
+                    format!(
+                        "Temp disk full during partitioning ({}), and the files are too large to compare in memory. Free up space in {} or choose a temp directory on a larger disk.",
+                        e,
+                        compare_config.temp_root().display()
+                    ),
+                ));
+            }
+            job.record_degradation(&format!(
+                "Temp disk full during partitioning ({}); switched to the in-memory engine",
+                e
+            ));
+            comparison_in_memory::run_comparison(sink, job, file_a_path, file_b_path, compare_config)
+        }
+        // Dropping the temp directory with the failed partitions cleans it up in the background.
+        Err(e) => Err(e),
+    }
+}
+
+/// Re-runs the analysis on the partitions of an earlier run, skipping partitioning entirely.
+pub fn rerun_from_cache<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    cache: PartitionCache,
+    compare_config: CompareConfig,
+) -> Result<(), IoError> {
+    emit_step(&sink, &job, "Reused partitions of the previous run".to_string(), 0);
+    analyze(&sink, &job, &cache, &compare_config)?;
+    job.keep_partitions(cache);
+    Ok(())
+}
+
+fn partition_both<S: ProgressSink>(
+    sink: &S,
+    job: &Arc<Job>,
+    temp_dir: TempDir,
+    file_a_path: &str,
+    file_b_path: &str,
+    compare_config: &CompareConfig,
+) -> Result<PartitionCache, IoError> {
+    let temp_dir_a = temp_dir.0.join("a");
+    let temp_dir_b = temp_dir.0.join("b");
+
+    let header_layout = resolve_header_layout(file_a_path, file_b_path, compare_config)?.map(Arc::new);
+    let header_layout_a = header_layout.clone();
+    let header_layout_b = header_layout.clone();
+
+    let sink_a = sink.clone();
+    let job_a = job.clone();
+    let path_a_clone = file_a_path.to_string();
+    let temp_dir_a_clone = temp_dir_a.clone();
+    let config_a_clone = compare_config.clone();
+
+    let sink_b = sink.clone();
+    let job_b = job.clone();
+    let path_b_clone = file_b_path.to_string();
+    let temp_dir_b_clone = temp_dir_b.clone();
+    let config_b_clone = compare_config.clone();
+
+    let (nl_path_a, nl_path_b) = if compare_config.use_single_thread {
+        let path_a = partition_file_once(
+            &sink_a,
+            &job_a,
+            &path_a_clone,
+            &temp_dir_a_clone,
+            "A",
+            compare_config,
+            header_layout_a.as_deref(),
+        )?;
+        let path_b = partition_file_once(
+            &sink_b,
+            &job_b,
+            &path_b_clone,
+            &temp_dir_b_clone,
+            "B",
+            compare_config,
+            header_layout_b.as_deref(),
+        )?;
+        (path_a, path_b)
+    } else {
+        let handle_a_thread = thread::spawn(move || {
+            partition_file_once(
+                &sink_a,
+                &job_a,
+                &path_a_clone,
+                &temp_dir_a_clone,
+                "A",
+                &config_a_clone,
+                header_layout_a.as_deref(),
+            )
+        });
+        let handle_b_thread = thread::spawn(move || {
+            partition_file_once(
+                &sink_b,
+                &job_b,
+                &path_b_clone,
+                &temp_dir_b_clone,
+                "B",
+                &config_b_clone,
+                header_layout_b.as_deref(),
+            )
+        });
+        let path_a = handle_a_thread.join().unwrap()?;
+        let path_b = handle_b_thread.join().unwrap()?;
+        (path_a, path_b)
+    };
+
+    Ok(PartitionCache::new(
+        temp_dir,
+        file_a_path.to_string(),
+        file_b_path.to_string(),
+        (nl_path_a, nl_path_b),
+        header_layout,
+        compare_config,
+    ))
+}
+
+fn analyze<S: ProgressSink>(
+    sink: &S,
+    job: &Arc<Job>,
+    cache: &PartitionCache,
+    compare_config: &CompareConfig,
+) -> Result<(), IoError> {
+    let file_a_path = cache.file_a_path.clone();
+    let file_b_path = cache.file_b_path.clone();
+    let header_layout = cache.header_layout.clone();
+    let mut summary = ComparisonSummary {
+        header_mismatch: header_layout.as_ref().and_then(|h| h.mismatch.clone()),
+        delimiter_mismatch: check_delimiters(&file_a_path, &file_b_path, compare_config)?,
+        ..Default::default()
+    };
+    if let Some(mismatch) = &summary.delimiter_mismatch {
+        sink.emit("delimiter_mismatch", mismatch);
+    }
+
+    job.progress.shared_progress(sink, 0.0, 50.0, "A", "Aggregating partitions...");
+
+    let now = std::time::Instant::now();
+    let (temp_dir_a, temp_dir_b) = (cache.temp_dir.0.join("a"), cache.temp_dir.0.join("b"));
+    let collision_check = CollisionCheck::open(
+        &file_a_path,
+        &file_b_path,
+        compare_config,
+        header_layout.as_deref(),
+        [job.strips_cr("A"), job.strips_cr("B")],
+    )?;
+    let aggregation =
+        aggregate_partitions(sink, job, &temp_dir_a, &temp_dir_b, compare_config, collision_check.as_ref())?;
+    drop(collision_check);
+    summary.hash_collisions = aggregation.collisions;
+    summary.stopped_early = aggregation.stopped_early;
+    let unique_to_a = aggregation.unique_to_a;
+    let unique_to_b = aggregation.unique_to_b;
+    let aggregation_ms = now.elapsed().as_millis();
+    emit_step(sink, job, "Partition Aggregation".to_string(), aggregation_ms);
+
+    if compare_config.report_duplicates {
+        summary.duplicates =
+            report_duplicates(sink, job, &file_a_path, &file_b_path, aggregation.duplicates, compare_config)?;
+    }
+    if let Some(counters) = aggregation.frequencies {
+        summary.frequencies = report_frequencies(sink, &file_a_path, &file_b_path, counters, compare_config)?;
+    }
+
+    let sink_a_collect = sink.clone();
+    let job_a_collect = job.clone();
+    let config_for_a = compare_config.clone();
+    let nl_path_a = cache.nl_path_a.clone();
+    let nl_path_b = cache.nl_path_b.clone();
+    let handle_collect_a = thread::spawn(move || {
+        collect_unique_lines(
+            &sink_a_collect,
+            &job_a_collect,
+            &file_a_path,
+            &unique_to_a,
+            nl_path_a.as_ref(),
+            &config_for_a,
+            "A",
+        )
+    });
+
+    let sink_b_collect = sink.clone();
+    let job_b_collect = job.clone();
+    let config_for_b = compare_config.clone();
+    let handle_collect_b = thread::spawn(move || {
+        collect_unique_lines(
+            &sink_b_collect,
+            &job_b_collect,
+            &file_b_path,
+            &unique_to_b,
+            nl_path_b.as_ref(),
+            &config_for_b,
+            "B",
+        )
+    });
+
+    handle_collect_a.join().unwrap()?;
+    handle_collect_b.join().unwrap()?;
+    job.check_cancelled()?;
+    report_differences(sink, job, compare_config, header_layout.as_ref(), &mut summary)?;
+    job.progress.shared_progress(sink, 1.0, 100.0, "B", "Comparison Finished");
+    finish_comparison(sink, job, summary);
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use crate::external::spill::{FOOTER_LEN, RECORD_LEN};
+use crate::jobs::Job;
+use crate::memory_budget::estimate_line_count;
+use crate::CompareConfig;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::Path;
+use sysinfo::Disks;
+
+const MIB: u64 = 1024 * 1024;
+
+/// Free bytes on the volume holding `dir`, or `None` when it can't be told.
+fn available_space(dir: &Path) -> Option<u64> {
+    let dir = dir.canonicalize().ok()?;
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| dir.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+/// Temp bytes partitioning the files needs: the partitions, and separately the newline indexes.
+fn needed_space(lines: u64, files: u64, config: &CompareConfig) -> (u64, u64) {
+    let partitions = lines.saturating_mul(RECORD_LEN) + files * config.partitions() * FOOTER_LEN;
+    let indexes = if config.ignore_line_number || config.on_demand_line_numbers {
+        0
+    } else {
+        lines.saturating_mul(size_of::<usize>() as u64)
+    };
+    (partitions, indexes)
+}
+
+/// Checks before partitioning that the temp volume has room for the partitions of both files.
+/// Without room for the newline indexes as well, line numbers are counted on demand instead.
+pub fn check_temp_space(job: &Job, file_a_path: &str, file_b_path: &str, config: &mut CompareConfig) -> Result<(), IoError> {
+    let temp_root = config.temp_root();
+    let Some(available) = available_space(&temp_root) else {
+        return Ok(());
+    };
+    let terminator = config.terminator();
+    let lines = estimate_line_count(file_a_path, terminator)?.saturating_add(estimate_line_count(file_b_path, terminator)?);
+    let (partitions, indexes) = needed_space(lines, 2, config);
+    if partitions > available {
+        return Err(IoError::new(
+            ErrorKind::StorageFull,
+            format!(
+                "Not enough space in temp directory {}: partitioning needs about {} MiB but only {} MiB are free. Choose a temp directory on a larger disk.",
+                temp_root.display(),
+                partitions.div_ceil(MIB),
+                available / MIB
+            ),
+        ));
+    }
+    if partitions + indexes > available {
+        job.record_degradation(&format!(
+            "Temp directory {} has no room for the newline indexes; line numbers were counted on demand",
+            temp_root.display()
+        ));
+        config.on_demand_line_numbers = true;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newline_indexes_are_only_counted_when_written() {
+        let mut config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": true,
+            "ignoreOccurences": false,
+            "useSingleThread": false,
+            "ignoreLineNumber": false,
+            "onDemandLineNumbers": false,
+            "partitionCount": 4,
+        }))
+        .unwrap();
+        assert_eq!(needed_space(1000, 2, &config), (16_000 + 8 * 24, 8000));
+        config.on_demand_line_numbers = true;
+        assert_eq!(needed_space(1000, 2, &config).1, 0);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::config::CompareConfig;
+use crate::external::file_processing::NEWLINE_INDEX_FILE;
+use crate::identity::{canonical_path, file_identity};
+use crate::jobs::Job;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{Error as IoError, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Directory under the temp root holding one entry per cached input.
+const CACHE_DIR: &str = "lfc_file_cache";
+const ENTRY_FILE: &str = "entry.json";
+/// Entries kept at most; the least recently used ones are removed first.
+const MAX_ENTRIES: usize = 8;
+/// Bytes read at the start, middle and end of a file for its content sample.
+const SAMPLE_BLOCK: u64 = 64 * 1024;
+
+/// What a cache entry holds the partitions of. The partitions are only reused while every
+/// field still matches the file and the config of the new run.
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    path: String,
+    /// Side the file was partitioned as, which per-file settings depend on.
+    file_id: String,
+    size: u64,
+    modified_ms: Option<u64>,
+    /// Hash of a few blocks of the file, catching rewrites that kept size and modification time.
+    sample: String,
+    hashing_rules: Value,
+}
+
+impl Entry {
+    fn new(path: &str, file_id: &str, config: &CompareConfig) -> Result<Self, IoError> {
+        let identity = file_identity(path)?;
+        Ok(Self {
+            path: identity.canonical_path,
+            file_id: file_id.to_string(),
+            size: identity.size,
+            modified_ms: identity.modified_ms,
+            sample: content_sample(path, identity.size)?,
+            hashing_rules: config.hashing_rules(),
+        })
+    }
+}
+
+fn content_sample(path: &str, size: u64) -> Result<String, IoError> {
+    let mut file = File::open(path)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut block = vec![0u8; SAMPLE_BLOCK as usize];
+    for offset in [0, size.saturating_sub(SAMPLE_BLOCK) / 2, size.saturating_sub(SAMPLE_BLOCK)] {
+        file.seek(SeekFrom::Start(offset))?;
+        let read = file.read(&mut block)?;
+        hasher.update(&block[..read]);
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn entry_dir(path: &str, file_id: &str, config: &CompareConfig) -> PathBuf {
+    let key = blake3::hash(format!("{}\0{}", canonical_path(path), file_id).as_bytes());
+    config.temp_root().join(CACHE_DIR).join(&key.to_hex()[..32])
+}
+
+/// Whether the partitions of `input_path` may be shared with other runs. Header layouts
+/// depend on the counterpart, and local copies are gone once their job is.
+fn cacheable(job: &Job, input_path: &str, config: &CompareConfig) -> bool {
+    config.reuse_file_hashes && !config.has_header() && !job.is_local_copy(input_path)
+}
+
+fn read_entry(dir: &Path) -> Option<Entry> {
+    serde_json::from_slice(&fs::read(dir.join(ENTRY_FILE)).ok()?).ok()
+}
+
+/// Hard-links every file of `from` into `to`, replacing files already there. Falls back to
+/// copying where the two directories are on different file systems.
+fn link_files(from: &Path, to: &Path) -> Result<(), IoError> {
+    fs::create_dir_all(to)?;
+    for file in fs::read_dir(from)? {
+        let file = file?;
+        if file.file_name() == ENTRY_FILE || !file.file_type()?.is_file() {
+            continue;
+        }
+        let target = to.join(file.file_name());
+        if target.exists() {
+            fs::remove_file(&target)?;
+        }
+        if fs::hard_link(file.path(), &target).is_err() {
+            fs::copy(file.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Fills `output_dir` with the cached partitions of `input_path` if the file hasn't changed
+/// since they were written. Returns whether it did.
+pub fn restore(job: &Job, input_path: &str, output_dir: &Path, file_id: &str, config: &CompareConfig) -> bool {
+    if !cacheable(job, input_path, config) {
+        return false;
+    }
+    let dir = entry_dir(input_path, file_id, config);
+    let (Some(cached), Ok(current)) = (read_entry(&dir), Entry::new(input_path, file_id, config)) else {
+        return false;
+    };
+    // Runs that didn't need line numbers cached no newline index.
+    let wants_index = !config.ignore_line_number && !config.on_demand_line_numbers;
+    if cached != current || (wants_index && !dir.join(NEWLINE_INDEX_FILE).exists()) {
+        return false;
+    }
+    match link_files(&dir, output_dir) {
+        Ok(()) => {
+            // Marks the entry as recently used.
+            let _ = File::options().write(true).open(dir.join(ENTRY_FILE)).and_then(|f| f.set_modified(SystemTime::now()));
+            true
+        }
+        Err(e) => {
+            eprintln!("Failed to restore cached partitions of {}: {}", input_path, e);
+            false
+        }
+    }
+}
+
+/// Keeps the finished partitions in `output_dir` for later runs on the same file, then trims
+/// the cache to its most recently used entries.
+pub fn store(job: &Job, input_path: &str, output_dir: &Path, file_id: &str, config: &CompareConfig) -> Result<(), IoError> {
+    if !cacheable(job, input_path, config) {
+        return Ok(());
+    }
+    let dir = entry_dir(input_path, file_id, config);
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    let entry = Entry::new(input_path, file_id, config)?;
+    link_files(output_dir, &dir)?;
+    fs::write(dir.join(ENTRY_FILE), serde_json::to_vec(&entry)?)?;
+    evict(&config.temp_root().join(CACHE_DIR));
+    Ok(())
+}
+
+fn evict(cache_dir: &Path) {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return;
+    };
+    let mut entries: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| {
+            let used = fs::metadata(entry.path().join(ENTRY_FILE)).and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+            (used, entry.path())
+        })
+        .collect();
+    entries.sort_by_key(|(used, _)| std::cmp::Reverse(*used));
+    for (_, dir) in entries.into_iter().skip(MAX_ENTRIES) {
+        if let Err(e) = fs::remove_dir_all(&dir) {
+            eprintln!("Failed to remove cached partitions {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// Takes over the hash seed of cached partitions of either input, so they can be reused.
+/// Call before the config is seeded.
+pub fn adopt_seed(config: &mut CompareConfig, file_a_path: &str, file_b_path: &str) {
+    if config.hash_seed.is_some() || !config.reuse_file_hashes || config.has_header() {
+        return;
+    }
+    for (path, file_id) in [(file_a_path, "A"), (file_b_path, "B")] {
+        let Some(cached) = read_entry(&entry_dir(path, file_id, config)) else {
+            continue;
+        };
+        let mut seeded = config.clone();
+        seeded.hash_seed = cached.hashing_rules["hashSeed"].as_u64();
+        if Entry::new(path, file_id, &seeded).is_ok_and(|current| current == cached) {
+            config.hash_seed = seeded.hash_seed;
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_sample_covers_start_middle_and_end() {
+        let path = std::env::temp_dir().join(format!("file_cache_test_{}", std::process::id()));
+        let mut data = vec![b'x'; 3 * SAMPLE_BLOCK as usize];
+        fs::write(&path, &data).unwrap();
+        let path_str = path.to_str().unwrap();
+        let original = content_sample(path_str, data.len() as u64).unwrap();
+
+        for offset in [0, data.len() / 2, data.len() - 1] {
+            data[offset] = b'y';
+            fs::write(&path, &data).unwrap();
+            assert_ne!(content_sample(path_str, data.len() as u64).unwrap(), original);
+            data[offset] = b'x';
+        }
+        fs::write(&path, &data).unwrap();
+        assert_eq!(content_sample(path_str, data.len() as u64).unwrap(), original);
+        fs::remove_file(path).unwrap();
+    }
+}
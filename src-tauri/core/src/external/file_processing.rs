@@ -0,0 +1,265 @@
+use crate::results::collected::CollectedLine;
+use crate::external::line_numbers::{line_numbers_for_offsets, newline_index, write_newline_index};
+use crate::external::spill::{HashOffset, PartitionWriter};
+use crate::external::streaming::{line_numbers_streaming, open_records, partition_streaming};
+use crate::blank_lines::LineClassCounter;
+use crate::fields::{find_record_ends, record_len, RecordHasher};
+use crate::header::HeaderLayout;
+use crate::jobs::Job;
+use crate::progress::{FileCounter, SCAN_SHARE};
+use crate::records::find_line_ends;
+use crate::sink::ProgressSink;
+use crate::utils::emit_step_detail;
+use crate::CompareConfig;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::fs::File;
+use std::borrow::Cow;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Name of the newline index written next to a file's partitions.
+pub const NEWLINE_INDEX_FILE: &str = "newline_positions.bin";
+
+/// How many lines are processed between two cancellation checks.
+const CANCEL_CHECK_INTERVAL: usize = 1 << 16;
+
+pub fn partition_file<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    input_path: &str,
+    output_dir: &Path,
+    progress_file_id: &str,
+    compare_config: &CompareConfig,
+    header_layout: Option<&HeaderLayout>,
+) -> Result<Option<PathBuf>, IoError> {
+    let total_start = Instant::now();
+    emit_step_detail(sink, job, progress_file_id, "Partitioning Started", 0);
+
+    let file = File::open(input_path)?;
+    let file_size = file.metadata()?.len();
+    if file_size == 0 {
+        return Ok(None);
+    }
+    std::fs::create_dir_all(output_dir)?;
+    let mapped = if compare_config.streaming {
+        None
+    } else {
+        unsafe { Mmap::map(&file) }
+            .inspect_err(|e| {
+                job.record_degradation(&format!(
+                    "File {} can't be memory-mapped ({}); read it as a stream instead",
+                    progress_file_id, e
+                ))
+            })
+            .ok()
+    };
+    let mmap = match mapped {
+        Some(mmap) => mmap,
+        None => {
+            let now = Instant::now();
+            let newline_positions =
+                partition_streaming(sink, job, input_path, output_dir, progress_file_id, compare_config, header_layout)?;
+            emit_step_detail(sink, job, progress_file_id, "Streamed and Wrote Partitions", now.elapsed().as_millis());
+            emit_step_detail(sink, job, progress_file_id, "Total Partitioning Time", total_start.elapsed().as_millis());
+            return save_newline_index(job, output_dir, progress_file_id, compare_config, &newline_positions);
+        }
+    };
+
+    let now = Instant::now();
+    let counter = FileCounter::new(sink, &job.progress, progress_file_id, "Scanning...", file_size, (0.0, SCAN_SHARE));
+    let newline_positions = match compare_config.record_quote() {
+        Some(quote) => find_record_ends(&mmap, compare_config.terminator(), quote),
+        None => find_line_ends(&mmap, compare_config.terminator(), compare_config.scan_chunk(), |len| counter.add(len as u64)),
+    };
+    emit_step_detail(sink, job, progress_file_id, "Found Newlines", now.elapsed().as_millis());
+
+    let now = Instant::now();
+    let partitions = compare_config.partitions();
+    let writers: Vec<_> = (0..partitions)
+        .map(|i| PartitionWriter::create(&output_dir.join(format!("part_{}", i))).map(Mutex::new))
+        .collect::<Result<Vec<_>, IoError>>()?;
+
+    let record_hasher = RecordHasher::new(compare_config, header_layout, progress_file_id, job.strips_cr(progress_file_id))?;
+    let first_record = if compare_config.has_header() { 1 } else { 0 };
+    let line_classes = LineClassCounter::default();
+    let lines = newline_positions.len() as u64;
+    let counter = FileCounter::new(sink, &job.progress, progress_file_id, "Partitioning...", lines, (SCAN_SHARE, 1.0));
+    (first_record..newline_positions.len())
+        .into_par_iter()
+        .try_for_each(|i| -> Result<(), IoError> {
+            if i % CANCEL_CHECK_INTERVAL == 0 {
+                job.check_cancelled()?;
+                counter.add(CANCEL_CHECK_INTERVAL as u64);
+            }
+            let start = if i == 0 { 0 } else { newline_positions[i - 1] + 1 };
+            let end = newline_positions[i];
+            let line_bytes = &mmap[start..end];
+            let line_bytes_cleaned = record_hasher.content(line_bytes);
+
+            if !line_classes.skip(line_bytes_cleaned, compare_config) {
+                let hash = record_hasher.hash(line_bytes_cleaned);
+                let offset = start as u64;
+                let partition_index = (hash % partitions) as usize;
+
+                writers[partition_index].lock().unwrap().write(HashOffset(hash, offset))?;
+            }
+            Ok(())
+        })?;
+    for writer in writers {
+        writer.into_inner().unwrap().finish()?;
+    }
+    line_classes.record(job, progress_file_id);
+    job.progress.file_progress(sink, progress_file_id, 1.0, "Partitioned");
+    emit_step_detail(
+        sink,
+        job,
+        progress_file_id,
+        "Hashing and Writing Partitions",
+        now.elapsed().as_millis(),
+    );
+
+    emit_step_detail(
+        sink,
+        job,
+        progress_file_id,
+        "Total Partitioning Time",
+        total_start.elapsed().as_millis(),
+    );
+
+    save_newline_index(job, output_dir, progress_file_id, compare_config, &newline_positions)
+}
+
+/// Writes the newline index used to number the reported lines, unless line numbers are
+/// ignored or counted on demand. Returns its path.
+fn save_newline_index(
+    job: &Job,
+    output_dir: &Path,
+    progress_file_id: &str,
+    compare_config: &CompareConfig,
+    newline_positions: &[usize],
+) -> Result<Option<PathBuf>, IoError> {
+    if compare_config.ignore_line_number || compare_config.on_demand_line_numbers {
+        return Ok(None);
+    }
+    let nl_path = output_dir.join(NEWLINE_INDEX_FILE);
+    match write_newline_index(&nl_path, newline_positions) {
+        Ok(()) => Ok(Some(nl_path)),
+        Err(e) if is_storage_full(&e) => {
+            // Line numbers are then counted on demand for the reported lines only.
+            let _ = std::fs::remove_file(&nl_path);
+            job.record_degradation(&format!(
+                "Temp disk full while writing the newline index of file {}; line numbers were counted on demand",
+                progress_file_id
+            ));
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+pub fn is_storage_full(e: &IoError) -> bool {
+    e.kind() == ErrorKind::StorageFull
+}
+
+pub fn collect_unique_lines<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    file_path: &str,
+    unique_offsets: &[(u64, usize)],
+    newline_positions_path: Option<&PathBuf>,
+    compare_config: &CompareConfig,
+    file_id: &str,
+) -> Result<(), IoError> {
+    let now = Instant::now();
+    if unique_offsets.is_empty() {
+        return Ok(())
+    }
+
+    let file = File::open(file_path)?;
+    let file_size = file.metadata()?.len();
+    // Inputs that can't be mapped were partitioned by streaming and are read that way too.
+    let mmap = if compare_config.streaming { None } else { unsafe { Mmap::map(&file) }.ok() };
+    // Its own handle: a clone would share the cursor that counting line numbers moves below.
+    let mut records = open_records(file_path, compare_config)?;
+    let mut record = Vec::new();
+
+    let mut sorted_unique_offsets = unique_offsets.to_vec();
+    sorted_unique_offsets.sort_unstable_by_key(|k| k.0);
+
+    let nl_mmap_handle;
+    let mut nl_positions_slice: &[usize] = &[];
+
+    if !compare_config.ignore_line_number
+        && let Some(path) = newline_positions_path
+    {
+        let nl_file = File::open(path)?;
+        nl_mmap_handle = unsafe { Mmap::map(&nl_file)? };
+        nl_positions_slice = newline_index(&nl_mmap_handle)?;
+    }
+
+    // Without a persisted index (on-demand mode, or it didn't fit on disk) count the numbers now.
+    let targeted_line_numbers = if !compare_config.ignore_line_number && newline_positions_path.is_none() {
+        let offsets: Vec<usize> = sorted_unique_offsets.iter().map(|(o, _)| *o as usize).collect();
+        let now = Instant::now();
+        let numbers = match &mmap {
+            Some(mmap) => line_numbers_for_offsets(mmap, &offsets, compare_config.terminator(), compare_config.record_quote()),
+            None => line_numbers_streaming(file_path, &offsets, compare_config)?,
+        };
+        emit_step_detail(sink, job, file_id, "Counted Line Numbers", now.elapsed().as_millis());
+        Some(numbers)
+    } else {
+        None
+    };
+
+    for (i, (offset, count)) in sorted_unique_offsets.into_iter().enumerate() {
+        job.check_cancelled()?;
+        let current_offset = offset as usize;
+        if offset > file_size {
+            return Err(IoError::new(ErrorKind::InvalidData, format!("Temp partition offset {} is past the end of file {}", offset, file_id)));
+        }
+
+        let line_bytes = match &mmap {
+            Some(mmap) => {
+                let line_end = current_offset + record_len(&mmap[current_offset..], compare_config.terminator(), compare_config.record_quote());
+                Cow::Borrowed(&mmap[current_offset..line_end])
+            }
+            None => {
+                records.seek_to(offset)?;
+                records.next_record(&mut record)?;
+                Cow::Borrowed(record.as_slice())
+            }
+        };
+        let line_str = String::from_utf8_lossy(&line_bytes).trim_end().to_string();
+
+        let mut line_number = 0;
+        if let Some(numbers) = &targeted_line_numbers {
+            line_number = numbers[i];
+        } else if !compare_config.ignore_line_number {
+            line_number = nl_positions_slice
+                .binary_search(&current_offset)
+                .unwrap_or_else(|p| p)
+                + 1;
+        }
+
+        job.results.lock().unwrap().push_unique(
+            file_id,
+            CollectedLine {
+                line_number,
+                text: line_str,
+                count,
+            },
+        );
+    }
+
+    emit_step_detail(
+        sink,
+        job,
+        file_id,
+        "Collecting Unique Lines",
+        now.elapsed().as_millis(),
+    );
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,99 @@
+use crate::records::find_record_ends;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{BufWriter, Error as IoError, ErrorKind, Write};
+use std::path::Path;
+
+const CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Computes 1-based line numbers for a handful of record offsets without a persisted
+/// newline index: only the bytes up to the largest offset are scanned, in parallel chunks.
+/// `sorted_offsets` must be in ascending order; the result is aligned with it.
+pub fn line_numbers_for_offsets(
+    data: &[u8],
+    sorted_offsets: &[usize],
+    terminator: u8,
+    quote: Option<u8>,
+) -> Vec<usize> {
+    let Some(&max_offset) = sorted_offsets.last() else {
+        return Vec::new();
+    };
+    let scanned = &data[..max_offset.min(data.len())];
+
+    if let Some(quote) = quote {
+        // Quote state depends on everything before a position, so this scan can't be split.
+        let record_ends = find_record_ends(scanned, terminator, quote);
+        return sorted_offsets
+            .iter()
+            .map(|offset| record_ends.partition_point(|&end| end < *offset) + 1)
+            .collect();
+    }
+
+    let chunk_counts: Vec<usize> = scanned
+        .par_chunks(CHUNK_SIZE)
+        .map(|chunk| memchr::memchr_iter(terminator, chunk).count())
+        .collect();
+    let mut newlines_before_chunk = Vec::with_capacity(chunk_counts.len());
+    let mut total = 0;
+    for count in chunk_counts {
+        newlines_before_chunk.push(total);
+        total += count;
+    }
+
+    sorted_offsets
+        .par_iter()
+        .map(|&offset| {
+            let offset = offset.min(scanned.len());
+            let chunk_index = offset / CHUNK_SIZE;
+            let chunk_start = chunk_index * CHUNK_SIZE;
+            let preceding = newlines_before_chunk.get(chunk_index).copied().unwrap_or(total);
+            preceding + memchr::memchr_iter(terminator, &scanned[chunk_start..offset]).count() + 1
+        })
+        .collect()
+}
+
+/// Writes the newline positions of a file as native-endian `usize` values.
+pub fn write_newline_index(nl_path: &Path, newline_positions: &[usize]) -> Result<(), IoError> {
+    let mut nl_file = BufWriter::new(File::create(nl_path)?);
+    let positions_bytes: &[u8] = unsafe {
+        std::slice::from_raw_parts(
+            newline_positions.as_ptr() as *const u8,
+            std::mem::size_of_val(newline_positions),
+        )
+    };
+    nl_file.write_all(positions_bytes)?;
+    nl_file.flush()
+}
+
+/// Reads back an index written by `write_newline_index`, e.g. from a memory map. Fails on
+/// a misaligned buffer or a size that isn't a whole number of positions. The positions
+/// themselves aren't trusted: lookups only binary search them.
+pub fn newline_index(bytes: &[u8]) -> Result<&[usize], IoError> {
+    // Every bit pattern is a valid usize, and align_to only hands out the aligned middle.
+    let (head, positions, tail) = unsafe { bytes.align_to::<usize>() };
+    if !head.is_empty() || !tail.is_empty() {
+        return Err(IoError::new(ErrorKind::InvalidData, "Newline position file has invalid size"));
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_numbers_match_newline_index() {
+        let data = b"a\nbb\n\"q\nq\"\nccc\n";
+        assert_eq!(line_numbers_for_offsets(data, &[0, 2, 5, 12], b'\n', None), vec![1, 2, 3, 5]);
+        assert_eq!(line_numbers_for_offsets(data, &[0, 2, 5, 12], b'\n', Some(b'"')), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_newline_index_rejects_partial_positions() {
+        let positions = [3usize, 9, 27];
+        let bytes = unsafe { std::slice::from_raw_parts(positions.as_ptr() as *const u8, size_of_val(&positions[..])) };
+        assert_eq!(newline_index(bytes).unwrap(), &positions[..]);
+        assert!(newline_index(&bytes[..bytes.len() - 1]).is_err());
+        assert!(newline_index(&bytes[1..]).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::external::spill::{read_partition_into_maps, PartitionMaps};
+use crate::external::file_processing::partition_file;
+use crate::external::line_numbers::line_numbers_for_offsets;
+use crate::fields::record_len;
+use crate::jobs::Job;
+use crate::CompareConfig;
+use crate::sink::ProgressSink;
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Start of the names of the temp directories created by `TempDir`.
+pub const TEMP_DIR_PREFIX: &str = "bcomp_";
+
+/// Temp directory of a comparison over more than two files, removed in the background on drop
+/// (right away once the sink shuts down).
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new(job: &Job) -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        TempDir(job.config.temp_root().join(format!("{}{}_{}", TEMP_DIR_PREFIX, job.id, nanos)))
+    }
+}
+
+/// Checks that temp files can be created in `dir` by writing and removing a probe file.
+pub fn check_writable(dir: &Path) -> Result<(), IoError> {
+    if !dir.is_dir() {
+        return Err(IoError::new(ErrorKind::NotFound, format!("{} is not a directory", dir.display())));
+    }
+    let probe = dir.join(format!(".bcomp_probe_{}", std::process::id()));
+    fs::write(&probe, b"probe")?;
+    fs::remove_file(&probe)
+}
+
+/// Set on shutdown, when background threads would be killed before they are done.
+static REMOVE_IMMEDIATELY: AtomicBool = AtomicBool::new(false);
+
+/// Makes dropped temp directories get deleted on the dropping thread from now on.
+pub fn remove_temp_dirs_immediately() {
+    REMOVE_IMMEDIATELY.store(true, Ordering::SeqCst);
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let dir = std::mem::take(&mut self.0);
+        crate::external::checkpoint::release(&dir);
+        let remove = move || {
+            if dir.exists()
+                && let Err(e) = fs::remove_dir_all(dir)
+            {
+                eprintln!("Failed to clean up temporary directory: {}", e);
+            }
+        };
+        if REMOVE_IMMEDIATELY.load(Ordering::SeqCst) {
+            remove();
+        } else {
+            thread::spawn(remove);
+        }
+    }
+}
+
+/// Partitions every file into `temp_dir/<file id>` in parallel. Line numbers are only
+/// needed for the reported lines, so no newline indexes are written.
+pub fn partition_files<S: ProgressSink>(
+    sink: &S,
+    job: &Arc<Job>,
+    temp_dir: &Path,
+    paths: &[String],
+    file_ids: &[String],
+    compare_config: &CompareConfig,
+) -> Result<(), IoError> {
+    let mut config = compare_config.clone();
+    config.on_demand_line_numbers = true;
+    // Aggregating several files isn't reported, so partitioning fills the overall progress.
+    let sizes = paths.iter().zip(file_ids).map(|(path, file_id)| {
+        (file_id.clone(), std::fs::metadata(path).map_or(0, |m| m.len()))
+    });
+    job.progress.start(sizes, 1.0);
+    let handles: Vec<_> = paths
+        .iter()
+        .zip(file_ids)
+        .map(|(path, file_id)| {
+            let (sink, job, path, file_id, config) = (sink.clone(), job.clone(), path.clone(), file_id.clone(), config.clone());
+            let dir = temp_dir.join(&file_id);
+            thread::spawn(move || partition_file(&sink, &job, &path, &dir, &file_id, &config, None))
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap()?;
+    }
+    job.check_cancelled()
+}
+
+/// Reads partition `index` of every file and returns the maps with all hashes seen, sorted.
+pub fn read_partition_of_all(
+    temp_dir: &Path,
+    file_ids: &[String],
+    index: u64,
+) -> Result<(Vec<PartitionMaps>, Vec<u64>), IoError> {
+    let maps = file_ids
+        .iter()
+        .map(|file_id| read_partition_into_maps(temp_dir.join(file_id).join(format!("part_{}", index))))
+        .collect::<Result<Vec<_>, IoError>>()?;
+    let mut hashes: Vec<u64> = maps.iter().flat_map(|(counts, _)| counts.keys().copied()).collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    Ok((maps, hashes))
+}
+
+/// Reads the records starting at `sorted_offsets` with their 1-based line numbers
+/// (0 when line numbers are ignored).
+pub fn read_records_at(
+    path: &str,
+    sorted_offsets: &[usize],
+    compare_config: &CompareConfig,
+) -> Result<Vec<(usize, String)>, IoError> {
+    if sorted_offsets.is_empty() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    let (terminator, quote) = (compare_config.terminator(), compare_config.record_quote());
+    let line_numbers = if compare_config.ignore_line_number {
+        vec![0; sorted_offsets.len()]
+    } else {
+        line_numbers_for_offsets(&mmap, sorted_offsets, terminator, quote)
+    };
+    Ok(sorted_offsets
+        .iter()
+        .zip(line_numbers)
+        .map(|(&start, line_number)| {
+            let end = start + record_len(&mmap[start..], terminator, quote);
+            (line_number, String::from_utf8_lossy(&mmap[start..end]).trim_end().to_string())
+        })
+        .collect())
+}
@@ -0,0 +1,102 @@
+use crate::event_limit::emit_bounded;
+use crate::external::multi_file::{partition_files, read_partition_of_all, read_records_at, TempDir};
+use crate::input::prepare_input;
+use crate::jobs::Job;
+use crate::payloads::{MultiWayFinishedPayload, MultiWayLine};
+use crate::results::store::ResultStatus;
+use crate::CompareConfig;
+use crate::sink::ProgressSink;
+use rayon::prelude::*;
+use std::io::Error as IoError;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A line whose counts differ between the files, before its text is read.
+struct DifferingOffset {
+    file: usize,
+    offset: u64,
+    counts: Vec<usize>,
+}
+
+fn aggregate_multi_way(
+    job: &Job,
+    temp_dir: &Path,
+    file_ids: &[String],
+    compare_config: &CompareConfig,
+) -> Result<Vec<DifferingOffset>, IoError> {
+    (0..compare_config.partitions())
+        .into_par_iter()
+        .map(|i| -> Result<Vec<DifferingOffset>, IoError> {
+            job.check_cancelled()?;
+            let (maps, hashes) = read_partition_of_all(temp_dir, file_ids, i)?;
+            let mut differing = Vec::new();
+            for hash in hashes {
+                let counts: Vec<usize> = maps
+                    .iter()
+                    .map(|(counts, _)| counts.get(&hash).copied().unwrap_or(0))
+                    .map(|count| if compare_config.ignore_occurences { count.min(1) } else { count })
+                    .collect();
+                if counts.iter().all(|&count| count == counts[0]) {
+                    continue;
+                }
+                // Text and line number are taken from the first file containing the line.
+                let Some(file) = counts.iter().position(|&count| count > 0) else {
+                    continue;
+                };
+                if let Some(&offset) = maps[file].1.get(&hash) {
+                    differing.push(DifferingOffset { file, offset, counts });
+                }
+            }
+            Ok(differing)
+        })
+        .try_reduce(Vec::new, |mut a, b| {
+            a.extend(b);
+            Ok(a)
+        })
+}
+
+/// Compares any number of files and reports, for each line whose counts aren't the same
+/// everywhere, how often it occurs in every file. Lines present equally in all files are skipped.
+pub fn run_multi_way<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    paths: Vec<String>,
+    compare_config: CompareConfig,
+) -> Result<(), IoError> {
+    let temp_dir = TempDir::new(&job);
+    let file_ids: Vec<String> = (1..=paths.len()).map(|i| format!("F{}", i)).collect();
+    let paths = paths
+        .iter()
+        .zip(&file_ids)
+        .map(|(path, file_id)| prepare_input(&sink, &job, path, file_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    partition_files(&sink, &job, &temp_dir.0, &paths, &file_ids, &compare_config)?;
+
+    let differing = aggregate_multi_way(&job, &temp_dir.0, &file_ids, &compare_config)?;
+    let mut lines = Vec::with_capacity(differing.len());
+    for (file, path) in paths.iter().enumerate() {
+        let mut file_lines: Vec<&DifferingOffset> = differing.iter().filter(|d| d.file == file).collect();
+        file_lines.sort_unstable_by_key(|d| d.offset);
+        let offsets: Vec<usize> = file_lines.iter().map(|d| d.offset as usize).collect();
+        let records = read_records_at(path, &offsets, &compare_config)?;
+        lines.extend(file_lines.iter().zip(records).map(|(d, (line_number, text))| MultiWayLine {
+            file,
+            line_number,
+            text,
+            counts: d.counts.clone(),
+        }));
+    }
+    job.check_cancelled()?;
+
+    for line in &lines {
+        emit_bounded(&sink, "multi_way_line", line, compare_config.max_event_bytes);
+    }
+    let payload = MultiWayFinishedPayload { files: paths.len(), differing_lines: lines.len() };
+    {
+        let mut store = job.results.lock().unwrap();
+        store.multi_way = lines;
+        store.status = ResultStatus::Complete;
+    }
+    sink.on_finished("multi_way_finished", &payload);
+    Ok(())
+}
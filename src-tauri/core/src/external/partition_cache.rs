@@ -0,0 +1,65 @@
+use crate::config::CompareConfig;
+use crate::external::multi_file::TempDir;
+use crate::header::HeaderLayout;
+use crate::identity::file_identity;
+use crate::payloads::FileIdentity;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Partitions and newline indexes of a finished external run, kept so the job can be
+/// re-run with different settings without hashing both files again. The temp directory
+/// is removed when the cache is dropped.
+pub struct PartitionCache {
+    pub temp_dir: TempDir,
+    pub file_a_path: String,
+    pub file_b_path: String,
+    pub nl_path_a: Option<PathBuf>,
+    pub nl_path_b: Option<PathBuf>,
+    pub header_layout: Option<Arc<HeaderLayout>>,
+    /// Hashing rules and input identities the partitions were built from.
+    hashing_rules: serde_json::Value,
+    inputs: Vec<FileIdentity>,
+}
+
+impl PartitionCache {
+    pub fn new(
+        temp_dir: TempDir,
+        file_a_path: String,
+        file_b_path: String,
+        nl_paths: (Option<PathBuf>, Option<PathBuf>),
+        header_layout: Option<Arc<HeaderLayout>>,
+        compare_config: &CompareConfig,
+    ) -> Self {
+        let inputs = [&file_a_path, &file_b_path]
+            .iter()
+            .filter_map(|path| file_identity(path).ok())
+            .collect();
+        Self {
+            temp_dir,
+            file_a_path,
+            file_b_path,
+            nl_path_a: nl_paths.0,
+            nl_path_b: nl_paths.1,
+            header_layout,
+            hashing_rules: compare_config.hashing_rules(),
+            inputs,
+        }
+    }
+
+    /// Whether a run with `compare_config` can use these partitions: the hashing rules must
+    /// be the same and neither file may have changed since.
+    pub fn reusable_for(&self, compare_config: &CompareConfig) -> bool {
+        if compare_config.hashing_rules() != self.hashing_rules || !compare_config.use_external_sort {
+            return false;
+        }
+        let current: Vec<FileIdentity> = [&self.file_a_path, &self.file_b_path]
+            .iter()
+            .filter_map(|path| file_identity(path).ok())
+            .collect();
+        current.len() == self.inputs.len()
+            && current
+                .iter()
+                .zip(&self.inputs)
+                .all(|(now, then)| now.size == then.size && now.modified_ms == then.modified_ms)
+    }
+}
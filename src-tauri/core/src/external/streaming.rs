@@ -0,0 +1,235 @@
+use crate::blank_lines::LineClassCounter;
+use crate::external::spill::{HashOffset, PartitionWriter};
+use crate::fields::{RecordHasher, MAX_QUOTED_RECORD};
+use crate::header::HeaderLayout;
+use crate::input::{open_streamed, StreamedInput};
+use crate::jobs::Job;
+use crate::progress::FileCounter;
+use crate::CompareConfig;
+use crate::sink::ProgressSink;
+use std::io::{BufRead, BufReader, Error as IoError, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Read buffer of the streaming path; large enough that network shares see big requests.
+const READ_BUFFER: usize = 4 * 1024 * 1024;
+/// How many records are read between two cancellation checks and progress updates.
+const CHECK_INTERVAL: usize = 1 << 16;
+
+/// Reads the records of a file one at a time, for inputs that can't be memory-mapped.
+/// Records end at `terminator` like in `find_record_ends`, so both paths see the same records.
+pub struct RecordReader<R> {
+    reader: R,
+    terminator: u8,
+    quote: Option<u8>,
+    offset: u64,
+}
+
+impl<R: BufRead> RecordReader<R> {
+    pub fn new(reader: R, terminator: u8, quote: Option<u8>) -> Self {
+        Self { reader, terminator, quote, offset: 0 }
+    }
+
+    /// Reads the next record into `record`, without its terminator. Returns the offset it
+    /// starts at and whether a terminator ended it, or `None` at the end of the input.
+    pub fn next_record(&mut self, record: &mut Vec<u8>) -> Result<Option<(u64, bool)>, IoError> {
+        record.clear();
+        let start = self.offset;
+        let mut in_quotes = false;
+        loop {
+            let from = record.len();
+            let read = self.reader.read_until(self.terminator, record)?;
+            self.offset += read as u64;
+            let terminated = read > 0 && record.last() == Some(&self.terminator);
+            if !terminated {
+                return Ok((!record.is_empty()).then_some((start, false)));
+            }
+            if let Some(quote) = self.quote {
+                in_quotes ^= memchr::memchr_iter(quote, &record[from..record.len() - 1]).count() % 2 == 1;
+            }
+            if !in_quotes || record.len() > MAX_QUOTED_RECORD {
+                record.pop();
+                return Ok(Some((start, true)));
+            }
+        }
+    }
+
+    /// Bytes consumed so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl<R: Read + Seek> RecordReader<BufReader<R>> {
+    /// Moves to the record starting at `offset`. Moving forward skips within the buffer
+    /// where it can, so reading sorted offsets doesn't refill the buffer for every record.
+    pub fn seek_to(&mut self, offset: u64) -> Result<(), IoError> {
+        match i64::try_from(offset.wrapping_sub(self.offset)) {
+            Ok(ahead) if offset >= self.offset => self.reader.seek_relative(ahead)?,
+            _ => {
+                self.reader.seek(SeekFrom::Start(offset))?;
+            }
+        }
+        self.offset = offset;
+        Ok(())
+    }
+}
+
+pub fn open_records(path: &str, config: &CompareConfig) -> Result<RecordReader<BufReader<StreamedInput>>, IoError> {
+    let file = open_streamed(path)?;
+    Ok(RecordReader::new(BufReader::with_capacity(READ_BUFFER, file), config.terminator(), config.record_quote()))
+}
+
+/// Hashes the records of a file into partitions in one sequential pass, for inputs that
+/// can't be mapped. Like the mapped path, a record cut off by the end of the file is left
+/// out. Returns the terminator positions, unless line numbers aren't indexed.
+pub fn partition_streaming<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    input_path: &str,
+    output_dir: &Path,
+    file_id: &str,
+    compare_config: &CompareConfig,
+    header_layout: Option<&HeaderLayout>,
+) -> Result<Vec<usize>, IoError> {
+    let file_size = std::fs::metadata(input_path)?.len();
+    let partitions = compare_config.partitions();
+    let mut writers = (0..partitions)
+        .map(|i| PartitionWriter::create(&output_dir.join(format!("part_{}", i))))
+        .collect::<Result<Vec<_>, IoError>>()?;
+    let hasher = RecordHasher::new(compare_config, header_layout, file_id, job.strips_cr(file_id))?;
+    let line_classes = LineClassCounter::default();
+    let index_lines = !compare_config.ignore_line_number && !compare_config.on_demand_line_numbers;
+    let counter = FileCounter::new(sink, &job.progress, file_id, "Partitioning...", file_size, (0.0, 1.0));
+
+    let mut reader = open_records(input_path, compare_config)?;
+    let mut record = Vec::new();
+    let mut newline_positions = Vec::new();
+    let (mut index, mut reported) = (0, 0);
+    while let Some((start, terminated)) = reader.next_record(&mut record)? {
+        if index % CHECK_INTERVAL == 0 {
+            job.check_cancelled()?;
+            counter.add(reader.offset() - reported);
+            reported = reader.offset();
+        }
+        if !terminated {
+            break;
+        }
+        if index_lines {
+            newline_positions.push(start as usize + record.len());
+        }
+        let content = hasher.content(&record);
+        let is_header = index == 0 && compare_config.has_header();
+        if !is_header && !line_classes.skip(content, compare_config) {
+            let hash = hasher.hash(content);
+            writers[(hash % partitions) as usize].write(HashOffset(hash, start))?;
+        }
+        index += 1;
+    }
+    for writer in writers {
+        writer.finish()?;
+    }
+    line_classes.record(job, file_id);
+    Ok(newline_positions)
+}
+
+/// 1-based line numbers of records starting at `sorted_offsets`, counted in one pass.
+pub fn line_numbers_streaming(path: &str, sorted_offsets: &[usize], compare_config: &CompareConfig) -> Result<Vec<usize>, IoError> {
+    let mut reader = open_records(path, compare_config)?;
+    let mut record = Vec::new();
+    let mut numbers = Vec::with_capacity(sorted_offsets.len());
+    let mut line_number = 0;
+    while numbers.len() < sorted_offsets.len() {
+        let Some((start, _)) = reader.next_record(&mut record)? else { break };
+        line_number += 1;
+        while sorted_offsets.get(numbers.len()).is_some_and(|&offset| offset as u64 <= start) {
+            numbers.push(line_number);
+        }
+    }
+    numbers.resize(sorted_offsets.len(), line_number);
+    Ok(numbers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(data: &[u8], quote: Option<u8>) -> Vec<(u64, bool, String)> {
+        let mut reader = RecordReader::new(data, b'\n', quote);
+        let mut record = Vec::new();
+        let mut found = Vec::new();
+        while let Some((start, terminated)) = reader.next_record(&mut record).unwrap() {
+            found.push((start, terminated, String::from_utf8(record.clone()).unwrap()));
+        }
+        found
+    }
+
+    #[test]
+    fn test_records_match_the_mapped_scanners() {
+        let data = b"1,\"multi\nline\"\n2,plain\n3,tail";
+        let quoted = records(data, Some(b'"'));
+        let starts: Vec<u64> = quoted.iter().map(|r| r.0).collect();
+        assert_eq!(starts, [0, 15, 23]);
+        assert_eq!(quoted[0].2, "1,\"multi\nline\"");
+        assert_eq!((quoted[2].1, quoted[2].2.as_str()), (false, "3,tail"));
+        let ends: Vec<usize> = quoted.iter().filter(|r| r.1).map(|r| r.0 as usize + r.2.len()).collect();
+        assert_eq!(ends, crate::fields::find_record_ends(data, b'\n', b'"'));
+
+        assert_eq!(records(data, None).len(), 4);
+        assert!(records(b"", None).is_empty());
+    }
+
+    #[test]
+    fn test_unmatched_quote_ends_at_the_first_terminator_past_the_cap() {
+        let mut data = b"1,\"stray\n".to_vec();
+        data.resize(MAX_QUOTED_RECORD + 8, b'x');
+        data.extend_from_slice(b"\n2,next\n3,\"ok\"");
+        let quoted = records(&data, Some(b'"'));
+        assert_eq!(quoted.iter().map(|r| r.2.len()).collect::<Vec<_>>(), [MAX_QUOTED_RECORD + 8, 6, 6]);
+        let ends: Vec<usize> = quoted.iter().filter(|r| r.1).map(|r| r.0 as usize + r.2.len()).collect();
+        assert_eq!(ends, crate::fields::find_record_ends(&data, b'\n', b'"'));
+        assert_eq!(crate::fields::record_len(&data, b'\n', Some(b'"')), MAX_QUOTED_RECORD + 8);
+    }
+
+    #[test]
+    fn test_seek_to_reads_records_forward_and_back() {
+        let mut reader = RecordReader::new(BufReader::with_capacity(8, std::io::Cursor::new(b"aa\nbb\ncc\ndd\n".to_vec())), b'\n', None);
+        let mut record = Vec::new();
+        for (offset, expected) in [(3, "bb"), (9, "dd"), (0, "aa"), (6, "cc")] {
+            reader.seek_to(offset).unwrap();
+            assert_eq!(reader.next_record(&mut record).unwrap(), Some((offset, true)));
+            assert_eq!(record, expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_streamed_partitions_match_mapped_ones() {
+        use crate::external::file_processing::partition_file;
+        use crate::external::spill::read_partition_sorted;
+        use crate::jobs::JobRegistry;
+        use crate::sink::CollectingSink;
+
+        let config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": true, "ignoreOccurences": false, "useSingleThread": false, "ignoreLineNumber": false,
+            "onDemandLineNumbers": false, "partitionCount": 4,
+        }))
+        .unwrap();
+        let job = JobRegistry::default().create(config.clone());
+        let sink = CollectingSink::default();
+        let dir = std::env::temp_dir().join(format!("streaming_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.txt");
+        let data: String = (0..5_000).map(|i| format!("line {}\r\n\n", i % 700)).collect();
+        std::fs::write(&input, data + "cut off").unwrap();
+        let input = input.to_str().unwrap();
+
+        let index = partition_file(&sink, &job, input, &dir.join("mapped"), "A", &config, None).unwrap().unwrap();
+        std::fs::create_dir_all(dir.join("streamed")).unwrap();
+        let ends = partition_streaming(&sink, &job, input, &dir.join("streamed"), "A", &config, None).unwrap();
+        assert_eq!(std::fs::read(index).unwrap(), ends.iter().flat_map(|end| end.to_ne_bytes()).collect::<Vec<u8>>());
+        for i in 0..4 {
+            let part = |kind: &str| read_partition_sorted(&dir.join(kind).join(format!("part_{}", i))).unwrap();
+            assert_eq!(part("mapped"), part("streamed"));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use crate::external::multi_file::{partition_files, read_partition_of_all, read_records_at, TempDir};
+use crate::input::prepare_input;
+use crate::jobs::Job;
+use crate::payloads::{ThreeWayChange, ThreeWayLine};
+use crate::results::three_way::{classify, report_three_way, BASE, FILE_A, FILE_B};
+use crate::CompareConfig;
+use crate::sink::ProgressSink;
+use rayon::prelude::*;
+use std::io::Error as IoError;
+use std::path::Path;
+use std::sync::Arc;
+
+/// File ids in the order of `results::three_way::{BASE, FILE_A, FILE_B}`.
+const FILE_IDS: [&str; 3] = ["BASE", "A", "B"];
+
+/// A changed line before its text is read: which file, where, how often and how.
+struct ChangedOffset {
+    file: usize,
+    offset: u64,
+    count: usize,
+    change: ThreeWayChange,
+}
+
+/// Compares BASE with two modified versions A and B, like a three-way merge view.
+/// Uses the partitioning of the external engine for all three files.
+pub fn run_three_way<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    base_path: String,
+    file_a_path: String,
+    file_b_path: String,
+    compare_config: CompareConfig,
+) -> Result<(), IoError> {
+    let temp_dir = TempDir::new(&job);
+    let file_ids = FILE_IDS.map(String::from);
+    let paths = [
+        prepare_input(&sink, &job, &base_path, FILE_IDS[BASE])?,
+        prepare_input(&sink, &job, &file_a_path, FILE_IDS[FILE_A])?,
+        prepare_input(&sink, &job, &file_b_path, FILE_IDS[FILE_B])?,
+    ];
+    partition_files(&sink, &job, &temp_dir.0, &paths, &file_ids, &compare_config)?;
+
+    let changes = aggregate_three_way(&job, &temp_dir.0, &file_ids, &compare_config)?;
+    let mut lines = Vec::with_capacity(changes.len());
+    for (file, path) in paths.iter().enumerate() {
+        let mut file_changes: Vec<&ChangedOffset> = changes.iter().filter(|c| c.file == file).collect();
+        file_changes.sort_unstable_by_key(|c| c.offset);
+        let offsets: Vec<usize> = file_changes.iter().map(|c| c.offset as usize).collect();
+        let records = read_records_at(path, &offsets, &compare_config)?;
+        lines.extend(file_changes.iter().zip(records).map(|(change, (line_number, text))| ThreeWayLine {
+            file: FILE_IDS[file].to_string(),
+            line_number,
+            text,
+            count: change.count,
+            change: change.change,
+        }));
+    }
+    job.check_cancelled()?;
+    report_three_way(&sink, &job, lines, &compare_config);
+    Ok(())
+}
+
+fn aggregate_three_way(
+    job: &Job,
+    temp_dir: &Path,
+    file_ids: &[String],
+    compare_config: &CompareConfig,
+) -> Result<Vec<ChangedOffset>, IoError> {
+    (0..compare_config.partitions())
+        .into_par_iter()
+        .map(|i| -> Result<Vec<ChangedOffset>, IoError> {
+            job.check_cancelled()?;
+            let (maps, hashes) = read_partition_of_all(temp_dir, file_ids, i)?;
+            let count = |file: usize, hash: &u64| maps[file].0.get(hash).copied().unwrap_or(0);
+            let mut changes = Vec::new();
+            for hash in &hashes {
+                for (change, file, n) in classify(count(0, hash), count(1, hash), count(2, hash), compare_config.ignore_occurences) {
+                    if let Some(&offset) = maps[file].1.get(hash) {
+                        changes.push(ChangedOffset { file, offset, count: n, change });
+                    }
+                }
+            }
+            Ok(changes)
+        })
+        .try_reduce(Vec::new, |mut a, b| {
+            a.extend(b);
+            Ok(a)
+        })
+}
@@ -0,0 +1,156 @@
+use crate::config::{CompareConfig, FieldFormat};
+use crate::header::HeaderLayout;
+use crate::normalize::NormalizerPipeline;
+use crate::numeric::normalize_number;
+use crate::records::split_delimited;
+pub use crate::records::{find_record_ends, record_len, MAX_QUOTED_RECORD};
+use crate::utils::LineHasher;
+use std::borrow::Cow;
+use std::hash::Hasher;
+use std::io::Error as IoError;
+
+/// Splits a record into fields as configured by `format`. Fixed-width fields past the end
+/// of a short record are empty.
+pub fn split_fields<'a>(record: &'a [u8], format: &FieldFormat) -> Vec<Cow<'a, [u8]>> {
+    match &format.fixed_width {
+        Some(layout) => layout.iter().map(|field| Cow::Borrowed(field.slice(record))).collect(),
+        None => split_delimited(record, format.delimiter_bytes(), format.quote_byte()),
+    }
+}
+
+const VERIFICATION_SEED: u64 = 0x5eed_c011_1510_04e5;
+
+/// Hashes records of one file according to the configured comparison rules.
+/// Without a field format the raw bytes are hashed, otherwise the parsed fields are.
+pub struct RecordHasher<'a> {
+    compare_config: &'a CompareConfig,
+    /// Pairs of (field index in this file, column index in file A) to hash, in canonical
+    /// column order. Set when columns are matched by header.
+    projection: Option<Vec<(usize, usize)>>,
+    normalizer: NormalizerPipeline,
+    seed: u64,
+}
+
+impl<'a> RecordHasher<'a> {
+    pub fn new(
+        compare_config: &'a CompareConfig,
+        header_layout: Option<&HeaderLayout>,
+        file_id: &str,
+        strip_cr: bool,
+    ) -> Result<Self, IoError> {
+        let projection = header_layout.map(|layout| {
+            let fields = if file_id == "A" { &layout.projection_a } else { &layout.projection_b };
+            fields.iter().copied().zip(layout.projection_a.iter().copied()).collect()
+        });
+        let normalizer = NormalizerPipeline::new(compare_config, strip_cr)?;
+        let seed = compare_config.hash_seed.unwrap_or(0);
+        Ok(Self { compare_config, projection, normalizer, seed })
+    }
+
+    /// A record as read, without its line ending; see `NormalizerPipeline::content`.
+    pub fn content<'r>(&self, record: &'r [u8]) -> &'r [u8] {
+        self.normalizer.content(record)
+    }
+
+    /// A record's content as it is hashed, before being split into fields.
+    pub fn normalize<'r>(&self, content: &'r [u8]) -> Cow<'r, [u8]> {
+        self.normalizer.normalize(content)
+    }
+
+    fn write_field(&self, hasher: &mut LineHasher, column: usize, field: &[u8]) {
+        if let Some(tolerance) = &self.compare_config.numeric_tolerance
+            && (tolerance.columns.is_empty() || tolerance.columns.contains(&column))
+            && let Some(normalized) = normalize_number(field, tolerance)
+        {
+            hasher.write_usize(normalized.len());
+            hasher.write(normalized.as_bytes());
+            return;
+        }
+        hasher.write_usize(field.len());
+        hasher.write(field);
+    }
+
+    pub fn hash(&self, record: &[u8]) -> u64 {
+        self.hash_with(self.compare_config.hash_algo().hasher(self.seed), record)
+    }
+
+    /// A second hash of the same canonical record, independent of `hash`, used to tell
+    /// collisions from real matches.
+    pub fn verification_hash(&self, record: &[u8]) -> u64 {
+        self.hash_with(self.compare_config.hash_algo().hasher(self.seed ^ VERIFICATION_SEED), record)
+    }
+
+    fn hash_with(&self, mut hasher: LineHasher, record: &[u8]) -> u64 {
+        let record = self.normalizer.normalize(record);
+        let record = record.as_ref();
+        match &self.compare_config.field_format {
+            Some(format) => {
+                let fields = split_fields(record, format);
+                match &self.projection {
+                    Some(projection) => {
+                        for &(i, column) in projection {
+                            let field = fields.get(i).map_or(&[][..], |f| f.as_ref());
+                            self.write_field(&mut hasher, column, field);
+                        }
+                    }
+                    None => {
+                        for (i, field) in fields.iter().enumerate() {
+                            if self.compare_config.ignore_columns.contains(&i) {
+                                continue;
+                            }
+                            self.write_field(&mut hasher, i, field);
+                        }
+                    }
+                }
+            }
+            None => hasher.write(record),
+        }
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FixedField;
+
+    fn csv() -> FieldFormat {
+        FieldFormat { delimiter: ",".to_string(), quote_char: Some('"'), has_header: false, fixed_width: None }
+    }
+
+    #[test]
+    fn test_split_quoted_fields() {
+        let fields = split_fields(b"1,\"a,b\",\"say \"\"hi\"\"\",", &csv());
+        let fields: Vec<&[u8]> = fields.iter().map(|f| f.as_ref()).collect();
+        assert_eq!(fields, vec![&b"1"[..], b"a,b", b"say \"hi\"", b""]);
+    }
+
+    #[test]
+    fn test_multi_byte_delimiter() {
+        let format = FieldFormat { delimiter: "||".to_string(), quote_char: None, has_header: false, fixed_width: None };
+        let fields = split_fields(b"a||b|c||", &format);
+        let fields: Vec<&[u8]> = fields.iter().map(|f| f.as_ref()).collect();
+        assert_eq!(fields, vec![&b"a"[..], b"b|c", b""]);
+    }
+
+    #[test]
+    fn test_fixed_width_fields() {
+        let field = |start, length| FixedField { start, length, name: None };
+        let format = FieldFormat {
+            fixed_width: Some(vec![field(0, 4), field(6, 3), field(12, 4), field(20, 2)]),
+            ..FieldFormat { delimiter: String::new(), quote_char: Some('"'), has_header: false, fixed_width: None }
+        };
+        let fields = split_fields(b"0042  \"A\"xxx12", &format);
+        let fields: Vec<&[u8]> = fields.iter().map(|f| f.as_ref()).collect();
+        assert_eq!(fields, vec![&b"0042"[..], b"\"A\"", b"12", b""]);
+        assert_eq!(format.quote_byte(), None);
+    }
+
+    #[test]
+    fn test_record_ends_skip_quoted_newlines() {
+        let data = b"1,\"multi\nline\"\n2,plain\n";
+        assert_eq!(find_record_ends(data, b'\n', b'"'), vec![14, 22]);
+        assert_eq!(record_len(data, b'\n', Some(b'"')), 14);
+        assert_eq!(record_len(data, b'\n', None), 8);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::config::{CompareConfig, SetOperation, Side};
+use crate::header::resolve_header_layout;
+use crate::input::prepare_input;
+use crate::internal::file_processing_in_memory::{collect_unique_lines_with_index, generate_hash_counts_and_index};
+use crate::jobs::Job;
+use crate::payloads::ComparisonSummary;
+use crate::results::collected::{finish_comparison, report_differences, CollectedLine};
+use crate::results::hash_export::LineHashes;
+use crate::sink::ProgressSink;
+use crate::utils::{emit_step, HashMap};
+use std::fs;
+use std::io::Error as IoError;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Sets the hash algorithm and seed the fingerprints were made with, so file A hashes the
+/// same way. CSV fingerprints don't record them, so the config must carry the seed.
+pub fn adopt_hashing(compare_config: &mut CompareConfig, fingerprints: &LineHashes) -> Result<(), String> {
+    if let Some(algo) = fingerprints.algo {
+        if !algo.is_supported() {
+            return Err(format!("The fingerprints were hashed with {:?}, which this build or CPU lacks", algo));
+        }
+        compare_config.hash_algo = Some(algo);
+    }
+    match fingerprints.seed {
+        Some(seed) => compare_config.hash_seed = Some(seed),
+        None if compare_config.hash_seed.is_none() => {
+            return Err("CSV fingerprints don't record their hash seed; set hashSeed to the seed they were exported with".to_string());
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+/// Hashes of the file on `side` reported by the set operation, usually those that occur
+/// more often than in the other file, with how many more times.
+fn count_differences(
+    side: Side,
+    counts: &HashMap<u64, usize>,
+    other: &HashMap<u64, usize>,
+    set_operation: SetOperation,
+    ignore_occurences: bool,
+) -> HashMap<u64, usize> {
+    counts
+        .iter()
+        .filter_map(|(&hash, &count)| {
+            let other_count = other.get(&hash).copied().unwrap_or(0);
+            let excess = set_operation.reported(side, count, other_count, ignore_occurences);
+            (excess > 0).then_some((hash, excess))
+        })
+        .collect()
+}
+
+/// Compares file A against the line hashes of a B that isn't available, e.g. because only
+/// its fingerprints may leave the environment it lives in. Lines of B have no text here,
+/// so its unique lines are reported by their hash, without line numbers.
+pub fn run_fingerprint_comparison<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    file_a_path: String,
+    fingerprints: LineHashes,
+    compare_config: CompareConfig,
+) -> Result<(), IoError> {
+    let file_a_path = prepare_input(&sink, &job, &file_a_path, "A")?;
+    let size_a = fs::metadata(&file_a_path)?.len();
+    job.progress.start([("A".to_string(), size_a)], 1.0);
+    // B's header isn't available, so columns are hashed in A's own order.
+    let header_layout = resolve_header_layout(&file_a_path, &file_a_path, &compare_config)?.map(Arc::new);
+
+    let now = Instant::now();
+    let (counts_a, index_a) =
+        generate_hash_counts_and_index(&sink, &job, &file_a_path, "A", &compare_config, header_layout.as_deref())?;
+    emit_step(&sink, &job, "Pass 1 (File A)".to_string(), now.elapsed().as_millis());
+
+    let now = Instant::now();
+    let (set_operation, ignore_occurences) = (compare_config.set_operation, compare_config.ignore_occurences);
+    let unique_to_a = count_differences(Side::A, &counts_a, &fingerprints.counts, set_operation, ignore_occurences);
+    let unique_to_b = count_differences(Side::B, &fingerprints.counts, &counts_a, set_operation, ignore_occurences);
+    emit_step(&sink, &job, "Fingerprint Comparison".to_string(), now.elapsed().as_millis());
+
+    let now = Instant::now();
+    collect_unique_lines_with_index(&job, &file_a_path, unique_to_a, &index_a, "A", &compare_config)?;
+    {
+        let mut store = job.results.lock().unwrap();
+        for (hash, count) in unique_to_b {
+            store.push_unique("B", CollectedLine { line_number: 0, text: format!("{:016x}", hash), count });
+        }
+    }
+    emit_step(&sink, &job, "Pass 2 (File A)".to_string(), now.elapsed().as_millis());
+    job.check_cancelled()?;
+
+    let mut summary = ComparisonSummary::default();
+    report_differences(&sink, &job, &compare_config, header_layout.as_ref(), &mut summary)?;
+    job.progress.shared_progress(&sink, 1.0, 100.0, "A", "Comparison Finished");
+    finish_comparison(&sink, &job, summary);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_are_compared_by_excess() {
+        let a = HashMap::from_iter([(1u64, 3usize), (2, 1)]);
+        let b = HashMap::from_iter([(1u64, 1usize), (3, 2)]);
+        let count_differences = |a, b, ignore_occurences| {
+            count_differences(Side::A, a, b, SetOperation::SymmetricDifference, ignore_occurences)
+        };
+        assert_eq!(count_differences(&a, &b, false), HashMap::from_iter([(1, 2), (2, 1)]));
+        assert_eq!(count_differences(&a, &b, true), HashMap::from_iter([(2, 1)]));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::jobs::Job;
+use crate::payloads::{ComparisonSummary, FirstDifference};
+use crate::results::collected::finish_comparison;
+use crate::results::store::ResultStatus;
+use crate::sink::ProgressSink;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error as IoError, Read, Seek, SeekFrom};
+
+/// Bytes read from each file at a time.
+const BLOCK_SIZE: usize = 1 << 20;
+/// Bytes of the diverging line included in the report, per file.
+const MAX_LINE_BYTES: u64 = 4096;
+
+/// Fills `buf` as far as the reader allows; shorter only at the end of the file.
+fn read_block(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, IoError> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Reads the line starting at `start`, without its terminator, or `None` at the end of the file.
+fn line_at(path: &str, start: u64, terminator: u8) -> Result<Option<String>, IoError> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut line = Vec::new();
+    BufReader::new(file).take(MAX_LINE_BYTES).read_until(terminator, &mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if line.last() == Some(&terminator) {
+        line.pop();
+    }
+    Ok(Some(String::from_utf8_lossy(&line).trim_end_matches('\r').to_string()))
+}
+
+/// Byte offset and line number where two streams stop being identical, with the offset
+/// the diverging line starts at, or `None` when they are identical. `on_block` is called
+/// with the bytes compared so far after every block and stops the scan when it fails.
+fn find_first_difference(
+    a: &mut impl Read,
+    b: &mut impl Read,
+    terminator: u8,
+    mut on_block: impl FnMut(u64) -> Result<(), IoError>,
+) -> Result<Option<(u64, u64, u64)>, IoError> {
+    let (mut buf_a, mut buf_b) = (vec![0u8; BLOCK_SIZE], vec![0u8; BLOCK_SIZE]);
+    let (mut offset, mut line_number, mut line_start) = (0u64, 1u64, 0u64);
+    loop {
+        let (len_a, len_b) = (read_block(a, &mut buf_a)?, read_block(b, &mut buf_b)?);
+        let len = len_a.min(len_b);
+        let same = buf_a[..len].iter().zip(&buf_b[..len]).position(|(x, y)| x != y).unwrap_or(len);
+        for end in memchr::memchr_iter(terminator, &buf_a[..same]) {
+            line_number += 1;
+            line_start = offset + end as u64 + 1;
+        }
+        if same < len || len_a != len_b {
+            return Ok(Some((offset + same as u64, line_number, line_start)));
+        }
+        if len == 0 {
+            return Ok(None);
+        }
+        offset += len as u64;
+        on_block(offset)?;
+    }
+}
+
+/// Streams both files in lockstep and stops at the first byte where they differ, the
+/// fastest way to tell whether two exports are identical. Reports the offset, the line
+/// number and the diverging line of each file.
+pub fn run_first_difference<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    file_a_path: &str,
+    file_b_path: &str,
+    terminator: u8,
+) -> Result<(), IoError> {
+    let (file_a, file_b) = (File::open(file_a_path)?, File::open(file_b_path)?);
+    let size = file_a.metadata()?.len().max(file_b.metadata()?.len()).max(1);
+    let (mut a, mut b) = (BufReader::new(file_a), BufReader::new(file_b));
+    let on_block = |offset: u64| {
+        let fraction = offset as f64 / size as f64;
+        job.progress.shared_progress(sink, fraction, fraction * 100.0, "A", "Looking for the first difference...");
+        job.check_cancelled()
+    };
+    let found = find_first_difference(&mut a, &mut b, terminator, on_block)?;
+
+    let first_difference = match found {
+        Some((offset, line_number, line_start)) => Some(FirstDifference {
+            offset,
+            line_number,
+            line_a: line_at(file_a_path, line_start, terminator)?,
+            line_b: line_at(file_b_path, line_start, terminator)?,
+        }),
+        None => None,
+    };
+    let summary = {
+        let mut store = job.results.lock().unwrap();
+        store.status = ResultStatus::Complete;
+        ComparisonSummary {
+            first_difference,
+            degradations: store.degradations.clone(),
+            inputs: store.inputs.clone(),
+            same_file: store.same_file,
+            ..Default::default()
+        }
+    };
+    job.progress.shared_progress(sink, 1.0, 100.0, "B", "Comparison Finished");
+    finish_comparison(sink, job, summary);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_difference(a: &[u8], b: &[u8]) -> Option<(u64, u64, u64)> {
+        find_first_difference(&mut &a[..], &mut &b[..], b'\n', |_| Ok(())).unwrap()
+    }
+
+    #[test]
+    fn test_reports_offset_and_line_of_divergence() {
+        assert_eq!(first_difference(b"one\ntwo\nthree\n", b"one\ntwo\nthreE\n"), Some((12, 3, 8)));
+        assert_eq!(first_difference(b"one\ntwo\n", b"one\ntwo\nmore\n"), Some((8, 3, 8)));
+        assert_eq!(first_difference(b"same\n", b"same\n"), None);
+    }
+}
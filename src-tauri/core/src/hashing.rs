@@ -0,0 +1,150 @@
+#[cfg(target_feature = "aes")]
+use gxhash::GxHasher;
+use serde::{Deserialize, Serialize};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::OnceLock;
+use xxhash_rust::xxh3::Xxh3;
+
+/// Algorithm the engines hash lines with. gxhash is the fastest but needs AES instructions
+/// at build time and on the running CPU; xxh3 runs anywhere; blake3 is a keyed cryptographic
+/// hash for inputs where even engineered collisions must be ruled out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    Gxhash,
+    Xxh3,
+    Blake3,
+}
+
+fn cpu_has_aes() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    return std::arch::is_x86_feature_detected!("aes");
+    #[cfg(target_arch = "aarch64")]
+    return std::arch::is_aarch64_feature_detected!("aes");
+    #[allow(unreachable_code)]
+    false
+}
+
+impl HashAlgo {
+    /// gxhash when this build and the CPU support it, xxh3 otherwise.
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<HashAlgo> = OnceLock::new();
+        *DETECTED.get_or_init(|| HashAlgo::preferred(HashAlgo::Gxhash.is_supported()))
+    }
+
+    fn preferred(gxhash_supported: bool) -> Self {
+        if gxhash_supported { HashAlgo::Gxhash } else { HashAlgo::Xxh3 }
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            HashAlgo::Gxhash => cfg!(target_feature = "aes") && cpu_has_aes(),
+            HashAlgo::Xxh3 | HashAlgo::Blake3 => true,
+        }
+    }
+
+    /// A streaming hasher seeded with `seed`. Unsupported algorithms fall back to xxh3.
+    pub fn hasher(self, seed: u64) -> LineHasher {
+        match self {
+            #[cfg(target_feature = "aes")]
+            HashAlgo::Gxhash if self.is_supported() => LineHasher::Gx(GxHasher::with_seed(seed as i64)),
+            HashAlgo::Blake3 => {
+                let mut key = [0u8; 32];
+                key[..8].copy_from_slice(&seed.to_le_bytes());
+                LineHasher::Blake3(blake3::Hasher::new_keyed(&key))
+            }
+            _ => LineHasher::Xxh3(Xxh3::with_seed(seed)),
+        }
+    }
+
+    /// Hash of one whole line.
+    pub fn hash_line(self, seed: u64, line: &[u8]) -> u64 {
+        let mut hasher = self.hasher(seed);
+        hasher.write(line);
+        hasher.finish()
+    }
+}
+
+/// A hasher of any of the `HashAlgo`s.
+// Hashers are created on the stack for every record; boxing the large ones would allocate per line.
+#[allow(clippy::large_enum_variant)]
+pub enum LineHasher {
+    #[cfg(target_feature = "aes")]
+    Gx(GxHasher),
+    Xxh3(Xxh3),
+    Blake3(blake3::Hasher),
+}
+
+impl Hasher for LineHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        match self {
+            #[cfg(target_feature = "aes")]
+            LineHasher::Gx(hasher) => hasher.write(bytes),
+            LineHasher::Xxh3(hasher) => hasher.update(bytes),
+            LineHasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        match self {
+            #[cfg(target_feature = "aes")]
+            LineHasher::Gx(hasher) => hasher.finish(),
+            LineHasher::Xxh3(hasher) => hasher.digest(),
+            LineHasher::Blake3(hasher) => {
+                let hash = hasher.finalize();
+                u64::from_le_bytes(hash.as_bytes()[..8].try_into().unwrap())
+            }
+        }
+    }
+}
+
+/// Builds hashers of the detected algorithm for the engines' hash maps and sets.
+#[derive(Clone, Copy)]
+pub struct BuildLineHasher(HashAlgo);
+
+impl Default for BuildLineHasher {
+    fn default() -> Self {
+        Self(HashAlgo::detect())
+    }
+}
+
+impl BuildHasher for BuildLineHasher {
+    type Hasher = LineHasher;
+
+    fn build_hasher(&self) -> LineHasher {
+        self.0.hasher(0)
+    }
+}
+
+pub type HashMap<K, V> = std::collections::HashMap<K, V, BuildLineHasher>;
+pub type HashSet<K> = std::collections::HashSet<K, BuildLineHasher>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(algo: HashAlgo, seed: u64, bytes: &[u8]) -> u64 {
+        algo.hash_line(seed, bytes)
+    }
+
+    #[test]
+    fn test_every_algorithm_is_seeded_and_deterministic() {
+        for algo in [HashAlgo::detect(), HashAlgo::Xxh3, HashAlgo::Blake3] {
+            assert_eq!(hash(algo, 1, b"line"), hash(algo, 1, b"line"));
+            assert_ne!(hash(algo, 1, b"line"), hash(algo, 2, b"line"));
+            assert_ne!(hash(algo, 1, b"line"), hash(algo, 1, b"other"));
+        }
+    }
+
+    #[test]
+    fn test_unsupported_gxhash_falls_back_to_xxh3() {
+        assert_eq!(HashAlgo::preferred(false), HashAlgo::Xxh3);
+        assert_eq!(HashAlgo::preferred(true), HashAlgo::Gxhash);
+        if !HashAlgo::Gxhash.is_supported() {
+            assert_eq!(HashAlgo::detect(), HashAlgo::Xxh3);
+            assert_eq!(hash(HashAlgo::Gxhash, 7, b"line"), hash(HashAlgo::Xxh3, 7, b"line"));
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::config::{CompareConfig, FieldFormat};
+use crate::external::streaming::RecordReader;
+use crate::fields::split_fields;
+use crate::payloads::HeaderMismatch;
+use std::fs::File;
+use std::io::{BufReader, Error as IoError};
+
+/// Column mapping derived from the headers of both files.
+pub struct HeaderLayout {
+    /// Field indices of file A to hash, in canonical column order.
+    pub projection_a: Vec<usize>,
+    /// Field indices of file B holding the same columns as `projection_a`.
+    pub projection_b: Vec<usize>,
+    pub mismatch: Option<HeaderMismatch>,
+}
+
+fn read_header(path: &str, format: &FieldFormat, terminator: u8) -> Result<Vec<String>, IoError> {
+    // Read rather than mapped, so inputs that can't be mapped work too.
+    let mut records = RecordReader::new(BufReader::new(File::open(path)?), terminator, format.quote_byte());
+    let mut record = Vec::new();
+    if records.next_record(&mut record)?.is_none() {
+        return Ok(Vec::new());
+    }
+    let mut header = record.as_slice();
+    if header.last() == Some(&b'\r') {
+        header = &header[..header.len() - 1];
+    }
+    Ok(split_fields(header, format)
+        .iter()
+        .map(|f| String::from_utf8_lossy(f).trim().to_string())
+        .collect())
+}
+
+/// Reads both headers and maps the columns of B onto the column order of A.
+/// Columns present on only one side are left out of the comparison and reported as a mismatch.
+pub fn resolve_header_layout(
+    file_a_path: &str,
+    file_b_path: &str,
+    compare_config: &CompareConfig,
+) -> Result<Option<HeaderLayout>, IoError> {
+    let format = match &compare_config.field_format {
+        Some(format) if format.has_header => format,
+        _ => return Ok(None),
+    };
+    let header_a = read_header(file_a_path, format, compare_config.terminator())?;
+    let header_b = read_header(file_b_path, format, compare_config.terminator())?;
+
+    let mut projection_a = Vec::new();
+    let mut projection_b = Vec::new();
+    let mut only_in_a = Vec::new();
+    // Positions in B of the shared columns, in A's order; reordered if they aren't ascending.
+    let mut shared_b = Vec::new();
+    for (index_a, name) in header_a.iter().enumerate() {
+        match header_b.iter().position(|b| b == name) {
+            Some(index_b) => {
+                shared_b.push(index_b);
+                if !compare_config.ignore_columns.contains(&index_a) {
+                    projection_a.push(index_a);
+                    projection_b.push(index_b);
+                }
+            }
+            None => only_in_a.push(name.clone()),
+        }
+    }
+    let only_in_b: Vec<String> = header_b
+        .iter()
+        .filter(|name| !header_a.contains(name))
+        .cloned()
+        .collect();
+    let reordered = !shared_b.is_sorted();
+
+    let mismatch = if only_in_a.is_empty() && only_in_b.is_empty() && !reordered {
+        None
+    } else {
+        Some(HeaderMismatch { only_in_a, only_in_b, reordered })
+    };
+
+    Ok(Some(HeaderLayout { projection_a, projection_b, mismatch }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_reports_missing_extra_and_reordered_columns() {
+        let dir = std::env::temp_dir().join(format!("header_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": false, "ignoreOccurences": false, "useSingleThread": false, "ignoreLineNumber": false,
+            "fieldFormat": { "delimiter": ",", "hasHeader": true },
+        }))
+        .unwrap();
+        let path_a = dir.join("a.csv");
+        std::fs::write(&path_a, "id,name,age\n1,x,2\n").unwrap();
+        let layout = |header_b: &str| {
+            let path_b = dir.join("b.csv");
+            std::fs::write(&path_b, format!("{}\n", header_b)).unwrap();
+            resolve_header_layout(path_a.to_str().unwrap(), path_b.to_str().unwrap(), &config).unwrap().unwrap()
+        };
+
+        assert!(layout("id,name,age").mismatch.is_none());
+
+        // Dropping a column shifts the later ones, but the shared columns keep their order.
+        let missing_and_extra = layout("id,age,extra");
+        assert_eq!((missing_and_extra.projection_a, missing_and_extra.projection_b), (vec![0, 2], vec![0, 1]));
+        let mismatch = missing_and_extra.mismatch.unwrap();
+        assert_eq!((mismatch.only_in_a, mismatch.only_in_b), (vec!["name".to_string()], vec!["extra".to_string()]));
+        assert!(!mismatch.reordered);
+
+        let reordered = layout("age,id,name");
+        assert_eq!(reordered.projection_b, [1, 2, 0]);
+        let mismatch = reordered.mismatch.unwrap();
+        assert!(mismatch.reordered && mismatch.only_in_a.is_empty() && mismatch.only_in_b.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,118 @@
+use crate::jobs::Job;
+use crate::payloads::ComparisonSummary;
+use crate::results::collected::finish_comparison;
+use crate::results::store::ResultStatus;
+use crate::sink::ProgressSink;
+use std::fs::File;
+use std::io::{Error as IoError, Read, Seek, SeekFrom};
+
+/// Blocks compared at evenly spaced offsets before anything is checksummed.
+const SAMPLE_BLOCKS: u64 = 16;
+const SAMPLE_BLOCK_SIZE: usize = 64 * 1024;
+/// Bytes checksummed between two cancellation checks and progress updates.
+const READ_BLOCK_SIZE: usize = 1 << 20;
+
+fn read_at(file: &mut File, offset: u64, buf: &mut [u8]) -> Result<usize, IoError> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Whether the files differ in size or in one of a few sampled blocks, which rules out
+/// identical files without reading them in full.
+fn sampled_difference(file_a_path: &str, file_b_path: &str) -> Result<bool, IoError> {
+    let (mut a, mut b) = (File::open(file_a_path)?, File::open(file_b_path)?);
+    let size = a.metadata()?.len();
+    if size != b.metadata()?.len() {
+        return Ok(true);
+    }
+    let (mut block_a, mut block_b) = (vec![0u8; SAMPLE_BLOCK_SIZE], vec![0u8; SAMPLE_BLOCK_SIZE]);
+    for i in 0..SAMPLE_BLOCKS {
+        let offset = size.saturating_sub(SAMPLE_BLOCK_SIZE as u64) * i / (SAMPLE_BLOCKS - 1);
+        let len = read_at(&mut a, offset, &mut block_a)?;
+        if len != read_at(&mut b, offset, &mut block_b)? || block_a[..len] != block_b[..len] {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn checksum<S: ProgressSink>(sink: &S, job: &Job, path: &str, file_id: &str) -> Result<blake3::Hash, IoError> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len().max(1);
+    let mut hasher = blake3::Hasher::new();
+    let mut buf = vec![0u8; READ_BLOCK_SIZE];
+    let mut read = 0u64;
+    loop {
+        job.check_cancelled()?;
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        read += n as u64;
+        job.progress.file_progress(sink, file_id, read as f64 / size as f64, "Checking whether the files are identical...");
+    }
+    Ok(hasher.finalize())
+}
+
+/// Whether both files have the same bytes: the same file, or the same size, sampled blocks
+/// and whole-file checksum, the two checksums being computed in parallel.
+pub fn files_identical<S: ProgressSink>(sink: &S, job: &Job, file_a_path: &str, file_b_path: &str) -> Result<bool, IoError> {
+    if job.results.lock().unwrap().same_file {
+        return Ok(true);
+    }
+    if sampled_difference(file_a_path, file_b_path)? {
+        return Ok(false);
+    }
+    let (a, b) = rayon::join(|| checksum(sink, job, file_a_path, "A"), || checksum(sink, job, file_b_path, "B"));
+    Ok(a? == b?)
+}
+
+/// Finishes a comparison of identical files without running an engine.
+pub fn finish_identical<S: ProgressSink>(sink: &S, job: &Job) {
+    let summary = {
+        let mut store = job.results.lock().unwrap();
+        store.status = ResultStatus::Complete;
+        ComparisonSummary {
+            identical: true,
+            degradations: store.degradations.clone(),
+            inputs: store.inputs.clone(),
+            same_file: store.same_file,
+            ..Default::default()
+        }
+    };
+    job.progress.shared_progress(sink, 1.0, 100.0, "B", "Comparison Finished");
+    finish_comparison(sink, job, summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_rule_out_different_files() {
+        let dir = std::env::temp_dir().join(format!("identical_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path_a, path_b) = (dir.join("a"), dir.join("b"));
+        let data: Vec<u8> = (0..1_000_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path_a, &data).unwrap();
+        std::fs::write(&path_b, &data).unwrap();
+        let (a, b) = (path_a.to_str().unwrap(), path_b.to_str().unwrap());
+        assert!(!sampled_difference(a, b).unwrap());
+
+        let mut changed = data.clone();
+        *changed.last_mut().unwrap() ^= 1;
+        std::fs::write(&path_b, &changed).unwrap();
+        assert!(sampled_difference(a, b).unwrap());
+        std::fs::write(&path_b, &data[1..]).unwrap();
+        assert!(sampled_difference(a, b).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
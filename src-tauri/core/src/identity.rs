@@ -0,0 +1,85 @@
+use crate::payloads::FileIdentity;
+use crate::remote::source::RemoteSource;
+use std::fs;
+use std::io::Error as IoError;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Absolute path with symlinks and `..` resolved, or `path` unchanged if it can't be resolved.
+/// The `\\?\` prefix Windows adds to canonical paths is dropped unless the path needs it.
+pub fn canonical_path(path: &str) -> String {
+    let Ok(canonical) = fs::canonicalize(path) else {
+        return path.to_string();
+    };
+    let canonical = canonical.to_string_lossy().into_owned();
+    match canonical.strip_prefix(r"\\?\") {
+        Some(stripped) if !stripped.starts_with("UNC\\") && stripped.len() < 260 => stripped.to_string(),
+        _ => canonical,
+    }
+}
+
+/// Short name for showing a file: its name and its parent directory, e.g. `logs/app.log`.
+pub fn display_name(path: &str) -> String {
+    let path = Path::new(path);
+    let Some(name) = path.file_name() else {
+        return path.to_string_lossy().into_owned();
+    };
+    match path.parent().and_then(Path::file_name) {
+        Some(parent) => format!("{}/{}", parent.to_string_lossy(), name.to_string_lossy()),
+        None => name.to_string_lossy().into_owned(),
+    }
+}
+
+/// Identity of an input file. Device and inode are only available on Unix; on other
+/// platforms `same_file` still compares the underlying file IDs.
+pub fn file_identity(path: &str) -> Result<FileIdentity, IoError> {
+    if let Some(source) = RemoteSource::parse(path) {
+        return source.identity(path);
+    }
+    let metadata = fs::metadata(path)?;
+    let modified_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_millis() as u64);
+
+    #[cfg(unix)]
+    let (device, inode) = {
+        use std::os::unix::fs::MetadataExt;
+        (Some(metadata.dev()), Some(metadata.ino()))
+    };
+    #[cfg(not(unix))]
+    let (device, inode) = (None, None);
+
+    let canonical = canonical_path(path);
+    Ok(FileIdentity {
+        path: path.to_string(),
+        display_name: display_name(&canonical),
+        canonical_path: canonical,
+        size: metadata.len(),
+        modified_ms,
+        device,
+        inode,
+    })
+}
+
+/// Whether two paths refer to the same file, e.g. through a hardlink, symlink or bind mount.
+/// Remote inputs are only the same when their paths are.
+pub fn is_same_file(file_a_path: &str, file_b_path: &str) -> Result<bool, IoError> {
+    if RemoteSource::parse(file_a_path).is_some() || RemoteSource::parse(file_b_path).is_some() {
+        return Ok(file_a_path == file_b_path);
+    }
+    same_file::is_same_file(file_a_path, file_b_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_name_keeps_file_and_parent() {
+        assert_eq!(display_name("/var/logs/app.log"), "logs/app.log");
+        assert_eq!(display_name("app.log"), "app.log");
+        assert_eq!(canonical_path("/no/such/file"), "/no/such/file");
+    }
+}
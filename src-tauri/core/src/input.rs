@@ -0,0 +1,155 @@
+use crate::config::LineTerminator;
+use crate::decompress::{decoder, detect_compression, stream_to_file};
+use crate::jobs::Job;
+use crate::line_endings::{uses_bare_cr, CrToLf, LineEndingCounts, SeparatorToByte};
+use crate::payloads::FileMetadataPayload;
+use crate::record_groups::RecordGroups;
+use crate::remote::source::RemoteSource;
+use crate::sink::ProgressSink;
+use crate::utils::emit_step;
+use crate::transcode::{source_encoding, Utf8Transcoder};
+use encoding_rs::UTF_8;
+use memmap2::Mmap;
+use regex::bytes::Regex;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error as IoError, ErrorKind, Read};
+use std::time::Instant;
+
+/// Bytes looked at to detect the encoding and line endings of an input.
+const SAMPLE_SIZE: usize = 64 * 1024;
+
+/// An input read as a stream rather than mapped. Tests read it through a `SlowReader`, so
+/// the engines can be run against slow or flaky storage.
+#[cfg(not(test))]
+pub type StreamedInput = File;
+#[cfg(test)]
+pub type StreamedInput = crate::slow_io::SlowReader<File>;
+
+/// Opens an input to be read as a stream, through the storage simulated for it in tests.
+pub fn open_streamed(path: &str) -> Result<StreamedInput, IoError> {
+    let file = File::open(path)?;
+    #[cfg(test)]
+    let file = crate::slow_io::SlowReader::new(file, crate::slow_io::simulated(path));
+    Ok(file)
+}
+
+/// Maps the file, retrying once, to find out whether the engines will be able to map it.
+fn check_mappable(file: &File) -> Result<(), IoError> {
+    if file.metadata()?.len() == 0 {
+        return Ok(());
+    }
+    unsafe { Mmap::map(file) }
+        .or_else(|_| unsafe { Mmap::map(file) })
+        .map(|_| ())
+}
+
+/// Records the dominant line ending of an input on the job and emits it with the file's metadata.
+fn report_line_ending<S: ProgressSink>(sink: &S, job: &Job, file_id: &str, counts: LineEndingCounts) {
+    let style = counts.dominant();
+    job.set_line_ending(file_id, style);
+    sink.emit(
+        "file_metadata",
+        &FileMetadataPayload {
+            file: file_id.to_string(),
+            line_ending: style,
+            lf_count: counts.lf,
+            crlf_count: counts.crlf,
+            cr_count: counts.cr,
+            strips_cr: job.strips_cr(file_id),
+        },
+    );
+}
+
+/// Downloads an `s3://`, `sftp://`, URL or database query input into the job's temp directory and returns the local path;
+/// other paths are returned unchanged. Done before anything reads the input, so
+/// decompression and transcoding apply to downloaded files as well.
+pub fn fetch_remote<S: ProgressSink>(sink: &S, job: &Job, path: &str, file_id: &str) -> Result<String, IoError> {
+    let Some(source) = RemoteSource::parse(path) else {
+        return Ok(path.to_string());
+    };
+    if let Some(copy) = job.local_copy(path) {
+        return Ok(copy);
+    }
+    let now = Instant::now();
+    let dir = job.local_copy_dir();
+    std::fs::create_dir_all(&dir)?;
+    let target = dir.join(format!("{}_download", file_id));
+    let size = source.download(sink, job, &target, file_id)?;
+    let copy = target.to_string_lossy().into_owned();
+    job.add_local_copy(path, &copy);
+    let step = format!("File {} - Downloaded {} MiB from {}", file_id, size / (1024 * 1024), source.name());
+    emit_step(sink, job, step, now.elapsed().as_millis());
+    Ok(copy)
+}
+
+/// Returns a path the engines can memory-map as UTF-8 with `\n` line endings. Compressed
+/// inputs are decompressed, UTF-16 (detected by its BOM) and legacy encodings transcoded,
+/// bare `\r` line endings converted unless a terminator is configured, multi-byte record
+/// separators replaced by a single byte and multi-line records grouped, all into the job's
+/// temp directory. Inputs on filesystems that can't be mapped (some network shares, FUSE
+/// and cloud mounts) are used as they are and marked on the job to be read as a stream.
+pub fn prepare_input<S: ProgressSink>(sink: &S, job: &Job, path: &str, file_id: &str) -> Result<String, IoError> {
+    if let Some(copy) = job.local_copy(path) {
+        return Ok(copy);
+    }
+    let now = Instant::now();
+    let compression = detect_compression(path)?;
+    let file = open_streamed(path)?;
+    let mut reader: BufReader<Box<dyn Read>> = BufReader::with_capacity(SAMPLE_SIZE, match compression {
+        Some(compression) => decoder(compression, file)?,
+        None => Box::new(file),
+    });
+    let sample = reader.fill_buf()?;
+    let encoding = source_encoding(job.config.encoding_for(file_id), sample)?;
+    let bare_cr = job.config.line_terminator == LineTerminator::Auto && uses_bare_cr(sample);
+    // Grouped records are made of `\n` lines, whatever the terminator.
+    let separator = job.config.line_terminator.sequence().filter(|_| job.config.record_start.is_none());
+    let record_start = match &job.config.record_start {
+        Some(pattern) => Some(Regex::new(pattern).map_err(|e| IoError::new(ErrorKind::InvalidInput, format!("Invalid record start regex: {}", e)))?),
+        None => None,
+    };
+    report_line_ending(sink, job, file_id, LineEndingCounts::count(sample));
+
+    let mut steps = Vec::new();
+    if let Some(compression) = compression {
+        steps.push(format!("Decompressed {:?} input", compression));
+    } else if encoding.is_none() && !bare_cr && separator.is_none() && record_start.is_none() {
+        if !job.config.streaming && check_mappable(&File::open(path)?).is_err() {
+            job.set_streamed();
+        }
+        return Ok(path.to_string());
+    }
+
+    let dir = job.local_copy_dir();
+    std::fs::create_dir_all(&dir)?;
+    let target = dir.join(file_id);
+    let mut source: Box<dyn Read> = match encoding {
+        Some(encoding) => {
+            steps.push(if encoding == UTF_8 {
+                "Removed UTF-8 byte order mark".to_string()
+            } else {
+                format!("Transcoded {} to UTF-8", encoding.name())
+            });
+            Box::new(Utf8Transcoder::new(reader, encoding))
+        }
+        None => Box::new(reader),
+    };
+    if bare_cr {
+        steps.push("Converted \\r line endings to \\n".to_string());
+        source = Box::new(CrToLf(source));
+    }
+    if let Some(separator) = separator {
+        steps.push(format!("Replaced record separator {:?} with \\x1e", String::from_utf8_lossy(separator)));
+        source = Box::new(SeparatorToByte::new(source, separator));
+    }
+    if let Some(start) = record_start {
+        steps.push("Grouped lines into records".to_string());
+        source = Box::new(RecordGroups::new(BufReader::with_capacity(SAMPLE_SIZE, source), start));
+    }
+    stream_to_file(job, source, &target)?;
+
+    let copy = target.to_string_lossy().into_owned();
+    job.add_local_copy(path, &copy);
+    emit_step(sink, job, format!("File {} - {}", file_id, steps.join(", ")), now.elapsed().as_millis());
+    Ok(copy)
+}
@@ -0,0 +1,189 @@
+use crate::config::Side;
+use crate::delimiter::check_delimiters;
+use crate::header::resolve_header_layout;
+use crate::jobs::Job;
+use crate::collisions::CollisionCheck;
+use crate::internal::file_processing_in_memory::{collect_unique_lines_with_index, generate_hash_counts_and_index};
+use crate::payloads::ComparisonSummary;
+use crate::results::collected::{finish_comparison, report_differences};
+use crate::results::duplicates::{duplicate_hashes, report_duplicates, DuplicateCandidate};
+use crate::results::frequency::{report_frequencies, FrequencyCounter};
+use crate::sink::ProgressSink;
+use crate::utils::{emit_step, HashMap};
+use std::sync::Arc;
+use std::thread;
+use crate::CompareConfig;
+
+pub fn run_comparison<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    file_a_path: String,
+    file_b_path: String,
+    compare_config: CompareConfig,
+) -> Result<(), std::io::Error> {
+    let start_time = std::time::Instant::now();
+
+    let header_layout = resolve_header_layout(&file_a_path, &file_b_path, &compare_config)?.map(Arc::new);
+    let header_layout_a = header_layout.clone();
+    let header_layout_b = header_layout.clone();
+    let summary = ComparisonSummary {
+        header_mismatch: header_layout.as_ref().and_then(|h| h.mismatch.clone()),
+        delimiter_mismatch: check_delimiters(&file_a_path, &file_b_path, &compare_config)?,
+        ..Default::default()
+    };
+    if let Some(mismatch) = &summary.delimiter_mismatch {
+        sink.emit("delimiter_mismatch", mismatch);
+    }
+
+    // --- Step 1: 并行处理两个文件，生成哈希计数和索引 ---
+    let sink_a = sink.clone();
+    let job_a = job.clone();
+    let path_a_clone = file_a_path.clone();
+    let config_a = compare_config.clone();
+    let handle_a = thread::spawn(move || {
+        let now = std::time::Instant::now();
+        let result = generate_hash_counts_and_index(&sink_a, &job_a, &path_a_clone, "A", &config_a, header_layout_a.as_deref());
+        (result, now.elapsed().as_millis())
+    });
+
+    let sink_b = sink.clone();
+    let job_b = job.clone();
+    let path_b_clone = file_b_path.clone();
+    let config_b = compare_config.clone();
+    let handle_b = thread::spawn(move || {
+        let now = std::time::Instant::now();
+        let result = generate_hash_counts_and_index(&sink_b, &job_b, &path_b_clone, "B", &config_b, header_layout_b.as_deref());
+        (result, now.elapsed().as_millis())
+    });
+
+    // 等待线程完成并获取计数的HashMap和索引
+    let (res_a, pass1_a_ms) = handle_a.join().unwrap();
+    emit_step(&sink, &job, "Pass 1 (File A)".to_string(), pass1_a_ms);
+
+    let (res_b, pass1_b_ms) = handle_b.join().unwrap();
+    emit_step(&sink, &job, "Pass 1 (File B)".to_string(), pass1_b_ms);
+
+    let (map_a_counts, index_a) = res_a?;
+    let (map_b_counts, index_b) = res_b?;
+    job.progress.shared_progress(&sink, 0.0, 100.0, "A", "Comparing Hashes");
+
+
+    // --- 中间步骤: 比较哈希计数，找出独有的哈希 ---
+    let now = std::time::Instant::now();
+    let mut unique_to_a_counts: HashMap<u64, usize> = HashMap::default();
+    let mut unique_to_b_counts: HashMap<u64, usize> = HashMap::default();
+    // Hashes shared by records that differ are treated as found in one file only.
+    let collided = CollisionCheck::open(
+        &file_a_path,
+        &file_b_path,
+        &compare_config,
+        header_layout.as_deref(),
+        [job.strips_cr("A"), job.strips_cr("B")],
+    )?
+        .map(|check| check.collided(&index_a, &index_b, |&(offset, _)| offset))
+        .unwrap_or_default();
+
+    let stop_after = compare_config.stop_after();
+    let mut stopped_early = false;
+
+    // Iterate through File A's hashes to find differences
+    let (set_operation, ignore_occurences) = (compare_config.set_operation, compare_config.ignore_occurences);
+    for (hash, &count_a) in &map_a_counts {
+        if unique_to_a_counts.len() >= stop_after {
+            stopped_early = true;
+            break;
+        }
+        let count_b = map_b_counts.get(hash).filter(|_| !collided.contains(hash)).copied().unwrap_or(0);
+        let reported = set_operation.reported(Side::A, count_a, count_b, ignore_occurences);
+        if reported > 0 {
+            unique_to_a_counts.insert(*hash, reported);
+        }
+    }
+
+    // Iterate through File B's hashes to find what's unique or more frequent in B
+    for (hash, &count_b) in &map_b_counts {
+        if unique_to_a_counts.len() + unique_to_b_counts.len() >= stop_after {
+            stopped_early = true;
+            break;
+        }
+        let count_a = map_a_counts.get(hash).filter(|_| !collided.contains(hash)).copied().unwrap_or(0);
+        let reported = set_operation.reported(Side::B, count_b, count_a, ignore_occurences);
+        if reported > 0 {
+            unique_to_b_counts.insert(*hash, reported);
+        }
+    }
+    let duplicates: Vec<DuplicateCandidate> = if compare_config.report_duplicates {
+        duplicate_hashes(&map_a_counts, &map_b_counts)
+            .into_iter()
+            .filter_map(|(hash, count_a, count_b)| {
+                let (in_a, &(offset, line_number)) = match index_a.get(&hash) {
+                    Some(info) => (true, info),
+                    None => (false, index_b.get(&hash)?),
+                };
+                Some(DuplicateCandidate { in_a, offset, line_number: Some(line_number), count_a, count_b })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let hash_map_comparison_ms = now.elapsed().as_millis();
+    emit_step(&sink, &job, "Hash Map Comparison".to_string(), hash_map_comparison_ms);
+    job.progress.shared_progress(&sink, 0.5, 50.0, "A", "Collecting unique lines");
+
+
+    let mut summary = summary;
+    summary.hash_collisions = collided.len();
+    summary.stopped_early = stopped_early;
+    if compare_config.report_duplicates {
+        summary.duplicates = report_duplicates(&sink, &job, &file_a_path, &file_b_path, duplicates, &compare_config)?;
+    }
+    if let Some(limit) = compare_config.frequency_report {
+        let counter = |counts: &HashMap<u64, usize>, index: &HashMap<u64, (u64, usize)>| {
+            let mut counter = FrequencyCounter::new(limit);
+            for (hash, &count) in counts {
+                if let Some(&(offset, line_number)) = index.get(hash) {
+                    counter.add(count, offset, Some(line_number));
+                }
+            }
+            counter
+        };
+        let counters = (counter(&map_a_counts, &index_a), counter(&map_b_counts, &index_b));
+        summary.frequencies = report_frequencies(&sink, &file_a_path, &file_b_path, counters, &compare_config)?;
+    }
+
+    // --- PASS 2: 并行根据唯一的哈希和索引取回行文本 ---
+    let job_collect_a = job.clone();
+    let config_collect_a = compare_config.clone();
+    let handle_collect_a = thread::spawn(move || {
+        let now = std::time::Instant::now();
+        let result = collect_unique_lines_with_index(&job_collect_a, &file_a_path, unique_to_a_counts, &index_a, "A", &config_collect_a);
+        (result, now.elapsed().as_millis())
+    });
+
+    let job_collect_b = job.clone();
+    let config_collect_b = compare_config.clone();
+    let handle_collect_b = thread::spawn(move || {
+        let now = std::time::Instant::now();
+        let result = collect_unique_lines_with_index(&job_collect_b, &file_b_path, unique_to_b_counts, &index_b, "B", &config_collect_b);
+        (result, now.elapsed().as_millis())
+    });
+
+    let (res_a, pass2_a_ms) = handle_collect_a.join().unwrap();
+    emit_step(&sink, &job, "Pass 2 (File A)".to_string(), pass2_a_ms);
+
+    let (res_b, pass2_b_ms) = handle_collect_b.join().unwrap();
+    emit_step(&sink, &job, "Pass 2 (File B)".to_string(), pass2_b_ms);
+
+    res_a?;
+    res_b?;
+    job.check_cancelled()?;
+
+    report_differences(&sink, &job, &compare_config, header_layout.as_ref(), &mut summary)?;
+    job.progress.shared_progress(&sink, 1.0, 100.0, "B", "Comparison Finished");
+
+    // --- 最后一步: 发送最终结果 ---
+    emit_step(&sink, &job, "Total".to_string(), start_time.elapsed().as_millis());
+    finish_comparison(&sink, &job, summary);
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+use crate::blank_lines::LineClassCounter;
+use crate::fields::{find_record_ends, record_len, RecordHasher};
+use crate::header::HeaderLayout;
+use crate::jobs::Job;
+use crate::progress::{FileCounter, SCAN_SHARE};
+use crate::records::find_line_ends;
+use crate::results::collected::CollectedLine;
+use crate::sink::ProgressSink;
+use crate::utils::{emit_step_detail, HashMap};
+use crate::CompareConfig;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::Error as IoError;
+use std::time::Instant;
+
+/// How many lines are processed between two cancellation checks.
+const CANCEL_CHECK_INTERVAL: usize = 1 << 16;
+
+pub fn generate_hash_counts_and_index<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    file_path: &str,
+    progress_file_id: &str,
+    compare_config: &CompareConfig,
+    header_layout: Option<&HeaderLayout>,
+) -> Result<(HashMap<u64, usize>, HashMap<u64, (u64, usize)>), IoError> {
+    let total_start = Instant::now();
+
+    // --- File Open & Metadata ---
+    let now = Instant::now();
+    let file = File::open(file_path)?;
+    let file_size = file.metadata()?.len();
+    emit_step_detail(sink, job, progress_file_id, "Opened file & read metadata", now.elapsed().as_millis());
+
+    if file_size == 0 {
+        return Ok((HashMap::default(), HashMap::default()));
+    }
+
+    job.progress.file_progress(sink, progress_file_id, 0.0, &format!("Hashing file {}...", progress_file_id));
+
+    // --- Memory Map ---
+    let now = Instant::now();
+    let mmap = unsafe { Mmap::map(&file)? };
+    emit_step_detail(sink, job, progress_file_id, "Created memory map", now.elapsed().as_millis());
+
+    // --- Find Newline Positions ---
+    let now = Instant::now();
+    let scanning = format!("Scanning file {}...", progress_file_id);
+    let counter = FileCounter::new(sink, &job.progress, progress_file_id, &scanning, file_size, (0.0, SCAN_SHARE));
+    let newline_positions: Vec<usize> = match compare_config.record_quote() {
+        Some(quote) => find_record_ends(&mmap, compare_config.terminator(), quote),
+        None => find_line_ends(&mmap, compare_config.terminator(), compare_config.scan_chunk(), |len| counter.add(len as u64)),
+    };
+    let total_lines = newline_positions.len();
+    emit_step_detail(sink, job, progress_file_id, "Found all newline positions", now.elapsed().as_millis());
+
+    // --- Parallel Processing ---
+    let now = Instant::now();
+    let record_hasher = RecordHasher::new(compare_config, header_layout, progress_file_id, job.strips_cr(progress_file_id))?;
+    let first_record = if compare_config.has_header() { 1 } else { 0 };
+    let line_classes = LineClassCounter::default();
+    let hashing = format!("Hashing file {}...", progress_file_id);
+    let counter = FileCounter::new(sink, &job.progress, progress_file_id, &hashing, total_lines as u64, (SCAN_SHARE, 1.0));
+    let (mut line_counts, mut line_index) = if total_lines > 0 {
+        (first_record..total_lines)
+            .into_par_iter()
+            .filter_map(|i| {
+                if i % CANCEL_CHECK_INTERVAL == 0 {
+                    if job.is_cancelled() {
+                        return None;
+                    }
+                    counter.add(CANCEL_CHECK_INTERVAL as u64);
+                }
+                let start = if i == 0 { 0 } else { newline_positions[i - 1] + 1 };
+                let end = newline_positions[i];
+                let line_bytes = &mmap[start..end];
+                let line_bytes_cleaned = record_hasher.content(line_bytes);
+                if line_classes.skip(line_bytes_cleaned, compare_config) {
+                    return None;
+                }
+                if std::str::from_utf8(line_bytes_cleaned).is_ok() {
+                    let hash = record_hasher.hash(line_bytes_cleaned);
+                    let offset = start as u64;
+                    let line_number = i + 1;
+                    Some((hash, offset, line_number))
+                } else {
+                    None
+                }
+            })
+            .fold(
+                || (HashMap::default(), HashMap::default()),
+                |mut acc, (hash, offset, line_number)| {
+                    *acc.0.entry(hash).or_insert(0) += 1;
+                    acc.1.entry(hash).or_insert((offset, line_number));
+                    acc
+                },
+            )
+            .reduce(
+                || (HashMap::default(), HashMap::default()),
+                |mut map_a, map_b| {
+                    for (hash, count_b) in map_b.0 {
+                        *map_a.0.entry(hash).or_insert(0) += count_b;
+                    }
+                    for (hash, info_b) in map_b.1 {
+                        map_a.1.entry(hash)
+                            .and_modify(|info_a| {
+                                if info_b.0 < info_a.0 {
+                                    *info_a = info_b;
+                                }
+                            })
+                            .or_insert(info_b);
+                    }
+                    map_a
+                },
+            )
+    } else {
+        (HashMap::default(), HashMap::default())
+    };
+    job.check_cancelled()?;
+    emit_step_detail(sink, job, progress_file_id, "Processed lines in parallel (hashing, counting, indexing)", now.elapsed().as_millis());
+
+    // --- Remainder Processing ---
+    let now = Instant::now();
+    let last_newline_pos = newline_positions.last().map_or(0, |p| p + 1);
+    // Without any terminator the remainder is the first record, i.e. the header if there is one.
+    let remainder_is_header = compare_config.has_header() && total_lines == 0;
+    if last_newline_pos < mmap.len() && !remainder_is_header {
+        let remainder = &mmap[last_newline_pos..];
+        let line_bytes_cleaned = record_hasher.content(remainder);
+        if !line_classes.skip(line_bytes_cleaned, compare_config) {
+            if std::str::from_utf8(line_bytes_cleaned).is_ok() {
+                let hash = record_hasher.hash(line_bytes_cleaned);
+                *line_counts.entry(hash).or_insert(0) += 1;
+                line_index.entry(hash).or_insert((last_newline_pos as u64, total_lines + 1));
+            }
+        }
+    }
+    if last_newline_pos < mmap.len() {
+        let remainder = &mmap[last_newline_pos..];
+        if !remainder.is_empty() {
+            emit_step_detail(sink, job, progress_file_id, "Processed file remainder", now.elapsed().as_millis());
+        }
+    }
+    line_classes.record(job, progress_file_id);
+    job.progress.file_progress(sink, progress_file_id, 1.0, &format!("Hashed file {}", progress_file_id));
+
+    emit_step_detail(sink, job, progress_file_id, "Total Hashing/Indexing Time", total_start.elapsed().as_millis());
+
+    Ok((line_counts, line_index))
+}
+
+pub fn collect_unique_lines_with_index(
+    job: &Job,
+    file_path: &str,
+    unique_hashes: HashMap<u64, usize>,
+    hash_to_info: &HashMap<u64, (u64, usize)>,
+    file_id: &str,
+    compare_config: &CompareConfig,
+) -> Result<(), IoError> {
+    if unique_hashes.is_empty() {
+        return Ok(());
+    }
+
+    let file = File::open(file_path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+
+    for (hash, count) in unique_hashes.iter() {
+        job.check_cancelled()?;
+        if let Some((offset, line_number)) = hash_to_info.get(hash) {
+            let start = *offset as usize;
+            let end = start + record_len(&mmap[start..], compare_config.terminator(), compare_config.record_quote());
+            let line_buffer = String::from_utf8_lossy(&mmap[start..end]);
+            job.results.lock().unwrap().push_unique(file_id, CollectedLine {
+                line_number: *line_number,
+                text: line_buffer.trim_end().to_string(),
+                count: *count,
+            });
+        }
+    }
+
+    Ok(())
+}
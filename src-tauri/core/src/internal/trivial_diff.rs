@@ -0,0 +1,284 @@
+use crate::blank_lines::LineClassCounter;
+use crate::config::{SetOperation, Side};
+use crate::delimiter::check_delimiters;
+use crate::event_limit::emit_bounded;
+use crate::fields::{find_record_ends, RecordHasher};
+use crate::header::{resolve_header_layout, HeaderLayout};
+use crate::jobs::Job;
+use crate::payloads::{ComparisonSummary, DiffLine, OrderedHunk};
+use crate::results::collected::{finish_comparison, report_differences, CollectedLine};
+use crate::sink::ProgressSink;
+use crate::utils::{emit_step, HashMap};
+use crate::CompareConfig;
+use std::fs;
+use std::io::Error as IoError;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Edits up to which the ordered diff is computed; more leave only the unordered results.
+const MAX_EDITS: usize = 1000;
+
+struct Record {
+    line_number: usize,
+    hash: u64,
+    text: String,
+}
+
+/// Hashes every record of a small file the way the engines do, keeping its text.
+fn read_records(job: &Job, path: &str, file_id: &str, config: &CompareConfig, layout: Option<&HeaderLayout>) -> Result<Vec<Record>, IoError> {
+    let data = fs::read(path)?;
+    let terminator = config.terminator();
+    let mut ends = match config.record_quote() {
+        Some(quote) => find_record_ends(&data, terminator, quote),
+        None => memchr::memchr_iter(terminator, &data).collect(),
+    };
+    if ends.last().map_or(0, |end| end + 1) < data.len() {
+        ends.push(data.len());
+    }
+    let hasher = RecordHasher::new(config, layout, file_id, job.strips_cr(file_id))?;
+    let line_classes = LineClassCounter::default();
+    let mut records = Vec::new();
+    let mut start = 0;
+    for (i, &end) in ends.iter().enumerate() {
+        let record = hasher.content(&data[start..end]);
+        start = end + 1;
+        if (i == 0 && config.has_header()) || line_classes.skip(record, config) {
+            continue;
+        }
+        if let Ok(text) = std::str::from_utf8(record) {
+            records.push(Record { line_number: i + 1, hash: hasher.hash(record), text: text.trim_end().to_string() });
+        }
+    }
+    line_classes.record(job, file_id);
+    Ok(records)
+}
+
+#[derive(Debug, PartialEq)]
+enum Edit {
+    Equal,
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Myers' shortest edit script from `a` to `b`, or `None` when it needs more than `max_edits`.
+fn shortest_edit(a: &[u64], b: &[u64], max_edits: usize) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let limit = (n + m).min(max_edits as isize);
+    let offset = limit + 1;
+    let at = |k: isize| (k + offset) as usize;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+    let mut found = false;
+    'search: for d in 0..=limit {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) { v[at(k + 1)] } else { v[at(k - 1)] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    let (mut x, mut y) = (n, m);
+    let mut edits = Vec::new();
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) { k + 1 } else { k - 1 };
+        let prev_x = if d == 0 { 0 } else { v[at(prev_k)] };
+        let prev_y = if d == 0 { 0 } else { prev_x - prev_k };
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal);
+        }
+        if d > 0 {
+            edits.push(if x == prev_x { Edit::Insert(prev_y as usize) } else { Edit::Delete(prev_x as usize) });
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    edits.reverse();
+    Some(edits)
+}
+
+/// Groups the edits into hunks of adjacent removed and added records.
+fn hunks(a: &[Record], b: &[Record], edits: &[Edit]) -> Vec<OrderedHunk> {
+    let line = |record: &Record| DiffLine { line_number: record.line_number, text: record.text.clone() };
+    let (mut i, mut j) = (0, 0);
+    let mut hunks: Vec<OrderedHunk> = Vec::new();
+    let mut open = false;
+    for edit in edits {
+        if *edit == Edit::Equal {
+            (i, j, open) = (i + 1, j + 1, false);
+            continue;
+        }
+        if !open {
+            let after = |records: &[Record], index: usize| index.checked_sub(1).map_or(0, |prev| records[prev].line_number);
+            hunks.push(OrderedHunk { after_a: after(a, i), after_b: after(b, j), removed: Vec::new(), added: Vec::new() });
+            open = true;
+        }
+        let hunk = hunks.last_mut().unwrap();
+        match edit {
+            Edit::Delete(index) => {
+                hunk.removed.push(line(&a[*index]));
+                i += 1;
+            }
+            Edit::Insert(index) => {
+                hunk.added.push(line(&b[*index]));
+                j += 1;
+            }
+            Edit::Equal => {}
+        }
+    }
+    hunks
+}
+
+/// Records of the file on `side` reported by the set operation, usually those occurring more
+/// often than in `other`: the first occurrence, with how many more times, as the hashing
+/// engines report them.
+fn unique_records(
+    side: Side,
+    records: &[Record],
+    other: &[Record],
+    set_operation: SetOperation,
+    ignore_occurences: bool,
+) -> Vec<CollectedLine> {
+    let mut other_counts: HashMap<u64, usize> = HashMap::default();
+    for record in other {
+        *other_counts.entry(record.hash).or_insert(0) += 1;
+    }
+    let mut counts: HashMap<u64, (usize, &Record)> = HashMap::default();
+    for record in records {
+        counts.entry(record.hash).or_insert((0, record)).0 += 1;
+    }
+    counts
+        .into_values()
+        .filter_map(|(count, record)| {
+            let other_count = other_counts.get(&record.hash).copied().unwrap_or(0);
+            let excess = set_operation.reported(side, count, other_count, ignore_occurences);
+            (excess > 0).then(|| CollectedLine { line_number: record.line_number, text: record.text.clone(), count: excess })
+        })
+        .collect()
+}
+
+/// What the direct diff finds: the records of each file missing from the other, and the
+/// ordered hunks when the files are within `MAX_EDITS` of each other.
+pub struct DirectDiff {
+    pub unique_to_a: Vec<CollectedLine>,
+    pub unique_to_b: Vec<CollectedLine>,
+    pub hunks: Option<Vec<OrderedHunk>>,
+}
+
+/// Reads, hashes and diffs both files, without reporting anything.
+pub fn direct_diff(
+    job: &Job,
+    file_a_path: &str,
+    file_b_path: &str,
+    compare_config: &CompareConfig,
+    header_layout: Option<&HeaderLayout>,
+) -> Result<DirectDiff, IoError> {
+    let a = read_records(job, file_a_path, "A", compare_config, header_layout)?;
+    let b = read_records(job, file_b_path, "B", compare_config, header_layout)?;
+    job.check_cancelled()?;
+    let hashes = |records: &[Record]| records.iter().map(|record| record.hash).collect::<Vec<_>>();
+    let (set_operation, ignore_occurences) = (compare_config.set_operation, compare_config.ignore_occurences);
+    let hunks = match set_operation {
+        SetOperation::SymmetricDifference => shortest_edit(&hashes(&a), &hashes(&b), MAX_EDITS).map(|edits| hunks(&a, &b, &edits)),
+        // An ordered diff only describes the differences of both files.
+        _ => None,
+    };
+    Ok(DirectDiff {
+        unique_to_a: unique_records(Side::A, &a, &b, set_operation, ignore_occurences),
+        unique_to_b: unique_records(Side::B, &b, &a, set_operation, ignore_occurences),
+        hunks,
+    })
+}
+
+/// Compares two small files directly in memory, adding an ordered diff of their records
+/// to the usual results.
+pub fn run_comparison<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    file_a_path: String,
+    file_b_path: String,
+    compare_config: CompareConfig,
+) -> Result<(), IoError> {
+    let now = Instant::now();
+    let header_layout = resolve_header_layout(&file_a_path, &file_b_path, &compare_config)?.map(Arc::new);
+    let mut summary = ComparisonSummary {
+        header_mismatch: header_layout.as_ref().and_then(|h| h.mismatch.clone()),
+        delimiter_mismatch: check_delimiters(&file_a_path, &file_b_path, &compare_config)?,
+        ..Default::default()
+    };
+    if let Some(mismatch) = &summary.delimiter_mismatch {
+        sink.emit("delimiter_mismatch", mismatch);
+    }
+    let diff = direct_diff(&job, &file_a_path, &file_b_path, &compare_config, header_layout.as_deref())?;
+    {
+        let mut store = job.results.lock().unwrap();
+        for line in diff.unique_to_a {
+            store.push_unique("A", line);
+        }
+        for line in diff.unique_to_b {
+            store.push_unique("B", line);
+        }
+    }
+    emit_step(&sink, &job, "Direct diff".to_string(), now.elapsed().as_millis());
+
+    report_differences(&sink, &job, &compare_config, header_layout.as_ref(), &mut summary)?;
+    if let Some(hunks) = diff.hunks {
+        if !job.is_quiet() {
+            for hunk in hunks.iter().take(compare_config.max_streamed_lines) {
+                emit_bounded(&sink, "ordered_hunk", hunk, compare_config.max_event_bytes);
+            }
+        }
+        summary.ordered_hunks = Some(hunks.len());
+        job.results.lock().unwrap().ordered_hunks = hunks;
+    }
+    job.progress.shared_progress(&sink, 1.0, 100.0, "B", "Comparison Finished");
+    finish_comparison(&sink, &job, summary);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(hashes: &[u64]) -> Vec<Record> {
+        hashes.iter().enumerate().map(|(i, &hash)| Record { line_number: i + 1, hash, text: hash.to_string() }).collect()
+    }
+
+    #[test]
+    fn test_ordered_hunks_follow_the_shortest_edit() {
+        let (a, b) = (records(&[1, 2, 3, 4]), records(&[1, 5, 3, 4, 6]));
+        let edits = shortest_edit(&[1, 2, 3, 4], &[1, 5, 3, 4, 6], MAX_EDITS).unwrap();
+        let hunks = hunks(&a, &b, &edits);
+        let texts = |lines: &[DiffLine]| lines.iter().map(|line| line.text.clone()).collect::<Vec<_>>();
+        assert_eq!(hunks.len(), 2);
+        assert_eq!((hunks[0].after_a, texts(&hunks[0].removed), texts(&hunks[0].added)), (1, vec!["2".to_string()], vec!["5".to_string()]));
+        assert_eq!((hunks[1].after_b, hunks[1].removed.len(), texts(&hunks[1].added)), (4, 0, vec!["6".to_string()]));
+        assert!(shortest_edit(&[1, 2], &[3, 4], 3).is_none());
+    }
+
+    #[test]
+    fn test_unique_records_count_excess_occurrences() {
+        let unique_records = |records: &[Record], other: &[Record], ignore_occurences| {
+            unique_records(Side::A, records, other, SetOperation::SymmetricDifference, ignore_occurences)
+        };
+        let mut unique = unique_records(&records(&[1, 1, 2]), &records(&[1]), false);
+        unique.sort_by_key(|line| line.line_number);
+        let found: Vec<(usize, usize)> = unique.iter().map(|line| (line.line_number, line.count)).collect();
+        assert_eq!(found, [(1, 1), (3, 1)]);
+        assert_eq!(unique_records(&records(&[1, 1]), &records(&[1]), true).len(), 0);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::config::CompareConfig;
+use crate::external::multi_file::{remove_temp_dirs_immediately, TempDir};
+use crate::external::partition_cache::PartitionCache;
+use crate::line_endings::LineEndingStyle;
+use crate::progress::ProgressModel;
+use crate::results::store::{ResultStatus, ResultStore};
+use std::collections::HashMap;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Finished jobs kept for paging, exports and re-runs. Older ones are dropped along with
+/// their results and temp files as new jobs start.
+const MAX_FINISHED_JOBS: usize = 20;
+
+/// A single comparison run. Engines poll `check_cancelled` at their cancellation points
+/// and record the differences they find in `results`.
+pub struct Job {
+    pub id: String,
+    /// Position among the jobs of the session, to find the oldest.
+    number: u64,
+    pub config: CompareConfig,
+    cancelled: AtomicBool,
+    /// Set for jobs run as part of a larger comparison: their lines are only stored, not emitted.
+    quiet: AtomicBool,
+    /// Set when an input can't be memory-mapped and has to be read as a stream.
+    streamed: AtomicBool,
+    /// Jobs started on behalf of this one, cancelled along with it.
+    children: Mutex<Vec<Arc<Job>>>,
+    pub results: Mutex<ResultStore>,
+    /// Partitions of the last external run, kept for `rerun_with`.
+    partitions: Mutex<Option<PartitionCache>>,
+    /// Directory holding local copies of inputs the engines can't map directly, kept as long as the job.
+    local_copy_dir: Mutex<Option<TempDir>>,
+    /// Original input path to the path of its local copy.
+    local_copies: Mutex<HashMap<String, String>>,
+    /// Dominant line ending of each input, by file id.
+    line_endings: Mutex<HashMap<String, LineEndingStyle>>,
+    /// Threads currently running this job, see `start_run`.
+    active_runs: AtomicUsize,
+    /// When the job first started running.
+    started: OnceLock<Instant>,
+    pub progress: ProgressModel,
+}
+
+impl Job {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        for child in self.children.lock().unwrap().iter() {
+            child.cancel();
+        }
+    }
+
+    pub fn add_child(&self, child: Arc<Job>) {
+        if self.is_cancelled() {
+            child.cancel();
+        }
+        self.children.lock().unwrap().push(child);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_quiet(&self) {
+        self.quiet.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_quiet(&self) -> bool {
+        self.quiet.load(Ordering::Relaxed)
+    }
+
+    pub fn set_streamed(&self) {
+        self.streamed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_streamed(&self) -> bool {
+        self.streamed.load(Ordering::Relaxed)
+    }
+
+    /// Notes a fallback taken to keep the job running, reported in the summary.
+    pub fn record_degradation(&self, message: &str) {
+        self.results.lock().unwrap().degradations.push(message.to_string());
+    }
+
+    pub fn keep_partitions(&self, cache: PartitionCache) {
+        *self.partitions.lock().unwrap() = Some(cache);
+    }
+
+    pub fn with_partitions<R>(&self, f: impl FnOnce(Option<&PartitionCache>) -> R) -> R {
+        f(self.partitions.lock().unwrap().as_ref())
+    }
+
+    pub fn take_partitions(&self) -> Option<PartitionCache> {
+        self.partitions.lock().unwrap().take()
+    }
+
+    /// Directory for local copies of this job's inputs, created on first use.
+    pub fn local_copy_dir(&self) -> PathBuf {
+        let mut dir = self.local_copy_dir.lock().unwrap();
+        dir.get_or_insert_with(|| TempDir::new(self)).0.clone()
+    }
+
+    pub fn add_local_copy(&self, path: &str, copy: &str) {
+        self.local_copies.lock().unwrap().insert(path.to_string(), copy.to_string());
+    }
+
+    pub fn local_copy(&self, path: &str) -> Option<String> {
+        self.local_copies.lock().unwrap().get(path).cloned()
+    }
+
+    pub fn is_local_copy(&self, path: &str) -> bool {
+        self.local_copies.lock().unwrap().values().any(|copy| copy == path)
+    }
+
+    /// The path to read an input from: its local copy if it has one, else the input itself.
+    /// Copies of copies are followed, e.g. a downloaded input that was then transcoded.
+    pub fn readable_path(&self, path: &str) -> String {
+        match self.local_copy(path) {
+            Some(copy) if copy != path => self.readable_path(&copy),
+            _ => path.to_string(),
+        }
+    }
+
+    /// Marks the job as running until the returned guard is dropped, so shutdown can wait for it.
+    pub fn start_run(self: &Arc<Self>) -> RunGuard {
+        self.active_runs.fetch_add(1, Ordering::SeqCst);
+        self.started.get_or_init(Instant::now);
+        RunGuard(self.clone())
+    }
+
+    /// Time since the job started running, zero if it never did.
+    pub fn run_time(&self) -> Duration {
+        self.started.get().map_or(Duration::ZERO, Instant::elapsed)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.active_runs.load(Ordering::SeqCst) > 0
+    }
+
+    /// Deletes the kept partitions and local input copies of the job.
+    pub fn remove_temp_files(&self) {
+        drop(self.take_partitions());
+        drop(self.local_copy_dir.lock().unwrap().take());
+    }
+
+    pub fn set_line_ending(&self, file_id: &str, style: LineEndingStyle) {
+        self.line_endings.lock().unwrap().insert(file_id.to_string(), style);
+    }
+
+    /// Whether the engines strip a trailing `\r` from the lines of this input.
+    pub fn strips_cr(&self, file_id: &str) -> bool {
+        self.config.strips_cr(self.line_endings.lock().unwrap().get(file_id).copied())
+    }
+
+    pub fn check_cancelled(&self) -> Result<(), IoError> {
+        if self.is_cancelled() {
+            Err(IoError::new(ErrorKind::Interrupted, "Comparison cancelled"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Drops the oldest finished jobs beyond `MAX_FINISHED_JOBS`. Jobs that haven't started
+/// yet, like queued ones, or are still running are kept.
+fn evict_finished(jobs: &mut HashMap<String, Arc<Job>>) {
+    let mut finished: Vec<(u64, String)> = jobs
+        .values()
+        .filter(|job| job.started.get().is_some() && !job.is_running())
+        .map(|job| (job.number, job.id.clone()))
+        .collect();
+    if finished.len() <= MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort_unstable();
+    for (_, id) in &finished[..finished.len() - MAX_FINISHED_JOBS] {
+        if let Some(job) = jobs.remove(id) {
+            job.remove_temp_files();
+        }
+    }
+}
+
+/// Held by the thread running a job, see `Job::start_run`.
+pub struct RunGuard(Arc<Job>);
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        self.0.active_runs.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Keeps track of all comparison jobs of this session. Managed as Tauri state.
+#[derive(Default)]
+pub struct JobRegistry {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<String, Arc<Job>>>,
+}
+
+impl JobRegistry {
+    pub fn create(&self, config: CompareConfig) -> Arc<Job> {
+        let number = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let id = format!("job-{}", number);
+        let job = Arc::new(Job {
+            id: id.clone(),
+            number,
+            cancelled: AtomicBool::new(false),
+            quiet: AtomicBool::new(false),
+            streamed: AtomicBool::new(false),
+            children: Mutex::new(Vec::new()),
+            results: Mutex::new(ResultStore::new(config.compress_results)),
+            partitions: Mutex::new(None),
+            local_copy_dir: Mutex::new(None),
+            local_copies: Mutex::new(HashMap::new()),
+            line_endings: Mutex::new(HashMap::new()),
+            active_runs: AtomicUsize::new(0),
+            started: OnceLock::new(),
+            progress: ProgressModel::default(),
+            config,
+        });
+        let mut jobs = self.jobs.lock().unwrap();
+        // Only the latest run keeps its partitions, older ones would just fill the temp disk.
+        for other in jobs.values() {
+            other.take_partitions();
+        }
+        evict_finished(&mut jobs);
+        jobs.insert(id, job.clone());
+        job
+    }
+
+    pub fn get(&self, job_id: &str) -> Option<Arc<Job>> {
+        self.jobs.lock().unwrap().get(job_id).cloned()
+    }
+
+    /// Cancels every job and waits up to `timeout` for their threads to flush partial results
+    /// and stop, then deletes all temp files right away. Called when the app exits.
+    pub fn shutdown(&self, timeout: Duration) {
+        remove_temp_dirs_immediately();
+        let jobs: Vec<Arc<Job>> = self.jobs.lock().unwrap().values().cloned().collect();
+        for job in &jobs {
+            job.cancel();
+        }
+        let deadline = Instant::now() + timeout;
+        while jobs.iter().any(|job| job.is_running()) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        if jobs.iter().any(|job| job.is_running()) {
+            eprintln!("Jobs still running after {:?}; exiting anyway", timeout);
+        }
+        for job in &jobs {
+            job.remove_temp_files();
+        }
+    }
+
+    /// Ids and configs of the jobs still running. Skips whatever is locked instead of waiting,
+    /// as it is called from the panic hook, possibly while a lock is held.
+    pub fn running_configs(&self) -> Vec<(String, CompareConfig)> {
+        let Ok(jobs) = self.jobs.try_lock() else {
+            return Vec::new();
+        };
+        jobs.values()
+            .filter(|job| job.results.try_lock().is_ok_and(|store| store.status == ResultStatus::Running))
+            .map(|job| (job.id.clone(), job.config.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oldest_finished_jobs_are_evicted() {
+        let config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": false, "ignoreOccurences": false, "useSingleThread": false, "ignoreLineNumber": false,
+        }))
+        .unwrap();
+        let registry = JobRegistry::default();
+        let running = registry.create(config.clone());
+        let _guard = running.start_run();
+        let waiting = registry.create(config.clone());
+        for _ in 0..MAX_FINISHED_JOBS + 1 {
+            drop(registry.create(config.clone()).start_run());
+        }
+        registry.create(config);
+        assert!(registry.get(&running.id).is_some() && registry.get(&waiting.id).is_some());
+        assert!(registry.get("job-3").is_none());
+        assert!(registry.get("job-4").is_some());
+        assert_eq!(registry.jobs.lock().unwrap().len(), MAX_FINISHED_JOBS + 3);
+    }
+}
@@ -0,0 +1,104 @@
+//! The comparison engines and everything they build on: line hashing, record splitting,
+//! the partition file format, job state, inputs, normalization and the result exports.
+//! None of it depends on Tauri; the app only adds the commands, settings and scheduling
+//! around it. Shared by the app, the benchmarks and the fuzz targets.
+
+pub mod hashing;
+pub mod line_endings;
+pub mod payloads;
+pub mod records;
+pub mod sink;
+pub mod spill;
+
+pub mod external {
+    pub mod aggregation;
+    pub mod checkpoint;
+    pub mod comparison;
+    pub mod disk_space;
+    pub mod file_cache;
+    pub mod file_processing;
+    pub mod line_numbers;
+    pub mod multi_file;
+    pub mod multi_way;
+    pub mod partition_cache;
+    pub mod streaming;
+    pub mod three_way;
+    pub use crate::spill;
+}
+
+pub mod internal {
+    pub mod comparison_in_memory;
+    pub mod file_processing_in_memory;
+    pub mod trivial_diff;
+}
+pub mod directory {
+    pub mod comparison;
+    pub mod pairing;
+}
+pub mod remote {
+    pub mod aws_credentials;
+    pub mod http;
+    pub mod s3;
+    pub mod sftp;
+    pub mod source;
+    pub mod sql;
+}
+pub mod merge {
+    pub mod merge_join;
+    pub mod sorted_reader;
+}
+pub mod results {
+    pub mod allowlist;
+    pub mod batch;
+    pub mod collected;
+    pub mod csv_export;
+    pub mod duplicates;
+    pub mod export;
+    pub mod export_writer;
+    pub mod frequency;
+    pub mod hash_export;
+    pub mod html_export;
+    pub mod in_words;
+    pub mod intra_line;
+    pub mod json_export;
+    pub mod page;
+    pub mod pairing;
+    pub mod sample;
+    pub mod similarity;
+    pub mod split_export;
+    pub mod store;
+    pub mod text_codec;
+    pub mod unified_diff;
+    pub mod three_way;
+    pub mod window;
+    pub mod xlsx_export;
+}
+pub mod binary;
+pub mod blank_lines;
+pub mod collisions;
+pub mod config;
+pub mod decompress;
+pub mod delimiter;
+pub mod engine;
+pub mod event_limit;
+pub mod explain;
+pub mod fields;
+pub mod fingerprint;
+pub mod first_difference;
+pub mod header;
+pub mod identical;
+pub mod identity;
+pub mod input;
+pub mod jobs;
+pub mod memory_budget;
+pub mod normalize;
+pub mod numeric;
+pub mod progress;
+pub mod record_groups;
+pub mod script;
+#[cfg(test)]
+mod slow_io;
+pub mod transcode;
+pub mod utils;
+
+pub use config::CompareConfig;
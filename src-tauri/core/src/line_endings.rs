@@ -0,0 +1,198 @@
+use serde::Serialize;
+use std::io::{Error as IoError, ErrorKind, Read};
+
+/// Dominant line ending of a file, detected from its first bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEndingStyle {
+    /// No line ending in the sample, e.g. a single line.
+    #[default]
+    None,
+    Lf,
+    CrLf,
+    Cr,
+}
+
+/// How often each line ending occurs in a sample.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineEndingCounts {
+    pub lf: usize,
+    pub crlf: usize,
+    pub cr: usize,
+}
+
+impl LineEndingCounts {
+    /// Counts the line endings of `sample`. NUL bytes are skipped so UTF-16 text counts too.
+    pub fn count(sample: &[u8]) -> Self {
+        let mut counts = Self::default();
+        let mut after_cr = false;
+        for &byte in sample.iter().filter(|&&b| b != 0) {
+            match byte {
+                b'\n' if after_cr => counts.crlf += 1,
+                b'\n' => counts.lf += 1,
+                _ if after_cr => counts.cr += 1,
+                _ => {}
+            }
+            after_cr = byte == b'\r';
+        }
+        // A final `\r` is left uncounted: it may be half of a `\r\n` cut off by the sample size.
+        counts
+    }
+
+    /// The most frequent line ending; `\r\n` wins ties, as it is the one whose `\r` is stripped.
+    pub fn dominant(&self) -> LineEndingStyle {
+        if self.crlf == 0 && self.lf == 0 && self.cr == 0 {
+            LineEndingStyle::None
+        } else if self.crlf >= self.lf && self.crlf >= self.cr {
+            LineEndingStyle::CrLf
+        } else if self.lf >= self.cr {
+            LineEndingStyle::Lf
+        } else {
+            LineEndingStyle::Cr
+        }
+    }
+}
+
+/// `record` without its trailing `\r` if `strip` is set.
+pub fn trim_cr(record: &[u8], strip: bool) -> &[u8] {
+    if strip { record.strip_suffix(b"\r").unwrap_or(record) } else { record }
+}
+
+/// Whether a sample ends its lines with bare `\r` only (classic Mac line endings).
+pub fn uses_bare_cr(sample: &[u8]) -> bool {
+    memchr::memchr(b'\n', sample).is_none() && memchr::memchr(b'\r', sample).is_some()
+}
+
+/// Streams `inner` with every `\r` replaced by `\n`.
+pub struct CrToLf<R>(pub R);
+
+impl<R: Read> Read for CrToLf<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let n = self.0.read(buf)?;
+        for byte in &mut buf[..n] {
+            if *byte == b'\r' {
+                *byte = b'\n';
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Byte a multi-byte record separator is replaced with, so records can be split on a single
+/// byte (the ASCII record separator).
+pub const RECORD_SEPARATOR: u8 = 0x1e;
+const SEPARATOR_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Streams `inner` with every occurrence of `separator` replaced by [`RECORD_SEPARATOR`].
+/// Fails if the input already contains that byte, as its records couldn't be told apart.
+pub struct SeparatorToByte<R> {
+    inner: R,
+    separator: Vec<u8>,
+    /// Read but not yet converted, kept while it could be the start of a separator.
+    pending: Vec<u8>,
+    converted: Vec<u8>,
+    position: usize,
+    eof: bool,
+}
+
+impl<R: Read> SeparatorToByte<R> {
+    pub fn new(inner: R, separator: &[u8]) -> Self {
+        SeparatorToByte {
+            inner,
+            separator: separator.to_vec(),
+            pending: Vec::new(),
+            converted: Vec::new(),
+            position: 0,
+            eof: false,
+        }
+    }
+
+    fn convert(&mut self) -> Result<(), IoError> {
+        let read_from = self.pending.len();
+        self.pending.resize(read_from + SEPARATOR_BUFFER_SIZE, 0);
+        let n = self.inner.read(&mut self.pending[read_from..])?;
+        self.pending.truncate(read_from + n);
+        self.eof = n == 0;
+        if memchr::memchr(RECORD_SEPARATOR, &self.pending[read_from..]).is_some() {
+            return Err(IoError::new(
+                ErrorKind::InvalidData,
+                "The input contains the byte \\x1e, so it can't be split on a multi-byte record separator",
+            ));
+        }
+        // A separator cut off at the end of the buffer is completed by the next read.
+        let keep = if self.eof { 0 } else { self.separator.len() - 1 };
+        let end = self.pending.len().saturating_sub(keep);
+        self.converted.clear();
+        self.position = 0;
+        let mut start = 0;
+        for found in memchr::memmem::find_iter(&self.pending, &self.separator) {
+            if found >= end {
+                break;
+            }
+            if found >= start {
+                self.converted.extend_from_slice(&self.pending[start..found]);
+                self.converted.push(RECORD_SEPARATOR);
+                start = found + self.separator.len();
+            }
+        }
+        let consumed = end.max(start);
+        self.converted.extend_from_slice(&self.pending[start..consumed]);
+        self.pending.drain(..consumed);
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for SeparatorToByte<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        while self.position == self.converted.len() {
+            if self.eof && self.pending.is_empty() {
+                return Ok(0);
+            }
+            self.convert()?;
+        }
+        let n = buf.len().min(self.converted.len() - self.position);
+        buf[..n].copy_from_slice(&self.converted[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bare_cr_lines_become_lf() {
+        assert!(uses_bare_cr(b"a\rb\r"));
+        assert!(!uses_bare_cr(b"a\r\nb\r\n"));
+        assert!(!uses_bare_cr(b"a\nb"));
+
+        let mut converted = String::new();
+        CrToLf(&b"a\rb\r"[..]).read_to_string(&mut converted).unwrap();
+        assert_eq!(converted, "a\nb\n");
+    }
+
+    #[test]
+    fn test_multi_byte_separators_become_one_byte() {
+        let input = b"a\nb<EOR>c<EOR>".repeat(20_000);
+        let mut converted = Vec::new();
+        SeparatorToByte::new(&input[..], b"<EOR>").read_to_end(&mut converted).unwrap();
+        assert_eq!(converted, b"a\nb\x1ec\x1e".repeat(20_000));
+
+        let mut rejected = Vec::new();
+        assert!(SeparatorToByte::new(&b"a\x1eb"[..], b"||").read_to_end(&mut rejected).is_err());
+    }
+
+    #[test]
+    fn test_dominant_line_ending_is_detected() {
+        let counts = LineEndingCounts::count(b"a\r\nb\r\nc\nd\r\n");
+        assert_eq!(counts, LineEndingCounts { lf: 1, crlf: 3, cr: 0 });
+        assert_eq!(counts.dominant(), LineEndingStyle::CrLf);
+        assert_eq!(LineEndingCounts::count(b"a\rb\nc\n").dominant(), LineEndingStyle::Lf);
+        assert_eq!(LineEndingCounts::count(b"a\rb\r").dominant(), LineEndingStyle::Cr);
+        assert_eq!(LineEndingCounts::count(b"a").dominant(), LineEndingStyle::None);
+        assert_eq!(LineEndingCounts::count(b"a\0\r\0\n\0").dominant(), LineEndingStyle::CrLf);
+        assert_eq!(trim_cr(b"a\r", false), b"a\r");
+        assert_eq!(trim_cr(b"a\r", true), b"a");
+    }
+}
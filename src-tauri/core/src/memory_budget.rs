@@ -0,0 +1,72 @@
+use crate::config::CompareConfig;
+use std::fs::File;
+use std::io::{Error as IoError, Read};
+use sysinfo::System;
+
+/// Bytes read from the start of a file to estimate its average line length.
+const SAMPLE_BYTES: u64 = 1024 * 1024;
+/// Peak memory of the in-memory engine per line: its newline position, plus an entry in the
+/// count map and one in the index map (41 bytes with the control byte), at up to twice the
+/// map capacity, twice over while the per-thread maps are merged.
+const BYTES_PER_LINE: u64 = 8 + 2 * 2 * 41;
+const MIB: u64 = 1024 * 1024;
+
+/// Lines in a file of `size` bytes whose first bytes are `sample`, extrapolated.
+fn estimated_lines(sample: &[u8], size: u64, terminator: u8) -> u64 {
+    if sample.is_empty() {
+        return 0;
+    }
+    let lines = memchr::memchr_iter(terminator, sample).count().max(1) as u64;
+    (size as u128 * lines as u128 / sample.len() as u128).max(1) as u64
+}
+
+/// Lines of the file at `path`, extrapolated from its first bytes.
+pub fn estimate_line_count(path: &str, terminator: u8) -> Result<u64, IoError> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut sample = Vec::new();
+    file.take(SAMPLE_BYTES).read_to_end(&mut sample)?;
+    Ok(estimated_lines(&sample, size, terminator))
+}
+
+/// Memory the in-memory engine is expected to need for one file.
+fn estimated_footprint(path: &str, terminator: u8) -> Result<u64, IoError> {
+    Ok(estimate_line_count(path, terminator)?.saturating_mul(BYTES_PER_LINE))
+}
+
+/// The configured memory budget, or else the memory available right now.
+fn budget(config: &CompareConfig) -> u64 {
+    config.max_memory.unwrap_or_else(|| {
+        let mut system = System::new();
+        system.refresh_memory();
+        // Platforms sysinfo can't read report 0, which leaves the engine choice alone.
+        Some(system.available_memory()).filter(|&bytes| bytes > 0).unwrap_or(u64::MAX)
+    })
+}
+
+/// Why the in-memory engine can't compare the files within the memory budget, if it can't.
+pub fn exceeded(file_a_path: &str, file_b_path: &str, config: &CompareConfig) -> Result<Option<String>, IoError> {
+    let terminator = config.terminator();
+    let needed = estimated_footprint(file_a_path, terminator)?.saturating_add(estimated_footprint(file_b_path, terminator)?);
+    let budget = budget(config);
+    Ok((needed > budget).then(|| {
+        format!(
+            "The in-memory engine would need about {} MiB, more than the {} MiB memory budget; used the external engine instead",
+            needed / MIB,
+            budget / MIB
+        )
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_are_extrapolated_from_the_sample() {
+        let sample = b"0123456789\n".repeat(100);
+        assert_eq!(estimated_lines(&sample, sample.len() as u64 * 1000, b'\n'), 100_000);
+        assert_eq!(estimated_lines(b"no terminator yet", 1 << 30, b'\n'), (1 << 30) / 17);
+        assert_eq!(estimated_lines(b"", 0, b'\n'), 0);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::config::{SetOperation, WhitespaceLines};
+use crate::external::comparison;
+use crate::internal::comparison_in_memory;
+use crate::jobs::Job;
+use crate::merge::sorted_reader::{find_unsorted, SortedReader};
+use crate::payloads::ComparisonSummary;
+use crate::results::collected::{finish_comparison, report_differences, CollectedLine};
+use crate::CompareConfig;
+use crate::sink::ProgressSink;
+use std::cmp::Ordering;
+use std::io::Error as IoError;
+use std::sync::Arc;
+
+/// How many lines are merged between two cancellation checks and progress updates.
+const CHECK_INTERVAL: usize = 1 << 16;
+
+/// Options the merge-join can't honour: it compares raw lines, so anything that makes
+/// differing bytes compare equal needs the hashing engines.
+fn hashing_required(config: &CompareConfig) -> Option<&'static str> {
+    if config.field_format.is_some() || config.numeric_tolerance.is_some() {
+        Some("field-aware comparison")
+    } else if !config.rewrite_rules.is_empty()
+        || config.canonicalize_json
+        || config.trim_whitespace
+        || config.ignore_case
+        || config.transform_script.is_some()
+        || config.whitespace_lines != WhitespaceLines::Literal
+    {
+        Some("line normalization")
+    } else if config.report_duplicates {
+        Some("duplicate reporting")
+    } else if config.frequency_report.is_some() {
+        Some("frequency reports")
+    } else {
+        None
+    }
+}
+
+fn run_hashing_engine<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    file_a_path: String,
+    file_b_path: String,
+    compare_config: CompareConfig,
+) -> Result<(), IoError> {
+    if compare_config.use_external_sort {
+        comparison::run_comparison(sink, job, file_a_path, file_b_path, compare_config)
+    } else {
+        comparison_in_memory::run_comparison(sink, job, file_a_path, file_b_path, compare_config)
+    }
+}
+
+/// Consecutive identical unique lines of one file, reported once with their count.
+struct UniqueRun {
+    file_id: &'static str,
+    bytes: Vec<u8>,
+    line: Option<CollectedLine>,
+}
+
+impl UniqueRun {
+    fn new(file_id: &'static str) -> Self {
+        Self { file_id, bytes: Vec::new(), line: None }
+    }
+
+    fn push(&mut self, job: &Job, reader: &SortedReader) {
+        if let Some(line) = &mut self.line
+            && self.bytes == reader.line
+        {
+            line.count += 1;
+            return;
+        }
+        self.flush(job);
+        self.bytes.clone_from(&reader.line);
+        self.line = Some(CollectedLine {
+            line_number: reader.line_number,
+            text: String::from_utf8_lossy(&reader.line).trim_end().to_string(),
+            count: 1,
+        });
+    }
+
+    fn flush(&mut self, job: &Job) {
+        if let Some(line) = self.line.take() {
+            job.results.lock().unwrap().push_unique(self.file_id, line);
+        }
+    }
+}
+
+fn emit_progress<S: ProgressSink>(sink: &S, job: &Job, reader: &SortedReader) {
+    let fraction = if reader.file_size == 0 {
+        1.0
+    } else {
+        reader.bytes_read as f64 / reader.file_size as f64
+    };
+    job.progress.file_progress(sink, reader.file_id, fraction, "Merging sorted files...");
+}
+
+/// How a merge of the two files ended.
+enum MergeEnd {
+    Complete,
+    /// `stop_after_differences` unique lines were found before the end of the files.
+    StoppedEarly,
+    /// A file went out of order, at the position described.
+    Unsorted(String),
+}
+
+/// Streams both files side by side until both end, a file turns out to be unsorted or
+/// `stop_after` unique lines were found.
+fn merge<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    file_a_path: &str,
+    file_b_path: &str,
+    compare_config: &CompareConfig,
+) -> Result<MergeEnd, IoError> {
+    let (ignore_occurences, terminator) = (compare_config.ignore_occurences, compare_config.terminator());
+    let set_operation = compare_config.set_operation;
+    let intersection = set_operation == SetOperation::Intersection;
+    let report_a = matches!(set_operation, SetOperation::SymmetricDifference | SetOperation::AMinusB);
+    let report_b = matches!(set_operation, SetOperation::SymmetricDifference | SetOperation::BMinusA);
+    let stop_after = compare_config.stop_after();
+    let mut found = 0;
+    let mut a = SortedReader::open(file_a_path, "A", terminator, job.strips_cr("A"))?;
+    let mut b = SortedReader::open(file_b_path, "B", terminator, job.strips_cr("B"))?;
+    a.advance()?;
+    b.advance()?;
+    let mut run_a = UniqueRun::new("A");
+    let mut run_b = UniqueRun::new("B");
+    // Last line found in both files, used to drop surplus occurrences when counts are ignored.
+    let mut matched: Vec<u8> = Vec::new();
+
+    let mut step = 0;
+    while !a.done || !b.done {
+        step += 1;
+        if step % CHECK_INTERVAL == 0 {
+            job.check_cancelled()?;
+            emit_progress(sink, job, &a);
+            emit_progress(sink, job, &b);
+        }
+        let order = match (a.done, b.done) {
+            (false, true) => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            _ => a.line.cmp(&b.line),
+        };
+        let sorted = match order {
+            Ordering::Equal => {
+                if intersection && !(ignore_occurences && a.line == matched) {
+                    run_a.push(job, &a);
+                    found += 1;
+                }
+                matched.clone_from(&a.line);
+                a.advance()? && b.advance()?
+            }
+            Ordering::Less => {
+                if report_a && !(ignore_occurences && a.line == matched) {
+                    run_a.push(job, &a);
+                    found += 1;
+                }
+                a.advance()?
+            }
+            Ordering::Greater => {
+                if report_b && !(ignore_occurences && b.line == matched) {
+                    run_b.push(job, &b);
+                    found += 1;
+                }
+                b.advance()?
+            }
+        };
+        if !sorted {
+            let reader = if a.sorted { &b } else { &a };
+            return Ok(MergeEnd::Unsorted(reader.unsorted_message()));
+        }
+        if found >= stop_after {
+            break;
+        }
+    }
+    run_a.flush(job);
+    run_b.flush(job);
+    emit_progress(sink, job, &a);
+    emit_progress(sink, job, &b);
+    Ok(if a.done && b.done { MergeEnd::Complete } else { MergeEnd::StoppedEarly })
+}
+
+/// Compares two files that are already sorted with a streaming merge-join: no hashing,
+/// no temp partitions and constant memory apart from the reported lines. Falls back to
+/// the hashing engine when the configuration needs it or a file turns out to be unsorted.
+pub fn run_comparison<S: ProgressSink>(
+    sink: S,
+    job: Arc<Job>,
+    file_a_path: String,
+    file_b_path: String,
+    compare_config: CompareConfig,
+) -> Result<(), IoError> {
+    if let Some(reason) = hashing_required(&compare_config) {
+        job.record_degradation(&format!("Presorted mode doesn't support {}; used the hashing engine", reason));
+        return run_hashing_engine(sink, job, file_a_path, file_b_path, compare_config);
+    }
+
+    let mut unsorted = None;
+    if compare_config.validate_sort_order {
+        unsorted = find_unsorted(&file_a_path, "A", compare_config.terminator(), job.strips_cr("A"))?;
+        if unsorted.is_none() {
+            unsorted = find_unsorted(&file_b_path, "B", compare_config.terminator(), job.strips_cr("B"))?;
+        }
+    }
+    let mut stopped_early = false;
+    if unsorted.is_none() {
+        match merge(&sink, &job, &file_a_path, &file_b_path, &compare_config)? {
+            MergeEnd::Complete => {}
+            MergeEnd::StoppedEarly => stopped_early = true,
+            MergeEnd::Unsorted(message) => unsorted = Some(message),
+        }
+    }
+    if let Some(message) = unsorted {
+        // Nothing has been emitted yet, so the partial merge results can simply be dropped.
+        job.results.lock().unwrap().clear_unique();
+        job.record_degradation(&format!("{}; used the hashing engine", message));
+        return run_hashing_engine(sink, job, file_a_path, file_b_path, compare_config);
+    }
+
+    job.check_cancelled()?;
+    let mut summary = ComparisonSummary { stopped_early, ..Default::default() };
+    report_differences(&sink, &job, &compare_config, None, &mut summary)?;
+    finish_comparison(&sink, &job, summary);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::JobRegistry;
+    use crate::sink::CollectingSink;
+
+    #[test]
+    fn test_whitespace_only_lines_are_left_to_the_hashing_engine() {
+        let dir = std::env::temp_dir().join(format!("merge_join_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path_a, path_b) = (dir.join("a"), dir.join("b"));
+        std::fs::write(&path_a, "  \na\nb\n").unwrap();
+        std::fs::write(&path_b, "\t\na\nb\n").unwrap();
+        let config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": false, "ignoreOccurences": false, "useSingleThread": false, "ignoreLineNumber": false,
+            "presorted": true, "whitespaceLines": "blank",
+        }))
+        .unwrap();
+        let job = JobRegistry::default().create(config.clone());
+        let sink = CollectingSink::default();
+        let (a, b) = (path_a.to_str().unwrap().to_string(), path_b.to_str().unwrap().to_string());
+        run_comparison(sink.clone(), job.clone(), a, b, config).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(sink.payloads("unique_line").is_empty());
+        assert!(job.results.lock().unwrap().degradations[0].contains("line normalization"));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::line_endings::trim_cr;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error as IoError, Read};
+
+const READ_BUFFER: usize = 1024 * 1024;
+
+/// Streams the non-empty lines of a file that is expected to be sorted byte-wise
+/// (as `LC_ALL=C sort` does). Only the current and the previous line are kept in memory.
+pub struct SortedReader {
+    reader: BufReader<Box<dyn Read + Send>>,
+    terminator: u8,
+    strip_cr: bool,
+    pub file_id: &'static str,
+    pub line: Vec<u8>,
+    previous: Vec<u8>,
+    pub line_number: usize,
+    pub bytes_read: u64,
+    pub file_size: u64,
+    pub done: bool,
+    /// Whether every line so far sorted after its predecessor.
+    pub sorted: bool,
+}
+
+impl SortedReader {
+    pub fn open(path: &str, file_id: &'static str, terminator: u8, strip_cr: bool) -> Result<Self, IoError> {
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        Ok(Self::new(file, file_size, file_id, terminator, strip_cr))
+    }
+
+    /// Streams the lines of any reader; `file_size` is only used for progress.
+    pub fn new(
+        reader: impl Read + Send + 'static,
+        file_size: u64,
+        file_id: &'static str,
+        terminator: u8,
+        strip_cr: bool,
+    ) -> Self {
+        Self {
+            reader: BufReader::with_capacity(READ_BUFFER, Box::new(reader)),
+            terminator,
+            strip_cr,
+            file_id,
+            line: Vec::new(),
+            previous: Vec::new(),
+            line_number: 0,
+            bytes_read: 0,
+            file_size,
+            done: false,
+            sorted: true,
+        }
+    }
+
+    /// Moves to the next non-empty line. Returns `false` if it sorts before the previous one.
+    pub fn advance(&mut self) -> Result<bool, IoError> {
+        std::mem::swap(&mut self.line, &mut self.previous);
+        loop {
+            self.line.clear();
+            let read = self.reader.read_until(self.terminator, &mut self.line)?;
+            if read == 0 {
+                self.done = true;
+                return Ok(true);
+            }
+            self.bytes_read += read as u64;
+            self.line_number += 1;
+            if self.line.last() == Some(&self.terminator) {
+                self.line.pop();
+            }
+            let len = trim_cr(&self.line, self.strip_cr).len();
+            self.line.truncate(len);
+            if !self.line.is_empty() {
+                self.sorted = self.line >= self.previous;
+                return Ok(self.sorted);
+            }
+        }
+    }
+
+    pub fn unsorted_message(&self) -> String {
+        format!("File {} is not sorted at line {}", self.file_id, self.line_number)
+    }
+}
+
+/// Sortedness pre-pass: reads the whole file and returns where it first goes out of order.
+pub fn find_unsorted(
+    path: &str,
+    file_id: &'static str,
+    terminator: u8,
+    strip_cr: bool,
+) -> Result<Option<String>, IoError> {
+    let mut reader = SortedReader::open(path, file_id, terminator, strip_cr)?;
+    while !reader.done {
+        if !reader.advance()? {
+            return Ok(Some(reader.unsorted_message()));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slow_io::{SlowReader, SlowStorage};
+    use std::io::Cursor;
+    use std::time::Duration;
+
+    #[test]
+    fn test_find_unsorted_reports_line() {
+        let path = std::env::temp_dir().join(format!("sorted_reader_test_{}", std::process::id()));
+        std::fs::write(&path, "a\r\nb\n\nb\nc\nab\n").unwrap();
+        let result = find_unsorted(path.to_str().unwrap(), "A", b'\n', true).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result, Some("File A is not sorted at line 6".to_string()));
+    }
+
+    #[test]
+    fn test_lines_from_slow_storage_and_read_errors() {
+        let data: Vec<u8> = (0..20_000u32).flat_map(|i| format!("{:08}\n", i).into_bytes()).collect();
+        let size = data.len() as u64;
+        let slow = SlowStorage { latency: Duration::from_millis(1), max_read: 16 * 1024, ..Default::default() };
+        let mut reader = SortedReader::new(SlowReader::new(Cursor::new(data.clone()), slow), size, "A", b'\n', false);
+        let mut lines = 0;
+        while reader.advance().unwrap() && !reader.done {
+            lines += 1;
+        }
+        assert_eq!((lines, reader.bytes_read), (20_000, size));
+
+        let broken = SlowStorage { fail_at: Some(50_000), failures: usize::MAX, ..Default::default() };
+        let mut reader = SortedReader::new(SlowReader::new(Cursor::new(data), broken), size, "A", b'\n', false);
+        let result = loop {
+            match reader.advance() {
+                Ok(_) if reader.done => break Ok(()),
+                Ok(_) => {}
+                Err(e) => break Err(e),
+            }
+        };
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+        assert!(reader.bytes_read < 50_000);
+    }
+}
@@ -0,0 +1,262 @@
+use crate::blank_lines::is_whitespace_only;
+use crate::config::{CompareConfig, RewriteRule, WhitespaceLines};
+use crate::line_endings::trim_cr;
+use crate::script::LineScript;
+use regex::bytes::Regex;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io::{Error as IoError, ErrorKind};
+
+/// Ordered `(pattern, replacement)` rewrites applied to each record before it is hashed,
+/// e.g. to blank out UUIDs or timestamps. Reported lines keep their original text.
+pub struct LineRewriter {
+    rules: Vec<(Regex, String)>,
+}
+
+impl LineRewriter {
+    pub fn new(rules: &[RewriteRule]) -> Result<Self, IoError> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                Regex::new(&rule.pattern)
+                    .map(|regex| (regex, rule.replacement.clone()))
+                    .map_err(|e| IoError::new(ErrorKind::InvalidInput, format!("Invalid rewrite pattern '{}': {}", rule.pattern, e)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Indices of the rules that change `record` when applied in order.
+    pub fn matching_rules(&self, record: &[u8]) -> Vec<usize> {
+        let mut record = record.to_vec();
+        let mut matched = Vec::new();
+        for (i, (regex, replacement)) in self.rules.iter().enumerate() {
+            if let Cow::Owned(rewritten) = regex.replace_all(&record, replacement.as_bytes()) {
+                record = rewritten;
+                matched.push(i);
+            }
+        }
+        matched
+    }
+
+    pub fn apply<'a>(&self, record: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut record = Cow::Borrowed(record);
+        for (regex, replacement) in &self.rules {
+            if let Cow::Owned(rewritten) = regex.replace_all(&record, replacement.as_bytes()) {
+                record = Cow::Owned(rewritten);
+            }
+        }
+        record
+    }
+}
+
+/// One step of the normalization applied to each record before it is hashed. A step that
+/// leaves the record as it is returns it unchanged, so unaffected lines aren't copied.
+pub trait LineNormalizer: Send + Sync {
+    fn normalize<'r>(&self, record: Cow<'r, [u8]>) -> Cow<'r, [u8]>;
+}
+
+/// `record`, or `changed` in its place.
+fn replace(record: Cow<'_, [u8]>, changed: Option<Vec<u8>>) -> Cow<'_, [u8]> {
+    changed.map_or(record, Cow::Owned)
+}
+
+impl LineNormalizer for LineRewriter {
+    fn normalize<'r>(&self, record: Cow<'r, [u8]>) -> Cow<'r, [u8]> {
+        let rewritten = match self.apply(&record) {
+            Cow::Owned(rewritten) => Some(rewritten),
+            Cow::Borrowed(_) => None,
+        };
+        replace(record, rewritten)
+    }
+}
+
+/// Makes all whitespace-only records equal to each other.
+struct WhitespaceOnly;
+
+impl LineNormalizer for WhitespaceOnly {
+    fn normalize<'r>(&self, record: Cow<'r, [u8]>) -> Cow<'r, [u8]> {
+        if is_whitespace_only(&record) { Cow::Borrowed(b" ") } else { record }
+    }
+}
+
+/// Drops leading and trailing ASCII whitespace.
+struct Trim;
+
+impl LineNormalizer for Trim {
+    fn normalize<'r>(&self, record: Cow<'r, [u8]>) -> Cow<'r, [u8]> {
+        match record {
+            Cow::Borrowed(record) => Cow::Borrowed(record.trim_ascii()),
+            Cow::Owned(record) if record.trim_ascii().len() == record.len() => Cow::Owned(record),
+            Cow::Owned(record) => Cow::Owned(record.trim_ascii().to_vec()),
+        }
+    }
+}
+
+/// Lowercases UTF-8 records fully and anything else byte-wise as ASCII.
+struct Lowercase;
+
+impl LineNormalizer for Lowercase {
+    fn normalize<'r>(&self, record: Cow<'r, [u8]>) -> Cow<'r, [u8]> {
+        let lowered = if record.is_ascii() {
+            record.iter().any(u8::is_ascii_uppercase).then(|| record.to_ascii_lowercase())
+        } else {
+            match std::str::from_utf8(&record) {
+                Ok(text) => Some(text.to_lowercase().into_bytes()).filter(|lowered| *lowered != *record),
+                Err(_) => Some(record.to_ascii_lowercase()),
+            }
+        };
+        replace(record, lowered)
+    }
+}
+
+struct CanonicalJson;
+
+impl LineNormalizer for CanonicalJson {
+    fn normalize<'r>(&self, record: Cow<'r, [u8]>) -> Cow<'r, [u8]> {
+        let canonical = canonicalize_json(&record);
+        replace(record, canonical)
+    }
+}
+
+/// The normalization steps a config asks for, in the order they apply. Dropped and
+/// projected columns and numeric tolerance are handled afterwards, when the record is
+/// split into fields.
+pub struct NormalizerPipeline {
+    /// Whether records lose the `\r` of a CRLF line ending.
+    strip_cr: bool,
+    steps: Vec<Box<dyn LineNormalizer>>,
+}
+
+impl NormalizerPipeline {
+    pub fn new(config: &CompareConfig, strip_cr: bool) -> Result<Self, IoError> {
+        let mut steps: Vec<Box<dyn LineNormalizer>> = Vec::new();
+        if config.whitespace_lines == WhitespaceLines::Normalize {
+            steps.push(Box::new(WhitespaceOnly));
+        }
+        if config.trim_whitespace {
+            steps.push(Box::new(Trim));
+        }
+        if config.canonicalize_json {
+            steps.push(Box::new(CanonicalJson));
+        }
+        if config.ignore_case {
+            steps.push(Box::new(Lowercase));
+        }
+        if !config.rewrite_rules.is_empty() {
+            steps.push(Box::new(LineRewriter::new(&config.rewrite_rules)?));
+        }
+        if let Some(script) = &config.transform_script {
+            steps.push(Box::new(LineScript::new(script)?));
+        }
+        Ok(Self { strip_cr, steps })
+    }
+
+    /// The text of a record as read, without its line ending. This is what blank lines
+    /// are detected on and what results report.
+    pub fn content<'r>(&self, record: &'r [u8]) -> &'r [u8] {
+        trim_cr(record, self.strip_cr)
+    }
+
+    /// `content` after every step of the pipeline.
+    pub fn normalize<'r>(&self, content: &'r [u8]) -> Cow<'r, [u8]> {
+        self.steps.iter().fold(Cow::Borrowed(content), |record, step| step.normalize(record))
+    }
+}
+
+fn write_canonical(out: &mut String, value: &Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, &Value::String(key.clone()));
+                out.push(':');
+                write_canonical(out, value);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, item);
+            }
+            out.push(']');
+        }
+        Value::Number(number) => {
+            // 1, 1.0 and 1e0 all hash the same.
+            match (number.as_i64(), number.as_u64(), number.as_f64()) {
+                (Some(i), _, _) => write!(out, "{}", i).unwrap(),
+                (_, Some(u), _) => write!(out, "{}", u).unwrap(),
+                (_, _, Some(f)) if f.fract() == 0.0 && f.abs() < 9.0e15 => write!(out, "{}", f as i64).unwrap(),
+                (_, _, Some(f)) => write!(out, "{}", f).unwrap(),
+                _ => out.push_str(&number.to_string()),
+            }
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Re-serializes a JSON line with sorted keys, no insignificant whitespace and normalized
+/// numbers. Returns `None` when the line isn't valid JSON, so it is compared verbatim.
+pub fn canonicalize_json(record: &[u8]) -> Option<Vec<u8>> {
+    let value: Value = serde_json::from_slice(record).ok()?;
+    let mut out = String::with_capacity(record.len());
+    write_canonical(&mut out, &value);
+    Some(out.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_apply_in_order() {
+        let rules = vec![
+            RewriteRule { pattern: r"\d{4}-\d{2}-\d{2}".to_string(), replacement: "<DATE>".to_string() },
+            RewriteRule { pattern: r"<DATE> (\w+)".to_string(), replacement: "$1".to_string() },
+        ];
+        let rewriter = LineRewriter::new(&rules).unwrap();
+        assert_eq!(rewriter.apply(b"at 2024-01-31 login ok").as_ref(), b"at login ok");
+        assert!(matches!(rewriter.apply(b"no match"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_canonical_json_ignores_key_order_and_number_format() {
+        let a = canonicalize_json(br#"{"b": [1.0, "x"], "a": {"d": 2e1, "c": null}}"#).unwrap();
+        let b = canonicalize_json(br#"{"a":{"c":null,"d":20},"b":[1,"x"]}"#).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a, br#"{"a":{"c":null,"d":20},"b":[1,"x"]}"#);
+        assert!(canonicalize_json(b"not json").is_none());
+    }
+
+    #[test]
+    fn test_pipeline_applies_configured_steps_in_order() {
+        let mut config: CompareConfig = serde_json::from_value(serde_json::json!({
+            "useExternalSort": false, "ignoreOccurences": false, "useSingleThread": false, "ignoreLineNumber": false,
+            "trimWhitespace": true, "ignoreCase": true,
+            "rewriteRules": [{ "pattern": "id=\\d+", "replacement": "id=<N>" }],
+        }))
+        .unwrap();
+        let pipeline = NormalizerPipeline::new(&config, true).unwrap();
+        let content = pipeline.content(b"  Login OK ID=42 \r");
+        assert_eq!(content, b"  Login OK ID=42 ");
+        assert_eq!(pipeline.normalize(content).as_ref(), b"login ok id=<N>");
+        assert!(matches!(pipeline.normalize(b"done"), Cow::Borrowed(_)));
+
+        config.trim_whitespace = false;
+        config.ignore_case = false;
+        config.rewrite_rules.clear();
+        let pipeline = NormalizerPipeline::new(&config, false).unwrap();
+        assert_eq!(pipeline.content(b"a\r"), b"a\r");
+        assert_eq!(pipeline.normalize(b" A ").as_ref(), b" A ");
+    }
+}
@@ -0,0 +1,57 @@
+use crate::config::NumericTolerance;
+
+/// Maps a numeric field onto a canonical token so that values within the tolerance
+/// usually hash alike. Hashing can only bucket values: an absolute tolerance rounds to the
+/// nearest multiple of the tolerance, a relative one to the matching number of significant
+/// digits. Values a tolerance or more apart never share a bucket, but closer values just
+/// either side of a bucket boundary don't either. Probing the neighbouring buckets would
+/// need every combination of them hashed per record, so the engines accept this limit.
+/// Returns `None` for fields that aren't numbers.
+pub fn normalize_number(field: &[u8], tolerance: &NumericTolerance) -> Option<String> {
+    let text = std::str::from_utf8(field).ok()?.trim();
+    let value: f64 = text.parse().ok()?;
+    if !value.is_finite() {
+        return None;
+    }
+
+    if let Some(absolute) = tolerance.absolute.filter(|t| *t > 0.0) {
+        let bucket = (value / absolute).round();
+        // Avoid "-0" and "0" hashing differently.
+        return Some(format!("{}", if bucket == 0.0 { 0.0 } else { bucket }));
+    }
+    if let Some(relative) = tolerance.relative.filter(|t| *t > 0.0 && *t < 1.0) {
+        if value == 0.0 {
+            return Some("0".to_string());
+        }
+        let digits = (-relative.log10()).ceil().max(1.0) as usize;
+        return Some(format!("{:.*e}", digits - 1, value));
+    }
+    Some(format!("{}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_within_tolerance_normalize_alike() {
+        let relative = NumericTolerance { absolute: None, relative: Some(1e-5), columns: vec![] };
+        assert_eq!(normalize_number(b"1.0", &relative), normalize_number(b"1.000001", &relative));
+        assert_ne!(normalize_number(b"1.0", &relative), normalize_number(b"1.1", &relative));
+
+        let absolute = NumericTolerance { absolute: Some(0.01), relative: None, columns: vec![] };
+        assert_eq!(normalize_number(b" 12.001", &absolute), normalize_number(b"12.0", &absolute));
+        assert_eq!(normalize_number(b"abc", &absolute), None);
+    }
+
+    #[test]
+    fn test_close_values_across_a_bucket_boundary_still_differ() {
+        let absolute = NumericTolerance { absolute: Some(0.01), relative: None, columns: vec![] };
+        assert_eq!(normalize_number(b"-0.0049", &absolute), normalize_number(b"0.0049", &absolute));
+        assert_ne!(normalize_number(b"0.0049", &absolute), normalize_number(b"0.0051", &absolute));
+        assert_ne!(normalize_number(b"1.0", &absolute), normalize_number(b"1.01", &absolute));
+
+        let relative = NumericTolerance { absolute: None, relative: Some(1e-5), columns: vec![] };
+        assert_ne!(normalize_number(b"1.000049", &relative), normalize_number(b"1.000051", &relative));
+    }
+}
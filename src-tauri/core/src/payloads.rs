@@ -0,0 +1,474 @@
+use crate::line_endings::LineEndingStyle;
+use crate::hashing::HashAlgo;
+
+#[derive(Clone, serde::Serialize)]
+pub struct ProgressPayload {
+    pub percentage: f64,
+    pub file: String,
+    pub text: String,
+    /// Progress of the whole job in percent, weighted by input size.
+    pub overall: f64,
+    /// Estimated time left, from the pace so far; unset until there is enough to go on.
+    pub eta_ms: Option<u64>,
+    /// Input bytes read per second so far.
+    pub bytes_per_sec: Option<f64>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct UniqueLinePayload {
+    pub file: String,
+    pub line_number: usize,
+    pub text: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct StepDetailPayload {
+    pub step: String,
+    pub duration_ms: u128,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ComparisonFinishedPayload {
+    pub summary: ComparisonSummary,
+}
+
+#[derive(Clone, Default, serde::Serialize)]
+pub struct ComparisonSummary {
+    pub unique_to_a: usize,
+    pub unique_to_b: usize,
+    pub modified: usize,
+    pub duplicates: usize,
+    /// Differences matching the expected-diff allowlist, not included in the counts above.
+    pub acknowledged: usize,
+    pub header_mismatch: Option<HeaderMismatch>,
+    pub delimiter_mismatch: Option<DelimiterMismatch>,
+    /// Fallbacks taken during the run, e.g. after the temp disk filled up.
+    pub degradations: Vec<String>,
+    pub inputs: Vec<FileIdentity>,
+    /// Both paths point to the same file (hardlink, symlink or mount).
+    pub same_file: bool,
+    /// Hashes shared by both files whose records turned out to differ (verification only).
+    pub hash_collisions: usize,
+    /// Blank and whitespace-only line counts of each file.
+    pub line_classes: Vec<LineClassCounts>,
+    /// Seed the line hashes were computed with; pass it as `hashSeed` to reproduce the run.
+    pub hash_seed: Option<u64>,
+    pub hash_algo: Option<HashAlgo>,
+    /// Fewer differences were emitted as events than found. The counts above are the true
+    /// totals; page through the rest with `get_results_page` or export them.
+    pub truncated: bool,
+    /// Number of `ordered_hunk` events of a small-file comparison, when the ordered diff was computed.
+    pub ordered_hunks: Option<usize>,
+    /// The comparison stopped at `stopAfterDifferences`; the counts above are lower bounds.
+    pub stopped_early: bool,
+    /// Where the files diverge, for a `firstDifferenceOnly` check that found a difference.
+    pub first_difference: Option<FirstDifference>,
+    /// The identical-file fast path found both files byte for byte the same; no engine ran.
+    pub identical: bool,
+    /// Lines found in both files, when an intersection was asked for. They are listed under
+    /// file A; the difference counts above stay 0.
+    pub in_both: Option<usize>,
+    /// How often the lines of each file repeat, when `frequencyReport` is set.
+    pub frequencies: Vec<FrequencyReport>,
+}
+
+/// The first position at which two files differ, with the line it falls on in each file.
+#[derive(Clone, serde::Serialize)]
+pub struct FirstDifference {
+    /// Byte offset of the first differing byte, or the length of the shorter file.
+    pub offset: u64,
+    pub line_number: u64,
+    /// The diverging line of each file, `None` when that file ends there.
+    pub line_a: Option<String>,
+    pub line_b: Option<String>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct LineClassCounts {
+    pub file: String,
+    pub blank: usize,
+    pub whitespace_only: usize,
+}
+
+/// Metadata of an input detected while preparing it, emitted as `file_metadata`.
+#[derive(Clone, serde::Serialize)]
+pub struct FileMetadataPayload {
+    pub file: String,
+    /// Dominant line ending in the first 64 KiB.
+    pub line_ending: LineEndingStyle,
+    pub lf_count: usize,
+    pub crlf_count: usize,
+    pub cr_count: usize,
+    /// Whether a trailing `\r` is stripped from its lines.
+    pub strips_cr: bool,
+}
+
+/// What `inspect_file` tells from samples of a file before it is compared.
+#[derive(Clone, serde::Serialize)]
+pub struct FileInspection {
+    pub size: u64,
+    /// Compression format of a compressed file; the fields below then describe its content.
+    pub compression: Option<String>,
+    /// Line count extrapolated from samples of the start, middle and end of the file, or
+    /// counted when `lines_exact`. `None` for compressed files.
+    pub estimated_lines: Option<u64>,
+    pub lines_exact: bool,
+    pub encoding: String,
+    pub bom: bool,
+    pub line_ending: LineEndingStyle,
+    /// Field delimiter the sampled records split on consistently, if any.
+    pub delimiter: Option<String>,
+    pub binary: bool,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct FileIdentity {
+    /// Path as given by the user.
+    pub path: String,
+    /// Absolute path with symlinks resolved.
+    pub canonical_path: String,
+    /// File name with its parent directory, for display.
+    pub display_name: String,
+    pub size: u64,
+    pub modified_ms: Option<u64>,
+    pub device: Option<u64>,
+    pub inode: Option<u64>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct HeaderMismatch {
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    /// Shared columns appear in a different order in file B.
+    pub reordered: bool,
+}
+
+/// The files parse cleanly with different delimiters. `None` means no candidate fit.
+#[derive(Clone, serde::Serialize)]
+pub struct DelimiterMismatch {
+    pub configured: String,
+    pub detected_a: Option<String>,
+    pub detected_b: Option<String>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct DiffLine {
+    pub line_number: usize,
+    pub text: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ModifiedLine {
+    pub line_a: DiffLine,
+    pub line_b: DiffLine,
+    /// Parts of `line_a.text` not found in `line_b.text`, and the other way round.
+    pub changes_a: Vec<TextRange>,
+    pub changes_b: Vec<TextRange>,
+}
+
+/// Adjacent records removed from A and added in B, in file order. `after_a` and `after_b`
+/// are the line numbers of the unchanged records before the hunk, 0 at the start.
+#[derive(Clone, serde::Serialize)]
+pub struct OrderedHunk {
+    pub after_a: usize,
+    pub after_b: usize,
+    pub removed: Vec<DiffLine>,
+    pub added: Vec<DiffLine>,
+}
+
+/// A range of a line's text in UTF-16 code units, the way JavaScript indexes strings.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct TextRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct PartialResultsPayload {
+    pub job_id: String,
+    pub reason: String,
+    pub unique_to_a: usize,
+    pub unique_to_b: usize,
+    pub modified: usize,
+}
+
+/// A line that occurs more than once across both files combined.
+#[derive(Clone, serde::Serialize)]
+pub struct DuplicateLinePayload {
+    /// File and line number of the first occurrence.
+    pub file: String,
+    pub line_number: usize,
+    pub text: String,
+    pub count_a: usize,
+    pub count_b: usize,
+}
+
+/// How often the lines of one file repeat, emitted as `frequency_report`.
+#[derive(Clone, serde::Serialize)]
+pub struct FrequencyReport {
+    pub file: String,
+    /// Number of different lines, as compared after normalization.
+    pub distinct_lines: usize,
+    /// The most frequent lines, most frequent first, located by their first occurrence.
+    pub top_lines: Vec<FrequentLine>,
+    /// Distinct lines by how often they occur, in power-of-two ranges of counts.
+    pub histogram: Vec<CountBucket>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct FrequentLine {
+    pub line_number: usize,
+    pub text: String,
+    pub count: usize,
+}
+
+/// Number of distinct lines occurring between `min_count` and `max_count` times.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct CountBucket {
+    pub min_count: usize,
+    pub max_count: usize,
+    pub lines: usize,
+}
+
+/// How a line of a three-way comparison changed relative to BASE.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThreeWayChange {
+    AddedInA,
+    AddedInB,
+    /// The same line was added in both versions.
+    AddedInBoth,
+    RemovedInA,
+    RemovedInB,
+    RemovedInBoth,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ThreeWayLine {
+    /// File the line was taken from: "A", "B" or "BASE" for removed lines.
+    pub file: String,
+    pub line_number: usize,
+    pub text: String,
+    pub count: usize,
+    pub change: ThreeWayChange,
+}
+
+/// A record (by key) that was changed differently in A and in B.
+#[derive(Clone, serde::Serialize)]
+pub struct ThreeWayConflict {
+    pub key: String,
+    pub line_base: Option<DiffLine>,
+    pub line_a: DiffLine,
+    pub line_b: DiffLine,
+}
+
+#[derive(Clone, Default, serde::Serialize)]
+pub struct ThreeWaySummary {
+    pub added_in_a: usize,
+    pub added_in_b: usize,
+    pub added_in_both: usize,
+    pub removed_in_a: usize,
+    pub removed_in_b: usize,
+    pub removed_in_both: usize,
+    pub conflicts: usize,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct ThreeWayFinishedPayload {
+    pub summary: ThreeWaySummary,
+}
+
+/// A line of an N-way comparison that doesn't occur equally often in all files.
+#[derive(Clone, serde::Serialize)]
+pub struct MultiWayLine {
+    /// Index of the file the text and line number were taken from.
+    pub file: usize,
+    pub line_number: usize,
+    pub text: String,
+    /// Occurrences per file, in the order the files were given.
+    pub counts: Vec<usize>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct MultiWayFinishedPayload {
+    pub files: usize,
+    pub differing_lines: usize,
+}
+
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryEntryStatus {
+    Compared,
+    OnlyInA,
+    OnlyInB,
+    Failed,
+}
+
+/// Result of one file pair of a directory comparison.
+#[derive(Clone, serde::Serialize)]
+pub struct DirectoryEntry {
+    pub relative_path: String,
+    pub status: DirectoryEntryStatus,
+    /// Child job holding the detailed results of the pair.
+    pub job_id: Option<String>,
+    pub summary: Option<ComparisonSummary>,
+    pub error: Option<String>,
+}
+
+/// Roll-up of a batch of file comparisons (a directory or a queue): one verdict for all files.
+#[derive(Clone, Default, serde::Serialize)]
+pub struct BatchSummary {
+    pub identical: usize,
+    pub different: usize,
+    pub only_in_a: usize,
+    pub only_in_b: usize,
+    pub failed: usize,
+    /// Unique and modified lines over all compared files.
+    pub total_differences: usize,
+    /// Files with the most differences, most first.
+    pub worst_offenders: Vec<BatchOffender>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct BatchOffender {
+    pub name: String,
+    pub job_id: Option<String>,
+    pub differences: usize,
+}
+
+/// One field of an explained line.
+#[derive(Clone, serde::Serialize)]
+pub struct ExplainedField {
+    /// Column index in file A the field is compared with, `None` if it isn't compared.
+    pub column: Option<usize>,
+    pub text: String,
+    /// Canonical form after numeric tolerance, when it applied.
+    pub normalized: Option<String>,
+}
+
+/// How a single line was treated by a comparison, for debugging missing or unexpected diffs.
+#[derive(Clone, serde::Serialize)]
+pub struct LineExplanation {
+    pub file: String,
+    pub line_number: usize,
+    pub text: String,
+    pub hash: String,
+    /// Text after JSON canonicalization and rewrite rules, as it was hashed.
+    pub normalized_text: String,
+    pub json_canonicalized: bool,
+    /// Indices of the rewrite rules that changed the line.
+    pub rewrite_rules_applied: Vec<usize>,
+    pub fields: Vec<ExplainedField>,
+    pub count_a: usize,
+    pub count_b: usize,
+    /// "unique", "modified" or "duplicate" when the line itself is in the results.
+    pub reported_as: Option<String>,
+    pub reason: String,
+}
+
+/// A difference matching the expected-diff allowlist, reported apart from the new ones.
+#[derive(Clone, serde::Serialize)]
+pub struct AcknowledgedDifference {
+    /// "unique" or "modified".
+    pub kind: String,
+    pub file: String,
+    pub line_number: usize,
+    pub text: String,
+    /// The B side of an acknowledged modified line.
+    pub other: Option<DiffLine>,
+}
+
+/// Stored differences of one file within a line range, for views scrolled alongside a file preview.
+#[derive(Clone, serde::Serialize)]
+pub struct ResultsWindow {
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub unique: Vec<DiffLine>,
+    pub modified: Vec<ModifiedLine>,
+    pub duplicates: Vec<DuplicateLinePayload>,
+    pub acknowledged: Vec<AcknowledgedDifference>,
+}
+
+/// One page of the stored differences of a side, for virtual scrolling.
+#[derive(Clone, serde::Serialize)]
+pub struct ResultsPage {
+    pub side: String,
+    pub offset: usize,
+    /// Differences stored for the side in total.
+    pub total: usize,
+    pub unique: Vec<DiffLine>,
+    pub modified: Vec<ModifiedLine>,
+}
+
+/// One page of the unique lines matching a `filter_results` query.
+#[derive(Clone, serde::Serialize)]
+pub struct FilteredResults {
+    pub offset: usize,
+    /// Matching lines in total.
+    pub total: usize,
+    pub lines: Vec<UniqueLinePayload>,
+}
+
+/// A difference picked by `get_result_sample`.
+#[derive(Clone, serde::Serialize)]
+pub struct SampledDifference {
+    /// `A` or `B` for unique lines, `modified` or `duplicate`.
+    pub category: String,
+    pub line_number: usize,
+    pub text: String,
+    pub count: usize,
+    /// The line of B a modified line of A was paired with.
+    pub other: Option<DiffLine>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueItemStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// Taken out of the queue before it ran.
+    Skipped,
+}
+
+/// One comparison of the job queue, in queue order.
+#[derive(Clone, serde::Serialize)]
+pub struct QueueItem {
+    pub job_id: String,
+    pub file_a_path: String,
+    pub file_b_path: String,
+    pub file_a_name: String,
+    pub file_b_name: String,
+    pub status: QueueItemStatus,
+    pub error: Option<String>,
+}
+
+/// Outcome of a comparison started by a schedule, emitted as `scheduled_run_finished`.
+#[derive(Clone, serde::Serialize)]
+pub struct ScheduledRun {
+    pub schedule_id: String,
+    pub job_id: Option<String>,
+    /// Export files written to the schedule's output directory.
+    pub outputs: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// A byte range of one file that has no counterpart in the other (binary block mode).
+#[derive(Clone, serde::Serialize)]
+pub struct BinaryRange {
+    pub file: String,
+    pub offset: u64,
+    pub len: u64,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct BinaryFinishedPayload {
+    pub size_a: u64,
+    pub size_b: u64,
+    pub differing_bytes_a: u64,
+    pub differing_bytes_b: u64,
+    pub ranges: usize,
+}
@@ -0,0 +1,168 @@
+use crate::payloads::ProgressPayload;
+use crate::sink::ProgressSink;
+use crate::utils::Throttle;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Part of a file's own progress taken by scanning it for line ends; hashing takes the rest.
+pub const SCAN_SHARE: f64 = 0.2;
+
+/// Progress of a job weighted by input size. Work done file by file (hashing, partitioning,
+/// merging) counts in proportion to each file's bytes, so a 100 GB file A outweighs a 1 GB
+/// file B; work over all files at once (aggregation) fills the rest of the overall bar.
+/// Updates from parallel workers are throttled so they don't flood the frontend.
+#[derive(Default)]
+pub struct ProgressModel {
+    state: Mutex<ProgressState>,
+    throttle: Throttle,
+}
+
+#[derive(Default)]
+struct ProgressState {
+    sizes: HashMap<String, u64>,
+    done: HashMap<String, f64>,
+    shared: f64,
+    /// Share of the overall progress taken by the per-file work.
+    file_share: f64,
+    started: Option<Instant>,
+    /// Text of the last update, which starts a new phase when it changes.
+    last_text: String,
+}
+
+impl ProgressState {
+    fn overall(&self) -> f64 {
+        let total: u64 = self.sizes.values().sum();
+        let files = if total == 0 {
+            let count = self.done.len().max(1) as f64;
+            self.done.values().sum::<f64>() / count
+        } else {
+            let done: f64 = self.done.iter().map(|(file, fraction)| self.sizes.get(file).copied().unwrap_or(0) as f64 * fraction).sum();
+            done / total as f64
+        };
+        (files * self.file_share + self.shared * (1.0 - self.file_share)) * 100.0
+    }
+
+    /// Input bytes the per-file work got through per second so far.
+    fn bytes_per_sec(&self) -> Option<f64> {
+        let elapsed = self.started?.elapsed().as_secs_f64();
+        let done: f64 = self.done.iter().map(|(file, fraction)| self.sizes.get(file).copied().unwrap_or(0) as f64 * fraction).sum();
+        (elapsed > 0.0 && done > 0.0).then(|| done / elapsed)
+    }
+
+    /// Time left if the job keeps its pace so far. Not guessed before 1% is done.
+    fn eta_ms(&self, overall: f64) -> Option<u64> {
+        let elapsed = self.started?.elapsed().as_millis() as f64;
+        (1.0..100.0).contains(&overall).then(|| (elapsed * (100.0 - overall) / overall) as u64)
+    }
+
+    /// Whether an update goes out: always the first of a phase and the one finishing it,
+    /// the others as the throttle allows.
+    fn should_emit(&mut self, throttle: &Throttle, text: &str, fraction: f64) -> bool {
+        let new_phase = self.last_text != text;
+        if new_phase {
+            self.last_text = text.to_string();
+        }
+        throttle.ready(new_phase || fraction >= 1.0)
+    }
+
+    fn payload(&self, percentage: f64, file_id: &str, text: &str) -> ProgressPayload {
+        let overall = self.overall();
+        ProgressPayload {
+            percentage,
+            file: file_id.to_string(),
+            text: text.to_string(),
+            overall,
+            eta_ms: self.eta_ms(overall),
+            bytes_per_sec: self.bytes_per_sec(),
+        }
+    }
+}
+
+impl ProgressModel {
+    /// Starts over with the given input sizes. `file_share` is the part of the overall
+    /// progress taken by per-file work, e.g. 0.5 when aggregation takes as long again.
+    pub fn start(&self, sizes: impl IntoIterator<Item = (String, u64)>, file_share: f64) {
+        *self.state.lock().unwrap() = ProgressState {
+            sizes: sizes.into_iter().collect(),
+            file_share: file_share.clamp(0.0, 1.0),
+            started: Some(Instant::now()),
+            ..Default::default()
+        };
+    }
+
+    /// Reports that `fraction` of the per-file work on `file_id` is done.
+    pub fn file_progress<S: ProgressSink>(&self, sink: &S, file_id: &str, fraction: f64, text: &str) {
+        let payload = {
+            let mut state = self.state.lock().unwrap();
+            state.done.insert(file_id.to_string(), fraction.clamp(0.0, 1.0));
+            if !state.should_emit(&self.throttle, text, fraction) {
+                return;
+            }
+            state.payload(fraction * 100.0, file_id, text)
+        };
+        emit(sink, payload);
+    }
+
+    /// Reports that `fraction` of the work over all files is done. `percentage` and `file_id`
+    /// are what the per-file bar shows.
+    pub fn shared_progress<S: ProgressSink>(&self, sink: &S, fraction: f64, percentage: f64, file_id: &str, text: &str) {
+        let payload = {
+            let mut state = self.state.lock().unwrap();
+            state.shared = fraction.clamp(0.0, 1.0);
+            if !state.should_emit(&self.throttle, text, fraction) {
+                return;
+            }
+            state.payload(percentage, file_id, text)
+        };
+        emit(sink, payload);
+    }
+}
+
+/// Moves one file's progress forward from parallel workers: each adds the work it finished
+/// and the file's bar shows the total so far, scaled into `range` of the file's progress.
+pub struct FileCounter<'a, S: ProgressSink> {
+    sink: &'a S,
+    model: &'a ProgressModel,
+    file_id: &'a str,
+    text: &'a str,
+    total: u64,
+    range: (f64, f64),
+    done: AtomicU64,
+}
+
+impl<'a, S: ProgressSink> FileCounter<'a, S> {
+    pub fn new(sink: &'a S, model: &'a ProgressModel, file_id: &'a str, text: &'a str, total: u64, range: (f64, f64)) -> Self {
+        Self { sink, model, file_id, text, total: total.max(1), range, done: AtomicU64::new(0) }
+    }
+
+    pub fn add(&self, amount: u64) {
+        let done = (self.done.fetch_add(amount, Ordering::Relaxed) + amount).min(self.total);
+        let fraction = self.range.0 + (self.range.1 - self.range.0) * done as f64 / self.total as f64;
+        self.model.file_progress(self.sink, self.file_id, fraction, self.text);
+    }
+}
+
+fn emit<S: ProgressSink>(sink: &S, payload: ProgressPayload) {
+    sink.on_progress(&payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overall_progress_is_weighted_by_size() {
+        let model = ProgressModel::default();
+        model.start([("A".to_string(), 99), ("B".to_string(), 1)], 0.5);
+        let mut state = model.state.lock().unwrap();
+        state.done.insert("B".to_string(), 1.0);
+        assert!((state.overall() - 0.5).abs() < 1e-9);
+        state.done.insert("A".to_string(), 1.0);
+        state.shared = 0.5;
+        assert!((state.overall() - 75.0).abs() < 1e-9);
+        assert!(state.eta_ms(75.0).is_some());
+        assert!(state.eta_ms(0.5).is_none() && state.eta_ms(100.0).is_none());
+    }
+}
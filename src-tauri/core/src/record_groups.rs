@@ -0,0 +1,76 @@
+use crate::line_endings::RECORD_SEPARATOR;
+use regex::bytes::Regex;
+use std::io::{BufRead, Error as IoError, ErrorKind, Read};
+
+/// Streams the lines of `lines` grouped into records: a line matching `start` begins a
+/// record, and the lines after it up to the next match (e.g. a stack trace under a log
+/// entry) stay in it joined by `\n`. Records end at [`RECORD_SEPARATOR`], which the input
+/// must not contain.
+pub struct RecordGroups<R> {
+    lines: R,
+    start: Regex,
+    line: Vec<u8>,
+    grouped: Vec<u8>,
+    position: usize,
+    first: bool,
+    done: bool,
+}
+
+impl<R: BufRead> RecordGroups<R> {
+    pub fn new(lines: R, start: Regex) -> Self {
+        RecordGroups { lines, start, line: Vec::new(), grouped: Vec::new(), position: 0, first: true, done: false }
+    }
+
+    fn next_line(&mut self) -> Result<(), IoError> {
+        self.grouped.clear();
+        self.position = 0;
+        self.line.clear();
+        if self.lines.read_until(b'\n', &mut self.line)? == 0 {
+            if !self.first {
+                self.grouped.push(RECORD_SEPARATOR);
+            }
+            self.done = true;
+            return Ok(());
+        }
+        if memchr::memchr(RECORD_SEPARATOR, &self.line).is_some() {
+            return Err(IoError::new(ErrorKind::InvalidData, "The input contains the byte \\x1e, so its lines can't be grouped into records"));
+        }
+        let line = self.line.strip_suffix(b"\n").unwrap_or(&self.line);
+        if !self.first {
+            let starts = self.start.is_match(line.strip_suffix(b"\r").unwrap_or(line));
+            self.grouped.push(if starts { RECORD_SEPARATOR } else { b'\n' });
+        }
+        self.first = false;
+        self.grouped.extend_from_slice(line);
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for RecordGroups<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        while self.position == self.grouped.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.next_line()?;
+        }
+        let n = buf.len().min(self.grouped.len() - self.position);
+        buf[..n].copy_from_slice(&self.grouped[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_continuation_lines_join_the_record_above() {
+        let log = "2024-01-01 ok\n2024-01-02 failed\n  at main.rs:3\n  at lib.rs:9\n2024-01-03 ok\n";
+        let start = Regex::new(r"^\d{4}-\d{2}-\d{2} ").unwrap();
+        let mut grouped = String::new();
+        RecordGroups::new(log.as_bytes(), start).read_to_string(&mut grouped).unwrap();
+        assert_eq!(grouped, "2024-01-01 ok\x1e2024-01-02 failed\n  at main.rs:3\n  at lib.rs:9\x1e2024-01-03 ok\x1e");
+    }
+}
@@ -0,0 +1,122 @@
+// Parsers for raw record bytes. Kept free of other crate modules so the fuzz targets
+// can include this file on its own.
+use rayon::prelude::*;
+use std::borrow::Cow;
+
+/// Positions of every `terminator` byte in `data`, in order, scanned in parallel chunks of
+/// `chunk_size` bytes. `on_chunk` is called with the length of each chunk once it is done.
+pub fn find_line_ends(data: &[u8], terminator: u8, chunk_size: usize, on_chunk: impl Fn(usize) + Sync) -> Vec<usize> {
+    let per_chunk: Vec<Vec<usize>> = data
+        .par_chunks(chunk_size.max(1))
+        .enumerate()
+        .map(|(chunk_index, chunk)| {
+            let base_offset = chunk_index * chunk_size.max(1);
+            let positions = memchr::memchr_iter(terminator, chunk).map(|pos| base_offset + pos).collect();
+            on_chunk(chunk.len());
+            positions
+        })
+        .collect();
+    let mut positions = Vec::with_capacity(per_chunk.iter().map(Vec::len).sum());
+    for chunk in per_chunk {
+        positions.extend(chunk);
+    }
+    positions
+}
+
+/// Longest a record may grow while a quote is open. A stray quote would otherwise merge the
+/// rest of the file into one record; past this length the next terminator ends it anyway.
+pub const MAX_QUOTED_RECORD: usize = 16 * 1024 * 1024;
+
+/// Finds the positions of record-terminating bytes, skipping terminators inside quoted fields
+/// up to `MAX_QUOTED_RECORD`. The result has the same shape as the plain newline scanners, so
+/// the engines can use either.
+pub fn find_record_ends(data: &[u8], terminator: u8, quote: u8) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for pos in memchr::memchr2_iter(terminator, quote, data) {
+        if data[pos] == quote {
+            // A doubled quote ("") toggles twice, which leaves the state unchanged.
+            in_quotes = !in_quotes;
+        } else if !in_quotes || pos - start >= MAX_QUOTED_RECORD {
+            positions.push(pos);
+            in_quotes = false;
+            start = pos + 1;
+        }
+    }
+    positions
+}
+
+/// Length of the record starting at the beginning of `data`, excluding its terminator.
+pub fn record_len(data: &[u8], terminator: u8, quote: Option<u8>) -> usize {
+    match quote {
+        Some(q) => {
+            let mut in_quotes = false;
+            for pos in memchr::memchr2_iter(terminator, q, data) {
+                if data[pos] == q {
+                    in_quotes = !in_quotes;
+                } else if !in_quotes || pos >= MAX_QUOTED_RECORD {
+                    return pos;
+                }
+            }
+            data.len()
+        }
+        None => memchr::memchr(terminator, data).unwrap_or(data.len()),
+    }
+}
+
+/// Splits a record into fields. Quoted fields are unquoted and doubled quotes unescaped.
+pub fn split_delimited<'a>(record: &'a [u8], delimiter: &[u8], quote: Option<u8>) -> Vec<Cow<'a, [u8]>> {
+    if delimiter.is_empty() {
+        return vec![Cow::Borrowed(record)];
+    }
+    let mut fields = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        if let Some(q) = quote
+            && record.get(pos) == Some(&q)
+        {
+            let mut value = Vec::new();
+            let mut i = pos + 1;
+            while i < record.len() {
+                if record[i] == q {
+                    if record.get(i + 1) == Some(&q) {
+                        value.push(q);
+                        i += 2;
+                        continue;
+                    }
+                    i += 1;
+                    break;
+                }
+                value.push(record[i]);
+                i += 1;
+            }
+            // Anything between the closing quote and the next delimiter is kept verbatim.
+            match memchr::memmem::find(&record[i..], delimiter) {
+                Some(off) => {
+                    value.extend_from_slice(&record[i..i + off]);
+                    fields.push(Cow::Owned(value));
+                    pos = i + off + delimiter.len();
+                }
+                None => {
+                    value.extend_from_slice(&record[i..]);
+                    fields.push(Cow::Owned(value));
+                    break;
+                }
+            }
+        } else {
+            match memchr::memmem::find(&record[pos..], delimiter) {
+                Some(off) => {
+                    fields.push(Cow::Borrowed(&record[pos..pos + off]));
+                    pos += off + delimiter.len();
+                }
+                None => {
+                    fields.push(Cow::Borrowed(&record[pos..]));
+                    break;
+                }
+            }
+        }
+    }
+    fields
+}
@@ -0,0 +1,220 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Instance metadata and container endpoints answer at once where they exist; elsewhere the
+/// connection hangs, so give up quickly.
+const METADATA_TIMEOUT: Duration = Duration::from_secs(1);
+const STS_TIMEOUT: Duration = Duration::from_secs(30);
+const IMDS: &str = "http://169.254.169.254";
+const ECS: &str = "http://169.254.170.2";
+
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+}
+
+/// Credentials as `credential_process` prints them and the instance and container
+/// endpoints return them.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CredentialsJson {
+    access_key_id: String,
+    secret_access_key: String,
+    #[serde(alias = "Token")]
+    session_token: Option<String>,
+}
+
+impl From<CredentialsJson> for Credentials {
+    fn from(json: CredentialsJson) -> Self {
+        Credentials { access_key: json.access_key_id, secret_key: json.secret_access_key, session_token: json.session_token }
+    }
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn aws_file(variable: &str, default: &str) -> Option<PathBuf> {
+    std::env::var_os(variable).map(PathBuf::from).or_else(|| {
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+        Some(Path::new(&home).join(".aws").join(default))
+    })
+}
+
+/// Keys of `[section]` in an INI file like `~/.aws/credentials`, empty if there is none.
+fn ini_section(path: Option<PathBuf>, section: &str) -> HashMap<String, String> {
+    let Some(content) = path.and_then(|path| fs::read_to_string(path).ok()) else {
+        return HashMap::new();
+    };
+    let mut values = HashMap::new();
+    let mut inside = false;
+    for line in content.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            inside = name.trim() == section;
+        } else if inside && let Some((key, value)) = line.split_once('=') {
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    values
+}
+
+/// The profile named by `AWS_PROFILE`, or `default`.
+pub fn profile() -> String {
+    env("AWS_PROFILE").unwrap_or_else(|| "default".to_string())
+}
+
+/// Keys of the profile in the shared config file, e.g. `region` or `credential_process`.
+pub fn profile_config(profile: &str) -> HashMap<String, String> {
+    let section = if profile == "default" { profile.to_string() } else { format!("profile {}", profile) };
+    ini_section(aws_file("AWS_CONFIG_FILE", "config"), &section)
+}
+
+/// Looks up credentials in the order of the AWS SDKs: `AWS_*` environment variables, the
+/// profile's keys in the shared credentials file, its `credential_process` or web identity
+/// role in the config file, a web identity token from the environment (as on EKS), the
+/// container endpoint (ECS) and finally the instance metadata service (IMDSv2). SSO isn't
+/// supported.
+pub fn resolve(profile: &str, region: &str) -> Result<Credentials, IoError> {
+    if let (Some(access_key), Some(secret_key)) = (env("AWS_ACCESS_KEY_ID"), env("AWS_SECRET_ACCESS_KEY")) {
+        return Ok(Credentials { access_key, secret_key, session_token: env("AWS_SESSION_TOKEN") });
+    }
+    let mut file = ini_section(aws_file("AWS_SHARED_CREDENTIALS_FILE", "credentials"), profile);
+    if let (Some(access_key), Some(secret_key)) = (file.remove("aws_access_key_id"), file.remove("aws_secret_access_key")) {
+        return Ok(Credentials { access_key, secret_key, session_token: file.remove("aws_session_token") });
+    }
+    let mut config = profile_config(profile);
+    if let Some(process) = config.remove("credential_process") {
+        return credential_process(&process);
+    }
+    if let (Some(token_file), Some(role)) = (config.remove("web_identity_token_file"), config.remove("role_arn")) {
+        return web_identity(&token_file, &role, region);
+    }
+    if let (Some(token_file), Some(role)) = (env("AWS_WEB_IDENTITY_TOKEN_FILE"), env("AWS_ROLE_ARN")) {
+        return web_identity(&token_file, &role, region);
+    }
+    if let Some(credentials) = container()? {
+        return Ok(credentials);
+    }
+    if env("AWS_EC2_METADATA_DISABLED").is_none_or(|disabled| !disabled.eq_ignore_ascii_case("true"))
+        && let Some(credentials) = instance_metadata()
+    {
+        return Ok(credentials);
+    }
+    Err(IoError::new(ErrorKind::PermissionDenied, "No AWS credentials found for S3 input"))
+}
+
+/// Runs the profile's `credential_process` through the shell and reads the credentials it prints.
+fn credential_process(process: &str) -> Result<Credentials, IoError> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").arg("/C").arg(process).output()
+    } else {
+        Command::new("sh").arg("-c").arg(process).output()
+    }?;
+    if !output.status.success() {
+        return Err(IoError::new(
+            ErrorKind::PermissionDenied,
+            format!("credential_process failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+        ));
+    }
+    let json: CredentialsJson = serde_json::from_slice(&output.stdout)
+        .map_err(|err| IoError::new(ErrorKind::InvalidData, format!("credential_process printed no credentials: {}", err)))?;
+    Ok(json.into())
+}
+
+/// Text of the first `<tag>` element of an XML response.
+fn xml_value<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    Some(&xml[start..end])
+}
+
+/// Exchanges the token in `token_file` for credentials of `role` with STS.
+fn web_identity(token_file: &str, role: &str, region: &str) -> Result<Credentials, IoError> {
+    let token = fs::read_to_string(token_file)?;
+    let response = ureq::get(&format!("https://sts.{}.amazonaws.com/", region))
+        .timeout(STS_TIMEOUT)
+        .query("Action", "AssumeRoleWithWebIdentity")
+        .query("Version", "2011-06-15")
+        .query("RoleArn", role)
+        .query("RoleSessionName", &env("AWS_ROLE_SESSION_NAME").unwrap_or_else(|| "large-file-compare".to_string()))
+        .query("WebIdentityToken", token.trim())
+        .call()
+        .map_err(|err| IoError::new(ErrorKind::PermissionDenied, format!("Assuming {} with the web identity token failed: {}", role, err)))?;
+    let body = response.into_string()?;
+    match (xml_value(&body, "AccessKeyId"), xml_value(&body, "SecretAccessKey")) {
+        (Some(access_key), Some(secret_key)) => Ok(Credentials {
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            session_token: xml_value(&body, "SessionToken").map(str::to_string),
+        }),
+        _ => Err(IoError::new(ErrorKind::InvalidData, "STS returned no credentials")),
+    }
+}
+
+/// Credentials of the task role from the container endpoint, if running in one.
+fn container() -> Result<Option<Credentials>, IoError> {
+    let url = match (env("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI"), env("AWS_CONTAINER_CREDENTIALS_FULL_URI")) {
+        (Some(relative), _) => format!("{}{}", ECS, relative),
+        (None, Some(full)) => full,
+        (None, None) => return Ok(None),
+    };
+    let mut request = ureq::get(&url).timeout(METADATA_TIMEOUT);
+    let token = match env("AWS_CONTAINER_AUTHORIZATION_TOKEN_FILE") {
+        Some(path) => Some(fs::read_to_string(path)?.trim().to_string()),
+        None => env("AWS_CONTAINER_AUTHORIZATION_TOKEN"),
+    };
+    if let Some(token) = token {
+        request = request.set("Authorization", &token);
+    }
+    let json: CredentialsJson = request
+        .call()
+        .map_err(|err| IoError::new(ErrorKind::PermissionDenied, format!("The container credentials endpoint failed: {}", err)))?
+        .into_json()?;
+    Ok(Some(json.into()))
+}
+
+/// Credentials of the instance role from IMDSv2, or `None` when not on an instance with one.
+fn instance_metadata() -> Option<Credentials> {
+    let token = ureq::put(&format!("{}/latest/api/token", IMDS))
+        .timeout(METADATA_TIMEOUT)
+        .set("X-aws-ec2-metadata-token-ttl-seconds", "21600")
+        .call()
+        .ok()?
+        .into_string()
+        .ok()?;
+    let get = |path: &str| {
+        ureq::get(&format!("{}/latest/meta-data/iam/security-credentials/{}", IMDS, path))
+            .timeout(METADATA_TIMEOUT)
+            .set("X-aws-ec2-metadata-token", &token)
+            .call()
+            .ok()
+    };
+    let role = get("")?.into_string().ok()?;
+    let json: CredentialsJson = get(role.lines().next()?.trim())?.into_json().ok()?;
+    Some(json.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credentials_are_read_from_process_metadata_and_sts_output() {
+        let process = r#"{"Version": 1, "AccessKeyId": "AKID", "SecretAccessKey": "secret", "SessionToken": "token", "Expiration": "2030-01-01T00:00:00Z"}"#;
+        let credentials: Credentials = serde_json::from_str::<CredentialsJson>(process).unwrap().into();
+        assert_eq!((credentials.access_key.as_str(), credentials.session_token.as_deref()), ("AKID", Some("token")));
+        let metadata = r#"{"Code": "Success", "AccessKeyId": "ASIA", "SecretAccessKey": "secret", "Token": "instance"}"#;
+        assert_eq!(serde_json::from_str::<CredentialsJson>(metadata).unwrap().session_token.as_deref(), Some("instance"));
+
+        let sts = "<Credentials><SessionToken>t/+=</SessionToken><AccessKeyId>ASIA</AccessKeyId></Credentials>";
+        assert_eq!(xml_value(sts, "SessionToken"), Some("t/+="));
+        assert_eq!(xml_value(sts, "AccessKeyId"), Some("ASIA"));
+        assert_eq!(xml_value(sts, "SecretAccessKey"), None);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::identity::display_name;
+use crate::jobs::Job;
+use crate::payloads::FileIdentity;
+use crate::sink::ProgressSink;
+use std::fs::File;
+use std::io::{BufWriter, Error as IoError, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Attempts in a row that may fail without getting any further before the download gives up.
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longest wait for the next bytes of a response before the connection counts as dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+const BUFFER_SIZE: usize = 1 << 20;
+
+pub fn is_url(path: &str) -> bool {
+    let scheme = path.split_once("://").map(|(scheme, _)| scheme.to_ascii_lowercase());
+    matches!(scheme.as_deref(), Some("http" | "https"))
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .user_agent(concat!("large-file-compare/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+fn status_error(url: &str, err: ureq::Error) -> IoError {
+    match err {
+        ureq::Error::Status(404, _) => IoError::new(ErrorKind::NotFound, format!("{} doesn't exist", url)),
+        ureq::Error::Status(status, _) if status >= 500 => {
+            IoError::new(ErrorKind::TimedOut, format!("{} answered with status {}", url, status))
+        }
+        ureq::Error::Status(status, _) => IoError::other(format!("{} answered with status {}", url, status)),
+        ureq::Error::Transport(transport) => IoError::new(ErrorKind::TimedOut, transport.to_string()),
+    }
+}
+
+/// Identity of a URL input from a HEAD request. Size is 0 when the server doesn't say.
+pub fn identity(url: &str) -> Result<FileIdentity, IoError> {
+    let response = agent().head(url).set("Accept-Encoding", "identity").call().map_err(|err| status_error(url, err))?;
+    let modified_ms = response
+        .header("Last-Modified")
+        .and_then(|modified| chrono::DateTime::parse_from_rfc2822(modified).ok())
+        .map(|modified| modified.timestamp_millis() as u64);
+    Ok(FileIdentity {
+        path: url.to_string(),
+        display_name: display_name(url),
+        canonical_path: url.to_string(),
+        size: response.header("Content-Length").and_then(|length| length.parse().ok()).unwrap_or(0),
+        modified_ms,
+        device: None,
+        inode: None,
+    })
+}
+
+/// What identifies the version of a response for `If-Range`: its strong ETag, or else its
+/// Last-Modified date.
+fn validator(response: &ureq::Response) -> Option<String> {
+    response
+        .header("ETag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| response.header("Last-Modified"))
+        .map(str::to_string)
+}
+
+/// Start of the range a `206` response holds, from its `Content-Range`.
+fn range_start(response: &ureq::Response) -> Option<u64> {
+    response.header("Content-Range")?.strip_prefix("bytes ")?.split_once('-')?.0.trim().parse().ok()
+}
+
+fn truncate(file: &mut BufWriter<File>) -> Result<(), IoError> {
+    file.flush()?;
+    file.get_mut().set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(())
+}
+
+/// Downloads `url` to `target`. A dropped connection or server error resumes with a range
+/// request from the bytes already written, guarded by `If-Range` so a file that changed in
+/// between is downloaded again from the start, as it is when the server ignores ranges or
+/// can't tell the version. Returns the size downloaded.
+pub fn download<S: ProgressSink>(sink: &S, job: &Job, url: &str, target: &Path, file_id: &str) -> Result<u64, IoError> {
+    let agent = agent();
+    let mut file = BufWriter::new(File::create(target)?);
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let (mut written, mut total, mut version) = (0u64, None, None::<String>);
+    let mut failed_attempts = 0;
+    loop {
+        job.check_cancelled()?;
+        let mut request = agent.get(url).set("Accept-Encoding", "identity");
+        match &version {
+            Some(version) if written > 0 => {
+                request = request.set("Range", &format!("bytes={}-", written)).set("If-Range", version);
+            }
+            _ if written > 0 => {
+                truncate(&mut file)?;
+                written = 0;
+            }
+            _ => {}
+        }
+        let result = match request.call() {
+            Ok(response) => {
+                if written > 0 && (response.status() != 206 || range_start(&response) != Some(written)) {
+                    truncate(&mut file)?;
+                    written = 0;
+                }
+                let length: Option<u64> = response.header("Content-Length").and_then(|length| length.parse().ok());
+                if written == 0 {
+                    total = length;
+                    version = validator(&response);
+                }
+                let mut reader = response.into_reader();
+                loop {
+                    job.check_cancelled()?;
+                    match reader.read(&mut buffer) {
+                        Ok(0) => break Ok(()),
+                        Ok(n) => {
+                            file.write_all(&buffer[..n])?;
+                            written += n as u64;
+                            failed_attempts = 0;
+                            let fraction = total.map_or(0.0, |total| written as f64 / total.max(1) as f64);
+                            job.progress.file_progress(sink, file_id, fraction, "Downloading...");
+                        }
+                        Err(e) => break Err(e),
+                    }
+                }
+            }
+            // Everything was written before the connection dropped.
+            Err(ureq::Error::Status(416, _)) if total == Some(written) => Ok(()),
+            // The file shrank or the server can't serve the range: start over.
+            Err(ureq::Error::Status(416, _)) => {
+                version = None;
+                continue;
+            }
+            Err(err) => Err(status_error(url, err)),
+        };
+        match result {
+            Ok(()) => {
+                file.flush()?;
+                return Ok(written);
+            }
+            Err(e) if e.kind() != ErrorKind::NotFound && failed_attempts + 1 < MAX_ATTEMPTS => {
+                eprintln!("Download of {} interrupted at {} bytes, resuming: {}", url, written, e);
+            }
+            Err(e) => return Err(e),
+        }
+        std::thread::sleep(RETRY_DELAY * 2u32.pow(failed_attempts));
+        failed_attempts += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::JobRegistry;
+    use crate::sink::CollectingSink;
+    use std::io::BufRead;
+    use std::net::TcpListener;
+
+    /// Serves `body`, dropping the first connection halfway through it. Range requests
+    /// that don't name the version in `If-Range` are dropped too.
+    fn flaky_server(body: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().take(2).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let (mut start, mut current) = (0, false);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    let header = line.to_ascii_lowercase();
+                    if let Some(range) = header.strip_prefix("range: bytes=") {
+                        start = range.trim().trim_end_matches('-').parse().unwrap();
+                    }
+                    current |= header.trim() == "if-range: \"v1\"";
+                    line.clear();
+                }
+                if start > 0 && !current {
+                    continue;
+                }
+                let status = if start > 0 { "206 Partial Content" } else { "200 OK" };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nETag: \"v1\"\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\n\r\n",
+                    status,
+                    start,
+                    body.len() - 1,
+                    body.len(),
+                    body.len() - start
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                let end = if index == 0 { body.len() / 2 } else { body.len() };
+                stream.write_all(&body[start..end]).unwrap();
+            }
+        });
+        format!("http://{}/data.csv", address)
+    }
+
+    #[test]
+    fn test_download_resumes_after_a_dropped_connection() {
+        assert!(is_url("HTTPS://example.com/data.csv") && !is_url("s3://bucket/key") && !is_url("/tmp/data.csv"));
+        let body: &[u8] = b"id,name\n1,a\n2,b\n3,c\n4,d\n";
+        let url = flaky_server(body);
+        let config = serde_json::from_value(serde_json::json!({
+            "useExternalSort": false, "ignoreOccurences": false, "useSingleThread": false, "ignoreLineNumber": false,
+        }))
+        .unwrap();
+        let job = JobRegistry::default().create(config);
+        let target = std::env::temp_dir().join(format!("http_test_{}", std::process::id()));
+        let size = download(&CollectingSink::default(), &job, &url, &target, "A").unwrap();
+        assert_eq!((size, std::fs::read(&target).unwrap()), (body.len() as u64, body.to_vec()));
+        std::fs::remove_file(&target).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use crate::identity::display_name;
+use crate::jobs::Job;
+use crate::payloads::FileIdentity;
+use crate::sink::ProgressSink;
+use std::io::{Error as IoError, ErrorKind, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+const SCHEME: &str = "sftp://";
+/// How often a running transfer is checked for cancellation and progress.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// A file named by an `sftp://user@host[:port]/path` input path. The path is absolute,
+/// or relative to the login directory when it starts with `/~/`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SftpFile {
+    destination: String,
+    port: Option<u16>,
+    path: String,
+}
+
+impl SftpFile {
+    pub fn parse(path: &str) -> Option<Self> {
+        let (authority, remote_path) = path.strip_prefix(SCHEME)?.split_once('/')?;
+        let (destination, port) = match authority.rsplit_once(':') {
+            Some((destination, port)) => (destination, Some(port.parse().ok()?)),
+            None => (authority, None),
+        };
+        let remote_path = match remote_path.strip_prefix("~/") {
+            Some(relative) => relative.to_string(),
+            None => format!("/{}", remote_path),
+        };
+        // A destination starting with `-` would be read by sftp as an option, e.g. a ProxyCommand.
+        (!destination.is_empty() && !destination.starts_with('-') && !remote_path.ends_with('/'))
+            .then(|| SftpFile { destination: destination.to_string(), port, path: remote_path })
+    }
+
+    /// Runs the OpenSSH `sftp` client on `commands`. Batch mode never prompts, so only
+    /// key-based authentication (keys from the SSH config or agent) is used.
+    fn run_batch(&self, commands: &str) -> Result<Child, IoError> {
+        let mut command = Command::new("sftp");
+        command.args(["-q", "-b", "-", "-o", "BatchMode=yes"]);
+        if let Some(port) = self.port {
+            command.arg("-P").arg(port.to_string());
+        }
+        let mut child = command
+            .arg("--")
+            .arg(&self.destination)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| match err.kind() {
+                ErrorKind::NotFound => IoError::new(ErrorKind::NotFound, "SFTP inputs need the OpenSSH sftp client"),
+                _ => err,
+            })?;
+        child.stdin.take().expect("stdin is piped").write_all(commands.as_bytes())?;
+        Ok(child)
+    }
+
+    fn failure(&self, stderr: &[u8]) -> IoError {
+        let message = String::from_utf8_lossy(stderr);
+        IoError::other(format!("SFTP transfer from {} failed: {}", self.destination, message.trim()))
+    }
+}
+
+/// Quotes an argument of an sftp batch command. Control characters are rejected: a line
+/// break would end the command and start another one, e.g. `rm`.
+fn quote(argument: &str) -> Result<String, IoError> {
+    if argument.chars().any(char::is_control) {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("SFTP path {:?} contains control characters", argument)));
+    }
+    Ok(format!("\"{}\"", argument.replace('\\', "\\\\").replace('"', "\\\"")))
+}
+
+/// Size from the `ls -l` listing of a single file, skipping the echoed commands.
+fn listed_size(listing: &str) -> Option<u64> {
+    listing
+        .lines()
+        .filter(|line| !line.starts_with("sftp>"))
+        .find_map(|line| line.split_whitespace().nth(4)?.parse().ok())
+}
+
+fn size(file: &SftpFile) -> Result<u64, IoError> {
+    let output = file.run_batch(&format!("ls -ln {}\n", quote(&file.path)?))?.wait_with_output()?;
+    if !output.status.success() {
+        return Err(file.failure(&output.stderr));
+    }
+    listed_size(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| IoError::other(format!("Unexpected listing of {}", file.path)))
+}
+
+/// Identity of an SFTP input from its listing. The modification time isn't parsed, as
+/// listings only give it to the minute and without the year for recent files.
+pub fn identity(path: &str, file: &SftpFile) -> Result<FileIdentity, IoError> {
+    Ok(FileIdentity {
+        path: path.to_string(),
+        display_name: display_name(path),
+        canonical_path: path.to_string(),
+        size: size(file)?,
+        modified_ms: None,
+        device: None,
+        inode: None,
+    })
+}
+
+/// Downloads the file to `target`, reporting progress from the bytes arrived so far and
+/// stopping the transfer when the job is cancelled. Returns its size.
+pub fn download<S: ProgressSink>(sink: &S, job: &Job, file: &SftpFile, target: &Path, file_id: &str) -> Result<u64, IoError> {
+    let total = size(file)?;
+    let target_path = target.to_string_lossy();
+    let mut child = file.run_batch(&format!("get {} {}\n", quote(&file.path)?, quote(&target_path)?))?;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if job.is_cancelled() {
+            child.kill()?;
+            child.wait()?;
+            return job.check_cancelled().map(|_| 0);
+        }
+        let done = std::fs::metadata(target).map_or(0, |metadata| metadata.len());
+        job.progress.file_progress(sink, file_id, done as f64 / total.max(1) as f64, "Downloading...");
+        std::thread::sleep(POLL_INTERVAL);
+    };
+    if !status.success() {
+        let mut stderr = Vec::new();
+        child.stderr.take().expect("stderr is piped").read_to_end(&mut stderr)?;
+        return Err(file.failure(&stderr));
+    }
+    Ok(std::fs::metadata(target)?.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paths_and_listings() {
+        let file = SftpFile::parse("sftp://partner@files.example.com:2222/in/data.csv").unwrap();
+        assert_eq!((file.destination.as_str(), file.port, file.path.as_str()), ("partner@files.example.com", Some(2222), "/in/data.csv"));
+        assert_eq!(SftpFile::parse("sftp://partner@host/~/data.csv").unwrap().path, "data.csv");
+        assert!(SftpFile::parse("sftp://host").is_none() && SftpFile::parse("sftp://host:port/a").is_none());
+        assert!(SftpFile::parse("sftp://-oProxyCommand=touch%20x/a").is_none());
+        assert_eq!(quote(r#"a "b"\c"#).unwrap(), r#""a \"b\"\\c""#);
+        let injected = SftpFile::parse("sftp://partner@host/in/data.csv\nrm /in/other.csv").unwrap();
+        assert_eq!(quote(&injected.path).unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(quote("a\rb").is_err());
+
+        let listing = "sftp> ls -ln \"/in/data.csv\"\n-rw-r--r--    ? 1001     1001        52428800 Mar  3 10:00 /in/data.csv\n";
+        assert_eq!(listed_size(listing), Some(52_428_800));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::jobs::Job;
+use crate::payloads::FileIdentity;
+use crate::remote::http;
+use crate::remote::s3::{self, S3Object};
+use crate::remote::sftp::{self, SftpFile};
+use crate::remote::sql::{self, SqlQuery};
+use crate::sink::ProgressSink;
+use std::io::Error as IoError;
+use std::path::Path;
+
+/// An input that isn't a local file and is downloaded (or for queries, exported) before the
+/// comparison reads it.
+pub enum RemoteSource {
+    S3(S3Object),
+    Http(String),
+    Sftp(SftpFile),
+    Sql(SqlQuery),
+}
+
+impl RemoteSource {
+    /// The remote source an input path names, or `None` for local paths.
+    pub fn parse(path: &str) -> Option<Self> {
+        if let Some(object) = S3Object::parse(path) {
+            Some(RemoteSource::S3(object))
+        } else if let Some(file) = SftpFile::parse(path) {
+            Some(RemoteSource::Sftp(file))
+        } else if let Some(query) = SqlQuery::parse(path) {
+            Some(RemoteSource::Sql(query))
+        } else if http::is_url(path) {
+            Some(RemoteSource::Http(path.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Name of the source in step details, e.g. "Downloaded 12 MiB from S3".
+    pub fn name(&self) -> &'static str {
+        match self {
+            RemoteSource::S3(_) => "S3",
+            RemoteSource::Http(_) => "HTTP",
+            RemoteSource::Sftp(_) => "SFTP",
+            RemoteSource::Sql(query) => query.dialect.name(),
+        }
+    }
+
+    pub fn identity(&self, path: &str) -> Result<FileIdentity, IoError> {
+        match self {
+            RemoteSource::S3(object) => s3::identity(path, object),
+            RemoteSource::Http(url) => http::identity(url),
+            RemoteSource::Sftp(file) => sftp::identity(path, file),
+            RemoteSource::Sql(query) => Ok(sql::identity(path, query)),
+        }
+    }
+
+    /// Downloads the input to `target`, reporting progress as file `file_id`. Returns its size.
+    pub fn download<S: ProgressSink>(&self, sink: &S, job: &Job, target: &Path, file_id: &str) -> Result<u64, IoError> {
+        match self {
+            RemoteSource::S3(object) => s3::download(sink, job, object, target, file_id),
+            RemoteSource::Http(url) => http::download(sink, job, url, target, file_id),
+            RemoteSource::Sftp(file) => sftp::download(sink, job, file, target, file_id),
+            RemoteSource::Sql(query) => sql::download(sink, job, query, target, file_id),
+        }
+    }
+}
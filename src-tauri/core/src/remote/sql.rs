@@ -0,0 +1,355 @@
+use crate::jobs::Job;
+use crate::payloads::FileIdentity;
+use crate::sink::ProgressSink;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Error as IoError, ErrorKind, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often a running query is checked for cancellation.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    Postgres,
+    MySql,
+}
+
+impl Dialect {
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Postgres => "PostgreSQL",
+            Dialect::MySql => "MySQL",
+        }
+    }
+
+    fn client(self) -> &'static str {
+        match self {
+            Dialect::Postgres => "psql",
+            Dialect::MySql => "mysql",
+        }
+    }
+}
+
+/// The rows of a query or table, named by a connection string with a `table` or `query`
+/// parameter, e.g. `postgres://app@db/sales?table=orders` or
+/// `mysql://app@db/sales?query=SELECT%20id,total%20FROM%20orders`. The rows are exported
+/// as CSV with a header line by the `psql` or `mysql` client, which must be installed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SqlQuery {
+    pub dialect: Dialect,
+    /// Connection string without the `table` and `query` parameters.
+    connection: String,
+    query: Option<String>,
+    label: String,
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+impl SqlQuery {
+    pub fn parse(path: &str) -> Option<Self> {
+        let (scheme, rest) = path.split_once("://")?;
+        let dialect = match scheme {
+            "postgres" | "postgresql" => Dialect::Postgres,
+            "mysql" => Dialect::MySql,
+            _ => return None,
+        };
+        let (base, params) = rest.split_once('?').unwrap_or((rest, ""));
+        let (mut query, mut label, mut kept) = (None, "query".to_string(), Vec::new());
+        for param in params.split('&').filter(|param| !param.is_empty()) {
+            match param.split_once('=') {
+                Some(("table", table)) => {
+                    label = percent_decode(table);
+                    query = is_table_name(&label).then(|| format!("SELECT * FROM {}", label));
+                }
+                Some(("query", text)) => query = Some(percent_decode(text).trim().trim_end_matches(';').to_string()),
+                _ => kept.push(param),
+            }
+        }
+        let database = base.rsplit_once('/').map_or("", |(_, database)| database);
+        let connection = match kept.is_empty() {
+            true => format!("{}://{}", scheme, base),
+            false => format!("{}://{}?{}", scheme, base, kept.join("&")),
+        };
+        Some(SqlQuery { dialect, connection, query, label: format!("{}/{}", database, label) })
+    }
+
+    /// The client command printing the rows to stdout: CSV from `psql`, tab-separated
+    /// values from `mysql`, which `download` converts. Passwords are passed in the
+    /// environment, where other users can't read them as they can the command line.
+    fn command(&self, query: &str) -> Result<Command, IoError> {
+        let (scheme, rest) = self.connection.split_once("://").unwrap_or(("", &self.connection));
+        let (rest, params) = rest.split_once('?').unwrap_or((rest, ""));
+        let (authority, database) = rest.split_once('/').unwrap_or((rest, ""));
+        let (user, host) = authority.rsplit_once('@').map_or((None, authority), |(user, host)| (Some(user), host));
+        let (user, password) = match user {
+            Some(user) => user.split_once(':').map_or((Some(user), None), |(user, password)| (Some(user), Some(password))),
+            None => (None, None),
+        };
+        let mut command = Command::new(self.dialect.client());
+        if let Some(password) = password {
+            let variable = match self.dialect {
+                Dialect::Postgres => "PGPASSWORD",
+                Dialect::MySql => "MYSQL_PWD",
+            };
+            command.env(variable, percent_decode(password));
+        }
+        match self.dialect {
+            Dialect::Postgres => {
+                let user = user.map_or(String::new(), |user| format!("{}@", user));
+                let mut connection = format!("{}://{}{}/{}", scheme, user, host, database);
+                let mut kept = Vec::new();
+                for param in params.split('&').filter(|param| !param.is_empty()) {
+                    match param.strip_prefix("password=") {
+                        Some(password) => {
+                            command.env("PGPASSWORD", percent_decode(password));
+                        }
+                        None => kept.push(param),
+                    }
+                }
+                if !kept.is_empty() {
+                    connection = format!("{}?{}", connection, kept.join("&"));
+                }
+                command.args(["--no-psqlrc", "--quiet", "-v", "ON_ERROR_STOP=1", "-c"]);
+                command.arg(format!("COPY ({}) TO STDOUT WITH (FORMAT csv, HEADER)", query));
+                command.arg(connection);
+            }
+            Dialect::MySql => {
+                command.args(["--batch", "--quick", "--default-character-set=utf8mb4"]);
+                for param in params.split('&').filter(|param| !param.is_empty()) {
+                    command.arg(mysql_option(param)?);
+                }
+                command.args(["-e", query]);
+                if let Some((host, port)) = host.rsplit_once(':') {
+                    command.args(["-h", host, "-P", port]);
+                } else if !host.is_empty() {
+                    command.args(["-h", host]);
+                }
+                if let Some(user) = user {
+                    command.arg("-u").arg(percent_decode(user));
+                }
+                if !database.is_empty() {
+                    command.arg(format!("--database={}", percent_decode(database)));
+                }
+            }
+        }
+        Ok(command)
+    }
+}
+
+/// Whether `name` is a plain, optionally schema-qualified table name, so it can be put in
+/// the query as is without letting the connection string add SQL of its own.
+fn is_table_name(name: &str) -> bool {
+    name.split('.').all(|part| {
+        part.chars().next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && part.chars().all(|char| char.is_ascii_alphanumeric() || matches!(char, '_' | '$'))
+    })
+}
+
+/// The `mysql` option for a connection string parameter. Unknown parameters are refused
+/// rather than dropped, as they may be needed to connect securely.
+fn mysql_option(param: &str) -> Result<String, IoError> {
+    let (name, value) = param.split_once('=').unwrap_or((param, ""));
+    let value = percent_decode(value);
+    let option = match name {
+        "ssl-mode" | "sslmode" => format!("--ssl-mode={}", value.to_ascii_uppercase().replace('-', "_")),
+        "ssl-ca" | "sslrootcert" => format!("--ssl-ca={}", value),
+        "ssl-cert" | "sslcert" => format!("--ssl-cert={}", value),
+        "ssl-key" | "sslkey" => format!("--ssl-key={}", value),
+        "connect-timeout" | "connect_timeout" => format!("--connect-timeout={}", value),
+        "socket" => format!("--socket={}", value),
+        _ => return Err(IoError::new(ErrorKind::InvalidInput, format!("Unsupported MySQL connection parameter: {}", name))),
+    };
+    Ok(option)
+}
+
+/// Writes a line of `mysql --batch` output as a CSV record: escapes are undone, `NULL`
+/// becomes an empty field like in PostgreSQL's CSV, and fields are quoted where needed.
+fn tsv_line_to_csv(line: &[u8], out: &mut Vec<u8>) {
+    for (index, field) in line.split(|&byte| byte == b'\t').enumerate() {
+        if index > 0 {
+            out.push(b',');
+        }
+        if field == b"NULL" {
+            continue;
+        }
+        let mut value = Vec::with_capacity(field.len());
+        let mut bytes = field.iter();
+        while let Some(&byte) = bytes.next() {
+            value.push(match (byte, bytes.as_slice().first()) {
+                (b'\\', Some(&escaped)) => {
+                    bytes.next();
+                    match escaped {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        b'0' => 0,
+                        b'b' => 8,
+                        other => other,
+                    }
+                }
+                (byte, _) => byte,
+            });
+        }
+        if value.iter().any(|byte| matches!(byte, b',' | b'"' | b'\n' | b'\r')) {
+            out.push(b'"');
+            for &byte in &value {
+                if byte == b'"' {
+                    out.push(b'"');
+                }
+                out.push(byte);
+            }
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(&value);
+        }
+    }
+    out.push(b'\n');
+}
+
+/// A query result has no size or modification time before it is run.
+pub fn identity(path: &str, query: &SqlQuery) -> FileIdentity {
+    FileIdentity {
+        path: path.to_string(),
+        display_name: query.label.clone(),
+        canonical_path: path.to_string(),
+        size: 0,
+        modified_ms: None,
+        device: None,
+        inode: None,
+    }
+}
+
+/// Runs the query and writes its rows to `target` as CSV, stopping the client when the job
+/// is cancelled. Returns the bytes written.
+pub fn download<S: ProgressSink>(sink: &S, job: &Job, query: &SqlQuery, target: &Path, file_id: &str) -> Result<u64, IoError> {
+    let Some(text) = &query.query else {
+        return Err(IoError::new(ErrorKind::InvalidInput, "Add a table= parameter with a table name such as schema.orders, or a query= parameter, to the connection string"));
+    };
+    job.progress.file_progress(sink, file_id, 0.0, "Running query...");
+    let mut child = query
+        .command(text)?
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => {
+                IoError::new(ErrorKind::NotFound, format!("{} inputs need the {} client", query.dialect.name(), query.dialect.client()))
+            }
+            _ => err,
+        })?;
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = std::thread::spawn(move || {
+        let mut message = String::new();
+        let _ = stderr.read_to_string(&mut message);
+        message
+    });
+    let mut rows = BufReader::with_capacity(1 << 20, child.stdout.take().expect("stdout is piped"));
+    let child = Mutex::new(child);
+    let finished = AtomicBool::new(false);
+    let copied = std::thread::scope(|scope| {
+        // The query may run a long time before the first row, so the client is stopped from
+        // a watcher rather than between rows.
+        scope.spawn(|| {
+            while !finished.load(Ordering::Relaxed) {
+                if job.is_cancelled() {
+                    let _ = child.lock().unwrap().kill();
+                    return;
+                }
+                std::thread::sleep(CANCEL_POLL_INTERVAL);
+            }
+        });
+        let copied = copy_rows(query.dialect, &mut rows, target);
+        finished.store(true, Ordering::Relaxed);
+        copied
+    });
+    let status = child.into_inner().unwrap().wait()?;
+    let message = errors.join().unwrap_or_default();
+    job.check_cancelled()?;
+    let written = copied?;
+    if !status.success() {
+        return Err(IoError::other(format!("Query on {} failed: {}", query.label, message.trim())));
+    }
+    Ok(written)
+}
+
+/// Writes the client's output to `target` as CSV. Returns the bytes written.
+fn copy_rows(dialect: Dialect, rows: &mut impl BufRead, target: &Path) -> Result<u64, IoError> {
+    let mut writer = BufWriter::new(File::create(target)?);
+    let (mut line, mut record, mut written) = (Vec::new(), Vec::new(), 0u64);
+    loop {
+        line.clear();
+        if rows.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let output = match dialect {
+            Dialect::Postgres => &line,
+            Dialect::MySql => {
+                record.clear();
+                tsv_line_to_csv(line.strip_suffix(b"\n").unwrap_or(&line), &mut record);
+                &record
+            }
+        };
+        writer.write_all(output)?;
+        written += output.len() as u64;
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_connection_strings_and_convert_mysql_rows() {
+        let table = SqlQuery::parse("postgres://app@db:5432/sales?sslmode=require&table=public.orders").unwrap();
+        assert_eq!(table.connection, "postgres://app@db:5432/sales?sslmode=require");
+        assert_eq!((table.query.as_deref(), table.label.as_str()), (Some("SELECT * FROM public.orders"), "sales/public.orders"));
+        let query = SqlQuery::parse("mysql://app:p%40ss@db/sales?query=SELECT%20id%20FROM%20orders;").unwrap();
+        assert_eq!((query.dialect, query.query.as_deref()), (Dialect::MySql, Some("SELECT id FROM orders")));
+        assert!(SqlQuery::parse("postgres://db/sales?table=orders;DROP%20TABLE%20orders").unwrap().query.is_none());
+        assert!(SqlQuery::parse("https://example.com/data.csv").is_none());
+
+        let args = |command: &Command| command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect::<Vec<_>>();
+        let dashed = SqlQuery::parse("mysql://db/--host=evil?table=t").unwrap();
+        assert!(args(&dashed.command("SELECT 1").unwrap()).ends_with(&["--database=--host=evil".to_string()]));
+        let command = query.command("SELECT 1").unwrap();
+        assert!(args(&command).ends_with(&["-h", "db", "-u", "app", "--database=sales"].map(String::from)));
+        assert!(command.get_envs().any(|(name, value)| name == "MYSQL_PWD" && value == Some("p@ss".as_ref())));
+        let secure = SqlQuery::parse("mysql://app@db/sales?ssl-mode=required&table=t").unwrap();
+        assert!(args(&secure.command("SELECT 1").unwrap()).contains(&"--ssl-mode=REQUIRED".to_string()));
+        assert!(SqlQuery::parse("mysql://db/sales?compress=1&table=t").unwrap().command("SELECT 1").is_err());
+
+        let postgres = SqlQuery::parse("postgres://app:s3cret@db:5432/sales?sslmode=require&table=t").unwrap();
+        let command = postgres.command("SELECT 1").unwrap();
+        assert_eq!(args(&command).last().unwrap(), "postgres://app@db:5432/sales?sslmode=require");
+        assert!(command.get_envs().any(|(name, value)| name == "PGPASSWORD" && value == Some("s3cret".as_ref())));
+
+        let mut csv = Vec::new();
+        tsv_line_to_csv(b"1\tsmith, j\tNULL\ttab\\there\t\"q\"", &mut csv);
+        assert_eq!(String::from_utf8(csv).unwrap(), "1,\"smith, j\",,tab\there,\"\"\"q\"\"\"\n");
+    }
+}
@@ -0,0 +1,86 @@
+use crate::config::CompareConfig;
+use crate::header::HeaderLayout;
+use crate::payloads::{AcknowledgedDifference, ModifiedLine};
+use crate::results::collected::CollectedLine;
+use crate::results::pairing::{key_extractors, KeyExtractor};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Error as IoError;
+
+/// Known, accepted differences: one exact line or record key per line of the file.
+pub struct Allowlist {
+    entries: HashSet<String>,
+}
+
+impl Allowlist {
+    pub fn load(path: &str) -> Result<Self, IoError> {
+        let content = fs::read_to_string(path)?;
+        let entries = content
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(Self { entries })
+    }
+
+    fn accepts(&self, text: &str, extractor: Option<&KeyExtractor>) -> bool {
+        self.entries.contains(text)
+            || extractor
+                .and_then(|extractor| extractor.key(text))
+                .is_some_and(|key| self.entries.contains(&key))
+    }
+}
+
+fn acknowledged_unique(file_id: &str, line: CollectedLine) -> AcknowledgedDifference {
+    AcknowledgedDifference {
+        kind: "unique".to_string(),
+        file: file_id.to_string(),
+        line_number: line.line_number,
+        text: line.display_text(),
+        other: None,
+    }
+}
+
+/// Differences after moving the accepted ones out of the headline results.
+pub struct Triaged {
+    pub acknowledged: Vec<AcknowledgedDifference>,
+    pub modified: Vec<ModifiedLine>,
+    pub rest_a: Vec<CollectedLine>,
+    pub rest_b: Vec<CollectedLine>,
+}
+
+/// Separates the differences matching the allowlist, by exact line or by record key.
+pub fn triage(
+    allowlist: &Allowlist,
+    modified: Vec<ModifiedLine>,
+    rest_a: Vec<CollectedLine>,
+    rest_b: Vec<CollectedLine>,
+    compare_config: &CompareConfig,
+    header_layout: Option<&HeaderLayout>,
+) -> Triaged {
+    let extractors = key_extractors(compare_config, header_layout);
+    let (key_a, key_b) = match &extractors {
+        Some((a, b)) => (Some(a), Some(b)),
+        None => (None, None),
+    };
+    let mut acknowledged = Vec::new();
+
+    let (accepted, modified): (Vec<_>, Vec<_>) = modified.into_iter().partition(|pair| {
+        allowlist.accepts(&pair.line_a.text, key_a) || allowlist.accepts(&pair.line_b.text, key_b)
+    });
+    acknowledged.extend(accepted.into_iter().map(|pair| AcknowledgedDifference {
+        kind: "modified".to_string(),
+        file: "A".to_string(),
+        line_number: pair.line_a.line_number,
+        text: pair.line_a.text,
+        other: Some(pair.line_b),
+    }));
+
+    let (accepted, rest_a): (Vec<_>, Vec<_>) = rest_a.into_iter().partition(|line| allowlist.accepts(&line.text, key_a));
+    acknowledged.extend(accepted.into_iter().map(|line| acknowledged_unique("A", line)));
+    let (accepted, rest_b): (Vec<_>, Vec<_>) = rest_b.into_iter().partition(|line| allowlist.accepts(&line.text, key_b));
+    acknowledged.extend(accepted.into_iter().map(|line| acknowledged_unique("B", line)));
+
+    Triaged { acknowledged, modified, rest_a, rest_b }
+}
@@ -0,0 +1,65 @@
+use crate::payloads::{BatchOffender, BatchSummary, DirectoryEntry, DirectoryEntryStatus};
+
+/// How many of the most different files the roll-up lists.
+const WORST_OFFENDERS: usize = 10;
+
+/// Rolls the per-file results of a batch up into one summary.
+pub fn summarize_batch(entries: &[DirectoryEntry]) -> BatchSummary {
+    let mut batch = BatchSummary::default();
+    let mut offenders = Vec::new();
+    for entry in entries {
+        match entry.status {
+            DirectoryEntryStatus::OnlyInA => batch.only_in_a += 1,
+            DirectoryEntryStatus::OnlyInB => batch.only_in_b += 1,
+            DirectoryEntryStatus::Failed => batch.failed += 1,
+            DirectoryEntryStatus::Compared => {
+                let differences = entry.summary.as_ref().map_or(0, |s| s.unique_to_a + s.unique_to_b + s.modified);
+                if differences == 0 {
+                    batch.identical += 1;
+                    continue;
+                }
+                batch.different += 1;
+                batch.total_differences += differences;
+                offenders.push(BatchOffender {
+                    name: entry.relative_path.clone(),
+                    job_id: entry.job_id.clone(),
+                    differences,
+                });
+            }
+        }
+    }
+    offenders.sort_by(|x, y| y.differences.cmp(&x.differences).then_with(|| x.name.cmp(&y.name)));
+    offenders.truncate(WORST_OFFENDERS);
+    batch.worst_offenders = offenders;
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payloads::ComparisonSummary;
+
+    fn entry(name: &str, status: DirectoryEntryStatus, differences: usize) -> DirectoryEntry {
+        DirectoryEntry {
+            relative_path: name.to_string(),
+            status,
+            job_id: None,
+            summary: Some(ComparisonSummary { unique_to_a: differences, ..Default::default() }),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_ranks_worst_offenders() {
+        let entries = [
+            entry("same.csv", DirectoryEntryStatus::Compared, 0),
+            entry("small.csv", DirectoryEntryStatus::Compared, 2),
+            entry("big.csv", DirectoryEntryStatus::Compared, 40),
+            entry("new.csv", DirectoryEntryStatus::OnlyInB, 0),
+        ];
+        let batch = summarize_batch(&entries);
+        assert_eq!((batch.identical, batch.different, batch.only_in_b, batch.total_differences), (1, 2, 1, 42));
+        let names: Vec<&str> = batch.worst_offenders.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["big.csv", "small.csv"]);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::config::{CompareConfig, SetOperation};
+use crate::event_limit::{bounded, emit_bounded};
+use crate::header::HeaderLayout;
+use crate::jobs::Job;
+use crate::payloads::{ComparisonFinishedPayload, ComparisonSummary, DiffLine, UniqueLinePayload};
+use crate::results::allowlist::{triage, Allowlist, Triaged};
+use crate::results::intra_line::highlight_fields;
+use crate::results::pairing::{emit_modified_lines, key_extractors, pair_modified_lines};
+use crate::results::similarity::pair_similar_lines;
+use crate::results::store::ResultStatus;
+use crate::sink::ProgressSink;
+use std::io::Error as IoError;
+use std::sync::Arc;
+
+/// A line found in only one of the files, as collected by the engines before reporting.
+#[derive(Clone, Debug)]
+pub struct CollectedLine {
+    pub line_number: usize,
+    pub text: String,
+    /// How many more times the line occurs in this file than in the other one.
+    pub count: usize,
+}
+
+impl CollectedLine {
+    pub fn display_text(&self) -> String {
+        if self.count > 1 {
+            format!("{}\n(x{})", self.text, self.count)
+        } else {
+            self.text.clone()
+        }
+    }
+
+    pub fn to_diff_line(&self) -> DiffLine {
+        DiffLine {
+            line_number: self.line_number,
+            text: self.display_text(),
+        }
+    }
+}
+
+fn emit_unique_lines<S: ProgressSink>(sink: &S, file_id: &str, lines: &[CollectedLine], limit: usize) {
+    for line in lines {
+        let payload = UniqueLinePayload {
+            file: file_id.to_string(),
+            line_number: line.line_number,
+            text: line.display_text(),
+        };
+        if let Some(value) = bounded("unique_line", &payload, limit) {
+            sink.on_unique_line(value);
+        }
+    }
+}
+
+/// Pairs modified records among the unique lines, and with an allowlist sets aside the
+/// expected differences.
+fn pair_and_triage(
+    job: &Job,
+    compare_config: &CompareConfig,
+    layout: Option<&HeaderLayout>,
+    lines_a: Vec<CollectedLine>,
+    lines_b: Vec<CollectedLine>,
+) -> Triaged {
+    let (mut modified, rest_a, rest_b) = pair_modified_lines(lines_a, lines_b, compare_config, layout);
+    let (rest_a, rest_b) = match &compare_config.similarity_pairing {
+        Some(options) => pair_similar_lines(options, &mut modified, rest_a, rest_b),
+        None => (rest_a, rest_b),
+    };
+    if let Some(layout) = compare_config.field_format.as_ref().and_then(|format| format.fixed_width.as_deref()) {
+        for pair in &mut modified {
+            highlight_fields(pair, layout);
+        }
+    }
+    let allowlist = compare_config.accepted_differences_path.as_deref().and_then(|path| {
+        Allowlist::load(path)
+            .map_err(|e| job.record_degradation(&format!("Could not read accepted differences {}: {}", path, e)))
+            .ok()
+    });
+    match &allowlist {
+        Some(allowlist) => triage(allowlist, modified, rest_a, rest_b, compare_config, layout),
+        None => Triaged { acknowledged: Vec::new(), modified, rest_a, rest_b },
+    }
+}
+
+/// Pairs up the modified records among the lines collected in the job's result store,
+/// emits all differences to the frontend and fills in the result counts of `summary`.
+/// Without pairing or an allowlist the lines stay stored as they are and only those
+/// emitted are decoded.
+pub fn report_differences<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    compare_config: &CompareConfig,
+    header_layout: Option<&Arc<HeaderLayout>>,
+    summary: &mut ComparisonSummary,
+) -> Result<(), IoError> {
+    job.results.lock().unwrap().sort_unique();
+    let layout = header_layout.map(|h| h.as_ref());
+    let needs_text = key_extractors(compare_config, layout).is_some()
+        || compare_config.similarity_pairing.is_some()
+        || compare_config.accepted_differences_path.is_some();
+    let Triaged { acknowledged, modified, rest_a, rest_b } = if needs_text {
+        let (lines_a, lines_b) = job.results.lock().unwrap().take_unique()?;
+        pair_and_triage(job, compare_config, layout, lines_a, lines_b)
+    } else {
+        Triaged { acknowledged: Vec::new(), modified: Vec::new(), rest_a: Vec::new(), rest_b: Vec::new() }
+    };
+    let (len_a, len_b) = if needs_text {
+        (rest_a.len(), rest_b.len())
+    } else {
+        let store = job.results.lock().unwrap();
+        (store.unique_count("A"), store.unique_count("B"))
+    };
+    // Each kind is capped on its own and all of them together share one budget.
+    let mut budget = compare_config.max_emitted_differences;
+    let mut head = |len: usize| {
+        let emitted = len.min(compare_config.max_streamed_lines).min(budget);
+        budget -= emitted;
+        emitted
+    };
+    let emitted = [head(modified.len()), head(len_a), head(len_b), head(acknowledged.len())];
+    if !job.is_quiet() {
+        let limit = compare_config.max_event_bytes;
+        emit_modified_lines(sink, &modified[..emitted[0]], limit);
+        if needs_text {
+            emit_unique_lines(sink, "A", &rest_a[..emitted[1]], limit);
+            emit_unique_lines(sink, "B", &rest_b[..emitted[2]], limit);
+        } else {
+            let (head_a, head_b) = {
+                let mut store = job.results.lock().unwrap();
+                (store.unique_page("A", 0, emitted[1])?, store.unique_page("B", 0, emitted[2])?)
+            };
+            emit_unique_lines(sink, "A", &head_a, limit);
+            emit_unique_lines(sink, "B", &head_b, limit);
+        }
+        for difference in &acknowledged[..emitted[3]] {
+            emit_bounded(sink, "acknowledged_difference", difference, limit);
+        }
+    }
+
+    summary.acknowledged = acknowledged.len();
+    summary.modified = modified.len();
+    if compare_config.set_operation == SetOperation::Intersection {
+        summary.in_both = Some(len_a);
+    } else {
+        summary.unique_to_a = len_a;
+    }
+    summary.unique_to_b = len_b;
+    summary.truncated = emitted.iter().sum::<usize>() < modified.len() + len_a + len_b + acknowledged.len();
+    summary.hash_seed = compare_config.hash_seed;
+    summary.hash_algo = Some(compare_config.hash_algo());
+    {
+        let store = job.results.lock().unwrap();
+        summary.degradations = store.degradations.clone();
+        summary.inputs = store.inputs.clone();
+        summary.same_file = store.same_file;
+        summary.line_classes = store.line_classes.clone();
+    }
+
+    let mut store = job.results.lock().unwrap();
+    if needs_text {
+        store.set_unique(rest_a, rest_b);
+    }
+    store.modified = modified;
+    store.acknowledged = acknowledged;
+    store.status = ResultStatus::Complete;
+    store.header_layout = header_layout.cloned();
+    Ok(())
+}
+
+/// Stores the final summary in the job and tells the frontend the comparison is done.
+pub fn finish_comparison<S: ProgressSink>(sink: &S, job: &Job, summary: ComparisonSummary) {
+    job.results.lock().unwrap().summary = Some(summary.clone());
+    if job.is_quiet() {
+        return;
+    }
+    sink.on_finished("comparison_finished", &ComparisonFinishedPayload { summary });
+}
@@ -0,0 +1,96 @@
+use crate::jobs::Job;
+use crate::results::export_writer::{ExportOptions, ExportWriter};
+use std::borrow::Cow;
+use std::io::Error as IoError;
+use std::path::Path;
+
+/// Leading characters that make Excel and similar tools evaluate a cell as a formula.
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Neutralizes a cell a spreadsheet would evaluate as a formula by prefixing a quote.
+pub fn escape_formula(cell: &str) -> Cow<'_, str> {
+    if cell.starts_with(FORMULA_PREFIXES) {
+        Cow::Owned(format!("'{}", cell))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+fn csv_cell(cell: &str, escape_formulas: bool) -> String {
+    let cell = if escape_formulas { escape_formula(cell) } else { Cow::Borrowed(cell) };
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.into_owned()
+    }
+}
+
+fn write_row(writer: &mut ExportWriter, cells: &[&str], options: &ExportOptions) -> Result<(), IoError> {
+    let row: Vec<String> = cells.iter().map(|cell| csv_cell(cell, options.escape_formulas)).collect();
+    writer.write_line(&row.join(","))
+}
+
+/// Writes the stored results of a job as CSV, one difference per row.
+pub fn export_csv_report(job: &Job, path: &Path, options: &ExportOptions) -> Result<(), IoError> {
+    let mut store = job.results.lock().unwrap();
+    let mut writer = ExportWriter::create(path, options)?;
+    write_row(
+        &mut writer,
+        &["kind", "file", "line_number", "text", "other_line_number", "other_text", "count"],
+        options,
+    )?;
+
+    for file_id in ["A", "B"] {
+        store.for_each_unique(file_id, |line| {
+            let (line_number, count) = (line.line_number.to_string(), line.count.to_string());
+            write_row(&mut writer, &["unique", file_id, &line_number, &line.text, "", "", &count], options)
+        })?;
+    }
+    for pair in &store.modified {
+        let (number_a, number_b) = (pair.line_a.line_number.to_string(), pair.line_b.line_number.to_string());
+        write_row(
+            &mut writer,
+            &["modified", "A", &number_a, &pair.line_a.text, &number_b, &pair.line_b.text, ""],
+            options,
+        )?;
+    }
+    for duplicate in &store.duplicates {
+        let line_number = duplicate.line_number.to_string();
+        let count = format!("{}/{}", duplicate.count_a, duplicate.count_b);
+        write_row(&mut writer, &["duplicate", &duplicate.file, &line_number, &duplicate.text, "", "", &count], options)?;
+    }
+    for difference in &store.acknowledged {
+        let line_number = difference.line_number.to_string();
+        let (other_number, other_text) = match &difference.other {
+            Some(other) => (other.line_number.to_string(), other.text.as_str()),
+            None => (String::new(), ""),
+        };
+        let kind = format!("acknowledged_{}", difference.kind);
+        write_row(
+            &mut writer,
+            &[&kind, &difference.file, &line_number, &difference.text, &other_number, other_text, ""],
+            options,
+        )?;
+    }
+    if let Some(batch) = &store.batch_summary {
+        for offender in &batch.worst_offenders {
+            let count = offender.differences.to_string();
+            write_row(&mut writer, &["worst_offender", &offender.name, "", "", "", "", &count], options)?;
+        }
+    }
+
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formula_cells_are_escaped_and_quoted() {
+        assert_eq!(csv_cell("=HYPERLINK(\"x\")", true), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_cell("-12", true), "'-12");
+        assert_eq!(csv_cell("-12", false), "-12");
+        assert_eq!(csv_cell("plain", true), "plain");
+    }
+}
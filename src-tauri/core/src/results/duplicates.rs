@@ -0,0 +1,107 @@
+use crate::config::CompareConfig;
+use crate::event_limit::emit_bounded;
+use crate::external::line_numbers::line_numbers_for_offsets;
+use crate::fields::record_len;
+use crate::jobs::Job;
+use crate::payloads::DuplicateLinePayload;
+use crate::sink::ProgressSink;
+use crate::utils::HashMap;
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::Error as IoError;
+
+/// A line occurring more than once across both files, located by its first occurrence.
+#[derive(Clone, Copy, Debug)]
+pub struct DuplicateCandidate {
+    /// True if the representative occurrence is in file A, false for file B.
+    pub in_a: bool,
+    pub offset: u64,
+    /// Known line number of the occurrence; computed later when the engine has none.
+    pub line_number: Option<usize>,
+    pub count_a: usize,
+    pub count_b: usize,
+}
+
+/// Returns `(hash, count_a, count_b)` for every hash seen more than once in A and B combined.
+pub fn duplicate_hashes(
+    counts_a: &HashMap<u64, usize>,
+    counts_b: &HashMap<u64, usize>,
+) -> Vec<(u64, usize, usize)> {
+    let mut duplicates: Vec<_> = counts_a
+        .iter()
+        .map(|(hash, &count_a)| (*hash, count_a, counts_b.get(hash).copied().unwrap_or(0)))
+        .filter(|(_, count_a, count_b)| count_a + count_b > 1)
+        .collect();
+    duplicates.extend(
+        counts_b
+            .iter()
+            .filter(|(hash, count_b)| **count_b > 1 && !counts_a.contains_key(*hash))
+            .map(|(hash, &count_b)| (*hash, 0, count_b)),
+    );
+    duplicates
+}
+
+fn read_side(
+    path: &str,
+    candidates: &[DuplicateCandidate],
+    compare_config: &CompareConfig,
+) -> Result<Vec<DuplicateLinePayload>, IoError> {
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+    let (terminator, quote) = (compare_config.terminator(), compare_config.record_quote());
+
+    let mut sorted = candidates.to_vec();
+    sorted.sort_unstable_by_key(|c| c.offset);
+    let computed_numbers = if compare_config.ignore_line_number || sorted.iter().all(|c| c.line_number.is_some()) {
+        None
+    } else {
+        let offsets: Vec<usize> = sorted.iter().map(|c| c.offset as usize).collect();
+        Some(line_numbers_for_offsets(&mmap, &offsets, terminator, quote))
+    };
+
+    Ok(sorted
+        .iter()
+        .enumerate()
+        .map(|(i, candidate)| {
+            let start = candidate.offset as usize;
+            let end = start + record_len(&mmap[start..], terminator, quote);
+            let line_number = candidate
+                .line_number
+                .or_else(|| computed_numbers.as_ref().map(|n| n[i]))
+                .unwrap_or(0);
+            DuplicateLinePayload {
+                file: if candidate.in_a { "A" } else { "B" }.to_string(),
+                line_number,
+                text: String::from_utf8_lossy(&mmap[start..end]).trim_end().to_string(),
+                count_a: candidate.count_a,
+                count_b: candidate.count_b,
+            }
+        })
+        .collect())
+}
+
+/// Reads the text of the duplicate candidates, stores them in the job's results and emits them,
+/// most frequent first. Returns the number of duplicate lines.
+pub fn report_duplicates<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    file_a_path: &str,
+    file_b_path: &str,
+    candidates: Vec<DuplicateCandidate>,
+    compare_config: &CompareConfig,
+) -> Result<usize, IoError> {
+    let (in_a, in_b): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|c| c.in_a);
+    let mut duplicates = read_side(file_a_path, &in_a, compare_config)?;
+    duplicates.extend(read_side(file_b_path, &in_b, compare_config)?);
+    duplicates.sort_by_key(|d| std::cmp::Reverse(d.count_a + d.count_b));
+
+    for duplicate in &duplicates {
+        emit_bounded(sink, "duplicate_line", duplicate, compare_config.max_event_bytes);
+    }
+    let count = duplicates.len();
+    job.results.lock().unwrap().duplicates = duplicates;
+    Ok(count)
+}
@@ -0,0 +1,111 @@
+use crate::jobs::Job;
+use crate::results::csv_export::export_csv_report;
+use crate::results::html_export::export_html_report;
+use crate::results::json_export::{export_json_report, export_ndjson_report};
+use crate::results::export_writer::{ExportFormat, ExportOptions, ExportWriter};
+use crate::results::pairing::key_extractors;
+use crate::results::split_export::package_export;
+use crate::results::xlsx_export::export_xlsx_report;
+use std::collections::HashSet;
+use std::io::{Error as IoError, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Writes the stored results of a job in the format selected by `options`, split or zipped
+/// when it exceeds `options.split_size`. Returns the files produced.
+pub fn export_report(job: &Job, path: &Path, options: &ExportOptions) -> Result<Vec<PathBuf>, IoError> {
+    match options.format {
+        ExportFormat::Text => export_text_report(job, path, options),
+        ExportFormat::Csv => export_csv_report(job, path, options),
+        ExportFormat::Json => export_json_report(job, path, options),
+        ExportFormat::Ndjson => export_ndjson_report(job, path, options),
+        ExportFormat::Html => export_html_report(job, path, options),
+        ExportFormat::Xlsx => export_xlsx_report(job, path),
+    }?;
+    let at_lines = !matches!(options.format, ExportFormat::Xlsx);
+    package_export(path, options.split_size, options.split_mode, at_lines)
+}
+
+/// Writes the stored results of a job as a plain text report.
+pub fn export_text_report(job: &Job, path: &Path, options: &ExportOptions) -> Result<(), IoError> {
+    let mut store = job.results.lock().unwrap();
+    let mut writer = ExportWriter::create(path, options)?;
+
+    if !store.inputs.is_empty() {
+        writer.write_line("# Inputs")?;
+        for (file_id, input) in ["A", "B"].iter().zip(&store.inputs) {
+            writer.write_line(&format!("{}\t{}\t{}", file_id, input.display_name, input.canonical_path))?;
+        }
+    }
+    writer.write_line("# Unique in File A")?;
+    store.for_each_unique("A", |line| writer.write_line(&format!("{}\t{}", line.line_number, line.display_text())))?;
+    writer.write_line("# Unique in File B")?;
+    store.for_each_unique("B", |line| writer.write_line(&format!("{}\t{}", line.line_number, line.display_text())))?;
+    writer.write_line("# Modified Lines")?;
+    for pair in &store.modified {
+        writer.write_line(&format!("A:{}\t{}", pair.line_a.line_number, pair.line_a.text))?;
+        writer.write_line(&format!("B:{}\t{}", pair.line_b.line_number, pair.line_b.text))?;
+    }
+    if !store.duplicates.is_empty() {
+        writer.write_line("# Duplicate Lines (count in A / count in B)")?;
+        for duplicate in &store.duplicates {
+            writer.write_line(&format!(
+                "{}:{}\t{}/{}\t{}",
+                duplicate.file, duplicate.line_number, duplicate.count_a, duplicate.count_b, duplicate.text
+            ))?;
+        }
+    }
+    if !store.binary_ranges.is_empty() {
+        writer.write_line("# Differing Byte Ranges (offset, length)")?;
+        for range in &store.binary_ranges {
+            writer.write_line(&format!("{}:{}\t{}", range.file, range.offset, range.len))?;
+        }
+    }
+    if let Some(batch) = &store.batch_summary {
+        writer.write_line("# Batch Summary")?;
+        writer.write_line(&format!(
+            "identical {}\tdifferent {}\tonly in A {}\tonly in B {}\tfailed {}\ttotal differences {}",
+            batch.identical, batch.different, batch.only_in_a, batch.only_in_b, batch.failed, batch.total_differences
+        ))?;
+        writer.write_line("# Worst Offenders (differences)")?;
+        for offender in &batch.worst_offenders {
+            writer.write_line(&format!("{}\t{}", offender.differences, offender.name))?;
+        }
+    }
+    if !store.acknowledged.is_empty() {
+        writer.write_line("# Acknowledged Differences")?;
+        for difference in &store.acknowledged {
+            writer.write_line(&format!("{}:{}\t{}", difference.file, difference.line_number, difference.text))?;
+            if let Some(other) = &difference.other {
+                writer.write_line(&format!("B:{}\t{}", other.line_number, other.text))?;
+            }
+        }
+    }
+
+    writer.finish()
+}
+
+/// Writes the distinct keys of records found in `side` but missing from the other file,
+/// one per line. Returns the number of keys written.
+pub fn export_missing_keys(job: &Job, side: &str, path: &Path, options: &ExportOptions) -> Result<usize, IoError> {
+    let mut store = job.results.lock().unwrap();
+    let (key_a, key_b) = key_extractors(&job.config, store.header_layout.as_deref()).ok_or_else(|| {
+        IoError::new(ErrorKind::InvalidInput, "No primary key regex or key columns configured")
+    })?;
+    let (extractor, lines) = match side {
+        "A" => (key_a, store.unique_lines("A")?),
+        "B" => (key_b, store.unique_lines("B")?),
+        _ => return Err(IoError::new(ErrorKind::InvalidInput, format!("Unknown side: {}", side))),
+    };
+
+    let mut writer = ExportWriter::create(path, options)?;
+    let mut seen = HashSet::new();
+    for line in &lines {
+        if let Some(key) = extractor.key(&line.text)
+            && seen.insert(key.clone())
+        {
+            writer.write_line(&key)?;
+        }
+    }
+    writer.finish()?;
+    Ok(seen.len())
+}
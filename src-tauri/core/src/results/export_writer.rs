@@ -0,0 +1,171 @@
+use encoding_rs::Encoding;
+use crate::results::split_export::SplitMode;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Error as IoError, ErrorKind, Write};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    Cr,
+    /// `\r\n` on Windows, `\n` elsewhere.
+    Native,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Cr => "\r",
+            LineEnding::Native => {
+                if cfg!(windows) {
+                    "\r\n"
+                } else {
+                    "\n"
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Text,
+    Csv,
+    Json,
+    /// Newline-delimited JSON, one record per line.
+    Ndjson,
+    /// A standalone HTML page.
+    Html,
+    /// An Excel workbook; encoding and line ending don't apply.
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Html => "html",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// Format, text encoding and line ending applied to exported reports.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// WHATWG encoding label, e.g. "utf-8", "utf-16le", "gbk", "shift_jis", "windows-1252".
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    #[serde(default)]
+    pub line_ending: LineEnding,
+    /// Write a byte order mark (UTF-8 and UTF-16 only).
+    #[serde(default)]
+    pub bom: bool,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Prefix CSV cells starting with `=`, `+`, `-` or `@` so spreadsheets don't run them as formulas.
+    #[serde(default = "default_true")]
+    pub escape_formulas: bool,
+    /// Size in bytes above which the export is split or zipped; `None` keeps one file.
+    #[serde(default)]
+    pub split_size: Option<u64>,
+    #[serde(default)]
+    pub split_mode: SplitMode,
+}
+
+fn default_encoding() -> String {
+    "utf-8".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            encoding: default_encoding(),
+            line_ending: LineEnding::default(),
+            bom: false,
+            format: ExportFormat::default(),
+            escape_formulas: true,
+            split_size: None,
+            split_mode: SplitMode::default(),
+        }
+    }
+}
+
+/// Writes report lines with the configured encoding. Line breaks inside a line's text
+/// (e.g. multi-line CSV records) are normalized to the configured line ending as well.
+pub struct ExportWriter {
+    writer: BufWriter<File>,
+    encoding: &'static Encoding,
+    line_ending: &'static str,
+}
+
+impl ExportWriter {
+    pub fn create(path: &Path, options: &ExportOptions) -> Result<Self, IoError> {
+        let encoding = Encoding::for_label(options.encoding.trim().as_bytes()).ok_or_else(|| {
+            IoError::new(ErrorKind::InvalidInput, format!("Unknown encoding: {}", options.encoding))
+        })?;
+        let mut writer = BufWriter::new(File::create(path)?);
+        if options.bom {
+            if encoding == encoding_rs::UTF_8 {
+                writer.write_all(b"\xEF\xBB\xBF")?;
+            } else if encoding == encoding_rs::UTF_16LE {
+                writer.write_all(b"\xFF\xFE")?;
+            } else if encoding == encoding_rs::UTF_16BE {
+                writer.write_all(b"\xFE\xFF")?;
+            }
+        }
+        Ok(Self {
+            writer,
+            encoding,
+            line_ending: options.line_ending.as_str(),
+        })
+    }
+
+    fn write_encoded(&mut self, text: &str) -> Result<(), IoError> {
+        if self.encoding == encoding_rs::UTF_16LE {
+            for unit in text.encode_utf16() {
+                self.writer.write_all(&unit.to_le_bytes())?;
+            }
+        } else if self.encoding == encoding_rs::UTF_16BE {
+            for unit in text.encode_utf16() {
+                self.writer.write_all(&unit.to_be_bytes())?;
+            }
+        } else {
+            // Characters the target encoding can't represent become numeric character references.
+            let (bytes, _, _) = self.encoding.encode(text);
+            self.writer.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_line(&mut self, text: &str) -> Result<(), IoError> {
+        let mut parts = text.split('\n').peekable();
+        while let Some(part) = parts.next() {
+            self.write_encoded(part.strip_suffix('\r').unwrap_or(part))?;
+            if parts.peek().is_some() {
+                self.write_encoded(self.line_ending)?;
+            }
+        }
+        self.write_encoded(self.line_ending)
+    }
+
+    pub fn finish(mut self) -> Result<(), IoError> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,149 @@
+use crate::config::CompareConfig;
+use crate::event_limit::emit_bounded;
+use crate::external::line_numbers::line_numbers_for_offsets;
+use crate::fields::record_len;
+use crate::payloads::{CountBucket, FrequencyReport, FrequentLine};
+use crate::sink::ProgressSink;
+use memmap2::Mmap;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::Error as IoError;
+
+/// A record by its count and first occurrence, ordered so the least frequent (and among
+/// equally frequent ones, the last in the file) is dropped first.
+type Ranked = Reverse<(usize, Reverse<u64>, Option<usize>)>;
+
+/// The most frequent records of one file and how the counts of all its records are
+/// distributed, gathered from the hash counts of the engines.
+#[derive(Clone, Debug)]
+pub struct FrequencyCounter {
+    limit: usize,
+    top: BinaryHeap<Ranked>,
+    /// Distinct records by the bit length of their count: entry `k` counts records occurring
+    /// `2^k` to `2^(k+1) - 1` times.
+    histogram: Vec<usize>,
+    distinct: usize,
+}
+
+impl FrequencyCounter {
+    pub fn new(limit: usize) -> Self {
+        FrequencyCounter { limit, top: BinaryHeap::new(), histogram: Vec::new(), distinct: 0 }
+    }
+
+    /// Adds a record occurring `count` times, first at `offset`.
+    pub fn add(&mut self, count: usize, offset: u64, line_number: Option<usize>) {
+        if count == 0 {
+            return;
+        }
+        self.distinct += 1;
+        let bucket = count.ilog2() as usize;
+        if self.histogram.len() <= bucket {
+            self.histogram.resize(bucket + 1, 0);
+        }
+        self.histogram[bucket] += 1;
+        self.rank(Reverse((count, Reverse(offset), line_number)));
+    }
+
+    fn rank(&mut self, record: Ranked) {
+        self.top.push(record);
+        if self.top.len() > self.limit {
+            self.top.pop();
+        }
+    }
+
+    /// Combines the counters of two parts of the same file, e.g. two partitions.
+    pub fn merge(mut self, other: FrequencyCounter) -> FrequencyCounter {
+        self.distinct += other.distinct;
+        if self.histogram.len() < other.histogram.len() {
+            self.histogram.resize(other.histogram.len(), 0);
+        }
+        for (bucket, lines) in other.histogram.into_iter().enumerate() {
+            self.histogram[bucket] += lines;
+        }
+        for record in other.top {
+            self.rank(record);
+        }
+        self
+    }
+
+    fn histogram(&self) -> Vec<CountBucket> {
+        self.histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, lines)| **lines > 0)
+            .map(|(k, &lines)| CountBucket { min_count: 1 << k, max_count: (1 << (k + 1)) - 1, lines })
+            .collect()
+    }
+}
+
+/// Reads the text of the most frequent records of `path`.
+fn read_report(file_id: &str, path: &str, counter: FrequencyCounter, compare_config: &CompareConfig) -> Result<FrequencyReport, IoError> {
+    let histogram = counter.histogram();
+    let mut top: Vec<_> = counter.top.into_iter().map(|Reverse((count, Reverse(offset), line_number))| (count, offset, line_number)).collect();
+    top.sort_unstable_by_key(|&(_, offset, _)| offset);
+    let mut top_lines = Vec::with_capacity(top.len());
+    if !top.is_empty() {
+        let mmap = unsafe { Mmap::map(&File::open(path)?)? };
+        let (terminator, quote) = (compare_config.terminator(), compare_config.record_quote());
+        let computed_numbers = if compare_config.ignore_line_number || top.iter().all(|record| record.2.is_some()) {
+            None
+        } else {
+            let offsets: Vec<usize> = top.iter().map(|record| record.1 as usize).collect();
+            Some(line_numbers_for_offsets(&mmap, &offsets, terminator, quote))
+        };
+        for (i, &(count, offset, line_number)) in top.iter().enumerate() {
+            let start = offset as usize;
+            let end = start + record_len(&mmap[start..], terminator, quote);
+            top_lines.push(FrequentLine {
+                line_number: line_number.or_else(|| computed_numbers.as_ref().map(|n| n[i])).unwrap_or(0),
+                text: String::from_utf8_lossy(&mmap[start..end]).trim_end().to_string(),
+                count,
+            });
+        }
+        // Stable, so equally frequent lines stay in file order.
+        top_lines.sort_by_key(|line| Reverse(line.count));
+    }
+    Ok(FrequencyReport { file: file_id.to_string(), distinct_lines: counter.distinct, top_lines, histogram })
+}
+
+/// Reads the most frequent lines of both files and emits a `frequency_report` for each.
+/// Returns the reports for the summary.
+pub fn report_frequencies<S: ProgressSink>(
+    sink: &S,
+    file_a_path: &str,
+    file_b_path: &str,
+    counters: (FrequencyCounter, FrequencyCounter),
+    compare_config: &CompareConfig,
+) -> Result<Vec<FrequencyReport>, IoError> {
+    let reports = vec![
+        read_report("A", file_a_path, counters.0, compare_config)?,
+        read_report("B", file_b_path, counters.1, compare_config)?,
+    ];
+    for report in &reports {
+        emit_bounded(sink, "frequency_report", report, compare_config.max_event_bytes);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_keeps_the_most_frequent_and_buckets_counts() {
+        let mut first = FrequencyCounter::new(2);
+        first.add(1, 0, None);
+        first.add(900, 10, None);
+        let mut second = FrequencyCounter::new(2);
+        second.add(3, 20, None);
+        second.add(3, 5, None);
+        let merged = first.merge(second);
+
+        let top: Vec<_> = merged.top.clone().into_sorted_vec().into_iter().map(|Reverse((count, Reverse(offset), _))| (count, offset)).collect();
+        assert_eq!(top, [(900, 10), (3, 5)]);
+        assert_eq!(merged.distinct, 4);
+        let buckets: Vec<_> = merged.histogram().iter().map(|b| (b.min_count, b.max_count, b.lines)).collect();
+        assert_eq!(buckets, [(1, 1, 1), (2, 3, 2), (512, 1023, 1)]);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::external::spill::read_partition_into_maps;
+use crate::internal::file_processing_in_memory::generate_hash_counts_and_index;
+use crate::jobs::Job;
+use crate::sink::ProgressSink;
+use crate::utils::{HashAlgo, HashMap};
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Error as IoError, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Start of a binary line-hash file, followed by a version byte.
+const MAGIC: &[u8; 7] = b"LFCHASH";
+const VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineHashFormat {
+    /// `hash,count` rows with the hash as 16 hex digits.
+    #[default]
+    Csv,
+    /// A header followed by little-endian `u64` hash and count pairs.
+    Binary,
+}
+
+/// Writes hash and count pairs in one of the `LineHashFormat`s.
+struct HashWriter {
+    out: BufWriter<File>,
+    format: LineHashFormat,
+    written: usize,
+}
+
+impl HashWriter {
+    /// Starts the file. The binary header names the hash algorithm and seed, so consumers
+    /// can hash their own lines the same way.
+    fn create(path: &Path, format: LineHashFormat, algo: &str, seed: u64) -> Result<Self, IoError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            LineHashFormat::Csv => out.write_all(b"hash,count\n")?,
+            LineHashFormat::Binary => {
+                out.write_all(MAGIC)?;
+                out.write_all(&[VERSION, algo.len() as u8])?;
+                out.write_all(algo.as_bytes())?;
+                out.write_all(&seed.to_le_bytes())?;
+            }
+        }
+        Ok(Self { out, format, written: 0 })
+    }
+
+    fn write_counts(&mut self, counts: &HashMap<u64, usize>) -> Result<(), IoError> {
+        for (hash, count) in counts {
+            match self.format {
+                LineHashFormat::Csv => writeln!(self.out, "{:016x},{}", hash, count)?,
+                LineHashFormat::Binary => {
+                    self.out.write_all(&hash.to_le_bytes())?;
+                    self.out.write_all(&(*count as u64).to_le_bytes())?;
+                }
+            }
+        }
+        self.written += counts.len();
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<usize, IoError> {
+        self.out.flush()?;
+        Ok(self.written)
+    }
+}
+
+/// Partition files of one side of a cached external run.
+fn partition_files(dir: &Path) -> Result<Vec<PathBuf>, IoError> {
+    let mut parts = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with("part_")) {
+            parts.push(path);
+        }
+    }
+    parts.sort();
+    Ok(parts)
+}
+
+/// Writes the hash of every distinct line of `side` with how often it occurs, for external
+/// set operations or fingerprint stores. Partitions kept from an external run are read
+/// back; in-memory runs hash the file again. Returns the number of distinct hashes.
+pub fn export_line_hashes<S: ProgressSink>(
+    sink: &S,
+    job: &Job,
+    side: &str,
+    path: &Path,
+    format: LineHashFormat,
+) -> Result<usize, IoError> {
+    let subdir = match side {
+        "A" => "a",
+        "B" => "b",
+        _ => return Err(IoError::new(ErrorKind::InvalidInput, format!("Unknown side: {}", side))),
+    };
+    let config = &job.config;
+    let algo = format!("{:?}", config.hash_algo()).to_lowercase();
+    let mut writer = HashWriter::create(path, format, &algo, config.hash_seed.unwrap_or_default())?;
+
+    let partitions = job.with_partitions(|cache| cache.map(|cache| partition_files(&cache.temp_dir.0.join(subdir))));
+    match partitions {
+        Some(parts) => {
+            for part in parts? {
+                job.check_cancelled()?;
+                writer.write_counts(&read_partition_into_maps(part)?.0)?;
+            }
+        }
+        None => {
+            let (input, layout) = {
+                let store = job.results.lock().unwrap();
+                let index = if side == "A" { 0 } else { 1 };
+                let input = store.inputs.get(index).map(|input| input.path.clone());
+                (input.ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "Job has no two input files"))?, store.header_layout.clone())
+            };
+            let input = job.readable_path(&input);
+            let (counts, _) = generate_hash_counts_and_index(sink, job, &input, side, config, layout.as_deref())?;
+            writer.write_counts(&counts)?;
+        }
+    }
+    writer.finish()
+}
+
+/// Line hashes read back from an export, possibly made elsewhere.
+pub struct LineHashes {
+    /// Algorithm and seed from the binary header; CSV files don't record them.
+    pub algo: Option<HashAlgo>,
+    pub seed: Option<u64>,
+    pub counts: HashMap<u64, usize>,
+}
+
+fn invalid(message: String) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message)
+}
+
+fn read_binary(mut reader: impl Read) -> Result<LineHashes, IoError> {
+    let mut header = [0u8; 9];
+    reader.read_exact(&mut header)?;
+    if header[7] != VERSION {
+        return Err(invalid(format!("Unsupported line hash file version {}", header[7])));
+    }
+    let mut algo = vec![0u8; header[8] as usize];
+    reader.read_exact(&mut algo)?;
+    let algo = serde_json::from_value(String::from_utf8_lossy(&algo).into()).map_err(|err| invalid(err.to_string()))?;
+    let mut word = [0u8; 8];
+    reader.read_exact(&mut word)?;
+    let seed = u64::from_le_bytes(word);
+
+    let mut counts = HashMap::default();
+    let mut pair = [0u8; 16];
+    loop {
+        match reader.read_exact(&mut pair) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let hash = u64::from_le_bytes(pair[..8].try_into().unwrap());
+        let count = u64::from_le_bytes(pair[8..].try_into().unwrap()) as usize;
+        *counts.entry(hash).or_insert(0) += count;
+    }
+    Ok(LineHashes { algo: Some(algo), seed: Some(seed), counts })
+}
+
+fn read_csv(reader: impl BufRead) -> Result<LineHashes, IoError> {
+    let mut counts = HashMap::default();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.is_empty() || (i == 0 && line.starts_with("hash")) {
+            continue;
+        }
+        let parsed = line
+            .split_once(',')
+            .and_then(|(hash, count)| Some((u64::from_str_radix(hash.trim(), 16).ok()?, count.trim().parse::<usize>().ok()?)));
+        let (hash, count) = parsed.ok_or_else(|| invalid(format!("Line {}: expected hash,count", i + 1)))?;
+        *counts.entry(hash).or_insert(0) += count;
+    }
+    Ok(LineHashes { algo: None, seed: None, counts })
+}
+
+/// Reads a file written by `export_line_hashes`, telling the formats apart by the binary header.
+pub fn read_line_hashes(path: &Path) -> Result<LineHashes, IoError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    if reader.fill_buf()?.starts_with(MAGIC) {
+        read_binary(reader)
+    } else {
+        read_csv(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_export_round_trips() {
+        let path = std::env::temp_dir().join(format!("line-hashes-{}.bin", std::process::id()));
+        let mut writer = HashWriter::create(&path, LineHashFormat::Binary, "xxh3", 7).unwrap();
+        writer.write_counts(&HashMap::from_iter([(0xabu64, 2usize)])).unwrap();
+        assert_eq!(writer.finish().unwrap(), 1);
+
+        let bytes = fs::read(&path).unwrap();
+        let hashes = read_line_hashes(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!((hashes.algo, hashes.seed), (Some(HashAlgo::Xxh3), Some(7)));
+        assert_eq!(hashes.counts.get(&0xab), Some(&2));
+        assert_eq!(&bytes[..9], b"LFCHASH\x01\x04");
+        assert_eq!(&bytes[9..13], b"xxh3");
+        assert_eq!(bytes[13..21], 7u64.to_le_bytes());
+        assert_eq!(bytes[21..29], 0xabu64.to_le_bytes());
+        assert_eq!(bytes[29..], 2u64.to_le_bytes());
+    }
+}
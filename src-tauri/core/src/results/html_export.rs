@@ -0,0 +1,137 @@
+use crate::jobs::Job;
+use crate::results::collected::CollectedLine;
+use crate::results::export_writer::{ExportOptions, ExportWriter};
+use std::io::Error as IoError;
+use std::path::Path;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:2px 8px;text-align:left;vertical-align:top}\
+th{background:#f0f0f0}td.text{font-family:monospace;white-space:pre-wrap}";
+
+/// Escapes the characters HTML gives a meaning to.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn row(cells: &[String]) -> String {
+    let cells: Vec<String> = cells.iter().map(|cell| format!("<td>{}</td>", escape_html(cell))).collect();
+    format!("<tr>{}</tr>", cells.concat())
+}
+
+fn write_unique_table(writer: &mut ExportWriter, title: &str, lines: &[CollectedLine]) -> Result<(), IoError> {
+    writer.write_line(&format!("<h2>{} ({})</h2>", escape_html(title), lines.len()))?;
+    writer.write_line("<table><tr><th>Line</th><th>Text</th><th>Count</th></tr>")?;
+    for line in lines {
+        writer.write_line(&format!(
+            "<tr><td>{}</td><td class=\"text\">{}</td><td>{}</td></tr>",
+            line.line_number,
+            escape_html(&line.text),
+            line.count
+        ))?;
+    }
+    writer.write_line("</table>")
+}
+
+/// Writes the stored results of a job as a standalone HTML page: summary, inputs, step
+/// timings and the unique and modified lines, with no external resources.
+pub fn export_html_report(job: &Job, path: &Path, options: &ExportOptions) -> Result<(), IoError> {
+    let mut store = job.results.lock().unwrap();
+    let mut writer = ExportWriter::create(path, options)?;
+
+    writer.write_line("<!DOCTYPE html>")?;
+    writer.write_line(&format!(
+        "<html><head><meta charset=\"{}\"><title>Comparison report</title><style>{}</style></head><body>",
+        escape_html(options.encoding.trim()),
+        STYLE
+    ))?;
+    writer.write_line("<h1>Comparison report</h1>")?;
+
+    if !store.inputs.is_empty() {
+        writer.write_line("<h2>Inputs</h2><table><tr><th>File</th><th>Name</th><th>Path</th><th>Size</th></tr>")?;
+        for (file_id, input) in ["A", "B"].iter().zip(&store.inputs) {
+            writer.write_line(&row(&[
+                file_id.to_string(),
+                input.display_name.clone(),
+                input.canonical_path.clone(),
+                input.size.to_string(),
+            ]))?;
+        }
+        writer.write_line("</table>")?;
+    }
+
+    if let Some(summary) = &store.summary {
+        writer.write_line("<h2>Summary</h2><table>")?;
+        let mut rows = vec![
+            ("Unique in A", summary.unique_to_a.to_string()),
+            ("Unique in B", summary.unique_to_b.to_string()),
+            ("Modified", summary.modified.to_string()),
+            ("Duplicates", summary.duplicates.to_string()),
+            ("Acknowledged", summary.acknowledged.to_string()),
+        ];
+        if let Some(algo) = summary.hash_algo {
+            rows.push(("Hash algorithm", format!("{:?}", algo)));
+        }
+        if let Some(seed) = summary.hash_seed {
+            rows.push(("Hash seed", seed.to_string()));
+        }
+        for degradation in &summary.degradations {
+            rows.push(("Degradation", degradation.clone()));
+        }
+        for (name, value) in rows {
+            writer.write_line(&format!("<tr><th>{}</th><td>{}</td></tr>", name, escape_html(&value)))?;
+        }
+        writer.write_line("</table>")?;
+    }
+
+    if !store.steps.is_empty() {
+        writer.write_line("<h2>Timings</h2><table><tr><th>Step</th><th>Duration (ms)</th></tr>")?;
+        for step in &store.steps {
+            writer.write_line(&row(&[step.step.clone(), step.duration_ms.to_string()]))?;
+        }
+        writer.write_line("</table>")?;
+    }
+
+    let (unique_a, unique_b) = (store.unique_lines("A")?, store.unique_lines("B")?);
+    write_unique_table(&mut writer, "Unique in File A", &unique_a)?;
+    write_unique_table(&mut writer, "Unique in File B", &unique_b)?;
+
+    if !store.modified.is_empty() {
+        writer.write_line(&format!("<h2>Modified Lines ({})</h2>", store.modified.len()))?;
+        writer.write_line("<table><tr><th>Line A</th><th>Text A</th><th>Line B</th><th>Text B</th></tr>")?;
+        for pair in &store.modified {
+            writer.write_line(&format!(
+                "<tr><td>{}</td><td class=\"text\">{}</td><td>{}</td><td class=\"text\">{}</td></tr>",
+                pair.line_a.line_number,
+                escape_html(&pair.line_a.text),
+                pair.line_b.line_number,
+                escape_html(&pair.line_b.text)
+            ))?;
+        }
+        writer.write_line("</table>")?;
+    }
+
+    writer.write_line("</body></html>")?;
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markup_in_lines_is_escaped() {
+        assert_eq!(escape_html("<a href=\"x\">&'"), "&lt;a href=&quot;x&quot;&gt;&amp;&#39;");
+    }
+}
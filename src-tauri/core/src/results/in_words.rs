@@ -0,0 +1,162 @@
+use crate::config::FieldFormat;
+use crate::fields::split_fields;
+use crate::jobs::Job;
+use crate::payloads::ModifiedLine;
+use crate::results::store::ResultStatus;
+use std::collections::BTreeMap;
+
+/// `1204` as `1,204`.
+fn count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Line numbers rounded the way people say them: `40k`, `1.2M`.
+fn line_number(n: usize) -> String {
+    match n {
+        0..10_000 => count(n),
+        10_000..1_000_000 => format!("{}k", (n + 500) / 1000),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
+
+fn plural(n: usize, word: &str) -> String {
+    format!("{} {}{}", count(n), word, if n == 1 { "" } else { "s" })
+}
+
+/// Where in the file the lines are: listed when there are a few, otherwise the range
+/// holding the middle 80% of them.
+fn location(mut lines: Vec<usize>) -> String {
+    lines.sort_unstable();
+    match lines.as_slice() {
+        [] => String::new(),
+        [line] => format!(" at line {}", count(*line)),
+        [first, second] => format!(" at lines {} and {}", count(*first), count(*second)),
+        [first, second, third] => format!(" at lines {}, {} and {}", count(*first), count(*second), count(*third)),
+        _ => {
+            let low = lines[lines.len() / 10];
+            let high = lines[(lines.len() * 9).div_ceil(10) - 1];
+            let mostly = if low == lines[0] && high == lines[lines.len() - 1] { "" } else { "mostly " };
+            format!(", {}between lines {}\u{2013}{}", mostly, line_number(low), line_number(high))
+        }
+    }
+}
+
+/// Counts modified pairs whose records differ in exactly one column, by 1-based column.
+fn single_column_changes(modified: &[ModifiedLine], format: &FieldFormat) -> BTreeMap<usize, usize> {
+    let mut columns = BTreeMap::new();
+    for pair in modified {
+        let a = split_fields(pair.line_a.text.as_bytes(), format);
+        let b = split_fields(pair.line_b.text.as_bytes(), format);
+        if a.len() != b.len() {
+            continue;
+        }
+        let mut differing = a.iter().zip(&b).enumerate().filter(|(_, (x, y))| x != y).map(|(i, _)| i);
+        if let (Some(column), None) = (differing.next(), differing.next()) {
+            *columns.entry(column + 1).or_insert(0) += 1;
+        }
+    }
+    columns
+}
+
+fn capitalize(sentence: &str) -> String {
+    let mut chars = sentence.chars();
+    chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// A short plain-language description of the results of a job, for screen readers and
+/// for pasting into status updates.
+pub fn summarize_in_words(job: &Job) -> String {
+    let store = job.results.lock().unwrap();
+    let Some(summary) = store.summary.clone() else {
+        return match store.status {
+            ResultStatus::Partial => "The comparison was interrupted before it finished.".to_string(),
+            _ => "The comparison has not finished yet.".to_string(),
+        };
+    };
+    if summary.same_file {
+        return "Both paths point to the same file.".to_string();
+    }
+    if summary.identical {
+        return "The files are byte for byte identical.".to_string();
+    }
+
+    let line_numbers = |file_id| store.unique_line_numbers(file_id);
+    let mut parts = Vec::new();
+    if let Some(in_both) = summary.in_both.filter(|&in_both| in_both > 0) {
+        parts.push(format!("{} in both files{}", plural(in_both, "line"), location(line_numbers("A"))));
+    }
+    for (file_id, other, total) in [("A", "B", summary.unique_to_a), ("B", "A", summary.unique_to_b)] {
+        if total > 0 {
+            parts.push(format!("{} is missing {} present in {}{}", other, plural(total, "line"), file_id, location(line_numbers(file_id))));
+        }
+    }
+
+    if summary.modified > 0 {
+        let format = job.config.field_format.as_ref();
+        let columns = format.map(|format| single_column_changes(&store.modified, format));
+        let mut modified = plural(summary.modified, "line");
+        modified.push_str(" changed");
+        if let (Some(format), Some((column, changed))) =
+            (format, columns.iter().flatten().max_by_key(|(_, changed)| **changed))
+        {
+            if *changed == summary.modified {
+                modified.push_str(&format!(", all only in {}", format.column_label(column - 1)));
+            } else {
+                modified.push_str(&format!(", {} only in {}", count(*changed), format.column_label(column - 1)));
+            }
+        }
+        parts.push(modified);
+    }
+    if summary.duplicates > 0 {
+        parts.push(format!("{} repeated", plural(summary.duplicates, "line")));
+    }
+    if summary.acknowledged > 0 {
+        parts.push(format!("{} matched the expected differences", plural(summary.acknowledged, "difference")));
+    }
+
+    let mut text = if parts.is_empty() && summary.in_both.is_some() {
+        "The files have no lines in common".to_string()
+    } else if parts.is_empty() {
+        "The files contain the same lines".to_string()
+    } else {
+        capitalize(&parts.join("; "))
+    };
+    if store.status == ResultStatus::Partial {
+        text.push_str(" (partial results)");
+    }
+    text.push('.');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payloads::DiffLine;
+    use crate::results::intra_line::modified_pair;
+
+    #[test]
+    fn test_numbers_and_locations_read_naturally() {
+        assert_eq!(count(1204), "1,204");
+        assert_eq!(line_number(40_213), "40k");
+        assert_eq!(location(vec![9, 3]), " at lines 3 and 9");
+        let lines: Vec<usize> = (40_000..55_000).chain([5, 90_000]).collect();
+        assert_eq!(location(lines), ", mostly between lines 41k\u{2013}54k");
+    }
+
+    #[test]
+    fn test_changes_confined_to_one_column_are_counted() {
+        let line = |text: &str| DiffLine { line_number: 1, text: text.to_string() };
+        let pair = |a: &str, b: &str| modified_pair(line(a), line(b));
+        let format = FieldFormat { delimiter: ",".to_string(), quote_char: None, has_header: false, fixed_width: None };
+        let modified = [pair("x,1,a", "x,2,a"), pair("y,5,b", "y,6,b"), pair("z,1,a", "q,2,a")];
+        assert_eq!(single_column_changes(&modified, &format), BTreeMap::from([(2, 2)]));
+    }
+}
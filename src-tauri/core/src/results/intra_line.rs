@@ -0,0 +1,173 @@
+use crate::config::FixedField;
+use crate::payloads::{DiffLine, ModifiedLine, TextRange};
+
+/// Largest token grid diffed exactly; longer lines only get their common start and end
+/// trimmed, leaving the middle highlighted as a whole.
+const MAX_CELLS: usize = 250_000;
+
+#[derive(PartialEq, Eq)]
+enum TokenClass {
+    Word,
+    Space,
+    Other,
+}
+
+fn class(c: char) -> TokenClass {
+    if c.is_alphanumeric() || c == '_' {
+        TokenClass::Word
+    } else if c.is_whitespace() {
+        TokenClass::Space
+    } else {
+        TokenClass::Other
+    }
+}
+
+/// Splits `text` into words, whitespace runs and single punctuation characters, as byte ranges.
+fn tokens(text: &str) -> Vec<(usize, usize)> {
+    let mut tokens: Vec<(usize, usize)> = Vec::new();
+    let mut previous = None;
+    for (i, c) in text.char_indices() {
+        let current = class(c);
+        match tokens.last_mut() {
+            Some(last) if current != TokenClass::Other && previous.as_ref() == Some(&current) => last.1 = i + c.len_utf8(),
+            _ => tokens.push((i, i + c.len_utf8())),
+        }
+        previous = Some(current);
+    }
+    tokens
+}
+
+/// Which tokens of each side are not part of a longest common token subsequence.
+fn unmatched(a: &[&str], b: &[&str]) -> (Vec<bool>, Vec<bool>) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let mut changed_a = vec![false; a.len()];
+    let mut changed_b = vec![false; b.len()];
+
+    if mid_a.len() * mid_b.len() > MAX_CELLS {
+        changed_a[prefix..a.len() - suffix].fill(true);
+        changed_b[prefix..b.len() - suffix].fill(true);
+        return (changed_a, changed_b);
+    }
+    // lengths[i][j]: longest common subsequence of mid_a[i..] and mid_b[j..].
+    let width = mid_b.len() + 1;
+    let mut lengths = vec![0u32; (mid_a.len() + 1) * width];
+    for i in (0..mid_a.len()).rev() {
+        for j in (0..mid_b.len()).rev() {
+            lengths[i * width + j] = if mid_a[i] == mid_b[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < mid_a.len() || j < mid_b.len() {
+        if i < mid_a.len() && j < mid_b.len() && mid_a[i] == mid_b[j] {
+            i += 1;
+            j += 1;
+        } else if j == mid_b.len() || (i < mid_a.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1]) {
+            changed_a[prefix + i] = true;
+            i += 1;
+        } else {
+            changed_b[prefix + j] = true;
+            j += 1;
+        }
+    }
+    (changed_a, changed_b)
+}
+
+/// Merges changed tokens into ranges in UTF-16 code units, the way JavaScript indexes strings.
+fn ranges(text: &str, tokens: &[(usize, usize)], changed: &[bool]) -> Vec<TextRange> {
+    let utf16 = |byte: usize| text[..byte].encode_utf16().count();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (&(start, end), _) in tokens.iter().zip(changed).filter(|(_, changed)| **changed) {
+        match ranges.last_mut() {
+            Some(last) if last.1 == start => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges.into_iter().map(|(start, end)| TextRange { start: utf16(start), end: utf16(end) }).collect()
+}
+
+/// Ranges of `a` and `b` that differ, found by diffing their tokens.
+pub fn intra_line_changes(a: &str, b: &str) -> (Vec<TextRange>, Vec<TextRange>) {
+    let (tokens_a, tokens_b) = (tokens(a), tokens(b));
+    let words_a: Vec<&str> = tokens_a.iter().map(|&(start, end)| &a[start..end]).collect();
+    let words_b: Vec<&str> = tokens_b.iter().map(|&(start, end)| &b[start..end]).collect();
+    let (changed_a, changed_b) = unmatched(&words_a, &words_b);
+    (ranges(a, &tokens_a, &changed_a), ranges(b, &tokens_b, &changed_b))
+}
+
+/// Pairs two lines as a modification, with the ranges that changed inside them.
+pub fn modified_pair(line_a: DiffLine, line_b: DiffLine) -> ModifiedLine {
+    let (changes_a, changes_b) = intra_line_changes(&line_a.text, &line_b.text);
+    ModifiedLine { line_a, line_b, changes_a, changes_b }
+}
+
+/// Highlights whole fields of fixed-width records instead of changed tokens, so the
+/// changes of a pair line up with the columns of the layout.
+pub fn highlight_fields(pair: &mut ModifiedLine, layout: &[FixedField]) {
+    let (a, b) = (pair.line_a.text.as_bytes(), pair.line_b.text.as_bytes());
+    let changed: Vec<&FixedField> = layout.iter().filter(|field| field.slice(a) != field.slice(b)).collect();
+    let field_ranges = |text: &str| {
+        // Byte ranges may split a multi-byte character; widen them to whole characters.
+        let floor = |mut byte: usize| {
+            byte = byte.min(text.len());
+            while !text.is_char_boundary(byte) {
+                byte -= 1;
+            }
+            byte
+        };
+        let ceil = |mut byte: usize| {
+            byte = byte.min(text.len());
+            while !text.is_char_boundary(byte) {
+                byte += 1;
+            }
+            byte
+        };
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for field in &changed {
+            let (start, end) = (floor(field.start), ceil(field.start + field.length));
+            match ranges.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ if start < end => ranges.push((start, end)),
+                _ => {}
+            }
+        }
+        let utf16 = |byte: usize| text[..byte].encode_utf16().count();
+        ranges.into_iter().map(|(start, end)| TextRange { start: utf16(start), end: utf16(end) }).collect()
+    };
+    pair.changes_a = field_ranges(&pair.line_a.text);
+    pair.changes_b = field_ranges(&pair.line_b.text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(ranges: &[TextRange]) -> Vec<(usize, usize)> {
+        ranges.iter().map(|range| (range.start, range.end)).collect()
+    }
+
+    #[test]
+    fn test_only_changed_tokens_are_highlighted() {
+        let (a, b) = intra_line_changes("id=7, amount=10.50", "id=7, amount=12.50 EUR");
+        assert_eq!(spans(&a), [(13, 15)]);
+        assert_eq!(spans(&b), [(13, 15), (18, 22)]);
+
+        // Offsets count UTF-16 code units, so text after an emoji is shifted by two.
+        let (a, b) = intra_line_changes("😀 old", "😀 new");
+        assert_eq!((spans(&a), spans(&b)), (vec![(3, 6)], vec![(3, 6)]));
+    }
+
+    #[test]
+    fn test_fixed_width_changes_cover_whole_fields() {
+        let line = |text: &str| DiffLine { line_number: 1, text: text.to_string() };
+        let layout = [(0, 4), (4, 6), (10, 3)].map(|(start, length)| FixedField { start, length, name: None });
+        let mut pair = modified_pair(line("0042SMITH 100"), line("0042SMYTH 250"));
+        highlight_fields(&mut pair, &layout);
+        assert_eq!((spans(&pair.changes_a), spans(&pair.changes_b)), (vec![(4, 13)], vec![(4, 13)]));
+    }
+}
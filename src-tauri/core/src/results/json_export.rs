@@ -0,0 +1,95 @@
+use crate::jobs::Job;
+use crate::results::export_writer::{ExportOptions, ExportWriter};
+use crate::results::store::ResultStore;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::Error as IoError;
+use std::path::Path;
+
+/// `record` as a JSON object with a `type` field naming its kind.
+fn tagged(kind: &str, record: impl Serialize) -> Value {
+    let mut value = json!(record);
+    if let Value::Object(map) = &mut value {
+        map.insert("type".to_string(), Value::from(kind));
+    }
+    value
+}
+
+/// Every stored difference as one JSON object, tagged with its `type`.
+fn difference_records(store: &mut ResultStore) -> Result<Vec<Value>, IoError> {
+    let mut records = Vec::new();
+    for file_id in ["A", "B"] {
+        for line in store.unique_lines(file_id)? {
+            records.push(json!({
+                "type": "unique",
+                "file": file_id,
+                "line_number": line.line_number,
+                "text": line.text,
+                "count": line.count,
+            }));
+        }
+    }
+    records.extend(store.modified.iter().map(|pair| tagged("modified", pair)));
+    records.extend(store.duplicates.iter().map(|duplicate| tagged("duplicate", duplicate)));
+    records.extend(store.acknowledged.iter().map(|difference| tagged("acknowledged", difference)));
+    records.extend(store.binary_ranges.iter().map(|range| tagged("binary_range", range)));
+    Ok(records)
+}
+
+/// The config the job ran with, its summary and batch summary, if any.
+fn report_header(job: &Job, store: &ResultStore) -> Value {
+    json!({
+        "job_id": job.id,
+        "status": store.status,
+        "config": job.config,
+        "summary": store.summary,
+        "batch_summary": store.batch_summary,
+    })
+}
+
+/// Writes the stored results of a job as a single JSON document: the header fields and
+/// a `differences` array with one record per line.
+pub fn export_json_report(job: &Job, path: &Path, options: &ExportOptions) -> Result<(), IoError> {
+    let mut store = job.results.lock().unwrap();
+    let mut writer = ExportWriter::create(path, options)?;
+    let header = report_header(job, &store);
+    let records = difference_records(&mut store)?;
+
+    writer.write_line("{")?;
+    if let Value::Object(fields) = header {
+        for (name, value) in fields {
+            writer.write_line(&format!("  {}: {},", json!(name), value))?;
+        }
+    }
+    writer.write_line("  \"differences\": [")?;
+    let last = records.len().saturating_sub(1);
+    for (i, record) in records.iter().enumerate() {
+        writer.write_line(&format!("    {}{}", record, if i < last { "," } else { "" }))?;
+    }
+    writer.write_line("  ]")?;
+    writer.write_line("}")?;
+    writer.finish()
+}
+
+/// Writes the stored results of a job as newline-delimited JSON: a `header` record
+/// with the config and summary, followed by one record per difference.
+pub fn export_ndjson_report(job: &Job, path: &Path, options: &ExportOptions) -> Result<(), IoError> {
+    let mut store = job.results.lock().unwrap();
+    let mut writer = ExportWriter::create(path, options)?;
+    writer.write_line(&tagged("header", report_header(job, &store)).to_string())?;
+    for record in difference_records(&mut store)? {
+        writer.write_line(&record.to_string())?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_tagged_with_their_kind() {
+        let record = tagged("binary_range", json!({ "file": "A", "offset": 4 }));
+        assert_eq!(record, json!({ "type": "binary_range", "file": "A", "offset": 4 }));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::jobs::Job;
+use crate::payloads::{FilteredResults, ResultsPage, UniqueLinePayload};
+use crate::results::store::ResultStore;
+use regex::Regex;
+use std::io::{Error as IoError, ErrorKind};
+
+/// Largest page handed out at once, so one request can't pull millions of lines over IPC.
+const MAX_PAGE: usize = 10_000;
+
+fn check_side(side: &str) -> Result<(), IoError> {
+    match side {
+        "A" | "B" | "modified" => Ok(()),
+        _ => Err(IoError::new(ErrorKind::InvalidInput, format!("Unknown side: {}", side))),
+    }
+}
+
+fn count(store: &ResultStore, side: &str) -> usize {
+    match side {
+        "modified" => store.modified.len(),
+        _ => store.unique_count(side),
+    }
+}
+
+fn page(store: &mut ResultStore, side: &str, offset: usize, limit: usize) -> Result<ResultsPage, IoError> {
+    let limit = limit.min(MAX_PAGE);
+    let (unique, modified) = match side {
+        "modified" => (Vec::new(), store.modified.iter().skip(offset).take(limit).cloned().collect()),
+        _ => (store.unique_page(side, offset, limit)?.iter().map(|line| line.to_diff_line()).collect(), Vec::new()),
+    };
+    Ok(ResultsPage { side: side.to_string(), offset, total: count(store, side), unique, modified })
+}
+
+/// Number of stored differences of `side`: `A` or `B` for unique lines, `modified` for pairs.
+pub fn results_count(job: &Job, side: &str) -> Result<usize, IoError> {
+    check_side(side)?;
+    Ok(count(&job.results.lock().unwrap(), side))
+}
+
+/// Up to `limit` stored differences of `side`, starting at the `offset`-th, in line order.
+pub fn results_page(job: &Job, side: &str, offset: usize, limit: usize) -> Result<ResultsPage, IoError> {
+    check_side(side)?;
+    page(&mut job.results.lock().unwrap(), side, offset, limit)
+}
+
+fn filter(
+    store: &mut ResultStore,
+    sides: &[&str],
+    matches: impl Fn(&str) -> bool,
+    offset: usize,
+    limit: usize,
+) -> Result<FilteredResults, IoError> {
+    let limit = limit.min(MAX_PAGE);
+    let mut total = 0;
+    let mut lines = Vec::new();
+    for side in sides {
+        store.for_each_unique::<IoError>(side, |line| {
+            if matches(&line.text) {
+                if total >= offset && lines.len() < limit {
+                    lines.push(UniqueLinePayload { file: side.to_string(), line_number: line.line_number, text: line.display_text() });
+                }
+                total += 1;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(FilteredResults { offset, total, lines })
+}
+
+/// Searches the stored unique lines of `side`, or of both files when unset, for `query`,
+/// either as a plain substring or as a regular expression. Returns one page of the
+/// matches, A before B and in line order, with the number of matches in total.
+pub fn filter_results(
+    job: &Job,
+    query: &str,
+    regex: bool,
+    side: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> Result<FilteredResults, IoError> {
+    let sides = match side {
+        Some(side @ ("A" | "B")) => vec![side],
+        Some(side) => return Err(IoError::new(ErrorKind::InvalidInput, format!("Unknown side: {}", side))),
+        None => vec!["A", "B"],
+    };
+    let mut store = job.results.lock().unwrap();
+    if regex {
+        let pattern = Regex::new(query).map_err(|err| IoError::new(ErrorKind::InvalidInput, err))?;
+        filter(&mut store, &sides, |text| pattern.is_match(text), offset, limit)
+    } else {
+        filter(&mut store, &sides, |text| text.contains(query), offset, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::collected::CollectedLine;
+
+    #[test]
+    fn test_pages_cover_the_stored_lines_in_order() {
+        let mut store = ResultStore::new(false);
+        for line_number in 1..=5 {
+            store.push_unique("A", CollectedLine { line_number, text: format!("line {}", line_number), count: 1 });
+        }
+        let numbers = |page: ResultsPage| page.unique.iter().map(|line| line.line_number).collect::<Vec<_>>();
+        assert_eq!(numbers(page(&mut store, "A", 3, 10).unwrap()), [4, 5]);
+        assert_eq!(page(&mut store, "A", 0, 2).unwrap().total, 5);
+        assert!(page(&mut store, "B", 0, 2).unwrap().unique.is_empty());
+        assert!(check_side("C").is_err());
+    }
+
+    #[test]
+    fn test_filter_counts_all_matches_and_pages_them() {
+        let mut store = ResultStore::new(false);
+        for (file_id, text) in [("A", "error 1"), ("A", "ok"), ("B", "error 2"), ("B", "error 3")] {
+            store.push_unique(file_id, CollectedLine { line_number: 1, text: text.to_string(), count: 1 });
+        }
+        let found = filter(&mut store, &["A", "B"], |text| text.contains("error"), 1, 1).unwrap();
+        assert_eq!(found.total, 3);
+        assert_eq!(found.lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>(), ["error 2"]);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::config::{CompareConfig, FieldFormat};
+use crate::event_limit::emit_bounded;
+use crate::fields::split_fields;
+use crate::header::HeaderLayout;
+use crate::payloads::ModifiedLine;
+use crate::results::collected::CollectedLine;
+use crate::results::intra_line::modified_pair;
+use crate::sink::ProgressSink;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Extracts the record key used to recognise the same record on both sides.
+pub enum KeyExtractor<'a> {
+    Regex(Regex),
+    Columns(&'a FieldFormat, Vec<usize>),
+}
+
+impl KeyExtractor<'_> {
+    pub fn key(&self, text: &str) -> Option<String> {
+        match self {
+            KeyExtractor::Regex(regex) => {
+                let captures = regex.captures(text)?;
+                let key = captures.get(1).or_else(|| captures.get(0))?;
+                Some(key.as_str().to_string())
+            }
+            KeyExtractor::Columns(format, columns) => {
+                let fields = split_fields(text.as_bytes(), format);
+                let parts: Vec<String> = columns
+                    .iter()
+                    .map(|&i| fields.get(i).map_or(String::new(), |f| String::from_utf8_lossy(f).into_owned()))
+                    .collect();
+                Some(parts.join("\u{1f}"))
+            }
+        }
+    }
+}
+
+/// Builds the key extractors for both files. Key columns refer to file A; with a header
+/// they are translated to the matching columns of file B.
+pub fn key_extractors<'a>(
+    compare_config: &'a CompareConfig,
+    header_layout: Option<&HeaderLayout>,
+) -> Option<(KeyExtractor<'a>, KeyExtractor<'a>)> {
+    if let Some(pattern) = &compare_config.primary_key_regex {
+        return match Regex::new(pattern) {
+            Ok(regex) => Some((KeyExtractor::Regex(regex.clone()), KeyExtractor::Regex(regex))),
+            Err(e) => {
+                eprintln!("Invalid primary key regex, skipping pairing: {}", e);
+                None
+            }
+        };
+    }
+    let format = compare_config.field_format.as_ref()?;
+    if compare_config.key_columns.is_empty() {
+        return None;
+    }
+    let columns_a = compare_config.key_columns.clone();
+    let columns_b = match header_layout {
+        Some(layout) => columns_a
+            .iter()
+            .map(|&a| {
+                layout
+                    .projection_a
+                    .iter()
+                    .position(|&p| p == a)
+                    .map_or(a, |pos| layout.projection_b[pos])
+            })
+            .collect(),
+        None => columns_a.clone(),
+    };
+    Some((KeyExtractor::Columns(format, columns_a), KeyExtractor::Columns(format, columns_b)))
+}
+
+/// Pairs lines unique to A with lines unique to B that share the same record key.
+/// Returns the pairs plus the lines of each side that stayed unpaired.
+pub fn pair_modified_lines(
+    lines_a: Vec<CollectedLine>,
+    lines_b: Vec<CollectedLine>,
+    compare_config: &CompareConfig,
+    header_layout: Option<&HeaderLayout>,
+) -> (Vec<ModifiedLine>, Vec<CollectedLine>, Vec<CollectedLine>) {
+    let Some((key_a, key_b)) = key_extractors(compare_config, header_layout) else {
+        return (Vec::new(), lines_a, lines_b);
+    };
+
+    let mut b_by_key: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, line) in lines_b.iter().enumerate() {
+        if let Some(key) = key_b.key(&line.text) {
+            b_by_key.entry(key).or_default().push(i);
+        }
+    }
+    // Pair in file order so the first occurrences on each side end up together.
+    for indices in b_by_key.values_mut() {
+        indices.reverse();
+    }
+
+    let mut paired_b = vec![false; lines_b.len()];
+    let mut modified = Vec::new();
+    let mut rest_a = Vec::new();
+    for line_a in lines_a {
+        let partner = key_a
+            .key(&line_a.text)
+            .and_then(|key| b_by_key.get_mut(&key))
+            .and_then(|indices| indices.pop());
+        match partner {
+            Some(index_b) => {
+                paired_b[index_b] = true;
+                modified.push(modified_pair(line_a.to_diff_line(), lines_b[index_b].to_diff_line()));
+            }
+            None => rest_a.push(line_a),
+        }
+    }
+    let rest_b = lines_b
+        .into_iter()
+        .zip(paired_b)
+        .filter(|(_, paired)| !paired)
+        .map(|(line, _)| line)
+        .collect();
+
+    (modified, rest_a, rest_b)
+}
+
+pub fn emit_modified_lines<S: ProgressSink>(sink: &S, modified: &[ModifiedLine], limit: usize) {
+    for pair in modified {
+        emit_bounded(sink, "modified_line", pair, limit);
+    }
+}
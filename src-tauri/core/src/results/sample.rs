@@ -0,0 +1,191 @@
+use crate::jobs::Job;
+use crate::payloads::SampledDifference;
+use crate::results::store::ResultStore;
+use rand::seq::index;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::Error as IoError;
+
+const CATEGORIES: [&str; 4] = ["A", "B", "modified", "duplicate"];
+
+/// How `get_result_sample` picks its differences.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleStrategy {
+    /// Uniformly from all differences.
+    Random,
+    /// Evenly from each category that has differences.
+    Stratified,
+    /// The unique and duplicate lines occurring most often.
+    TopByCount,
+}
+
+fn category_len(store: &ResultStore, category: &str) -> usize {
+    match category {
+        "modified" => store.modified.len(),
+        "duplicate" => store.duplicates.len(),
+        _ => store.unique_count(category),
+    }
+}
+
+/// The `index`-th stored difference of `category`.
+fn pick(store: &mut ResultStore, category: &str, index: usize) -> Result<Option<SampledDifference>, IoError> {
+    let sampled = |line_number, text: &str, count, other| SampledDifference {
+        category: category.to_string(),
+        line_number,
+        text: text.to_string(),
+        count,
+        other,
+    };
+    Ok(match category {
+        "modified" => store.modified.get(index).map(|pair| sampled(pair.line_a.line_number, &pair.line_a.text, 1, Some(pair.line_b.clone()))),
+        "duplicate" => store.duplicates.get(index).map(|d| sampled(d.line_number, &d.text, d.count_a + d.count_b, None)),
+        _ => store.unique_page(category, index, 1)?.pop().map(|line| sampled(line.line_number, &line.text, line.count, None)),
+    })
+}
+
+/// Splits `n` across the categories as evenly as their sizes allow.
+fn allot(lens: &[usize], n: usize) -> Vec<usize> {
+    let mut allotted = vec![0; lens.len()];
+    let mut remaining = n.min(lens.iter().sum());
+    while remaining > 0 {
+        for (taken, len) in allotted.iter_mut().zip(lens) {
+            if remaining > 0 && *taken < *len {
+                *taken += 1;
+                remaining -= 1;
+            }
+        }
+    }
+    allotted
+}
+
+fn top_by_count(store: &mut ResultStore, n: usize) -> Result<Vec<SampledDifference>, IoError> {
+    // Min-heap of the `n` largest counts seen so far, ties going to the earlier line.
+    let mut top = BinaryHeap::new();
+    let mut offer = |count: usize, order: usize, difference: SampledDifference| {
+        top.push((Reverse(count), order, DifferenceByCount(difference)));
+        if top.len() > n {
+            top.pop();
+        }
+    };
+    let mut order = 0;
+    for file_id in ["A", "B"] {
+        store.for_each_unique::<IoError>(file_id, |line| {
+            order += 1;
+            let difference = SampledDifference {
+                category: file_id.to_string(),
+                line_number: line.line_number,
+                text: line.text,
+                count: line.count,
+                other: None,
+            };
+            offer(line.count, order, difference);
+            Ok(())
+        })?;
+    }
+    for index in 0..store.duplicates.len() {
+        order += 1;
+        if let Some(difference) = pick(store, "duplicate", index)? {
+            offer(difference.count, order, difference);
+        }
+    }
+    let mut top = top.into_vec();
+    top.sort_by_key(|(count, order, _)| (*count, *order));
+    Ok(top.into_iter().map(|(_, _, difference)| difference.0).collect())
+}
+
+/// Orders heap entries by count and position only.
+struct DifferenceByCount(SampledDifference);
+
+impl PartialEq for DifferenceByCount {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for DifferenceByCount {}
+
+impl PartialOrd for DifferenceByCount {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DifferenceByCount {
+    fn cmp(&self, _: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+fn sample(store: &mut ResultStore, n: usize, strategy: SampleStrategy) -> Result<Vec<SampledDifference>, IoError> {
+    let lens: Vec<usize> = CATEGORIES.iter().map(|category| category_len(store, category)).collect();
+    let mut rng = rand::rng();
+    let picks: Vec<(usize, usize)> = match strategy {
+        SampleStrategy::TopByCount => return top_by_count(store, n),
+        SampleStrategy::Random => {
+            let total = lens.iter().sum();
+            let mut indices = index::sample(&mut rng, total, n.min(total)).into_vec();
+            indices.sort_unstable();
+            indices
+                .into_iter()
+                .map(|mut i| {
+                    let mut category = 0;
+                    while i >= lens[category] {
+                        i -= lens[category];
+                        category += 1;
+                    }
+                    (category, i)
+                })
+                .collect()
+        }
+        SampleStrategy::Stratified => allot(&lens, n)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(category, amount)| {
+                let mut indices = index::sample(&mut rng, lens[category], amount).into_vec();
+                indices.sort_unstable();
+                indices.into_iter().map(move |i| (category, i))
+            })
+            .collect(),
+    };
+    picks.into_iter().filter_map(|(category, i)| pick(store, CATEGORIES[category], i).transpose()).collect()
+}
+
+/// Up to `n` differences of a job picked with `strategy`, as examples for dashboards and
+/// notifications that don't need the full result set.
+pub fn result_sample(job: &Job, n: usize, strategy: SampleStrategy) -> Result<Vec<SampledDifference>, IoError> {
+    sample(&mut job.results.lock().unwrap(), n, strategy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payloads::DiffLine;
+    use crate::results::intra_line::modified_pair;
+    use crate::results::collected::CollectedLine;
+
+    fn store() -> ResultStore {
+        let mut store = ResultStore::new(false);
+        for line_number in 1..=5 {
+            store.push_unique("A", CollectedLine { line_number, text: format!("a{}", line_number), count: line_number });
+        }
+        let line = |text: &str| DiffLine { line_number: 9, text: text.to_string() };
+        store.modified.push(modified_pair(line("x,1"), line("x,2")));
+        store
+    }
+
+    #[test]
+    fn test_stratified_sample_spreads_over_categories() {
+        assert_eq!(allot(&[5, 0, 1, 0], 4), [3, 0, 1, 0]);
+        let picked = sample(&mut store(), 4, SampleStrategy::Stratified).unwrap();
+        assert_eq!(picked.iter().filter(|d| d.category == "modified").count(), 1);
+        assert_eq!(sample(&mut store(), 100, SampleStrategy::Random).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_top_by_count_orders_by_count() {
+        let counts: Vec<usize> = sample(&mut store(), 2, SampleStrategy::TopByCount).unwrap().iter().map(|d| d.count).collect();
+        assert_eq!(counts, [5, 4]);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::config::SimilarityPairing;
+use crate::payloads::ModifiedLine;
+use crate::results::collected::CollectedLine;
+use crate::results::intra_line::modified_pair;
+use std::collections::{HashMap, HashSet};
+
+/// Lines of B each line of A is compared with at most, which bounds the pass on large diffs.
+const MAX_CANDIDATES: usize = 256;
+
+fn token_set(text: &str) -> HashSet<&str> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '.' && c != '-')
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+/// Jaccard similarity of two token sets: shared tokens over all tokens.
+fn jaccard(a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+    let shared = a.intersection(b).count();
+    let all = a.len() + b.len() - shared;
+    if all == 0 { 1.0 } else { shared as f64 / all as f64 }
+}
+
+fn bucket(text: &str, prefix_len: usize) -> &str {
+    let end = text.char_indices().nth(prefix_len).map_or(text.len(), |(i, _)| i);
+    &text[..end]
+}
+
+/// Pairs the remaining unique lines of A and B that look like edits of each other: lines
+/// sharing their first `prefix_len` characters whose tokens are similar enough. Each line
+/// of A is compared with at most `MAX_CANDIDATES` lines of B and takes the most similar.
+pub fn pair_similar_lines(
+    options: &SimilarityPairing,
+    modified: &mut Vec<ModifiedLine>,
+    rest_a: Vec<CollectedLine>,
+    rest_b: Vec<CollectedLine>,
+) -> (Vec<CollectedLine>, Vec<CollectedLine>) {
+    if rest_a.is_empty() || rest_b.is_empty() {
+        return (rest_a, rest_b);
+    }
+    let tokens_b: Vec<HashSet<&str>> = rest_b.iter().map(|line| token_set(&line.text)).collect();
+    let mut buckets: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, line) in rest_b.iter().enumerate() {
+        buckets.entry(bucket(&line.text, options.prefix_len)).or_default().push(i);
+    }
+
+    let mut partner_of_a = vec![None; rest_a.len()];
+    let mut paired_b = vec![false; rest_b.len()];
+    for (i, line) in rest_a.iter().enumerate() {
+        let Some(candidates) = buckets.get_mut(bucket(&line.text, options.prefix_len)) else { continue };
+        candidates.retain(|&j| !paired_b[j]);
+        let tokens_a = token_set(&line.text);
+        let best = candidates
+            .iter()
+            .take(MAX_CANDIDATES)
+            .map(|&j| (j, jaccard(&tokens_a, &tokens_b[j])))
+            .filter(|&(_, similarity)| similarity >= options.min_similarity)
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+        if let Some((j, _)) = best {
+            paired_b[j] = true;
+            partner_of_a[i] = Some(j);
+        }
+    }
+    drop(tokens_b);
+
+    let mut rest_b: Vec<Option<CollectedLine>> = rest_b.into_iter().map(Some).collect();
+    let mut unpaired_a = Vec::new();
+    for (line, partner) in rest_a.into_iter().zip(partner_of_a) {
+        match partner.and_then(|j| rest_b[j].take()) {
+            Some(line_b) => modified.push(modified_pair(line.to_diff_line(), line_b.to_diff_line())),
+            None => unpaired_a.push(line),
+        }
+    }
+    (unpaired_a, rest_b.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(texts: &[&str]) -> Vec<CollectedLine> {
+        texts.iter().enumerate().map(|(i, text)| CollectedLine { line_number: i + 1, text: text.to_string(), count: 1 }).collect()
+    }
+
+    #[test]
+    fn test_similar_lines_become_modified_pairs() {
+        let options = SimilarityPairing { min_similarity: 0.5, prefix_len: 3 };
+        let mut modified = Vec::new();
+        let (rest_a, rest_b) = pair_similar_lines(
+            &options,
+            &mut modified,
+            lines(&["id=1 name=ann city=rome amount=10", "id=2 something else"]),
+            lines(&["zzz unrelated", "id=1 name=ann city=rome amount=12"]),
+        );
+        assert_eq!(modified.len(), 1);
+        assert_eq!(modified[0].line_b.text, "id=1 name=ann city=rome amount=12");
+        assert_eq!((rest_a.len(), rest_b.len()), (1, 1));
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Error as IoError, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// What to do with an export larger than `ExportOptions::split_size`.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitMode {
+    /// Numbered parts (`report.part1.csv`, ...) that end at line breaks.
+    #[default]
+    Parts,
+    /// A zip archive, itself cut into `.001`, `.002`, ... pieces if it's still too large.
+    Zip,
+}
+
+fn part_path(path: &Path, part: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}.part{}.{}", stem, part, ext.to_string_lossy()),
+        None => format!("{}.part{}", stem, part),
+    };
+    path.with_file_name(name)
+}
+
+fn piece_path(path: &Path, piece: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{:03}", piece));
+    PathBuf::from(name)
+}
+
+/// Splits a text file into parts of at most `limit` bytes, breaking only after a newline.
+/// A line longer than `limit` gets a part of its own.
+fn split_at_lines(path: &Path, limit: u64) -> Result<Vec<PathBuf>, IoError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut parts = Vec::new();
+    let mut writer: Option<BufWriter<File>> = None;
+    let mut written = 0u64;
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        if writer.is_none() || (written > 0 && written + line.len() as u64 > limit) {
+            if let Some(mut finished) = writer.take() {
+                finished.flush()?;
+            }
+            parts.push(part_path(path, parts.len() + 1));
+            writer = Some(BufWriter::new(File::create(parts.last().unwrap())?));
+            written = 0;
+        }
+        writer.as_mut().unwrap().write_all(&line)?;
+        written += line.len() as u64;
+        line.clear();
+    }
+    if let Some(mut finished) = writer {
+        finished.flush()?;
+    }
+    Ok(parts)
+}
+
+/// Cuts a file into pieces of exactly `limit` bytes (the last one shorter).
+fn split_at_bytes(path: &Path, limit: u64) -> Result<Vec<PathBuf>, IoError> {
+    let size = fs::metadata(path)?.len();
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut pieces = Vec::new();
+    for piece in 1..=size.div_ceil(limit) as usize {
+        let piece_path = piece_path(path, piece);
+        let mut writer = BufWriter::new(File::create(&piece_path)?);
+        io::copy(&mut reader.by_ref().take(limit), &mut writer)?;
+        writer.flush()?;
+        pieces.push(piece_path);
+    }
+    Ok(pieces)
+}
+
+fn zip_file(path: &Path) -> Result<PathBuf, IoError> {
+    let mut zip_path = path.as_os_str().to_owned();
+    zip_path.push(".zip");
+    let zip_path = PathBuf::from(zip_path);
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(&zip_path)?));
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(fs::metadata(path)?.len() >= u32::MAX as u64);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    zip.start_file(name, options).map_err(IoError::other)?;
+    io::copy(&mut BufReader::new(File::open(path)?), &mut zip)?;
+    zip.finish().map_err(IoError::other)?.flush()?;
+    Ok(zip_path)
+}
+
+/// Replaces a finished export larger than `limit` bytes with numbered parts or a zip
+/// archive, so it fits through mail and ticket systems that cap attachment sizes.
+/// Returns the files that make up the export. `at_lines` is false for binary formats,
+/// whose parts are cut at any byte.
+pub fn package_export(path: &Path, limit: Option<u64>, mode: SplitMode, at_lines: bool) -> Result<Vec<PathBuf>, IoError> {
+    let Some(limit) = limit.filter(|&limit| limit > 0) else {
+        return Ok(vec![path.to_path_buf()]);
+    };
+    if fs::metadata(path)?.len() <= limit {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let artifacts = match mode {
+        SplitMode::Parts if at_lines => split_at_lines(path, limit)?,
+        SplitMode::Parts => split_at_bytes(path, limit)?,
+        SplitMode::Zip => {
+            let zip_path = zip_file(path)?;
+            if fs::metadata(&zip_path)?.len() <= limit {
+                vec![zip_path]
+            } else {
+                let pieces = split_at_bytes(&zip_path, limit)?;
+                fs::remove_file(&zip_path)?;
+                pieces
+            }
+        }
+    };
+    fs::remove_file(path)?;
+    Ok(artifacts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts_end_at_line_breaks() {
+        let dir = std::env::temp_dir().join(format!("lfc_split_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("report.csv");
+        fs::write(&path, "aaaa\nbbbb\ncccccccccccc\nd\n").unwrap();
+
+        let parts = package_export(&path, Some(10), SplitMode::Parts, true).unwrap();
+        let contents: Vec<String> = parts.iter().map(|part| fs::read_to_string(part).unwrap()).collect();
+        assert_eq!(contents, ["aaaa\nbbbb\n", "cccccccccccc\n", "d\n"]);
+        assert_eq!(parts[0].file_name().unwrap(), "report.part1.csv");
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
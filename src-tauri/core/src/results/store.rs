@@ -0,0 +1,199 @@
+use crate::header::HeaderLayout;
+use crate::jobs::Job;
+use crate::payloads::{
+    AcknowledgedDifference, BatchSummary, BinaryRange, ComparisonSummary, DirectoryEntry, DuplicateLinePayload, FileIdentity, LineClassCounts, ModifiedLine, MultiWayLine, OrderedHunk, PartialResultsPayload, StepDetailPayload,
+    ThreeWayConflict, ThreeWayLine,
+};
+use crate::results::collected::CollectedLine;
+use crate::results::text_codec::{StoredText, TextCodec};
+use crate::sink::ProgressSink;
+use std::io::{Error as IoError, ErrorKind};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultStatus {
+    #[default]
+    Running,
+    Complete,
+    /// The run was cancelled or failed; the store holds what was found until then.
+    Partial,
+}
+
+struct StoredLine {
+    line_number: usize,
+    count: usize,
+    text: StoredText,
+}
+
+/// Differences found by one job, kept on the backend so they outlive the event stream.
+/// Unique line text is compressed when the job was configured with `compress_results`.
+#[derive(Default)]
+pub struct ResultStore {
+    unique_a: Vec<StoredLine>,
+    unique_b: Vec<StoredLine>,
+    codec: Option<TextCodec>,
+    pub modified: Vec<ModifiedLine>,
+    /// Differences matching the expected-diff allowlist.
+    pub acknowledged: Vec<AcknowledgedDifference>,
+    pub duplicates: Vec<DuplicateLinePayload>,
+    /// Results of a three-way comparison.
+    pub three_way: Vec<ThreeWayLine>,
+    pub conflicts: Vec<ThreeWayConflict>,
+    /// Results of an N-way comparison.
+    pub multi_way: Vec<MultiWayLine>,
+    /// Per-file results of a directory comparison.
+    pub directory: Vec<DirectoryEntry>,
+    /// Differing byte ranges of a binary block comparison.
+    pub binary_ranges: Vec<BinaryRange>,
+    /// Roll-up of a directory comparison.
+    pub batch_summary: Option<BatchSummary>,
+    pub status: ResultStatus,
+    /// Summary of the finished comparison.
+    pub summary: Option<ComparisonSummary>,
+    /// Fallbacks taken during the run, copied into the summary.
+    pub degradations: Vec<String>,
+    /// Identities of file A and B, recorded when the job starts.
+    pub inputs: Vec<FileIdentity>,
+    pub same_file: bool,
+    pub line_classes: Vec<LineClassCounts>,
+    /// Timings of the steps the engines reported, in the order they finished.
+    pub steps: Vec<StepDetailPayload>,
+    /// Column mapping the results were produced with, needed to extract keys later on.
+    pub header_layout: Option<Arc<HeaderLayout>>,
+    /// Ordered diff of a small-file comparison.
+    pub ordered_hunks: Vec<OrderedHunk>,
+}
+
+impl ResultStore {
+    pub fn new(compress: bool) -> Self {
+        Self {
+            codec: compress.then(TextCodec::new),
+            ..Default::default()
+        }
+    }
+
+    fn encode(&mut self, line: CollectedLine) -> StoredLine {
+        let text = match &mut self.codec {
+            Some(codec) => codec.encode(line.text),
+            None => StoredText::Raw(line.text.into_boxed_str()),
+        };
+        StoredLine {
+            line_number: line.line_number,
+            count: line.count,
+            text,
+        }
+    }
+
+    fn decode(codec: &mut Option<TextCodec>, line: &StoredLine) -> Result<CollectedLine, IoError> {
+        let text = match (codec, &line.text) {
+            (_, StoredText::Raw(text)) => text.to_string(),
+            (Some(codec), stored) => codec.decode(stored)?,
+            (None, _) => return Err(IoError::new(ErrorKind::InvalidData, "Compressed result text without a codec")),
+        };
+        Ok(CollectedLine {
+            line_number: line.line_number,
+            text,
+            count: line.count,
+        })
+    }
+
+    pub fn push_unique(&mut self, file_id: &str, line: CollectedLine) {
+        let stored = self.encode(line);
+        if file_id == "A" {
+            self.unique_a.push(stored);
+        } else {
+            self.unique_b.push(stored);
+        }
+    }
+
+    pub fn unique_count(&self, file_id: &str) -> usize {
+        if file_id == "A" { self.unique_a.len() } else { self.unique_b.len() }
+    }
+
+    /// Line numbers of the unique lines of one file, in stored order, without decoding them.
+    pub fn unique_line_numbers(&self, file_id: &str) -> Vec<usize> {
+        let lines = if file_id == "A" { &self.unique_a } else { &self.unique_b };
+        lines.iter().map(|line| line.line_number).collect()
+    }
+
+    /// Decoded copies of the unique lines of one file, in stored order.
+    pub fn unique_lines(&mut self, file_id: &str) -> Result<Vec<CollectedLine>, IoError> {
+        let lines = if file_id == "A" { &self.unique_a } else { &self.unique_b };
+        lines.iter().map(|line| Self::decode(&mut self.codec, line)).collect()
+    }
+
+    /// Passes the unique lines of one file to `f` one at a time, in stored order, so large
+    /// results can be written out without decoding them all first.
+    pub fn for_each_unique<E: From<IoError>>(&mut self, file_id: &str, mut f: impl FnMut(CollectedLine) -> Result<(), E>) -> Result<(), E> {
+        let lines = if file_id == "A" { &self.unique_a } else { &self.unique_b };
+        lines.iter().try_for_each(|line| f(Self::decode(&mut self.codec, line)?))
+    }
+
+    /// Decoded copies of the unique lines of one file whose line number lies in `range`.
+    pub fn unique_lines_in(&mut self, file_id: &str, range: &RangeInclusive<usize>) -> Result<Vec<CollectedLine>, IoError> {
+        let lines = if file_id == "A" { &self.unique_a } else { &self.unique_b };
+        lines
+            .iter()
+            .filter(|line| range.contains(&line.line_number))
+            .map(|line| Self::decode(&mut self.codec, line))
+            .collect()
+    }
+
+    /// Decoded copies of `limit` unique lines of one file starting at the `offset`-th, in
+    /// stored order, which is line order once the run has finished. Only these are
+    /// decompressed.
+    pub fn unique_page(&mut self, file_id: &str, offset: usize, limit: usize) -> Result<Vec<CollectedLine>, IoError> {
+        let lines = if file_id == "A" { &self.unique_a } else { &self.unique_b };
+        lines
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|line| Self::decode(&mut self.codec, line))
+            .collect()
+    }
+
+    /// Puts the unique lines of both files in line order, without decoding them.
+    pub fn sort_unique(&mut self) {
+        self.unique_a.sort_unstable_by_key(|line| line.line_number);
+        self.unique_b.sort_unstable_by_key(|line| line.line_number);
+    }
+
+    /// Drops the unique lines of both files.
+    pub fn clear_unique(&mut self) {
+        self.unique_a.clear();
+        self.unique_b.clear();
+    }
+
+    /// Removes and returns the unique lines of both files, for steps that need all of
+    /// their text at once.
+    pub fn take_unique(&mut self) -> Result<(Vec<CollectedLine>, Vec<CollectedLine>), IoError> {
+        let lines = (self.unique_lines("A")?, self.unique_lines("B")?);
+        self.clear_unique();
+        Ok(lines)
+    }
+
+    pub fn set_unique(&mut self, lines_a: Vec<CollectedLine>, lines_b: Vec<CollectedLine>) {
+        self.unique_a = lines_a.into_iter().map(|line| self.encode(line)).collect();
+        self.unique_b = lines_b.into_iter().map(|line| self.encode(line)).collect();
+    }
+}
+
+/// Reports why the job stopped, marks the results collected so far as partial and tells the
+/// frontend they can be fetched.
+pub fn flush_partial_results<S: ProgressSink>(sink: &S, job: &Job, reason: &str) {
+    sink.on_error(reason);
+    let payload = {
+        let mut store = job.results.lock().unwrap();
+        store.status = ResultStatus::Partial;
+        PartialResultsPayload {
+            job_id: job.id.clone(),
+            reason: reason.to_string(),
+            unique_to_a: store.unique_count("A"),
+            unique_to_b: store.unique_count("B"),
+            modified: store.modified.len(),
+        }
+    };
+    sink.emit("partial_results_available", &payload);
+}
@@ -0,0 +1,86 @@
+use std::io::{Error as IoError, ErrorKind};
+use zstd::bulk::{Compressor, Decompressor};
+
+/// Lines stored before a dictionary is trained from them.
+const TRAINING_SAMPLES: usize = 1000;
+const DICTIONARY_SIZE: usize = 16 * 1024;
+const COMPRESSION_LEVEL: i32 = 3;
+
+pub enum StoredText {
+    Raw(Box<str>),
+    Compressed { len: usize, data: Box<[u8]> },
+}
+
+/// Compresses result text with a zstd dictionary trained on the first lines of the job.
+/// Diff lines of one job tend to share structure (same columns, similar prefixes), so even
+/// short lines compress well with a shared dictionary.
+#[derive(Default)]
+pub struct TextCodec {
+    samples: Vec<String>,
+    compressor: Option<Compressor<'static>>,
+    decompressor: Option<Decompressor<'static>>,
+}
+
+impl TextCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn train(&mut self) {
+        let dictionary = zstd::dict::from_samples(&self.samples, DICTIONARY_SIZE).unwrap_or_default();
+        self.samples = Vec::new();
+        self.compressor = Compressor::with_dictionary(COMPRESSION_LEVEL, &dictionary).ok();
+        self.decompressor = Decompressor::with_dictionary(&dictionary).ok();
+    }
+
+    pub fn encode(&mut self, text: String) -> StoredText {
+        if self.compressor.is_none() {
+            if self.samples.len() < TRAINING_SAMPLES {
+                self.samples.push(text.clone());
+                return StoredText::Raw(text.into_boxed_str());
+            }
+            self.train();
+        }
+        match self.compressor.as_mut().map(|c| c.compress(text.as_bytes())) {
+            Some(Ok(data)) if data.len() < text.len() => StoredText::Compressed {
+                len: text.len(),
+                data: data.into_boxed_slice(),
+            },
+            _ => StoredText::Raw(text.into_boxed_str()),
+        }
+    }
+
+    pub fn decode(&mut self, stored: &StoredText) -> Result<String, IoError> {
+        match stored {
+            StoredText::Raw(text) => Ok(text.to_string()),
+            StoredText::Compressed { len, data } => {
+                let decompressor = self
+                    .decompressor
+                    .as_mut()
+                    .ok_or_else(|| IoError::new(ErrorKind::InvalidData, "Result text was compressed without a dictionary"))?;
+                let bytes = decompressor.decompress(data, *len)?;
+                String::from_utf8(bytes).map_err(|err| IoError::new(ErrorKind::InvalidData, err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_after_training() {
+        let mut codec = TextCodec::new();
+        let lines: Vec<String> = (0..TRAINING_SAMPLES + 50)
+            .map(|i| format!("{},customer-{},2024-01-{:02},status=ACTIVE,region=EU-WEST", i, i * 7, i % 28 + 1))
+            .collect();
+        let stored: Vec<StoredText> = lines.iter().map(|l| codec.encode(l.clone())).collect();
+        assert!(stored.iter().any(|s| matches!(s, StoredText::Compressed { .. })));
+        for (line, stored) in lines.iter().zip(&stored) {
+            assert_eq!(&codec.decode(stored).unwrap(), line);
+        }
+        let corrupt = StoredText::Compressed { len: 10, data: vec![1, 2, 3].into_boxed_slice() };
+        assert!(codec.decode(&corrupt).is_err());
+    }
+}
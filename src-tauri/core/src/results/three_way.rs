@@ -0,0 +1,125 @@
+use crate::config::CompareConfig;
+use crate::event_limit::emit_bounded;
+use crate::jobs::Job;
+use crate::payloads::{DiffLine, ThreeWayChange, ThreeWayConflict, ThreeWayFinishedPayload, ThreeWayLine, ThreeWaySummary};
+use crate::results::pairing::key_extractors;
+use crate::results::store::ResultStatus;
+use crate::sink::ProgressSink;
+use std::collections::HashMap;
+
+/// Index of the file a classified line is read from.
+pub const BASE: usize = 0;
+pub const FILE_A: usize = 1;
+pub const FILE_B: usize = 2;
+
+/// Splits the occurrence counts of one line in BASE, A and B into changes.
+/// Returns `(change, file index to read the line from, count)` for every non-zero change.
+pub fn classify(base: usize, a: usize, b: usize, ignore_occurences: bool) -> Vec<(ThreeWayChange, usize, usize)> {
+    let (base, a, b) = if ignore_occurences {
+        (base.min(1), a.min(1), b.min(1))
+    } else {
+        (base, a, b)
+    };
+    let added_a = a.saturating_sub(base);
+    let added_b = b.saturating_sub(base);
+    let removed_a = base.saturating_sub(a);
+    let removed_b = base.saturating_sub(b);
+    let added_both = added_a.min(added_b);
+    let removed_both = removed_a.min(removed_b);
+
+    [
+        (ThreeWayChange::AddedInBoth, FILE_A, added_both),
+        (ThreeWayChange::AddedInA, FILE_A, added_a - added_both),
+        (ThreeWayChange::AddedInB, FILE_B, added_b - added_both),
+        (ThreeWayChange::RemovedInBoth, BASE, removed_both),
+        (ThreeWayChange::RemovedInA, BASE, removed_a - removed_both),
+        (ThreeWayChange::RemovedInB, BASE, removed_b - removed_both),
+    ]
+    .into_iter()
+    .filter(|&(_, _, count)| count > 0)
+    .collect()
+}
+
+fn diff_line(line: &ThreeWayLine) -> DiffLine {
+    DiffLine { line_number: line.line_number, text: line.text.clone() }
+}
+
+/// Records with the same key added in A and in B but with different text. The BASE
+/// version is the removed line with that key, if there is one.
+fn find_conflicts(lines: &[ThreeWayLine], compare_config: &CompareConfig) -> Vec<ThreeWayConflict> {
+    let Some((extractor, _)) = key_extractors(compare_config, None) else {
+        return Vec::new();
+    };
+    let mut by_key: HashMap<String, [Option<&ThreeWayLine>; 3]> = HashMap::new();
+    for line in lines {
+        let slot = match line.change {
+            ThreeWayChange::AddedInA => FILE_A,
+            ThreeWayChange::AddedInB => FILE_B,
+            ThreeWayChange::RemovedInBoth => BASE,
+            _ => continue,
+        };
+        if let Some(key) = extractor.key(&line.text) {
+            by_key.entry(key).or_default()[slot].get_or_insert(line);
+        }
+    }
+    let mut conflicts: Vec<ThreeWayConflict> = by_key
+        .into_iter()
+        .filter_map(|(key, [base, a, b])| {
+            Some(ThreeWayConflict {
+                key,
+                line_base: base.map(diff_line),
+                line_a: diff_line(a?),
+                line_b: diff_line(b?),
+            })
+        })
+        .collect();
+    conflicts.sort_unstable_by_key(|c| c.line_a.line_number);
+    conflicts
+}
+
+/// Emits the classified lines and conflicts of a three-way comparison and stores them in the job.
+pub fn report_three_way<S: ProgressSink>(sink: &S, job: &Job, mut lines: Vec<ThreeWayLine>, compare_config: &CompareConfig) {
+    lines.sort_unstable_by(|x, y| x.file.cmp(&y.file).then(x.line_number.cmp(&y.line_number)));
+    let conflicts = find_conflicts(&lines, compare_config);
+
+    let mut summary = ThreeWaySummary { conflicts: conflicts.len(), ..Default::default() };
+    for line in &lines {
+        let counter = match line.change {
+            ThreeWayChange::AddedInA => &mut summary.added_in_a,
+            ThreeWayChange::AddedInB => &mut summary.added_in_b,
+            ThreeWayChange::AddedInBoth => &mut summary.added_in_both,
+            ThreeWayChange::RemovedInA => &mut summary.removed_in_a,
+            ThreeWayChange::RemovedInB => &mut summary.removed_in_b,
+            ThreeWayChange::RemovedInBoth => &mut summary.removed_in_both,
+        };
+        *counter += 1;
+        emit_bounded(sink, "three_way_line", line, compare_config.max_event_bytes);
+    }
+    for conflict in &conflicts {
+        emit_bounded(sink, "three_way_conflict", conflict, compare_config.max_event_bytes);
+    }
+
+    {
+        let mut store = job.results.lock().unwrap();
+        store.three_way = lines;
+        store.conflicts = conflicts;
+        store.status = ResultStatus::Complete;
+    }
+    sink.on_finished("three_way_finished", &ThreeWayFinishedPayload { summary });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_splits_shared_and_one_sided_changes() {
+        assert_eq!(classify(1, 1, 1, false), vec![]);
+        assert_eq!(
+            classify(0, 2, 1, false),
+            vec![(ThreeWayChange::AddedInBoth, FILE_A, 1), (ThreeWayChange::AddedInA, FILE_A, 1)]
+        );
+        assert_eq!(classify(2, 0, 2, false), vec![(ThreeWayChange::RemovedInA, BASE, 2)]);
+        assert_eq!(classify(1, 0, 3, true), vec![(ThreeWayChange::RemovedInA, BASE, 1)]);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::jobs::Job;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Error as IoError, ErrorKind, Write};
+use std::path::Path;
+
+/// Lines each file is read ahead to find where they match again after a difference.
+/// Bounds memory; differences longer than this are reported as one changed block.
+const LOOKAHEAD: usize = 4096;
+
+/// A line without its `\n`; only the last line of a file may lack one.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Line {
+    bytes: Vec<u8>,
+    newline: bool,
+}
+
+/// Reads one file ahead of the diff position.
+struct LineWindow<R> {
+    reader: R,
+    lines: VecDeque<Line>,
+    /// Line number of the first buffered line.
+    number: usize,
+    eof: bool,
+}
+
+impl<R: BufRead> LineWindow<R> {
+    fn new(reader: R) -> Self {
+        Self { reader, lines: VecDeque::new(), number: 1, eof: false }
+    }
+
+    fn fill(&mut self, count: usize) -> Result<(), IoError> {
+        while self.lines.len() < count && !self.eof {
+            let mut bytes = Vec::new();
+            if self.reader.read_until(b'\n', &mut bytes)? == 0 {
+                self.eof = true;
+                break;
+            }
+            let newline = bytes.last() == Some(&b'\n');
+            if newline {
+                bytes.pop();
+            }
+            self.lines.push_back(Line { bytes, newline });
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, count: usize) -> Vec<Line> {
+        self.number += count;
+        self.lines.drain(..count).collect()
+    }
+}
+
+/// Offsets into both windows of the nearest line they share, nearest by combined distance.
+fn resync(a: &VecDeque<Line>, b: &VecDeque<Line>) -> Option<(usize, usize)> {
+    let mut first_in_b: HashMap<&Line, usize> = HashMap::new();
+    for (j, line) in b.iter().enumerate() {
+        first_in_b.entry(line).or_insert(j);
+    }
+    a.iter()
+        .enumerate()
+        .filter_map(|(i, line)| first_in_b.get(line).map(|&j| (i, j)))
+        .min_by_key(|&(i, j)| i + j)
+}
+
+struct Hunk {
+    start_a: usize,
+    start_b: usize,
+    lines: Vec<(u8, Line)>,
+    /// Unchanged lines at the end of `lines`.
+    trailing: usize,
+}
+
+/// Writes unified diff hunks with `context` unchanged lines around each change.
+struct HunkWriter<W: Write> {
+    out: W,
+    context: usize,
+    before: VecDeque<Line>,
+    hunk: Option<Hunk>,
+    hunks: usize,
+}
+
+impl<W: Write> HunkWriter<W> {
+    fn unchanged(&mut self, line: Line) -> Result<(), IoError> {
+        let Some(hunk) = &mut self.hunk else {
+            self.before.push_back(line);
+            if self.before.len() > self.context {
+                self.before.pop_front();
+            }
+            return Ok(());
+        };
+        hunk.lines.push((b' ', line));
+        hunk.trailing += 1;
+        // Changes further apart than twice the context get hunks of their own.
+        if hunk.trailing > 2 * self.context {
+            let keep = hunk.lines.len() - hunk.trailing + self.context;
+            self.before = hunk.lines.drain(keep..).map(|(_, line)| line).skip(1).collect();
+            hunk.trailing = self.context;
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn changed(&mut self, removed: Vec<Line>, added: Vec<Line>, line_a: usize, line_b: usize) {
+        let before = std::mem::take(&mut self.before);
+        let hunk = self.hunk.get_or_insert_with(|| Hunk {
+            start_a: line_a - before.len(),
+            start_b: line_b - before.len(),
+            lines: before.into_iter().map(|line| (b' ', line)).collect(),
+            trailing: 0,
+        });
+        hunk.lines.extend(removed.into_iter().map(|line| (b'-', line)));
+        hunk.lines.extend(added.into_iter().map(|line| (b'+', line)));
+        hunk.trailing = 0;
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        let Some(mut hunk) = self.hunk.take() else { return Ok(()) };
+        let excess = hunk.trailing.saturating_sub(self.context);
+        hunk.lines.truncate(hunk.lines.len() - excess);
+        let len_a = hunk.lines.iter().filter(|(tag, _)| *tag != b'+').count();
+        let len_b = hunk.lines.iter().filter(|(tag, _)| *tag != b'-').count();
+        // An empty range is given by the line before it.
+        let start = |start: usize, len: usize| if len == 0 { start - 1 } else { start };
+        writeln!(
+            self.out,
+            "@@ -{},{} +{},{} @@",
+            start(hunk.start_a, len_a),
+            len_a,
+            start(hunk.start_b, len_b),
+            len_b
+        )?;
+        for (tag, line) in &hunk.lines {
+            self.out.write_all(&[*tag])?;
+            self.out.write_all(&line.bytes)?;
+            self.out.write_all(b"\n")?;
+            if !line.newline {
+                self.out.write_all(b"\\ No newline at end of file\n")?;
+            }
+        }
+        self.hunks += 1;
+        Ok(())
+    }
+}
+
+/// Streams both inputs in order and writes their differences as a unified diff.
+/// Returns the number of hunks written.
+pub fn write_unified_diff<R: BufRead, W: Write>(
+    a: R,
+    b: R,
+    labels: (&str, &str),
+    out: W,
+    context: usize,
+) -> Result<usize, IoError> {
+    let (mut a, mut b) = (LineWindow::new(a), LineWindow::new(b));
+    let mut writer = HunkWriter { out, context, before: VecDeque::new(), hunk: None, hunks: 0 };
+    writeln!(writer.out, "--- {}", labels.0)?;
+    writeln!(writer.out, "+++ {}", labels.1)?;
+
+    loop {
+        a.fill(1)?;
+        b.fill(1)?;
+        match (a.lines.front(), b.lines.front()) {
+            (None, None) => break,
+            (Some(x), Some(y)) if x == y => {
+                b.take(1);
+                writer.unchanged(a.take(1).remove(0))?;
+            }
+            _ => {
+                a.fill(LOOKAHEAD)?;
+                b.fill(LOOKAHEAD)?;
+                let (skip_a, skip_b) = resync(&a.lines, &b.lines).unwrap_or((a.lines.len(), b.lines.len()));
+                let (line_a, line_b) = (a.number, b.number);
+                writer.changed(a.take(skip_a), b.take(skip_b), line_a, line_b);
+            }
+        }
+    }
+    writer.flush()?;
+    writer.out.flush()?;
+    Ok(writer.hunks)
+}
+
+/// Writes the inputs of a two-file job as a unified diff to `path`, e.g. to review it in
+/// a diff viewer or apply it with `patch`. Unlike the other exports it is order-sensitive.
+pub fn export_unified_diff(job: &Job, path: &Path, context: usize) -> Result<usize, IoError> {
+    let (path_a, path_b) = {
+        let store = job.results.lock().unwrap();
+        let [a, b] = store.inputs.as_slice() else {
+            return Err(IoError::new(ErrorKind::InvalidInput, "Job has no two input files"));
+        };
+        (a.path.clone(), b.path.clone())
+    };
+    let open = |path: &str| File::open(job.readable_path(path)).map(BufReader::new);
+    let out = BufWriter::new(File::create(path)?);
+    write_unified_diff(open(&path_a)?, open(&path_b)?, (&path_a, &path_b), out, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(a: &str, b: &str) -> String {
+        let mut out = Vec::new();
+        write_unified_diff(a.as_bytes(), b.as_bytes(), ("a", "b"), &mut out, 1).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_changes_become_hunks_with_context() {
+        assert_eq!(diff("1\n2\n3\n4\n5\n6\n7\n", "1\n2\nx\n4\n5\n6\n7\n8"), concat!(
+            "--- a\n+++ b\n",
+            "@@ -2,3 +2,3 @@\n 2\n-3\n+x\n 4\n",
+            "@@ -7,1 +7,2 @@\n 7\n+8\n\\ No newline at end of file\n",
+        ));
+        assert_eq!(diff("same\n", "same\n"), "--- a\n+++ b\n");
+        assert_eq!(diff("", "new\n"), "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+new\n");
+    }
+}
@@ -0,0 +1,48 @@
+use crate::jobs::Job;
+use crate::payloads::ResultsWindow;
+use std::io::{Error as IoError, ErrorKind};
+
+/// Collects the stored differences of `file` between `start_line` and `end_line` (inclusive).
+pub fn results_window(job: &Job, file: &str, start_line: usize, end_line: usize) -> Result<ResultsWindow, IoError> {
+    if file != "A" && file != "B" {
+        return Err(IoError::new(ErrorKind::InvalidInput, format!("Unknown side: {}", file)));
+    }
+    let range = start_line..=end_line;
+    let mut store = job.results.lock().unwrap();
+
+    let unique = store.unique_lines_in(file, &range)?.iter().map(|line| line.to_diff_line()).collect();
+    let modified = store
+        .modified
+        .iter()
+        .filter(|pair| {
+            let line = if file == "A" { &pair.line_a } else { &pair.line_b };
+            range.contains(&line.line_number)
+        })
+        .cloned()
+        .collect();
+    let duplicates = store
+        .duplicates
+        .iter()
+        .filter(|duplicate| duplicate.file == file && range.contains(&duplicate.line_number))
+        .cloned()
+        .collect();
+    let acknowledged = store
+        .acknowledged
+        .iter()
+        .filter(|difference| {
+            (difference.file == file && range.contains(&difference.line_number))
+                || (file == "B" && difference.other.as_ref().is_some_and(|other| range.contains(&other.line_number)))
+        })
+        .cloned()
+        .collect();
+
+    Ok(ResultsWindow {
+        file: file.to_string(),
+        start_line,
+        end_line,
+        unique,
+        modified,
+        duplicates,
+        acknowledged,
+    })
+}
@@ -0,0 +1,205 @@
+use crate::jobs::Job;
+use std::fs::File;
+use std::io::{BufWriter, Error as IoError, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Rows Excel allows per sheet; longer results continue on numbered sheets.
+const MAX_ROWS: usize = 1_048_576;
+/// Characters Excel allows per cell.
+const MAX_CELL_CHARS: usize = 32_767;
+
+/// XML-escapes a cell value, dropping control characters XML can't represent and cutting
+/// it to the length Excel accepts.
+fn xml_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars().take(MAX_CELL_CHARS) {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+enum Cell<'a> {
+    Text(&'a str),
+    Number(u64),
+}
+
+/// Streams worksheets into the xlsx zip one row at a time. Strings are stored inline, so
+/// no shared string table has to be kept in memory.
+struct XlsxWriter {
+    zip: ZipWriter<BufWriter<File>>,
+    sheets: Vec<String>,
+    /// Rows written to the open sheet, `None` when no sheet is open.
+    rows: Option<usize>,
+    /// Name and header of the open sheet, to start continuation sheets with.
+    current: (String, Vec<String>),
+    /// Sheets written for the current name so far.
+    part: usize,
+}
+
+impl XlsxWriter {
+    fn create(path: &Path) -> Result<Self, IoError> {
+        Ok(Self {
+            zip: ZipWriter::new(BufWriter::new(File::create(path)?)),
+            sheets: Vec::new(),
+            rows: None,
+            current: (String::new(), Vec::new()),
+            part: 0,
+        })
+    }
+
+    fn start_file(&mut self, name: &str) -> Result<(), IoError> {
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        self.zip.start_file(name, options).map_err(IoError::other)
+    }
+
+    fn start_sheet(&mut self, name: &str, header: &[&str]) -> Result<(), IoError> {
+        self.end_sheet()?;
+        self.current = (name.to_string(), header.iter().map(|h| h.to_string()).collect());
+        self.part = 0;
+        self.open_sheet(name.to_string())
+    }
+
+    fn open_sheet(&mut self, name: String) -> Result<(), IoError> {
+        self.sheets.push(name);
+        self.part += 1;
+        self.start_file(&format!("xl/worksheets/sheet{}.xml", self.sheets.len()))?;
+        self.zip.write_all(concat!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+            r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#
+        ).as_bytes())?;
+        self.rows = Some(0);
+        let header = self.current.1.clone();
+        self.row(&header.iter().map(|h| Cell::Text(h)).collect::<Vec<_>>())
+    }
+
+    fn end_sheet(&mut self) -> Result<(), IoError> {
+        if self.rows.take().is_some() {
+            self.zip.write_all(b"</sheetData></worksheet>")?;
+        }
+        Ok(())
+    }
+
+    fn row(&mut self, cells: &[Cell]) -> Result<(), IoError> {
+        if self.rows == Some(MAX_ROWS) {
+            self.end_sheet()?;
+            let name = format!("{} ({})", self.current.0, self.part + 1);
+            self.open_sheet(name)?;
+        }
+        let mut xml = String::from("<row>");
+        for cell in cells {
+            match cell {
+                Cell::Text(text) => xml.push_str(&format!(r#"<c t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#, xml_text(text))),
+                Cell::Number(number) => xml.push_str(&format!("<c><v>{}</v></c>", number)),
+            }
+        }
+        xml.push_str("</row>");
+        self.zip.write_all(xml.as_bytes())?;
+        self.rows = self.rows.map(|rows| rows + 1);
+        Ok(())
+    }
+
+    /// Writes the workbook parts that list the sheets and closes the file.
+    fn finish(mut self) -> Result<(), IoError> {
+        self.end_sheet()?;
+        let count = self.sheets.len();
+        let overrides: String = (1..=count)
+            .map(|i| format!(r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#, i))
+            .collect();
+        let sheets: String = self.sheets.iter().enumerate()
+            .map(|(i, name)| format!(r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#, xml_text(&sheet_name(name)), i + 1, i + 1))
+            .collect();
+        let relationships: String = (1..=count)
+            .map(|i| format!(r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#, i, i))
+            .collect();
+        let parts = [
+            ("[Content_Types].xml", format!(concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+                r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+                r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+                r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+                "{}</Types>"), overrides)),
+            ("_rels/.rels", concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+                r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+                "</Relationships>").to_string()),
+            ("xl/workbook.xml", format!(concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+                r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+                "<sheets>{}</sheets></workbook>"), sheets)),
+            ("xl/_rels/workbook.xml.rels", format!(concat!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#,
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+                "{}</Relationships>"), relationships)),
+        ];
+        for (name, content) in parts {
+            self.start_file(name)?;
+            self.zip.write_all(content.as_bytes())?;
+        }
+        self.zip.finish().map_err(IoError::other)?.flush()
+    }
+}
+
+/// Sheet names are limited to 31 characters and may not contain `[]:*?/\`.
+fn sheet_name(name: &str) -> String {
+    name.chars().filter(|c| !"[]:*?/\\".contains(*c)).take(31).collect()
+}
+
+/// Writes the stored results of a job as an Excel workbook with a summary sheet and one
+/// sheet of lines per file. Rows are streamed, so the workbook is never held in memory.
+pub fn export_xlsx_report(job: &Job, path: &Path) -> Result<(), IoError> {
+    let mut store = job.results.lock().unwrap();
+    let mut xlsx = XlsxWriter::create(path)?;
+
+    xlsx.start_sheet("Summary", &["Item", "Value"])?;
+    for (file_id, input) in ["A", "B"].iter().zip(&store.inputs) {
+        xlsx.row(&[Cell::Text(&format!("File {}", file_id)), Cell::Text(&input.canonical_path)])?;
+    }
+    if let Some(summary) = store.summary.clone() {
+        let counts = [
+            ("Unique in A", summary.unique_to_a),
+            ("Unique in B", summary.unique_to_b),
+            ("Modified", summary.modified),
+            ("Duplicates", summary.duplicates),
+            ("Acknowledged", summary.acknowledged),
+        ];
+        for (name, count) in counts {
+            xlsx.row(&[Cell::Text(name), Cell::Number(count as u64)])?;
+        }
+        for degradation in &summary.degradations {
+            xlsx.row(&[Cell::Text("Degradation"), Cell::Text(degradation)])?;
+        }
+    }
+
+    for (file_id, name) in [("A", "A only"), ("B", "B only")] {
+        xlsx.start_sheet(name, &["Line", "Text", "Count"])?;
+        store.for_each_unique(file_id, |line| {
+            xlsx.row(&[Cell::Number(line.line_number as u64), Cell::Text(&line.text), Cell::Number(line.count as u64)])
+        })?;
+    }
+    xlsx.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells_are_valid_xml() {
+        assert_eq!(xml_text("a<b & \u{1}c"), "a&lt;b &amp; c");
+        assert_eq!(xml_text(&"x".repeat(40_000)).len(), MAX_CELL_CHARS);
+        assert_eq!(sheet_name("A only [2024/01]"), "A only 202401");
+    }
+}
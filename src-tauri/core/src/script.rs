@@ -0,0 +1,72 @@
+use crate::normalize::LineNormalizer;
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::borrow::Cow;
+use std::io::{Error as IoError, ErrorKind};
+
+/// Operations a script may take per line, so a runaway loop fails instead of hanging the run.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A user-supplied Rhai script transforming each line before it is hashed. The script
+/// sees the line as `line` and its value is what gets hashed, e.g.
+/// `line.split(",")[2] + line.split(",")[0]` or `line.replace("session=", "")`.
+///
+/// A script evaluating to `()` leaves the line unchanged. Lines that aren't UTF-8, or on
+/// which the script fails, are hashed as they are.
+pub struct LineScript {
+    engine: Engine,
+    ast: AST,
+}
+
+impl LineScript {
+    /// Compiles `source`, rejecting syntax errors before any line is read.
+    pub fn new(source: &str) -> Result<Self, IoError> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| IoError::new(ErrorKind::InvalidInput, format!("Invalid transform script: {}", e)))?;
+        Ok(Self { engine, ast })
+    }
+
+    /// The script's value for `line`, or `None` when it keeps the line as it is.
+    pub fn transform(&self, line: &str) -> Result<Option<String>, String> {
+        let mut scope = Scope::new();
+        scope.push("line", line.to_string());
+        let value: Dynamic = self.engine.eval_ast_with_scope(&mut scope, &self.ast).map_err(|e| e.to_string())?;
+        if value.is_unit() {
+            Ok(None)
+        } else if value.is_string() {
+            Ok(Some(value.into_string().map_err(|e| e.to_string())?).filter(|text| text != line))
+        } else {
+            Ok(Some(value.to_string()))
+        }
+    }
+}
+
+impl LineNormalizer for LineScript {
+    fn normalize<'r>(&self, record: Cow<'r, [u8]>) -> Cow<'r, [u8]> {
+        let transformed = match std::str::from_utf8(&record) {
+            Ok(line) => self.transform(line).ok().flatten(),
+            Err(_) => None,
+        };
+        match transformed {
+            Some(text) => Cow::Owned(text.into_bytes()),
+            None => record,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_reorders_fields_and_keeps_failing_lines() {
+        let script = LineScript::new(r#"let f = line.split(","); if f.len() < 3 { throw "short" } f[2] + "," + f[0]"#).unwrap();
+        assert_eq!(script.normalize(Cow::Borrowed(b"a,b,c")).as_ref(), b"c,a");
+        assert_eq!(script.normalize(Cow::Borrowed(b"a,b")).as_ref(), b"a,b");
+        assert_eq!(LineScript::new("()").unwrap().transform("x"), Ok(None));
+        assert!(LineScript::new("loop {").is_err());
+        assert!(LineScript::new("loop {}").unwrap().transform("x").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::payloads::{ProgressPayload, StepDetailPayload};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+/// Receives everything the engines report while they run. The app forwards it to the
+/// frontend as events; tests and other tools can collect or ignore it instead.
+///
+/// Only `on_event` is required: the other methods default to it with the event names the
+/// frontend listens for, so a sink overrides just what it handles differently.
+pub trait ProgressSink: Clone + Send + Sync + 'static {
+    /// Any event without a method of its own, e.g. `modified_line` or `delimiter_mismatch`.
+    fn on_event(&self, event: &str, payload: Value);
+
+    fn on_progress(&self, progress: &ProgressPayload) {
+        self.emit("progress", progress);
+    }
+
+    /// A line found in only one file, already bounded to the event size limit.
+    fn on_unique_line(&self, line: Value) {
+        self.on_event("unique_line", line);
+    }
+
+    fn on_step(&self, step: &StepDetailPayload) {
+        self.emit("step_completed", step);
+    }
+
+    /// The final summary of a run; `event` names the kind of run, e.g. `comparison_finished`.
+    fn on_finished<T: Serialize>(&self, event: &str, summary: &T) {
+        self.emit(event, summary);
+    }
+
+    /// A run stopped early, with the reason.
+    fn on_error(&self, message: &str) {
+        self.on_event("comparison_error", json!({ "message": message }));
+    }
+
+    /// Serializes `payload` and passes it to `on_event`.
+    fn emit<T: Serialize>(&self, event: &str, payload: &T) {
+        match serde_json::to_value(payload) {
+            Ok(value) => self.on_event(event, value),
+            Err(e) => eprintln!("Failed to serialize {} event: {}", event, e),
+        }
+    }
+}
+
+/// Keeps every event in memory, in the order they were reported.
+#[derive(Clone, Default)]
+pub struct CollectingSink {
+    events: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+impl CollectingSink {
+    pub fn events(&self) -> Vec<(String, Value)> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Payloads of the events named `event`.
+    pub fn payloads(&self, event: &str) -> Vec<Value> {
+        self.events.lock().unwrap().iter().filter(|(name, _)| name == event).map(|(_, payload)| payload.clone()).collect()
+    }
+}
+
+impl ProgressSink for CollectingSink {
+    fn on_event(&self, event: &str, payload: Value) {
+        self.events.lock().unwrap().push((event.to_string(), payload));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_methods_report_frontend_event_names() {
+        let sink = CollectingSink::default();
+        sink.on_unique_line(json!({ "text": "a" }));
+        sink.on_step(&StepDetailPayload { step: "Partitioning".to_string(), duration_ms: 3 });
+        sink.on_finished("comparison_finished", &json!({}));
+        sink.on_error("cancelled");
+
+        let names: Vec<String> = sink.events().into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["unique_line", "step_completed", "comparison_finished", "comparison_error"]);
+        assert_eq!(sink.payloads("step_completed")[0]["duration_ms"], 3);
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::{Error as IoError, ErrorKind, Read, Seek, SeekFrom};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Storage simulated for input paths, read by `input::open_streamed`.
+static SIMULATED: Mutex<Vec<(String, SlowStorage)>> = Mutex::new(Vec::new());
+
+/// Makes the engines read `path` as if it were on `storage`.
+pub fn simulate(path: &str, storage: SlowStorage) {
+    SIMULATED.lock().unwrap().push((path.to_string(), storage));
+}
+
+/// The storage simulated for `path`, or a normal one.
+pub fn simulated(path: &str) -> SlowStorage {
+    let simulated = SIMULATED.lock().unwrap();
+    simulated.iter().rfind(|(simulated, _)| simulated == path).map(|(_, storage)| storage.clone()).unwrap_or_default()
+}
+
+/// How a simulated slow or flaky storage, e.g. a network share, misbehaves in tests.
+#[derive(Clone, Debug)]
+pub struct SlowStorage {
+    /// Delay added to every read call.
+    pub latency: Duration,
+    /// Bytes per second the reads are throttled to.
+    pub bytes_per_sec: Option<u64>,
+    /// Most bytes returned by one read call, like a small network packet.
+    pub max_read: usize,
+    /// Offset at which reads fail, `failures` times in a row.
+    pub fail_at: Option<u64>,
+    pub failures: usize,
+    pub error_kind: ErrorKind,
+}
+
+impl Default for SlowStorage {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            bytes_per_sec: None,
+            max_read: usize::MAX,
+            fail_at: None,
+            failures: 0,
+            error_kind: ErrorKind::TimedOut,
+        }
+    }
+}
+
+/// Wraps a reader with the latency, throughput limit and errors of a `SlowStorage`.
+pub struct SlowReader<R> {
+    inner: R,
+    storage: SlowStorage,
+    position: u64,
+    failed: usize,
+}
+
+impl<R: Read> SlowReader<R> {
+    pub fn new(inner: R, storage: SlowStorage) -> Self {
+        Self { inner, storage, position: 0, failed: 0 }
+    }
+}
+
+impl<R: Read> Read for SlowReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        thread::sleep(self.storage.latency);
+        let mut len = buf.len().min(self.storage.max_read);
+        if let Some(fail_at) = self.storage.fail_at {
+            if self.position >= fail_at && self.failed < self.storage.failures {
+                self.failed += 1;
+                return Err(IoError::new(self.storage.error_kind, "simulated storage error"));
+            }
+            if self.position < fail_at {
+                len = len.min((fail_at - self.position) as usize);
+            }
+        }
+        let n = self.inner.read(&mut buf[..len])?;
+        self.position += n as u64;
+        if let Some(rate) = self.storage.bytes_per_sec {
+            thread::sleep(Duration::from_secs_f64(n as f64 / rate.max(1) as f64));
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for SlowReader<R> {
+    fn seek(&mut self, position: SeekFrom) -> Result<u64, IoError> {
+        self.position = self.inner.seek(position)?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::run_engine;
+    use crate::jobs::JobRegistry;
+    use crate::sink::CollectingSink;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+    use std::path::Path;
+    use std::time::Instant;
+
+    #[test]
+    fn test_throughput_is_limited_and_errors_hit_at_the_offset() {
+        let data = vec![b'x'; 20_000];
+        let storage = SlowStorage { bytes_per_sec: Some(200_000), max_read: 4096, ..Default::default() };
+        let now = Instant::now();
+        let mut copy = Vec::new();
+        SlowReader::new(&data[..], storage).read_to_end(&mut copy).unwrap();
+        assert_eq!(copy, data);
+        assert!(now.elapsed() >= Duration::from_millis(90));
+
+        let storage = SlowStorage { fail_at: Some(100), failures: 1, ..Default::default() };
+        let mut reader = SlowReader::new(&data[..], storage);
+        let mut buf = [0u8; 256];
+        assert_eq!(reader.read(&mut buf).unwrap(), 100);
+        assert_eq!(reader.read(&mut buf).unwrap_err().kind(), ErrorKind::TimedOut);
+        assert_eq!(reader.read(&mut buf).unwrap(), 256);
+    }
+
+    /// Sorted `unique_line` events of a comparison of `a` and `b`.
+    fn unique_lines(a: &Path, b: &Path, overrides: serde_json::Value) -> Vec<String> {
+        let mut config = serde_json::json!({
+            "useExternalSort": true, "ignoreOccurences": false, "useSingleThread": false, "ignoreLineNumber": false,
+            "trivialDiffLimit": 0, "partitionCount": 4,
+        });
+        config.as_object_mut().unwrap().extend(overrides.as_object().unwrap().clone());
+        let config: crate::CompareConfig = serde_json::from_value(config).unwrap();
+        let job = JobRegistry::default().create(config.clone());
+        let sink = CollectingSink::default();
+        let (a, b) = (a.to_string_lossy().into_owned(), b.to_string_lossy().into_owned());
+        run_engine(sink.clone(), job, a, b, config, None).unwrap();
+        let mut lines: Vec<String> = sink.payloads("unique_line").iter().map(|payload| payload.to_string()).collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_engines_on_slow_storage_report_the_fast_path_events() {
+        let dir = std::env::temp_dir().join(format!("slow_io_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+        std::fs::write(&a, (0..3_000).map(|i| format!("row {}\n", i * 7)).collect::<String>()).unwrap();
+        std::fs::write(&b, (0..3_000).map(|i| format!("row {}\n", i * 5)).collect::<String>()).unwrap();
+        let expected = unique_lines(&a, &b, serde_json::json!({}));
+        assert!(!expected.is_empty());
+
+        // The external engine maps a decompressed copy, read through packet-sized, failing reads.
+        let packed = dir.join("a.txt.gz");
+        let mut encoder = GzEncoder::new(std::fs::File::create(&packed).unwrap(), Compression::default());
+        encoder.write_all(&std::fs::read(&a).unwrap()).unwrap();
+        encoder.finish().unwrap();
+        let flaky = SlowStorage { max_read: 512, fail_at: Some(2_000), failures: 2, ..Default::default() };
+        simulate(packed.to_str().unwrap(), flaky);
+        assert_eq!(unique_lines(&packed, &b, serde_json::json!({})), expected);
+
+        // The streaming path reads both inputs through the slow storage, including the unique lines.
+        let slow = SlowStorage { latency: Duration::from_micros(100), max_read: 4096, ..Default::default() };
+        simulate(a.to_str().unwrap(), slow.clone());
+        simulate(b.to_str().unwrap(), slow);
+        assert_eq!(unique_lines(&a, &b, serde_json::json!({ "streaming": true })), expected);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
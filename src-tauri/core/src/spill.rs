@@ -0,0 +1,188 @@
+use crate::hashing::HashMap;
+use extsort::Sortable;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error as IoError, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const FOOTER_MAGIC: u64 = u64::from_le_bytes(*b"LFCPART1");
+pub const FOOTER_LEN: u64 = 24;
+pub const RECORD_LEN: u64 = 16;
+
+/// A line hash and the offset of the line it was computed from, as stored in partitions.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+pub struct HashOffset(pub u64, pub u64);
+
+impl Sortable for HashOffset {
+    fn encode<W: Write>(&self, writer: &mut W) -> Result<(), IoError> {
+        writer.write_all(&self.0.to_le_bytes())?;
+        writer.write_all(&self.1.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> Result<Self, IoError> {
+        let mut hash_bytes = [0u8; 8];
+        reader.read_exact(&mut hash_bytes)?;
+        let mut offset_bytes = [0u8; 8];
+        reader.read_exact(&mut offset_bytes)?;
+        Ok(HashOffset(
+            u64::from_le_bytes(hash_bytes),
+            u64::from_le_bytes(offset_bytes),
+        ))
+    }
+}
+
+fn fold_checksum(checksum: u64, item: &HashOffset) -> u64 {
+    (checksum ^ item.0).wrapping_mul(0x100000001b3).rotate_left(17) ^ item.1
+}
+
+/// Writes the records of one partition followed by a footer holding the record count and
+/// an order-dependent checksum, so truncated or corrupted spill files are detected on read.
+pub struct PartitionWriter {
+    writer: BufWriter<File>,
+    count: u64,
+    checksum: u64,
+}
+
+impl PartitionWriter {
+    pub fn create(path: &Path) -> Result<Self, IoError> {
+        let file = File::create(path)?;
+        Ok(Self {
+            writer: BufWriter::with_capacity(1024 * 1024, file),
+            count: 0,
+            checksum: 0,
+        })
+    }
+
+    pub fn write(&mut self, item: HashOffset) -> Result<(), IoError> {
+        item.encode(&mut self.writer)?;
+        self.count += 1;
+        self.checksum = fold_checksum(self.checksum, &item);
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), IoError> {
+        self.writer.write_all(&FOOTER_MAGIC.to_le_bytes())?;
+        self.writer.write_all(&self.count.to_le_bytes())?;
+        self.writer.write_all(&self.checksum.to_le_bytes())?;
+        self.writer.flush()
+    }
+}
+
+fn corrupt(path: &Path, reason: &str) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidData,
+        format!("Temp partition {} is corrupt ({}); check the temp disk", path.display(), reason),
+    )
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, IoError> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Reads every record of a partition written by `PartitionWriter`, verifying its footer.
+/// Every partition file is created before any record is written, so a missing one means
+/// the temp directory was tampered with, not that the partition is empty.
+pub fn read_partition(path: &Path, mut visit: impl FnMut(HashOffset)) -> Result<(), IoError> {
+    let mut file = File::open(path).map_err(|e| IoError::new(e.kind(), format!("Partition {} is unreadable: {}", path.display(), e)))?;
+    let len = file.metadata()?.len();
+    if len < FOOTER_LEN || !(len - FOOTER_LEN).is_multiple_of(RECORD_LEN) {
+        return Err(corrupt(path, "unexpected size"));
+    }
+    file.seek(SeekFrom::Start(len - FOOTER_LEN))?;
+    if read_u64(&mut file)? != FOOTER_MAGIC {
+        return Err(corrupt(path, "missing footer"));
+    }
+    let count = read_u64(&mut file)?;
+    let checksum = read_u64(&mut file)?;
+    if count != (len - FOOTER_LEN) / RECORD_LEN {
+        return Err(corrupt(path, "record count mismatch"));
+    }
+
+    file.seek(SeekFrom::Start(0))?;
+    let mut reader = BufReader::new(file);
+    let mut actual = 0;
+    for _ in 0..count {
+        let item = HashOffset::decode(&mut reader)?;
+        actual = fold_checksum(actual, &item);
+        visit(item);
+    }
+    if actual != checksum {
+        return Err(corrupt(path, "checksum mismatch"));
+    }
+    Ok(())
+}
+
+/// Per-partition hash counts and the offset of the first line with each hash.
+pub type PartitionMaps = (HashMap<u64, usize>, HashMap<u64, u64>);
+
+pub fn read_partition_into_maps(partition_path: PathBuf) -> Result<PartitionMaps, IoError> {
+    let mut counts = HashMap::default();
+    let mut first_offsets = HashMap::default();
+    read_partition(&partition_path, |item| {
+        *counts.entry(item.0).or_insert(0) += 1;
+        first_offsets.entry(item.0).or_insert(item.1);
+    })?;
+
+    Ok((counts, first_offsets))
+}
+
+/// Distinct hashes of one partition in ascending order, each with the smallest offset it
+/// occurs at and its number of occurrences. Much smaller than the two hash maps.
+pub type SortedHashes = Vec<(u64, u64, usize)>;
+
+pub fn read_partition_sorted(partition_path: &Path) -> Result<SortedHashes, IoError> {
+    let mut items = Vec::new();
+    read_partition(partition_path, |item| items.push((item.0, item.1)))?;
+    items.sort_unstable();
+    let mut sorted: SortedHashes = Vec::new();
+    for (hash, offset) in items {
+        match sorted.last_mut() {
+            Some(last) if last.0 == hash => last.2 += 1,
+            _ => sorted.push((hash, offset, 1)),
+        }
+    }
+    Ok(sorted)
+}
+
+/// Whether the partition at `path` ends with a footer matching its size, i.e. was written in full.
+pub fn is_finished(path: &Path) -> bool {
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let Ok(len) = file.metadata().map(|m| m.len()) else {
+        return false;
+    };
+    if len < FOOTER_LEN || file.seek(SeekFrom::Start(len - FOOTER_LEN)).is_err() {
+        return false;
+    }
+    matches!((read_u64(&mut file), read_u64(&mut file)), (Ok(FOOTER_MAGIC), Ok(count)) if count == (len - FOOTER_LEN) / RECORD_LEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_flipped_byte() {
+        let path = std::env::temp_dir().join(format!("spill_test_{}", std::process::id()));
+        let mut writer = PartitionWriter::create(&path).unwrap();
+        writer.write(HashOffset(7, 0)).unwrap();
+        writer.write(HashOffset(9, 42)).unwrap();
+        writer.finish().unwrap();
+
+        let mut items = Vec::new();
+        read_partition(&path, |item| items.push(item)).unwrap();
+        assert_eq!(items, vec![HashOffset(7, 0), HashOffset(9, 42)]);
+        assert!(is_finished(&path));
+
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[3] ^= 1;
+        std::fs::write(&path, &bytes).unwrap();
+        let err = read_partition(&path, |_| {}).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(read_partition_into_maps(path).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,138 @@
+use encoding_rs::{Decoder, Encoding, GBK, SHIFT_JIS, UTF_8, WINDOWS_1252};
+use std::io::{Error as IoError, ErrorKind, Read};
+
+const INPUT_BLOCK: usize = 64 * 1024;
+
+/// Whether `sample` is UTF-8, ignoring a sequence cut off at the end of the sample.
+fn is_utf8(sample: &[u8]) -> bool {
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30ff}')
+}
+
+/// Guesses the legacy encoding of a non-UTF-8 sample. Latin-1 text has isolated high bytes
+/// between ASCII letters, while GBK and Shift-JIS use runs of them; Japanese text is told
+/// apart from Chinese by its kana.
+pub fn detect_encoding(sample: &[u8]) -> &'static Encoding {
+    if is_utf8(sample) {
+        return UTF_8;
+    }
+    let high = sample.iter().filter(|b| **b >= 0x80).count();
+    let in_runs = sample.windows(2).filter(|w| w[0] >= 0x80 && w[1] >= 0x80).count() * 2;
+    if in_runs < high {
+        return WINDOWS_1252;
+    }
+    // Don't let a character cut off at the end of the sample rule out a candidate.
+    let sample = &sample[..sample.len().saturating_sub(1)];
+    let decode = |encoding: &'static Encoding| encoding.decode_without_bom_handling_and_without_replacement(sample);
+    let looks_japanese =
+        |text: &str| text.chars().filter(|c| is_kana(*c)).count() * 5 >= text.chars().filter(|c| !c.is_ascii()).count();
+    match (decode(SHIFT_JIS), decode(GBK)) {
+        (Some(text), _) if looks_japanese(&text) => SHIFT_JIS,
+        (_, Some(_)) => GBK,
+        (Some(_), None) => SHIFT_JIS,
+        (None, None) => WINDOWS_1252,
+    }
+}
+
+/// Resolves the configured input encoding for a file starting with `sample`: `None` means
+/// UTF-8, "auto" detects a legacy encoding, anything else is an encoding label such as
+/// "gbk", "shift_jis" or "latin1". A byte order mark always wins, and UTF-8 files with one
+/// are transcoded too so the mark doesn't end up in the first line. Returns `None` when the
+/// file can be used as is.
+pub fn source_encoding(setting: Option<&str>, sample: &[u8]) -> Result<Option<&'static Encoding>, IoError> {
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return Ok(Some(encoding));
+    }
+    let encoding = match setting {
+        None => UTF_8,
+        Some(label) if label.eq_ignore_ascii_case("auto") => detect_encoding(sample),
+        Some(label) => encoding_for_label(label)?,
+    };
+    Ok((encoding != UTF_8).then_some(encoding))
+}
+
+pub fn encoding_for_label(label: &str) -> Result<&'static Encoding, IoError> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, format!("Unknown encoding: {}", label)))
+}
+
+/// Streams the text of `inner` as UTF-8, dropping the byte order mark. Malformed sequences
+/// become U+FFFD.
+pub struct Utf8Transcoder<R> {
+    inner: R,
+    decoder: Decoder,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl<R: Read> Utf8Transcoder<R> {
+    pub fn new(inner: R, encoding: &'static Encoding) -> Self {
+        Self {
+            inner,
+            decoder: encoding.new_decoder_with_bom_removal(),
+            input: vec![0; INPUT_BLOCK],
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read> Read for Utf8Transcoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        while self.position == self.output.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            let n = self.inner.read(&mut self.input)?;
+            self.finished = n == 0;
+            let capacity = self.decoder.max_utf8_buffer_length(n).unwrap_or(n * 3 + 16);
+            self.output.resize(capacity, 0);
+            let (_, _, written, _) = self.decoder.decode_to_utf8(&self.input[..n], &mut self.output, self.finished);
+            self.output.truncate(written);
+            self.position = 0;
+        }
+        let n = buf.len().min(self.output.len() - self.position);
+        buf[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::UTF_16LE;
+
+    #[test]
+    fn test_utf16le_with_bom_becomes_utf8() {
+        let text = "id,name\r\n1,Zoë\r\n";
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(text.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+        assert_eq!(source_encoding(Some("gbk"), &bytes).unwrap(), Some(UTF_16LE));
+        assert_eq!(source_encoding(None, b"\xef\xbb\xbfid").unwrap(), Some(UTF_8));
+        assert_eq!(source_encoding(None, b"id").unwrap(), None);
+
+        let mut decoded = String::new();
+        Utf8Transcoder::new(bytes.as_slice(), UTF_16LE).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, text);
+    }
+
+    #[test]
+    fn test_detects_legacy_encodings() {
+        let sample = |encoding: &'static Encoding, text: &str| encoding.encode(text).0.into_owned();
+        assert_eq!(detect_encoding(b"plain ascii, caf\xc3\xa9"), UTF_8);
+        assert_eq!(detect_encoding(&sample(WINDOWS_1252, "id,name\n1,café crème\n2,Müller\n")), WINDOWS_1252);
+        assert_eq!(detect_encoding(&sample(GBK, "编号,名称\n1,大文件比较工具\n")), GBK);
+        assert_eq!(detect_encoding(&sample(SHIFT_JIS, "番号,名前\n1,これは日本語のテキストです\n")), SHIFT_JIS);
+        assert!(encoding_for_label("no-such-charset").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::jobs::Job;
+use crate::payloads::StepDetailPayload;
+use crate::sink::ProgressSink;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub use crate::hashing::{HashAlgo, HashMap, HashSet, LineHasher};
+
+/// Records the timing of a finished step in the job's results and emits it to the frontend.
+pub fn emit_step<S: ProgressSink>(sink: &S, job: &Job, step: String, duration_ms: u128) {
+    let payload = StepDetailPayload { step, duration_ms };
+    job.results.lock().unwrap().steps.push(payload.clone());
+    sink.on_step(&payload);
+}
+
+// Helper to emit step details to the frontend
+pub fn emit_step_detail<S: ProgressSink>(sink: &S, job: &Job, file_id: &str, step_name: &str, duration_ms: u128) {
+    emit_step(sink, job, format!("File {} - {}", file_id, step_name), duration_ms);
+}
+
+/// Most progress-like events let through per second.
+const MAX_EVENTS_PER_SEC: u32 = 10;
+
+/// Lets through at most `MAX_EVENTS_PER_SEC` events and drops the rest. Meant for events
+/// carrying a running total, like progress, where the latest one supersedes those dropped.
+pub struct Throttle {
+    interval: Duration,
+    last: Mutex<Option<Instant>>,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self { interval: Duration::from_secs(1) / MAX_EVENTS_PER_SEC, last: Mutex::new(None) }
+    }
+}
+
+impl Throttle {
+    /// Whether an event may go out now. `force` lets it through regardless, e.g. the last
+    /// one of a phase, and restarts the interval.
+    pub fn ready(&self, force: bool) -> bool {
+        let mut last = self.last.lock().unwrap();
+        let now = Instant::now();
+        if force || last.is_none_or(|at| now.duration_since(at) >= self.interval) {
+            *last = Some(now);
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_drops_events_within_the_interval() {
+        let throttle = Throttle::default();
+        assert!(throttle.ready(false));
+        assert!(!throttle.ready(false));
+        assert!(throttle.ready(true));
+        std::thread::sleep(throttle.interval);
+        assert!(throttle.ready(false));
+    }
+}
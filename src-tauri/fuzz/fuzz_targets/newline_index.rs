@@ -0,0 +1,30 @@
+#![no_main]
+
+// The newline index is read back from the temp disk and mapped as raw `usize` values.
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../core/src/records.rs"]
+#[allow(dead_code)]
+mod records;
+
+#[path = "../../core/src/external/line_numbers.rs"]
+#[allow(dead_code)]
+mod line_numbers;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(positions) = line_numbers::newline_index(data) {
+        assert_eq!(size_of_val(positions), data.len());
+        // Lookups into an index that isn't sorted give wrong line numbers, not panics.
+        for &position in positions.iter().take(64) {
+            let _ = positions.binary_search(&position);
+        }
+    }
+
+    let mut offsets: Vec<usize> = data.chunks(2).map(|pair| pair.iter().map(|&b| b as usize).sum::<usize>() * 7).collect();
+    offsets.sort_unstable();
+    let numbers = line_numbers::line_numbers_for_offsets(data, &offsets, b'\n', None);
+    assert_eq!(numbers.len(), offsets.len());
+    let quoted = line_numbers::line_numbers_for_offsets(data, &offsets, b'\n', Some(b'"'));
+    assert_eq!(quoted.len(), offsets.len());
+});
@@ -0,0 +1,33 @@
+#![no_main]
+
+// Corrupted or tampered temp partitions must be rejected, never crash the comparison.
+
+use extsort::Sortable;
+use libfuzzer_sys::fuzz_target;
+use std::io::Write;
+
+#[path = "../../core/src/hashing.rs"]
+#[allow(dead_code)]
+mod hashing;
+
+#[path = "../../core/src/spill.rs"]
+#[allow(dead_code)]
+mod spill;
+
+use spill::{read_partition, HashOffset};
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = data;
+    while let Ok(item) = HashOffset::decode(&mut reader) {
+        let mut encoded = Vec::new();
+        item.encode(&mut encoded).unwrap();
+        assert_eq!(HashOffset::decode(&mut &encoded[..]).unwrap(), item);
+    }
+
+    let path = std::env::temp_dir().join(format!("partition_decode_{}", std::process::id()));
+    std::fs::File::create(&path).unwrap().write_all(data).unwrap();
+    let mut records = 0u64;
+    if read_partition(&path, |_| records += 1).is_ok() {
+        assert_eq!(records * 16 + 24, data.len() as u64);
+    }
+});
@@ -0,0 +1,32 @@
+#![no_main]
+
+// Record and field parsing run on every byte of both inputs, quoted or not.
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../core/src/records.rs"]
+#[allow(dead_code)]
+mod records;
+
+fuzz_target!(|data: &[u8]| {
+    // The first bytes pick the terminator, the quote and the delimiter.
+    let [terminator, quote, delimiter_len, rest @ ..] = data else { return };
+    let delimiter_len = (*delimiter_len as usize % 4).min(rest.len());
+    let (delimiter, record) = rest.split_at(delimiter_len);
+
+    let ends = records::find_record_ends(record, *terminator, *quote);
+    assert!(ends.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(ends.iter().all(|&end| end < record.len()));
+    for quote in [None, Some(*quote)] {
+        let len = records::record_len(record, *terminator, quote);
+        assert!(len <= record.len());
+    }
+
+    let fields = records::split_delimited(record, delimiter, Some(*quote));
+    assert!(!fields.is_empty());
+    let unquoted = records::split_delimited(record, delimiter, None);
+    if !delimiter.is_empty() {
+        let joined = unquoted.join(delimiter);
+        assert_eq!(joined, record);
+    }
+});
@@ -0,0 +1,187 @@
+use crate::config::CompareConfig;
+use crate::prefs;
+use crate::utils::HashAlgo;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Error as IoError, Read, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+use sysinfo::System;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const STORE_FILE: &str = "store.json";
+const CALIBRATION_KEY: &str = "calibration";
+/// Bytes written and read back by the disk benchmarks.
+const BENCH_BYTES: usize = 64 * 1024 * 1024;
+const BENCH_BLOCK: usize = 1024 * 1024;
+/// Bytes of sample lines hashed by the hash benchmark.
+const HASH_BENCH_BYTES: usize = 16 * 1024 * 1024;
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Measured throughput of this machine and the engine defaults derived from it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Calibration {
+    /// Line hashing throughput of one thread.
+    pub hash_bytes_per_sec: f64,
+    /// Sequential read of the benchmark file right after writing it, so mostly the page cache.
+    pub read_bytes_per_sec: f64,
+    /// Sequential write to the temp directory, including the final sync.
+    pub temp_write_bytes_per_sec: f64,
+    pub total_memory: u64,
+    pub cores: usize,
+    pub scan_chunk_size: usize,
+    pub partition_count: u64,
+    pub in_memory_limit: u64,
+}
+
+fn throughput(bytes: usize, started: Instant) -> f64 {
+    bytes as f64 / started.elapsed().as_secs_f64().max(1e-6)
+}
+
+fn bench_hash() -> f64 {
+    let line: Vec<u8> = (0..63u8).map(|i| b'a' + i % 26).chain([b'\n']).collect();
+    let data = line.repeat(HASH_BENCH_BYTES / line.len());
+    let algo = HashAlgo::detect();
+    let started = Instant::now();
+    let checksum = data
+        .split(|&byte| byte == b'\n')
+        .fold(0u64, |acc, line| acc ^ algo.hash_line(0, line));
+    std::hint::black_box(checksum);
+    throughput(data.len(), started)
+}
+
+/// Writes and reads back a file in the temp directory. Returns the write and read throughput.
+fn bench_disk(temp_dir: PathBuf) -> Result<(f64, f64), IoError> {
+    let path = temp_dir.join(format!("calibration_{}.bin", std::process::id()));
+    let block = vec![0x5au8; BENCH_BLOCK];
+    let result = (|| {
+        let started = Instant::now();
+        let mut file = File::create(&path)?;
+        for _ in 0..BENCH_BYTES / BENCH_BLOCK {
+            file.write_all(&block)?;
+        }
+        file.sync_all()?;
+        let write = throughput(BENCH_BYTES, started);
+
+        let started = Instant::now();
+        let mut file = File::open(&path)?;
+        let mut buffer = vec![0u8; BENCH_BLOCK];
+        while file.read(&mut buffer)? > 0 {}
+        Ok((write, throughput(BENCH_BYTES, started)))
+    })();
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// Derives engine defaults from the measurements.
+fn derive(hash_bytes_per_sec: f64, read_bytes_per_sec: f64, temp_write_bytes_per_sec: f64, total_memory: u64, cores: usize) -> Calibration {
+    // About 10 ms of scanning per chunk keeps the per-chunk overhead negligible.
+    let scan_chunk_size = ((read_bytes_per_sec / 100.0) as usize)
+        .next_power_of_two()
+        .clamp(4 * 1024 * 1024, 64 * 1024 * 1024);
+    // Enough partitions to keep every core busy during aggregation with small partitions,
+    // but few open files on slow (spinning or network) temp storage.
+    let mut partition_count = ((cores * 16) as u64).next_power_of_two().clamp(64, 1024);
+    if temp_write_bytes_per_sec < 150.0 * MIB {
+        partition_count = partition_count.min(128);
+    }
+    // The in-memory engine maps both files and keeps a hash table of every line.
+    let in_memory_limit = total_memory / 3;
+    Calibration {
+        hash_bytes_per_sec,
+        read_bytes_per_sec,
+        temp_write_bytes_per_sec,
+        total_memory,
+        cores,
+        scan_chunk_size,
+        partition_count,
+        in_memory_limit,
+    }
+}
+
+/// Runs the micro-benchmarks (about a second) and derives the defaults from them.
+pub fn calibrate(temp_dir: PathBuf) -> Result<Calibration, IoError> {
+    let hash = bench_hash();
+    let (write, read) = bench_disk(temp_dir)?;
+    let mut system = System::new();
+    system.refresh_memory();
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    Ok(derive(hash, read, write, system.total_memory(), cores))
+}
+
+pub fn load(app: &AppHandle) -> Option<Calibration> {
+    let store = app.store(STORE_FILE).ok()?;
+    store.get(CALIBRATION_KEY).and_then(|value| serde_json::from_value(value).ok())
+}
+
+fn save(app: &AppHandle, calibration: &Calibration) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|err| err.to_string())?;
+    store.set(CALIBRATION_KEY, serde_json::to_value(calibration).map_err(|err| err.to_string())?);
+    store.save().map_err(|err| err.to_string())
+}
+
+fn temp_dir(app: &AppHandle) -> PathBuf {
+    prefs::load(app).temp_dir.map_or_else(std::env::temp_dir, PathBuf::from)
+}
+
+/// Calibrates in the background on first launch, when nothing has been stored yet.
+pub fn calibrate_on_first_run(app: AppHandle) {
+    if load(&app).is_some() {
+        return;
+    }
+    std::thread::spawn(move || match calibrate(temp_dir(&app)) {
+        Ok(calibration) => {
+            if let Err(e) = save(&app, &calibration) {
+                eprintln!("Failed to store calibration: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Calibration failed: {}", e),
+    });
+}
+
+/// Fills the engine settings the config leaves unset with the temp directory preference
+/// and the calibrated values.
+pub fn apply_defaults(app: &AppHandle, config: &mut CompareConfig) {
+    if config.temp_dir.is_none() {
+        config.temp_dir = prefs::load(app).temp_dir;
+    }
+    let Some(calibration) = load(app) else {
+        return;
+    };
+    config.in_memory_limit.get_or_insert(calibration.in_memory_limit);
+    config.partition_count.get_or_insert(calibration.partition_count);
+    config.scan_chunk_size.get_or_insert(calibration.scan_chunk_size);
+}
+
+#[tauri::command]
+pub fn get_calibration(app: AppHandle) -> Option<Calibration> {
+    load(&app)
+}
+
+/// Runs the calibration again, e.g. after moving the temp directory to another disk.
+#[tauri::command]
+pub async fn run_calibration(app: AppHandle) -> Result<Calibration, String> {
+    let calibration = calibrate(temp_dir(&app)).map_err(|err| err.to_string())?;
+    save(&app, &calibration)?;
+    Ok(calibration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_follow_the_hardware() {
+        let gib = 1024 * 1024 * 1024;
+        let fast = derive(4e9, 8e9, 2e9, 32 * gib, 16);
+        assert_eq!(fast.partition_count, 256);
+        assert_eq!(fast.scan_chunk_size, 64 * 1024 * 1024);
+        assert_eq!(fast.in_memory_limit, 32 * gib / 3);
+
+        let slow = derive(1e9, 2e8, 1e8, 8 * gib, 16);
+        assert_eq!(slow.partition_count, 128);
+        assert_eq!(slow.scan_chunk_size, 4 * 1024 * 1024);
+    }
+}
@@ -0,0 +1,276 @@
+use crate::config::CompareConfig;
+use crate::engine::run_engine;
+use crate::external::multi_file::{remove_temp_dirs_immediately, TempDir, TEMP_DIR_PREFIX};
+use crate::jobs::JobRegistry;
+use large_file_compare_core::sink::ProgressSink;
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{BufWriter, Error as IoError, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Input path that stands for standard input, as in `dump_table | <app> - baseline.csv`.
+const STDIN_PATH: &str = "-";
+/// Subcommand that asks for a comparison on the command line even when the arguments are
+/// incomplete, so mistakes are reported instead of opening the window.
+const SUBCOMMAND: &str = "compare";
+
+fn usage() -> String {
+    let program = std::env::args()
+        .next()
+        .and_then(|path| std::path::Path::new(&path).file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string());
+    format!(
+        "Usage: {} [{}] <file-a> <file-b> [--streaming] [--config <json>]\nUse - for one of the files to read it from stdin.",
+        program, SUBCOMMAND
+    )
+}
+
+/// A comparison requested on the command line.
+struct Invocation {
+    file_a_path: String,
+    file_b_path: String,
+    compare_config: CompareConfig,
+}
+
+/// The comparison the arguments ask for, or `None` when the app opens its window instead:
+/// the command line is used with the `compare` subcommand, a comparison option or exactly
+/// two files, so e.g. the `-psn_…` argument macOS passes to apps or a single file opened
+/// with the app still open the window. `--config` takes comparison settings as in the
+/// frontend, e.g. `{"ignoreColumns":[2]}`.
+fn parse_args(args: &[String]) -> Result<Option<Invocation>, String> {
+    let mut args: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("-psn_")).collect();
+    let subcommand = args.first().is_some_and(|arg| *arg == SUBCOMMAND);
+    if subcommand {
+        args.remove(0);
+    }
+    let mut paths = Vec::new();
+    let mut overrides = json!({});
+    let mut options = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        options |= arg.starts_with("--");
+        match arg.as_str() {
+            "--streaming" => overrides["streaming"] = json!(true),
+            "--help" => return Err(usage()),
+            "--config" => {
+                let config = args.next().ok_or_else(usage)?;
+                let config: Value = serde_json::from_str(config).map_err(|err| format!("--config: {}", err))?;
+                for (key, value) in config.as_object().ok_or("--config must be a JSON object")? {
+                    overrides[key] = value.clone();
+                }
+            }
+            _ => paths.push(arg.clone()),
+        }
+    }
+    if !subcommand && !options && paths.len() != 2 {
+        return Ok(None);
+    }
+    let Ok([file_a_path, file_b_path]) = <[String; 2]>::try_from(paths) else {
+        return Err(usage());
+    };
+    if file_a_path == STDIN_PATH && file_b_path == STDIN_PATH {
+        return Err("Only one input can be read from stdin".to_string());
+    }
+    let base: CompareConfig = serde_json::from_value(json!({
+        "useExternalSort": false,
+        "ignoreOccurences": false,
+        "useSingleThread": false,
+        "ignoreLineNumber": false,
+    }))
+    .map_err(|err| err.to_string())?;
+    let compare_config = base.with_overrides(&overrides).map_err(|err| err.to_string())?;
+    Ok(Some(Invocation { file_a_path, file_b_path, compare_config }))
+}
+
+/// Writes `input` to a temp file the engines can read twice, as the external partitions
+/// and line numbers need. The file is deleted with the returned directory.
+fn spool(mut input: impl Read, compare_config: &CompareConfig) -> Result<(TempDir, String), IoError> {
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let dir = TempDir(compare_config.temp_root().join(format!("{}stdin_{}", TEMP_DIR_PREFIX, nanos)));
+    fs::create_dir_all(&dir.0)?;
+    let path = dir.0.join("stdin");
+    let mut writer = BufWriter::new(File::create(&path)?);
+    std::io::copy(&mut input, &mut writer)?;
+    writer.flush()?;
+    let path = path.to_string_lossy().into_owned();
+    Ok((dir, path))
+}
+
+/// Prints the lines found in only one file as `< line` (A) or `> line` (B) and the
+/// summary and frequency reports to stderr, like `diff`.
+#[derive(Clone)]
+struct ConsoleSink {
+    differs: Arc<AtomicBool>,
+    max_streamed_lines: usize,
+    max_emitted_differences: usize,
+}
+
+impl ConsoleSink {
+    fn new(compare_config: &CompareConfig) -> Self {
+        Self {
+            differs: Arc::default(),
+            max_streamed_lines: compare_config.max_streamed_lines,
+            max_emitted_differences: compare_config.max_emitted_differences,
+        }
+    }
+
+    /// Names the config keys whose cap left differences unprinted.
+    fn truncation_hint(&self, counts: [u64; 3]) -> String {
+        let mut keys = Vec::new();
+        if counts.iter().any(|&count| count as usize > self.max_streamed_lines) {
+            keys.push("maxStreamedLines");
+        }
+        if keys.is_empty() || counts.iter().sum::<u64>() as usize > self.max_emitted_differences {
+            keys.push("maxEmittedDifferences");
+        }
+        format!("Not every difference was printed, raise {} in --config to see all of them", keys.join(" and "))
+    }
+}
+
+impl ProgressSink for ConsoleSink {
+    fn on_event(&self, event: &str, payload: Value) {
+        match event {
+            "unique_line" => {
+                let marker = if payload["file"] == "A" { '<' } else { '>' };
+                let text = payload["text"].as_str().unwrap_or_default();
+                // A closed pipe (e.g. `| head`) just ends the output.
+                let _ = writeln!(std::io::stdout().lock(), "{} {}", marker, text);
+            }
+            "comparison_finished" => {
+                let summary = &payload["summary"];
+                let counts = ["unique_to_a", "unique_to_b", "modified"].map(|key| summary[key].as_u64().unwrap_or(0));
+                self.differs.store(counts.iter().any(|&count| count > 0), Ordering::Relaxed);
+                if let Some(in_both) = summary["in_both"].as_u64() {
+                    eprintln!("{} in both files", in_both);
+                } else {
+                    eprintln!("{} only in A, {} only in B, {} modified", counts[0], counts[1], counts[2]);
+                }
+                if summary["truncated"] == true {
+                    eprintln!("{}", self.truncation_hint(counts));
+                }
+                for degradation in summary["degradations"].as_array().into_iter().flatten() {
+                    eprintln!("Note: {}", degradation.as_str().unwrap_or_default());
+                }
+            }
+            "frequency_report" => {
+                let file = payload["file"].as_str().unwrap_or_default();
+                eprintln!("File {}: {} distinct lines", file, payload["distinct_lines"]);
+                for line in payload["top_lines"].as_array().into_iter().flatten() {
+                    eprintln!("{:>10}x {}", line["count"].as_u64().unwrap_or(0), line["text"].as_str().unwrap_or_default());
+                }
+                for bucket in payload["histogram"].as_array().into_iter().flatten() {
+                    let lines = bucket["lines"].as_u64().unwrap_or(0);
+                    eprintln!("{:>10} lines occur {}-{} times", lines, bucket["min_count"], bucket["max_count"]);
+                }
+            }
+            "comparison_error" => eprintln!("Error: {}", payload["message"].as_str().unwrap_or_default()),
+            _ => {}
+        }
+    }
+}
+
+/// Runs the comparison in the foreground. Returns whether the files differ.
+fn compare(invocation: Invocation, stdin: impl Read) -> Result<bool, String> {
+    let Invocation { mut file_a_path, mut file_b_path, mut compare_config } = invocation;
+    compare_config.seed_hashes();
+    let mut spooled = None;
+    for path in [&mut file_a_path, &mut file_b_path] {
+        if path == STDIN_PATH {
+            let (dir, copy) = spool(stdin, &compare_config).map_err(|err| format!("stdin: {}", err))?;
+            *path = copy;
+            spooled = Some(dir);
+            break;
+        }
+    }
+    let jobs = JobRegistry::default();
+    let job = crate::commands::create_job(&jobs, &file_a_path, &file_b_path, &compare_config)?;
+    let sink = ConsoleSink::new(&compare_config);
+    let _guard = job.start_run();
+    run_engine(sink.clone(), job, file_a_path, file_b_path, compare_config, None).map_err(|err| err.to_string())?;
+    drop(spooled);
+    Ok(sink.differs.load(Ordering::Relaxed))
+}
+
+/// Release builds on Windows run without a console so the window opens alone; started from
+/// a terminal, print to the terminal the app was started from.
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails harmlessly when there is no parent console or the process already has one.
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+/// Runs a comparison given on the command line without opening the window. Returns the exit
+/// code, 0 for identical files, 1 when they differ and 2 on errors, or `None` when the
+/// arguments don't ask for one.
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let parsed = parse_args(&args);
+    if !matches!(parsed, Ok(None)) {
+        attach_parent_console();
+    }
+    let invocation = match parsed {
+        Ok(invocation) => invocation?,
+        Err(message) => {
+            eprintln!("{}", message);
+            return Some(2);
+        }
+    };
+    // The process exits right after, before background deletions would finish.
+    remove_temp_dirs_immediately();
+    match compare(invocation, std::io::stdin().lock()) {
+        Ok(differs) => Some(i32::from(differs)),
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            Some(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stdin_input_is_spooled_and_compared() {
+        let args = |args: &[&str]| parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>());
+        assert!(args(&[]).unwrap().is_none());
+        assert!(args(&["-psn_0_12345"]).unwrap().is_none());
+        assert!(args(&["only-one.txt"]).unwrap().is_none());
+        assert!(args(&["compare", "only-one.txt"]).is_err());
+        assert!(args(&["only-one.txt", "--streaming"]).is_err());
+        assert!(args(&["-", "-"]).is_err());
+        assert!(args(&["compare", "a.txt", "b.txt", "-psn_0_1"]).unwrap().is_some());
+
+        let dir = std::env::temp_dir().join(format!("cli_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let baseline = dir.join("baseline.csv");
+        std::fs::write(&baseline, "a\nb\nc\n").unwrap();
+        let baseline = baseline.to_str().unwrap();
+        let streaming = args(&["-", baseline, "--streaming", "--config", "{\"partitionCount\":4}"]).unwrap().unwrap();
+        assert!(streaming.compare_config.streaming);
+        assert_eq!(streaming.compare_config.partitions(), 4);
+        assert!(compare(streaming, &b"c\nb\nx\n"[..]).unwrap());
+        let same = args(&[baseline, "-"]).unwrap().unwrap();
+        assert!(!compare(same, &b"b\na\nc\n"[..]).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_truncation_hint_names_the_cap_that_was_hit() {
+        let config = r#"{"maxStreamedLines": 2, "maxEmittedDifferences": 5}"#;
+        let invocation = parse_args(&["a.txt", "b.txt", "--config", config].map(String::from)).unwrap().unwrap();
+        let sink = ConsoleSink::new(&invocation.compare_config);
+        assert!(sink.truncation_hint([3, 0, 0]).contains("raise maxStreamedLines in"));
+        assert!(sink.truncation_hint([2, 2, 2]).contains("raise maxEmittedDifferences in"));
+        assert!(sink.truncation_hint([3, 2, 2]).contains("maxStreamedLines and maxEmittedDifferences"));
+    }
+}
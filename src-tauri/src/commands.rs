@@ -0,0 +1,255 @@
+use crate::calibration;
+use crate::config::CompareConfig;
+use crate::directory::comparison::run_directory_comparison;
+use crate::directory::pairing::DirectoryOptions;
+use crate::engine::run_engine;
+use crate::external::multi_file::check_writable;
+use crate::external::partition_cache::PartitionCache;
+use crate::external::{self, multi_way, three_way};
+use crate::fingerprint;
+use crate::identity;
+use crate::jobs::{Job, JobRegistry};
+use crate::normalize::NormalizerPipeline;
+use crate::profiles;
+use crate::results;
+use crate::results::hash_export;
+use crate::results::store::flush_partial_results;
+use crate::tauri_sink::TauriSink;
+use crate::transcode;
+use std::sync::Arc;
+use std::thread;
+use tauri::{AppHandle, Manager, State};
+
+/// Rejects a temp directory the engines couldn't write partitions to.
+fn check_temp_dir(compare_config: &CompareConfig) -> Result<(), String> {
+    let dir = compare_config.temp_root();
+    check_writable(&dir).map_err(|err| format!("Temp directory {} is not usable: {}", dir.display(), err))
+}
+
+/// Creates a job for comparing two files, recording their identities.
+pub fn create_job(
+    jobs: &JobRegistry,
+    file_a_path: &str,
+    file_b_path: &str,
+    compare_config: &CompareConfig,
+) -> Result<Arc<Job>, String> {
+    // Reject invalid rewrite patterns or scripts and a missing allowlist before the job starts.
+    NormalizerPipeline::new(compare_config, false).map_err(|err| err.to_string())?;
+    check_temp_dir(compare_config)?;
+    let labels = compare_config.encoding.iter().chain(compare_config.file_encodings.values());
+    for label in labels.filter(|label| !label.eq_ignore_ascii_case("auto")) {
+        transcode::encoding_for_label(label).map_err(|err| err.to_string())?;
+    }
+    if let Some(algo) = compare_config.hash_algo
+        && !algo.is_supported()
+    {
+        return Err(format!("{:?} needs AES instructions, which this build or CPU lacks; use xxh3 or blake3", algo));
+    }
+    if let Some(path) = &compare_config.accepted_differences_path {
+        std::fs::metadata(path).map_err(|err| format!("{}: {}", path, err))?;
+    }
+    let inputs = vec![
+        identity::file_identity(file_a_path).map_err(|err| format!("{}: {}", file_a_path, err))?,
+        identity::file_identity(file_b_path).map_err(|err| format!("{}: {}", file_b_path, err))?,
+    ];
+    let same_file = identity::is_same_file(file_a_path, file_b_path).map_err(|err| err.to_string())?;
+    let job = jobs.create(compare_config.clone());
+    {
+        let mut store = job.results.lock().unwrap();
+        store.inputs = inputs;
+        store.same_file = same_file;
+    }
+    Ok(job)
+}
+
+/// Runs the comparison of a job in the background, on cached partitions when given.
+fn spawn_comparison(
+    app: AppHandle,
+    job: Arc<Job>,
+    file_a_path: String,
+    file_b_path: String,
+    compare_config: CompareConfig,
+    cache: Option<PartitionCache>,
+) {
+    spawn_run(app, job, "Comparison", move |sink, job| {
+        run_engine(sink, job, file_a_path, file_b_path, compare_config, cache)
+    });
+}
+
+/// Runs a job on its own thread, keeping it marked as running for shutdown until it ends.
+fn spawn_run(
+    app: AppHandle,
+    job: Arc<Job>,
+    kind: &'static str,
+    run: impl FnOnce(TauriSink, Arc<Job>) -> Result<(), std::io::Error> + Send + 'static,
+) {
+    let guard = job.start_run();
+    thread::spawn(move || {
+        let sink = TauriSink(app.clone());
+        if let Err(e) = run(sink.clone(), job.clone()) {
+            eprintln!("{} failed: {}", kind, e);
+            // Keep whatever was found before the interruption available to the frontend.
+            flush_partial_results(&sink, &job, &e.to_string());
+        }
+        results::database::persist_job(&app, &job);
+        drop(guard);
+    });
+}
+
+/// Starts comparing two files. `profile` names stored settings applied on top of
+/// `compare_config`.
+#[tauri::command]
+pub async fn start_comparison(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    file_a_path: String,
+    file_b_path: String,
+    mut compare_config: CompareConfig,
+    profile: Option<String>,
+) -> Result<String, String> {
+    if let Some(name) = profile {
+        compare_config = profiles::find(&app, &name)?.apply(&compare_config)?;
+    }
+    calibration::apply_defaults(&app, &mut compare_config);
+    external::checkpoint::adopt_seed(&mut compare_config, &file_a_path, &file_b_path);
+    external::file_cache::adopt_seed(&mut compare_config, &file_a_path, &file_b_path);
+    compare_config.seed_hashes();
+    let job = create_job(&jobs, &file_a_path, &file_b_path, &compare_config)?;
+    let job_id = job.id.clone();
+    spawn_comparison(app, job, file_a_path, file_b_path, compare_config, None);
+    Ok(job_id)
+}
+
+/// Runs a comparison from the history again, with the same files and settings.
+/// Returns the id of the new job.
+#[tauri::command]
+pub async fn rerun_saved_job(app: AppHandle, jobs: State<'_, JobRegistry>, id: i64) -> Result<String, String> {
+    let (file_a_path, file_b_path, compare_config) = results::database::load_saved_run(&app, id)?;
+    start_comparison(app, jobs, file_a_path, file_b_path, compare_config, None).await
+}
+
+/// Runs a finished job again with some settings changed. When the change doesn't affect
+/// line hashes (e.g. ignoring occurrences or pairing keys) the partitions of the original
+/// run are reused. Returns the id of the new job.
+#[tauri::command]
+pub async fn rerun_with(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    config_overrides: serde_json::Value,
+) -> Result<String, String> {
+    let original = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    let compare_config = original.config.with_overrides(&config_overrides).map_err(|err| err.to_string())?;
+    let (file_a_path, file_b_path) = {
+        let store = original.results.lock().unwrap();
+        match store.inputs.as_slice() {
+            [a, b] => (a.path.clone(), b.path.clone()),
+            _ => return Err(format!("Job {} can't be re-run", job_id)),
+        }
+    };
+    let cache = original
+        .take_partitions()
+        .filter(|cache| !compare_config.presorted && cache.reusable_for(&compare_config));
+    let job = create_job(&jobs, &file_a_path, &file_b_path, &compare_config)?;
+    let job_id = job.id.clone();
+    spawn_comparison(app, job, file_a_path, file_b_path, compare_config, cache);
+    Ok(job_id)
+}
+
+/// Compares file A against fingerprints exported with `export_line_hashes` from a file
+/// that can't be shared itself. The fingerprints take the place of file B.
+#[tauri::command]
+pub async fn start_fingerprint_comparison(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    file_a_path: String,
+    fingerprint_path: String,
+    mut compare_config: CompareConfig,
+) -> Result<String, String> {
+    calibration::apply_defaults(&app, &mut compare_config);
+    let fingerprints = hash_export::read_line_hashes(std::path::Path::new(&fingerprint_path))
+        .map_err(|err| format!("{}: {}", fingerprint_path, err))?;
+    fingerprint::adopt_hashing(&mut compare_config, &fingerprints)?;
+    let job = create_job(&jobs, &file_a_path, &fingerprint_path, &compare_config)?;
+    let job_id = job.id.clone();
+    spawn_run(app, job, "Fingerprint comparison", move |sink, job| {
+        fingerprint::run_fingerprint_comparison(sink, job, file_a_path, fingerprints, compare_config)
+    });
+    Ok(job_id)
+}
+
+/// Compares two modified versions A and B against their common BASE.
+#[tauri::command]
+pub async fn start_three_way_comparison(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    base_path: String,
+    file_a_path: String,
+    file_b_path: String,
+    mut compare_config: CompareConfig,
+) -> Result<String, String> {
+    calibration::apply_defaults(&app, &mut compare_config);
+    compare_config.seed_hashes();
+    NormalizerPipeline::new(&compare_config, false).map_err(|err| err.to_string())?;
+    check_temp_dir(&compare_config)?;
+    let job = jobs.create(compare_config.clone());
+    let job_id = job.id.clone();
+    spawn_run(app, job, "Three-way comparison", move |sink, job| {
+        three_way::run_three_way(sink, job, base_path, file_a_path, file_b_path, compare_config)
+    });
+    Ok(job_id)
+}
+
+/// Compares any number of files, reporting per differing line how often each file contains it.
+#[tauri::command]
+pub async fn start_multi_way_comparison(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    file_paths: Vec<String>,
+    mut compare_config: CompareConfig,
+) -> Result<String, String> {
+    calibration::apply_defaults(&app, &mut compare_config);
+    compare_config.seed_hashes();
+    if file_paths.len() < 2 {
+        return Err("Select at least two files".to_string());
+    }
+    NormalizerPipeline::new(&compare_config, false).map_err(|err| err.to_string())?;
+    check_temp_dir(&compare_config)?;
+    let job = jobs.create(compare_config.clone());
+    let job_id = job.id.clone();
+    spawn_run(app, job, "Multi-way comparison", move |sink, job| {
+        multi_way::run_multi_way(sink, job, file_paths, compare_config)
+    });
+    Ok(job_id)
+}
+
+/// Compares two directories file by file, pairing files by relative path.
+#[tauri::command]
+pub async fn start_directory_comparison(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    dir_a_path: String,
+    dir_b_path: String,
+    mut compare_config: CompareConfig,
+    options: Option<DirectoryOptions>,
+) -> Result<String, String> {
+    calibration::apply_defaults(&app, &mut compare_config);
+    compare_config.seed_hashes();
+    NormalizerPipeline::new(&compare_config, false).map_err(|err| err.to_string())?;
+    check_temp_dir(&compare_config)?;
+    let job = jobs.create(compare_config.clone());
+    let job_id = job.id.clone();
+    let options = options.unwrap_or_default();
+    spawn_run(app, job, "Directory comparison", move |sink, job| {
+        let registry = sink.0.state::<JobRegistry>();
+        run_directory_comparison(sink.clone(), &registry, job, dir_a_path, dir_b_path, compare_config, options)
+    });
+    Ok(job_id)
+}
+
+#[tauri::command]
+pub fn cancel_comparison(jobs: State<'_, JobRegistry>, job_id: String) -> Result<(), String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    job.cancel();
+    Ok(())
+}
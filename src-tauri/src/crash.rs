@@ -0,0 +1,104 @@
+use crate::config::CompareConfig;
+use crate::jobs::JobRegistry;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const CRASH_DIR: &str = "crash_reports";
+
+/// A job that was running when the app panicked. Only its config is kept, no file contents.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashedJob {
+    pub id: String,
+    pub config: CompareConfig,
+}
+
+/// What is known about a panic, written to the app data dir by the panic hook.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub timestamp_ms: u64,
+    pub app_version: String,
+    pub os: String,
+    pub thread: Option<String>,
+    pub message: String,
+    /// File and line of the panic.
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub running_jobs: Vec<CrashedJob>,
+}
+
+fn crash_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(CRASH_DIR))
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+fn write_report(dir: &Path, report: &CrashReport) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash-{}-{}.json", report.timestamp_ms, std::process::id()));
+    fs::write(&path, serde_json::to_vec_pretty(report)?)?;
+    Ok(path)
+}
+
+/// Installs a panic hook that writes a crash report before the default hook runs.
+pub fn install_panic_hook(app: AppHandle) {
+    let Some(dir) = crash_dir(&app) else {
+        eprintln!("No app data dir; crash reports are disabled");
+        return;
+    };
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let running_jobs = app
+            .try_state::<JobRegistry>()
+            .map(|jobs| jobs.running_configs())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, config)| CrashedJob { id, config })
+            .collect();
+        let report = CrashReport {
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            thread: std::thread::current().name().map(str::to_string),
+            message: panic_message(info),
+            location: info.location().map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            backtrace: Backtrace::force_capture().to_string(),
+            running_jobs,
+        };
+        match write_report(&dir, &report) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write crash report: {}", e),
+        }
+        default_hook(info);
+    }));
+}
+
+/// Crash reports of earlier panics, newest first.
+#[tauri::command]
+pub fn get_crash_reports(app: AppHandle) -> Result<Vec<CrashReport>, String> {
+    let Some(dir) = crash_dir(&app) else {
+        return Ok(Vec::new());
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut reports: Vec<CrashReport> = entries
+        .filter_map(|entry| fs::read(entry.ok()?.path()).ok())
+        .filter_map(|bytes| serde_json::from_slice(&bytes).ok())
+        .collect();
+    reports.sort_by_key(|report| std::cmp::Reverse(report.timestamp_ms));
+    Ok(reports)
+}
@@ -0,0 +1,122 @@
+use crate::config::FieldFormat;
+use crate::decompress::{decoder, detect_compression};
+use crate::delimiter::detect_delimiter;
+use crate::line_endings::{LineEndingCounts, LineEndingStyle};
+use crate::payloads::FileInspection;
+use crate::transcode::detect_encoding;
+use encoding_rs::Encoding;
+use std::fs::File;
+use std::io::{Error as IoError, Read, Seek, SeekFrom};
+
+/// Bytes read from each sampled position of a file.
+const SAMPLE_SIZE: usize = 64 * 1024;
+/// Positions sampled for the line count: start, middle and end.
+const SAMPLES: u64 = 3;
+
+fn read_at(file: &mut File, offset: u64, len: usize) -> Result<Vec<u8>, IoError> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut sample = Vec::with_capacity(len);
+    file.take(len as u64).read_to_end(&mut sample)?;
+    Ok(sample)
+}
+
+/// NUL bytes or many control characters, unless a byte order mark says it's UTF-16 text.
+fn looks_binary(sample: &[u8], bom: bool) -> bool {
+    if bom {
+        return false;
+    }
+    let control = sample.iter().filter(|&&b| b < 0x20 && !matches!(b, b'\n' | b'\r' | b'\t' | 0x0c)).count();
+    sample.contains(&0) || control * 10 > sample.len()
+}
+
+/// Lines of a file of `size` bytes starting with `head`, and whether they were counted
+/// rather than extrapolated from the density of terminators in the samples.
+fn estimate_lines(file: &mut File, size: u64, head: &[u8], terminator: u8) -> Result<(u64, bool), IoError> {
+    let count = |sample: &[u8]| memchr::memchr_iter(terminator, sample).count() as u64;
+    if size <= SAMPLE_SIZE as u64 * SAMPLES {
+        let data = read_at(file, 0, size as usize)?;
+        let unterminated = !data.is_empty() && !data.ends_with(&[terminator]);
+        return Ok((count(&data) + u64::from(unterminated), true));
+    }
+    let middle = read_at(file, size / 2, SAMPLE_SIZE)?;
+    let tail = read_at(file, size - SAMPLE_SIZE as u64, SAMPLE_SIZE)?;
+    let samples = [head, &middle, &tail];
+    let bytes: usize = samples.iter().map(|sample| sample.len()).sum();
+    let lines: u64 = samples.iter().map(|sample| count(sample)).sum();
+    Ok(((lines as f64 * size as f64 / bytes as f64).round() as u64, false))
+}
+
+fn inspect(path: &str) -> Result<FileInspection, IoError> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let compression = detect_compression(path)?;
+    let head = match compression {
+        Some(compression) => {
+            let mut head = Vec::with_capacity(SAMPLE_SIZE);
+            decoder(compression, File::open(path)?)?.take(SAMPLE_SIZE as u64).read_to_end(&mut head)?;
+            head
+        }
+        None => read_at(&mut file, 0, SAMPLE_SIZE)?,
+    };
+
+    let (encoding, bom) = match Encoding::for_bom(&head) {
+        Some((encoding, _)) => (encoding, true),
+        None => (detect_encoding(&head), false),
+    };
+    let line_ending = LineEndingCounts::count(&head).dominant();
+    let terminator = if line_ending == LineEndingStyle::Cr { b'\r' } else { b'\n' };
+    let binary = looks_binary(&head, bom);
+    let (estimated_lines, lines_exact) = match compression {
+        Some(_) => (None, false),
+        None => {
+            let (lines, exact) = estimate_lines(&mut file, size, &head, terminator)?;
+            (Some(lines), exact)
+        }
+    };
+    let csv = FieldFormat { delimiter: ",".to_string(), quote_char: Some('"'), has_header: false, fixed_width: None };
+    Ok(FileInspection {
+        size,
+        compression: compression.map(|compression| format!("{:?}", compression).to_lowercase()),
+        estimated_lines,
+        lines_exact,
+        encoding: encoding.name().to_string(),
+        bom,
+        line_ending,
+        delimiter: if binary { None } else { detect_delimiter(&head, &csv, terminator) },
+        binary,
+    })
+}
+
+/// Samples a file to describe its format, so mismatched inputs can be pointed out before a
+/// long comparison starts.
+#[tauri::command]
+pub async fn inspect_file(path: String) -> Result<FileInspection, String> {
+    inspect(&path).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_detects_format_and_extrapolates_lines() {
+        let dir = std::env::temp_dir().join(format!("inspect_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let small = dir.join("small.csv");
+        std::fs::write(&small, "\u{feff}id;name\r\n1;a\r\n2;b").unwrap();
+        let found = inspect(small.to_str().unwrap()).unwrap();
+        assert_eq!((found.encoding.as_str(), found.bom, found.line_ending), ("UTF-8", true, LineEndingStyle::CrLf));
+        assert_eq!((found.estimated_lines, found.lines_exact, found.delimiter.as_deref()), (Some(3), true, Some(";")));
+        assert!(!found.binary);
+
+        let large = dir.join("large.txt");
+        std::fs::write(&large, "0123456789abcde\n".repeat(100_000)).unwrap();
+        let found = inspect(large.to_str().unwrap()).unwrap();
+        assert_eq!((found.estimated_lines, found.lines_exact, found.delimiter), (Some(100_000), false, None));
+
+        let binary = dir.join("data.bin");
+        std::fs::write(&binary, [0u8, 1, 2, 3, 0xff, b'\n']).unwrap();
+        assert!(inspect(binary.to_str().unwrap()).unwrap().binary);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,79 +1,65 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::thread;
-use tauri::{AppHandle};
-use crate::external::comparison;
-use crate::internal::comparison_in_memory;
-use serde_json::json;
+use tauri::{Manager, RunEvent};
+use large_file_compare_core::{
+    config, decompress, delimiter, directory, engine, explain, external, fingerprint, identity, jobs,
+    line_endings, normalize, payloads, transcode, utils,
+};
 
-mod external {
-    pub mod comparison;
-    pub mod file_processing;
+/// The engine's result modules plus the app's commands and saved-run database.
+mod results {
+    pub use large_file_compare_core::results::*;
+    pub mod commands;
+    pub mod database;
 }
+mod calibration;
+mod cli;
+mod commands;
+mod crash;
+mod inspect;
+mod prefs;
+mod profiles;
+mod queue;
+mod recent;
+mod schedule;
+mod tauri_sink;
+#[cfg(test)]
+mod scenarios;
+#[cfg(test)]
+mod tests;
+mod updates;
 
-mod internal {
-    pub mod comparison_in_memory;
-    pub mod file_processing_in_memory;
-}
-mod payloads;
-
-#[derive(Clone)]
-struct CompareConfig {
-    use_external_sort: bool,
-    ignore_occurences: bool,
-    use_single_thread: bool,
-    ignore_line_number: bool
-}
+use crate::jobs::JobRegistry;
+use crate::queue::JobQueue;
+use std::time::Duration;
 
-#[tauri::command]
-async fn start_comparison(
-    app: AppHandle,
-    file_a_path: String,
-    file_b_path: String,
-    use_external_sort: bool,
-    ignore_occurences: bool,
-    use_single_thread: bool,
-    ignore_line_number: bool
-) -> Result<(), String> {
-    let compare_config = CompareConfig {use_external_sort, ignore_occurences, use_single_thread, ignore_line_number};
-    thread::spawn(move || {
-        if compare_config.use_external_sort {
-            if let Err(e) = comparison::run_comparison(app, file_a_path, file_b_path, compare_config) {
-                // Handle errors, maybe emit an event to the frontend
-                eprintln!("Comparison failed: {}", e);
-            }
-        } else {
-            if let Err(e) = comparison_in_memory::run_comparison(app, file_a_path, file_b_path, compare_config) {
-                // Handle errors, maybe emit an event to the frontend
-                eprintln!("Comparison failed: {}", e);
-            }
-        }
-
-    });
-    Ok(())
-}
-
-use std::fs;
-use tauri_plugin_store::StoreExt;
-
-#[tauri::command]
-fn save_file(path: String, content: String) -> Result<(), String> {
-    fs::write(path, content).map_err(|err| err.to_string())
-}
+/// How long the app waits on exit for cancelled jobs to flush their results and stop.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
 fn main() {
+    if let Some(code) = cli::run_from_args() {
+        std::process::exit(code);
+    }
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_store::Builder::new().build())
-        .invoke_handler(tauri::generate_handler![start_comparison, save_file])
+        .manage(JobRegistry::default())
+        .manage(JobQueue::default())
+        .invoke_handler(tauri::generate_handler![commands::start_comparison, commands::start_fingerprint_comparison, commands::start_three_way_comparison, commands::start_multi_way_comparison, commands::start_directory_comparison, commands::rerun_with, commands::rerun_saved_job, commands::cancel_comparison, results::commands::export_results, results::commands::export_missing_keys, results::commands::export_line_hashes, results::commands::export_unified_diff, results::commands::explain_line, results::commands::get_results_window, results::commands::get_results_page, results::commands::get_results_count, results::commands::get_result_sample, results::commands::filter_results, results::commands::summarize_in_words, queue::enqueue_comparison, queue::cancel_queued_job, queue::skip_queued_job, queue::move_queued_job, queue::get_queue_state, prefs::get_prefs, prefs::set_prefs, recent::get_recent_pairs, recent::record_recent_pair, recent::pin_recent_pair, recent::remove_recent_pair, schedule::get_schedules, schedule::save_schedule, schedule::remove_schedule, profiles::get_profiles, profiles::save_profile, profiles::delete_profile, profiles::apply_profile, calibration::get_calibration, calibration::run_calibration, updates::check_for_updates, crash::get_crash_reports, results::database::list_saved_jobs, results::database::get_saved_lines, results::database::get_saved_timings, results::database::delete_saved_job, inspect::inspect_file, results::commands::save_file])
         .setup(|app| {
-            let store = app.store("store.json")?;
-            store.set("some-key", json!({"value": 5}));
-            let value = store.get("some-key").expect("Failed to get value from store");
-            println!("{}", value); // {"value":5}
+            crash::install_panic_hook(app.handle().clone());
+            prefs::apply_thread_count(&prefs::load(app.handle()));
+            calibration::calibrate_on_first_run(app.handle().clone());
+            schedule::start_scheduler(app.handle().clone());
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Stop the engine threads cleanly instead of killing them mid-write on exit.
+            if let RunEvent::Exit = event {
+                app.state::<JobRegistry>().shutdown(SHUTDOWN_TIMEOUT);
+            }
+        });
 }
\ No newline at end of file
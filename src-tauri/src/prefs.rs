@@ -0,0 +1,150 @@
+use crate::external::multi_file::check_writable;
+use crate::results::export_writer::ExportOptions;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+pub const STORE_FILE: &str = "store.json";
+const PREFS_KEY: &str = "preferences";
+/// Bumped whenever a field is renamed or changes meaning; `migrate` upgrades older documents.
+pub const PREFS_VERSION: u32 = 1;
+
+/// Engine preselected for new comparisons.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultEngine {
+    /// In-memory for files that fit in RAM, external sort otherwise.
+    #[default]
+    Auto,
+    InMemory,
+    External,
+    /// External partitions filled without memory-mapping the inputs.
+    Streaming,
+    Presorted,
+}
+
+/// User preferences persisted in the app store. Missing fields take their defaults,
+/// so documents written by older versions still load.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Preferences {
+    pub version: u32,
+    pub default_engine: DefaultEngine,
+    /// Worker threads for the comparison engines; `None` uses every core. Applied at startup.
+    pub thread_count: Option<usize>,
+    /// Directory for partitions and local copies; `None` uses the system temp dir.
+    pub temp_dir: Option<String>,
+    /// Result lines the frontend receives per update.
+    pub emission_batch_size: usize,
+    /// Defaults for the export dialogs.
+    pub export: ExportOptions,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            version: PREFS_VERSION,
+            default_engine: DefaultEngine::default(),
+            thread_count: None,
+            temp_dir: None,
+            emission_batch_size: 500,
+            export: ExportOptions::default(),
+        }
+    }
+}
+
+impl Preferences {
+    fn validate(&self) -> Result<(), String> {
+        if self.thread_count == Some(0) {
+            return Err("Thread count must be at least 1".to_string());
+        }
+        if self.emission_batch_size == 0 {
+            return Err("Emission batch size must be at least 1".to_string());
+        }
+        if let Some(dir) = &self.temp_dir {
+            check_writable(Path::new(dir)).map_err(|err| format!("Temp directory {} is not usable: {}", dir, err))?;
+        }
+        crate::transcode::encoding_for_label(&self.export.encoding).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Upgrades a stored preferences document to the current schema. Renamed fields are moved
+/// here, one step per version; documents from a newer version keep the fields this one knows.
+fn migrate(value: Value) -> Preferences {
+    let mut prefs: Preferences = serde_json::from_value(value).unwrap_or_else(|e| {
+        eprintln!("Failed to read stored preferences, using defaults: {}", e);
+        Preferences::default()
+    });
+    prefs.version = PREFS_VERSION;
+    prefs
+}
+
+/// Reads the stored preferences, falling back to the defaults.
+pub fn load(app: &AppHandle) -> Preferences {
+    match app.store(STORE_FILE) {
+        Ok(store) => {
+            // Left behind by the store plugin's sample setup code.
+            store.delete("some-key");
+            store.get(PREFS_KEY).map(migrate).unwrap_or_default()
+        }
+        Err(e) => {
+            eprintln!("Failed to open preferences store: {}", e);
+            Preferences::default()
+        }
+    }
+}
+
+/// Sizes the global rayon pool used by the engines. Only takes effect once per process.
+pub fn apply_thread_count(prefs: &Preferences) {
+    if let Some(threads) = prefs.thread_count
+        && let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()
+    {
+        eprintln!("Failed to set thread count: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn get_prefs(app: AppHandle) -> Preferences {
+    load(&app)
+}
+
+/// Validates and stores `prefs`. A changed thread count applies after a restart.
+#[tauri::command]
+pub fn set_prefs(app: AppHandle, mut prefs: Preferences) -> Result<Preferences, String> {
+    prefs.validate()?;
+    prefs.version = PREFS_VERSION;
+    let store = app.store(STORE_FILE).map_err(|err| err.to_string())?;
+    store.set(PREFS_KEY, serde_json::to_value(&prefs).map_err(|err| err.to_string())?);
+    store.save().map_err(|err| err.to_string())?;
+    Ok(prefs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_missing_fields_take_defaults() {
+        let prefs = migrate(json!({ "defaultEngine": "external", "threadCount": 4 }));
+        assert_eq!(prefs.version, PREFS_VERSION);
+        assert_eq!(prefs.thread_count, Some(4));
+        assert_eq!(prefs.default_engine, DefaultEngine::External);
+        assert_eq!(prefs.emission_batch_size, 500);
+        assert_eq!(prefs.export.encoding, "utf-8");
+
+        assert_eq!(migrate(json!("garbage")).default_engine, DefaultEngine::Auto);
+    }
+
+    #[test]
+    fn test_temp_dir_must_be_a_writable_directory() {
+        let temp_dir = std::env::temp_dir().to_string_lossy().to_string();
+        assert!(Preferences { temp_dir: Some(temp_dir), ..Default::default() }.validate().is_ok());
+        let missing = std::env::temp_dir().join("no_such_dir_for_prefs_test");
+        let prefs = Preferences { temp_dir: Some(missing.to_string_lossy().to_string()), ..Default::default() };
+        assert!(prefs.validate().unwrap_err().contains("not a directory"));
+    }
+}
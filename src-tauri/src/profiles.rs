@@ -0,0 +1,125 @@
+use crate::config::CompareConfig;
+use crate::normalize::NormalizerPipeline;
+use crate::prefs::STORE_FILE;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const PROFILES_KEY: &str = "profiles";
+
+/// Named comparison settings persisted in the app store, e.g. "CSV export, ignore audit
+/// columns". `settings` holds the `CompareConfig` fields the profile sets, such as
+/// `fieldFormat`, `ignoreColumns`, `trimWhitespace`, `rewriteRules`, `useExternalSort`
+/// or `partitionCount`; everything else keeps the value of the comparison it's applied to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub name: String,
+    pub settings: Value,
+}
+
+impl Profile {
+    /// `base` with the profile's settings applied.
+    pub fn apply(&self, base: &CompareConfig) -> Result<CompareConfig, String> {
+        base.with_overrides(&self.settings)
+            .map_err(|err| format!("Profile {} doesn't fit the comparison settings: {}", self.name, err))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Profile name must not be empty".to_string());
+        }
+        if !self.settings.is_object() {
+            return Err("Profile settings must be an object of comparison settings".to_string());
+        }
+        let base: CompareConfig = serde_json::from_value(json!({
+            "useExternalSort": false,
+            "ignoreOccurences": false,
+            "useSingleThread": false,
+            "ignoreLineNumber": false,
+        }))
+        .map_err(|err| err.to_string())?;
+        let config = self.apply(&base)?;
+        NormalizerPipeline::new(&config, false).map_err(|err| err.to_string())?;
+        Ok(())
+    }
+}
+
+fn load(app: &AppHandle) -> Result<Vec<Profile>, String> {
+    let store = app.store(STORE_FILE).map_err(|err| err.to_string())?;
+    Ok(store
+        .get(PROFILES_KEY)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+fn save(app: &AppHandle, profiles: &[Profile]) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|err| err.to_string())?;
+    store.set(PROFILES_KEY, serde_json::to_value(profiles).map_err(|err| err.to_string())?);
+    store.save().map_err(|err| err.to_string())
+}
+
+/// The stored profile called `name`.
+pub fn find(app: &AppHandle, name: &str) -> Result<Profile, String> {
+    load(app)?
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .ok_or_else(|| format!("Unknown profile: {}", name))
+}
+
+#[tauri::command]
+pub fn get_profiles(app: AppHandle) -> Result<Vec<Profile>, String> {
+    load(&app)
+}
+
+/// Adds a profile, or replaces the one with the same name.
+#[tauri::command]
+pub fn save_profile(app: AppHandle, profile: Profile) -> Result<(), String> {
+    profile.validate()?;
+    let mut profiles = load(&app)?;
+    match profiles.iter_mut().find(|existing| existing.name == profile.name) {
+        Some(existing) => *existing = profile,
+        None => profiles.push(profile),
+    }
+    save(&app, &profiles)
+}
+
+#[tauri::command]
+pub fn delete_profile(app: AppHandle, name: String) -> Result<(), String> {
+    let mut profiles = load(&app)?;
+    profiles.retain(|profile| profile.name != name);
+    save(&app, &profiles)
+}
+
+/// `compare_config` with the settings of profile `name`, for showing them before a run.
+#[tauri::command]
+pub fn apply_profile(app: AppHandle, name: String, compare_config: CompareConfig) -> Result<CompareConfig, String> {
+    find(&app, &name)?.apply(&compare_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_overrides_only_its_settings() {
+        let profile = Profile {
+            name: "csv".to_string(),
+            settings: json!({ "fieldFormat": { "delimiter": ";" }, "ignoreColumns": [2], "partitionCount": 64 }),
+        };
+        assert!(profile.validate().is_ok());
+        let base: CompareConfig = serde_json::from_value(json!({
+            "useExternalSort": true, "ignoreOccurences": true, "useSingleThread": false, "ignoreLineNumber": false,
+        }))
+        .unwrap();
+        let config = profile.apply(&base).unwrap();
+        assert_eq!(config.field_format.as_ref().unwrap().delimiter, ";");
+        assert_eq!((config.ignore_columns.as_slice(), config.partitions()), (&[2][..], 64));
+        assert!(config.use_external_sort && config.ignore_occurences);
+
+        let broken = Profile { settings: json!({ "rewriteRules": [{ "pattern": "(" }] }), ..profile.clone() };
+        assert!(broken.validate().is_err());
+        assert!(Profile { settings: json!([1]), ..profile }.validate().is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::config::CompareConfig;
+use crate::engine::run_engine;
+use crate::identity::{canonical_path, display_name};
+use crate::jobs::{Job, JobRegistry};
+use crate::payloads::{DirectoryEntry, DirectoryEntryStatus, QueueItem, QueueItemStatus};
+use crate::results::batch::summarize_batch;
+use crate::results::database::persist_job;
+use crate::results::store::flush_partial_results;
+use crate::tauri_sink::TauriSink;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+struct QueuedJob {
+    job: Arc<Job>,
+    item: QueueItem,
+}
+
+/// Comparisons run one after the other. Pending jobs can be cancelled, skipped or moved
+/// while the queue runs. Managed as Tauri state.
+#[derive(Default)]
+pub struct JobQueue {
+    state: Mutex<QueueState>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    jobs: Vec<QueuedJob>,
+    running: bool,
+}
+
+impl QueueState {
+    fn items(&self) -> Vec<QueueItem> {
+        self.jobs.iter().map(|queued| queued.item.clone()).collect()
+    }
+
+    fn find(&mut self, job_id: &str) -> Result<&mut QueuedJob, String> {
+        self.jobs
+            .iter_mut()
+            .find(|queued| queued.item.job_id == job_id)
+            .ok_or_else(|| format!("Job {} isn't queued", job_id))
+    }
+
+    /// Marks the first pending job as running and returns it with its item.
+    fn start_next(&mut self) -> Option<(Arc<Job>, QueueItem)> {
+        let queued = self.jobs.iter_mut().find(|queued| queued.item.status == QueueItemStatus::Pending)?;
+        queued.item.status = QueueItemStatus::Running;
+        Some((queued.job.clone(), queued.item.clone()))
+    }
+
+    /// Moves a pending job to `position` among all jobs of the queue.
+    fn move_pending(&mut self, job_id: &str, position: usize) -> Result<(), String> {
+        let index = self
+            .jobs
+            .iter()
+            .position(|queued| queued.item.job_id == job_id)
+            .ok_or_else(|| format!("Job {} isn't queued", job_id))?;
+        if self.jobs[index].item.status != QueueItemStatus::Pending {
+            return Err(format!("Job {} is no longer pending", job_id));
+        }
+        let queued = self.jobs.remove(index);
+        self.jobs.insert(position.min(self.jobs.len()), queued);
+        Ok(())
+    }
+}
+
+fn entry_name(item: &QueueItem) -> String {
+    format!("{} ↔ {}", item.file_a_name, item.file_b_name)
+}
+
+/// The finished jobs of a queue run as batch entries; cancelled and skipped jobs are left out.
+fn batch_entries(jobs: &[QueuedJob]) -> Vec<DirectoryEntry> {
+    jobs.iter()
+        .filter_map(|queued| {
+            let status = match queued.item.status {
+                QueueItemStatus::Completed => DirectoryEntryStatus::Compared,
+                QueueItemStatus::Failed => DirectoryEntryStatus::Failed,
+                _ => return None,
+            };
+            Some(DirectoryEntry {
+                relative_path: entry_name(&queued.item),
+                status,
+                job_id: Some(queued.item.job_id.clone()),
+                summary: queued.job.results.lock().unwrap().summary.clone(),
+                error: queued.item.error.clone(),
+            })
+        })
+        .collect()
+}
+
+impl JobQueue {
+    fn emit_state(&self, app: &AppHandle) {
+        let items = self.state.lock().unwrap().items();
+        if let Err(e) = app.emit("queue_state", items) {
+            eprintln!("Failed to emit queue_state event: {}", e);
+        }
+    }
+
+    fn set_status(&self, job_id: &str, status: QueueItemStatus, error: Option<String>) {
+        if let Ok(queued) = self.state.lock().unwrap().find(job_id) {
+            queued.item.status = status;
+            queued.item.error = error;
+        }
+    }
+
+    /// Runs pending jobs until none are left, then emits the roll-up of the queue run.
+    fn run(&self, app: &AppHandle) {
+        loop {
+            let next = self.state.lock().unwrap().start_next();
+            let Some((job, item)) = next else { break };
+            self.emit_state(app);
+            let _run = job.start_run();
+            let sink = TauriSink(app.clone());
+            let result = run_engine(sink.clone(), job.clone(), item.file_a_path, item.file_b_path, job.config.clone(), None);
+            match result {
+                Ok(()) => self.set_status(&job.id, QueueItemStatus::Completed, None),
+                Err(e) => {
+                    flush_partial_results(&sink, &job, &e.to_string());
+                    if job.is_cancelled() {
+                        self.set_status(&job.id, QueueItemStatus::Cancelled, None);
+                    } else {
+                        self.set_status(&job.id, QueueItemStatus::Failed, Some(e.to_string()));
+                    }
+                }
+            }
+            persist_job(app, &job);
+            self.emit_state(app);
+        }
+
+        let summary = {
+            let mut state = self.state.lock().unwrap();
+            state.running = false;
+            summarize_batch(&batch_entries(&state.jobs))
+        };
+        if let Err(e) = app.emit("queue_finished", summary) {
+            eprintln!("Failed to emit queue_finished event: {}", e);
+        }
+    }
+}
+
+/// Adds a two-file comparison to the queue, starting the queue when it's idle.
+#[tauri::command]
+pub async fn enqueue_comparison(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    queue: State<'_, JobQueue>,
+    file_a_path: String,
+    file_b_path: String,
+    mut compare_config: CompareConfig,
+) -> Result<String, String> {
+    crate::calibration::apply_defaults(&app, &mut compare_config);
+    crate::external::checkpoint::adopt_seed(&mut compare_config, &file_a_path, &file_b_path);
+    crate::external::file_cache::adopt_seed(&mut compare_config, &file_a_path, &file_b_path);
+    compare_config.seed_hashes();
+    let job = crate::commands::create_job(&jobs, &file_a_path, &file_b_path, &compare_config)?;
+    let job_id = job.id.clone();
+    let start = {
+        let mut state = queue.state.lock().unwrap();
+        if !state.running {
+            // A new queue run: forget the jobs of the previous one.
+            state.jobs.clear();
+        }
+        state.jobs.push(QueuedJob {
+            job,
+            item: QueueItem {
+                job_id: job_id.clone(),
+                file_a_name: display_name(&canonical_path(&file_a_path)),
+                file_b_name: display_name(&canonical_path(&file_b_path)),
+                file_a_path,
+                file_b_path,
+                status: QueueItemStatus::Pending,
+                error: None,
+            },
+        });
+        !std::mem::replace(&mut state.running, true)
+    };
+    queue.emit_state(&app);
+    if start {
+        thread::spawn(move || app.state::<JobQueue>().run(&app));
+    }
+    Ok(job_id)
+}
+
+/// Cancels a queued job: a pending job won't run, a running one is interrupted.
+#[tauri::command]
+pub fn cancel_queued_job(app: AppHandle, queue: State<'_, JobQueue>, job_id: String) -> Result<(), String> {
+    {
+        let mut state = queue.state.lock().unwrap();
+        let queued = state.find(&job_id)?;
+        queued.job.cancel();
+        if queued.item.status == QueueItemStatus::Pending {
+            queued.item.status = QueueItemStatus::Cancelled;
+        }
+    }
+    queue.emit_state(&app);
+    Ok(())
+}
+
+/// Takes a pending job out of the current queue run without cancelling it.
+#[tauri::command]
+pub fn skip_queued_job(app: AppHandle, queue: State<'_, JobQueue>, job_id: String) -> Result<(), String> {
+    {
+        let mut state = queue.state.lock().unwrap();
+        let queued = state.find(&job_id)?;
+        if queued.item.status != QueueItemStatus::Pending {
+            return Err(format!("Job {} is no longer pending", job_id));
+        }
+        queued.item.status = QueueItemStatus::Skipped;
+    }
+    queue.emit_state(&app);
+    Ok(())
+}
+
+/// Moves a pending job to another position in the queue list; position 0 runs it next.
+#[tauri::command]
+pub fn move_queued_job(app: AppHandle, queue: State<'_, JobQueue>, job_id: String, position: usize) -> Result<(), String> {
+    queue.state.lock().unwrap().move_pending(&job_id, position)?;
+    queue.emit_state(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_queue_state(queue: State<'_, JobQueue>) -> Vec<QueueItem> {
+    queue.state.lock().unwrap().items()
+}
@@ -0,0 +1,126 @@
+use crate::prefs::STORE_FILE;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+const RECENT_KEY: &str = "recentPairs";
+/// Unpinned pairs kept; pinned pairs are never dropped.
+const MAX_RECENT: usize = 20;
+
+/// A pair of files compared before, as persisted in the app store.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentPair {
+    pub file_a: String,
+    pub file_b: String,
+    pub last_compared_ms: u64,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// A recent pair with whether its files still exist, so stale entries can be greyed out.
+#[derive(Clone, serde::Serialize)]
+pub struct RecentEntry {
+    #[serde(flatten)]
+    pub pair: RecentPair,
+    pub file_a_exists: bool,
+    pub file_b_exists: bool,
+}
+
+fn is_pair(pair: &RecentPair, file_a: &str, file_b: &str) -> bool {
+    pair.file_a == file_a && pair.file_b == file_b
+}
+
+/// Moves the pair to the front, keeping its pin, and drops the oldest unpinned pairs
+/// beyond `MAX_RECENT`.
+fn record(pairs: &mut Vec<RecentPair>, file_a: &str, file_b: &str, now_ms: u64) {
+    let pinned = pairs.iter().any(|pair| is_pair(pair, file_a, file_b) && pair.pinned);
+    pairs.retain(|pair| !is_pair(pair, file_a, file_b));
+    pairs.insert(0, RecentPair { file_a: file_a.to_string(), file_b: file_b.to_string(), last_compared_ms: now_ms, pinned });
+    let mut unpinned = 0;
+    pairs.retain(|pair| {
+        unpinned += usize::from(!pair.pinned);
+        pair.pinned || unpinned <= MAX_RECENT
+    });
+}
+
+fn load(app: &AppHandle) -> Result<Vec<RecentPair>, String> {
+    let store = app.store(STORE_FILE).map_err(|err| err.to_string())?;
+    Ok(store
+        .get(RECENT_KEY)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+fn save(app: &AppHandle, pairs: &[RecentPair]) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|err| err.to_string())?;
+    store.set(RECENT_KEY, serde_json::to_value(pairs).map_err(|err| err.to_string())?);
+    store.save().map_err(|err| err.to_string())
+}
+
+/// Returns pinned pairs first, then the others, most recently compared first.
+#[tauri::command]
+pub fn get_recent_pairs(app: AppHandle) -> Result<Vec<RecentEntry>, String> {
+    let mut pairs = load(&app)?;
+    pairs.sort_by_key(|pair| (!pair.pinned, std::cmp::Reverse(pair.last_compared_ms)));
+    Ok(pairs
+        .into_iter()
+        .map(|pair| RecentEntry {
+            file_a_exists: Path::new(&pair.file_a).exists(),
+            file_b_exists: Path::new(&pair.file_b).exists(),
+            pair,
+        })
+        .collect())
+}
+
+/// Records that `file_a` and `file_b` were just compared.
+#[tauri::command]
+pub fn record_recent_pair(app: AppHandle, file_a: String, file_b: String) -> Result<(), String> {
+    let mut pairs = load(&app)?;
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+    record(&mut pairs, &file_a, &file_b, now_ms);
+    save(&app, &pairs)
+}
+
+/// Pins or unpins a pair, adding it if it isn't in the list yet.
+#[tauri::command]
+pub fn pin_recent_pair(app: AppHandle, file_a: String, file_b: String, pinned: bool) -> Result<(), String> {
+    let mut pairs = load(&app)?;
+    match pairs.iter_mut().find(|pair| is_pair(pair, &file_a, &file_b)) {
+        Some(pair) => pair.pinned = pinned,
+        None if pinned => pairs.push(RecentPair { file_a, file_b, last_compared_ms: 0, pinned }),
+        None => return Ok(()),
+    }
+    save(&app, &pairs)
+}
+
+#[tauri::command]
+pub fn remove_recent_pair(app: AppHandle, file_a: String, file_b: String) -> Result<(), String> {
+    let mut pairs = load(&app)?;
+    pairs.retain(|pair| !is_pair(pair, &file_a, &file_b));
+    save(&app, &pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_pairs_survive_trimming() {
+        let mut pairs = Vec::new();
+        record(&mut pairs, "pinned.a", "pinned.b", 0);
+        pairs[0].pinned = true;
+        for i in 0..MAX_RECENT + 5 {
+            record(&mut pairs, &format!("{}.a", i), "b", i as u64 + 1);
+        }
+        assert_eq!(pairs.len(), MAX_RECENT + 1);
+        assert!(pairs.iter().any(|pair| pair.pinned && pair.file_a == "pinned.a"));
+        assert_eq!(pairs[0].file_a, format!("{}.a", MAX_RECENT + 4));
+
+        record(&mut pairs, "pinned.a", "pinned.b", 100);
+        assert!(pairs[0].pinned);
+        assert_eq!(pairs.len(), MAX_RECENT + 1);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::jobs::JobRegistry;
+use crate::payloads::{FilteredResults, LineExplanation, ResultsPage, ResultsWindow, SampledDifference};
+use crate::results::export::{self, export_report};
+use crate::results::export_writer::ExportOptions;
+use crate::results::hash_export::{self, LineHashFormat};
+use crate::results::sample::SampleStrategy;
+use crate::results::unified_diff;
+use crate::results::window::results_window;
+use crate::results;
+use crate::explain;
+use crate::tauri_sink::TauriSink;
+use std::fs;
+use tauri::{AppHandle, State};
+
+/// Matches `filter_results` returns when no limit is given.
+const FILTER_PAGE: usize = 500;
+
+/// Exports the results of a job. Returns the files written, which differ from `path` when
+/// the export exceeded `options.splitSize` and was split or zipped.
+#[tauri::command]
+pub fn export_results(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<Vec<String>, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    let artifacts = export_report(&job, std::path::Path::new(&path), &options.unwrap_or_default())
+        .map_err(|err| err.to_string())?;
+    results::database::record_export(&app, &job_id, &path);
+    Ok(artifacts.iter().map(|artifact| artifact.to_string_lossy().into_owned()).collect())
+}
+
+/// Exports the keys of records present in `side` ("A" or "B") but missing from the other file.
+#[tauri::command]
+pub fn export_missing_keys(
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    side: String,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<usize, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    export::export_missing_keys(&job, &side, std::path::Path::new(&path), &options.unwrap_or_default())
+        .map_err(|err| err.to_string())
+}
+
+/// Exports the hash and occurrence count of every distinct line of `side` ("A" or "B") as
+/// `csv` (default) or `binary`. Returns the number of hashes written.
+#[tauri::command]
+pub async fn export_line_hashes(
+    app: AppHandle,
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    side: String,
+    path: String,
+    format: Option<LineHashFormat>,
+) -> Result<usize, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    hash_export::export_line_hashes(&TauriSink(app), &job, &side, std::path::Path::new(&path), format.unwrap_or_default())
+        .map_err(|err| err.to_string())
+}
+
+/// Writes the inputs of a job as a unified diff with `context` unchanged lines (default 3)
+/// around each change. Returns the number of hunks.
+#[tauri::command]
+pub async fn export_unified_diff(
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    path: String,
+    context: Option<usize>,
+) -> Result<usize, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    unified_diff::export_unified_diff(&job, std::path::Path::new(&path), context.unwrap_or(3))
+        .map_err(|err| err.to_string())
+}
+
+/// Explains how one line of a finished job was treated, to debug unexpected (missing) diffs.
+#[tauri::command]
+pub async fn explain_line(
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    file: String,
+    line_number: usize,
+) -> Result<LineExplanation, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    explain::explain_line(&job, &file, line_number).map_err(|err| err.to_string())
+}
+
+/// Returns the stored differences of one file within a line range, e.g. for the lines
+/// currently visible in a file preview.
+#[tauri::command]
+pub async fn get_results_window(
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    file: String,
+    start_line: usize,
+    end_line: usize,
+) -> Result<ResultsWindow, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    results_window(&job, &file, start_line, end_line).map_err(|err| err.to_string())
+}
+
+/// Returns one page of the stored differences of `side` (`A`, `B` or `modified`), so long
+/// result lists can be scrolled without emitting every line.
+#[tauri::command]
+pub async fn get_results_page(
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    side: String,
+    offset: usize,
+    limit: usize,
+) -> Result<ResultsPage, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    results::page::results_page(&job, &side, offset, limit).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_results_count(jobs: State<'_, JobRegistry>, job_id: String, side: String) -> Result<usize, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    results::page::results_count(&job, &side).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn filter_results(
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    query: String,
+    regex: bool,
+    side: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<FilteredResults, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    results::page::filter_results(&job, &query, regex, side.as_deref(), offset.unwrap_or(0), limit.unwrap_or(FILTER_PAGE))
+        .map_err(|err| err.to_string())
+}
+
+/// Returns up to `n` example differences of a job, picked `random`ly, `stratified` by
+/// category or `top_by_count`.
+#[tauri::command]
+pub async fn get_result_sample(
+    jobs: State<'_, JobRegistry>,
+    job_id: String,
+    n: usize,
+    strategy: SampleStrategy,
+) -> Result<Vec<SampledDifference>, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    results::sample::result_sample(&job, n, strategy).map_err(|err| err.to_string())
+}
+
+/// Describes the results of a finished job in a sentence or two.
+#[tauri::command]
+pub async fn summarize_in_words(jobs: State<'_, JobRegistry>, job_id: String) -> Result<String, String> {
+    let job = jobs.get(&job_id).ok_or_else(|| format!("Unknown job: {}", job_id))?;
+    Ok(results::in_words::summarize_in_words(&job))
+}
+
+#[tauri::command]
+pub fn save_file(path: String, content: String) -> Result<(), String> {
+    fs::write(path, content).map_err(|err| err.to_string())
+}
@@ -0,0 +1,279 @@
+use crate::config::CompareConfig;
+use crate::jobs::Job;
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const DATABASE_FILE: &str = "results.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY,
+    job_id TEXT NOT NULL,
+    finished_ms INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    file_a TEXT,
+    file_b TEXT,
+    config TEXT NOT NULL,
+    summary TEXT,
+    duration_ms INTEGER,
+    export_path TEXT
+);
+CREATE TABLE IF NOT EXISTS unique_lines (
+    job INTEGER NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
+    file TEXT NOT NULL,
+    line_number INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    count INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS unique_lines_by_job ON unique_lines(job, file, line_number);
+CREATE TABLE IF NOT EXISTS timings (
+    job INTEGER NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
+    step TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL
+);
+";
+
+/// Columns added to `jobs` after its first release, created on databases that predate them.
+const ADDED_JOB_COLUMNS: [(&str, &str); 2] = [("duration_ms", "INTEGER"), ("export_path", "TEXT")];
+
+/// A comparison saved to the results database. `id` identifies it across restarts,
+/// unlike `job_id`, which is only unique within one session.
+#[derive(Clone, serde::Serialize)]
+pub struct SavedJob {
+    pub id: i64,
+    pub job_id: String,
+    pub finished_ms: i64,
+    pub status: String,
+    pub file_a: Option<String>,
+    pub file_b: Option<String>,
+    pub summary: Option<Value>,
+    pub duration_ms: Option<i64>,
+    /// Where the results were last exported to, if they were.
+    pub export_path: Option<String>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct SavedLine {
+    pub line_number: i64,
+    pub text: String,
+    pub count: i64,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct SavedTiming {
+    pub step: String,
+    pub duration_ms: i64,
+}
+
+fn database_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|err| err.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    Ok(dir.join(DATABASE_FILE))
+}
+
+fn prepare(conn: &Connection) -> SqlResult<()> {
+    conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+    conn.execute_batch(SCHEMA)?;
+    let columns = conn
+        .prepare("SELECT name FROM pragma_table_info('jobs')")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<SqlResult<Vec<_>>>()?;
+    for (name, kind) in ADDED_JOB_COLUMNS {
+        if !columns.iter().any(|column| column == name) {
+            conn.execute_batch(&format!("ALTER TABLE jobs ADD COLUMN {} {}", name, kind))?;
+        }
+    }
+    Ok(())
+}
+
+fn open(app: &AppHandle) -> Result<Connection, String> {
+    let conn = Connection::open(database_path(app)?).map_err(|err| err.to_string())?;
+    prepare(&conn).map_err(|err| err.to_string())?;
+    Ok(conn)
+}
+
+fn to_json(value: impl serde::Serialize) -> String {
+    serde_json::to_string(&value).unwrap_or_default()
+}
+
+/// Writes the stored results of a job in one transaction and returns its database id.
+fn save(conn: &mut Connection, job: &Job) -> SqlResult<i64> {
+    let mut store = job.results.lock().unwrap();
+    let finished_ms = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64);
+    let path = |i: usize| store.inputs.get(i).map(|input| input.canonical_path.clone());
+    let (file_a, file_b) = (path(0), path(1));
+    let status = serde_json::to_value(store.status).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+    let summary = store.summary.as_ref().map(to_json);
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO jobs (job_id, finished_ms, status, file_a, file_b, config, summary, duration_ms) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![job.id, finished_ms, status, file_a, file_b, to_json(&job.config), summary, job.run_time().as_millis() as i64],
+    )?;
+    let id = tx.last_insert_rowid();
+    {
+        let mut insert = tx.prepare("INSERT INTO unique_lines (job, file, line_number, text, count) VALUES (?1, ?2, ?3, ?4, ?5)")?;
+        for file_id in ["A", "B"] {
+            store
+                .for_each_unique::<Box<dyn std::error::Error + Send + Sync>>(file_id, |line| {
+                    insert.execute(params![id, file_id, line.line_number as i64, line.text, line.count as i64])?;
+                    Ok(())
+                })
+                .map_err(rusqlite::Error::ToSqlConversionFailure)?;
+        }
+        let mut insert = tx.prepare("INSERT INTO timings (job, step, duration_ms) VALUES (?1, ?2, ?3)")?;
+        for step in &store.steps {
+            insert.execute(params![id, step.step, step.duration_ms as i64])?;
+        }
+    }
+    tx.commit()?;
+    Ok(id)
+}
+
+/// Saves the results of a finished or interrupted job, so they survive a restart.
+pub fn persist_job(app: &AppHandle, job: &Job) {
+    let result = open(app).and_then(|mut conn| save(&mut conn, job).map_err(|err| err.to_string()));
+    if let Err(e) = result {
+        eprintln!("Failed to save results of {}: {}", job.id, e);
+    }
+}
+
+fn saved_jobs(conn: &Connection) -> SqlResult<Vec<SavedJob>> {
+    let mut query = conn.prepare(
+        "SELECT id, job_id, finished_ms, status, file_a, file_b, summary, duration_ms, export_path FROM jobs ORDER BY id DESC",
+    )?;
+    let rows = query.query_map([], |row| {
+        let summary: Option<String> = row.get(6)?;
+        Ok(SavedJob {
+            id: row.get(0)?,
+            job_id: row.get(1)?,
+            finished_ms: row.get(2)?,
+            status: row.get(3)?,
+            file_a: row.get(4)?,
+            file_b: row.get(5)?,
+            summary: summary.and_then(|summary| serde_json::from_str(&summary).ok()),
+            duration_ms: row.get(7)?,
+            export_path: row.get(8)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Notes where the latest saved run of `job_id` was exported to.
+pub fn record_export(app: &AppHandle, job_id: &str, path: &str) {
+    let result = open(app).and_then(|conn| {
+        conn.execute(
+            "UPDATE jobs SET export_path = ?2 WHERE id = (SELECT MAX(id) FROM jobs WHERE job_id = ?1)",
+            params![job_id, path],
+        )
+        .map_err(|err| err.to_string())
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to record export of {}: {}", job_id, e);
+    }
+}
+
+/// The files and settings of a saved two-file comparison, to run it again.
+fn saved_run(conn: &Connection, id: i64) -> Result<(String, String, CompareConfig), String> {
+    let (file_a, file_b, config): (Option<String>, Option<String>, String) = conn
+        .query_row("SELECT file_a, file_b, config FROM jobs WHERE id = ?1", [id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(|err| format!("No saved comparison {}: {}", id, err))?;
+    let (Some(file_a), Some(file_b)) = (file_a, file_b) else {
+        return Err(format!("Saved comparison {} has no two input files", id));
+    };
+    let config = serde_json::from_str(&config).map_err(|err| format!("Settings of comparison {} no longer load: {}", id, err))?;
+    Ok((file_a, file_b, config))
+}
+
+pub fn load_saved_run(app: &AppHandle, id: i64) -> Result<(String, String, CompareConfig), String> {
+    saved_run(&open(app)?, id)
+}
+
+fn saved_lines(conn: &Connection, id: i64, file: &str, offset: i64, limit: i64) -> SqlResult<Vec<SavedLine>> {
+    let mut query = conn.prepare(
+        "SELECT line_number, text, count FROM unique_lines WHERE job = ?1 AND file = ?2 \
+         ORDER BY line_number LIMIT ?3 OFFSET ?4",
+    )?;
+    let rows = query.query_map(params![id, file, limit, offset], |row| {
+        Ok(SavedLine { line_number: row.get(0)?, text: row.get(1)?, count: row.get(2)? })
+    })?;
+    rows.collect()
+}
+
+fn saved_timings(conn: &Connection, id: i64) -> SqlResult<Vec<SavedTiming>> {
+    let mut query = conn.prepare("SELECT step, duration_ms FROM timings WHERE job = ?1 ORDER BY rowid")?;
+    let rows = query.query_map([id], |row| Ok(SavedTiming { step: row.get(0)?, duration_ms: row.get(1)? }))?;
+    rows.collect()
+}
+
+/// The comparison history: saved comparisons, newest first.
+#[tauri::command]
+pub async fn list_saved_jobs(app: AppHandle) -> Result<Vec<SavedJob>, String> {
+    saved_jobs(&open(&app)?).map_err(|err| err.to_string())
+}
+
+/// One page of the saved unique lines of file `A` or `B`, ordered by line number.
+#[tauri::command]
+pub async fn get_saved_lines(app: AppHandle, id: i64, file: String, offset: i64, limit: i64) -> Result<Vec<SavedLine>, String> {
+    saved_lines(&open(&app)?, id, &file, offset, limit).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn get_saved_timings(app: AppHandle, id: i64) -> Result<Vec<SavedTiming>, String> {
+    saved_timings(&open(&app)?, id).map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_saved_job(app: AppHandle, id: i64) -> Result<(), String> {
+    open(&app)?.execute("DELETE FROM jobs WHERE id = ?1", [id]).map_err(|err| err.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_lines_are_paged_by_line_number() {
+        let conn = Connection::open_in_memory().unwrap();
+        prepare(&conn).unwrap();
+        conn.execute("INSERT INTO jobs (job_id, finished_ms, status, config) VALUES ('job-1', 0, 'complete', '{}')", []).unwrap();
+        for line_number in [3, 1, 2] {
+            conn.execute("INSERT INTO unique_lines VALUES (1, 'A', ?1, 'x', 1)", [line_number]).unwrap();
+        }
+        let page: Vec<i64> = saved_lines(&conn, 1, "A", 1, 5).unwrap().iter().map(|l| l.line_number).collect();
+        assert_eq!(page, [2, 3]);
+        assert!(saved_lines(&conn, 1, "B", 0, 5).unwrap().is_empty());
+
+        conn.execute("DELETE FROM jobs WHERE id = 1", []).unwrap();
+        assert!(saved_lines(&conn, 1, "A", 0, 5).unwrap().is_empty());
+        assert!(saved_jobs(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_old_databases_gain_history_columns() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE jobs (id INTEGER PRIMARY KEY, job_id TEXT NOT NULL, finished_ms INTEGER NOT NULL, \
+             status TEXT NOT NULL, file_a TEXT, file_b TEXT, config TEXT NOT NULL, summary TEXT)",
+        )
+        .unwrap();
+        let config = r#"{"useExternalSort":true,"ignoreOccurences":false,"useSingleThread":false,"ignoreLineNumber":false}"#;
+        conn.execute("INSERT INTO jobs (job_id, finished_ms, status, file_a, file_b, config) VALUES ('job-1', 0, 'complete', 'a', 'b', ?1)", [config]).unwrap();
+        conn.execute("INSERT INTO jobs (job_id, finished_ms, status, config) VALUES ('job-2', 0, 'complete', '{}')", []).unwrap();
+        prepare(&conn).unwrap();
+
+        let jobs = saved_jobs(&conn).unwrap();
+        assert_eq!((jobs[1].duration_ms, jobs[1].export_path.as_deref()), (None, None));
+        let (file_a, file_b, config) = saved_run(&conn, 1).unwrap();
+        assert_eq!((file_a.as_str(), file_b.as_str(), config.use_external_sort), ("a", "b", true));
+        assert!(saved_run(&conn, 2).unwrap_err().contains("no two input files"));
+        assert!(saved_run(&conn, 3).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use crate::engine::run_engine;
+use crate::jobs::JobRegistry;
+use crate::results::collected::CollectedLine;
+use crate::tests::file_modifier::FileModifier;
+use large_file_compare_core::internal::trivial_diff::direct_diff;
+use large_file_compare_core::sink::CollectingSink;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fs::File;
+use std::io::{BufWriter, Error as IoError, Write};
+use std::path::{Path, PathBuf};
+
+/// One edit of a scenario script. Line numbers are 1-based and refer to the file as left
+/// by the previous mutations.
+#[derive(Clone, Debug)]
+pub enum Mutation {
+    Add { after: usize, text: String },
+    Delete(usize),
+    Replace { line: usize, text: String },
+}
+
+/// A generated base file, a copy of it edited with a script of mutations, and the
+/// differences the engine has to report between the two.
+pub struct Scenario {
+    pub base_lines: usize,
+    pub mutations: Vec<Mutation>,
+}
+
+/// Lines found in only one file, as `(line number, text)` sorted by line number.
+type Expected = Vec<(usize, String)>;
+
+impl Scenario {
+    /// A script of `count` random mutations, reproducible from `seed`. Every added or
+    /// replacing line is new, so each mutation shows up as exactly one difference.
+    pub fn random(base_lines: usize, count: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut len = base_lines;
+        let mutations = (0..count)
+            .map(|i| {
+                let text = format!("mutation {} of seed {}", i, seed);
+                match rng.random_range(0..3) {
+                    0 => {
+                        len += 1;
+                        Mutation::Add { after: rng.random_range(0..len), text }
+                    }
+                    1 if len > 1 => {
+                        len -= 1;
+                        Mutation::Delete(rng.random_range(1..=len + 1))
+                    }
+                    _ => Mutation::Replace { line: rng.random_range(1..=len), text },
+                }
+            })
+            .collect();
+        Self { base_lines, mutations }
+    }
+
+    fn write_base(&self, path: &Path) -> Result<Vec<String>, IoError> {
+        let lines: Vec<String> = (1..=self.base_lines).map(|i| format!("record {:06},value {}", i, i * 7919 % 10007)).collect();
+        let mut writer = BufWriter::new(File::create(path)?);
+        for line in &lines {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+        Ok(lines)
+    }
+
+    /// Applies the script to the file with `FileModifier` and to `lines` alongside, the
+    /// latter being what the file is expected to hold afterwards.
+    fn apply(&self, path: &Path, lines: &mut Vec<String>) -> Result<(), IoError> {
+        let modifier = FileModifier::new(path);
+        for mutation in &self.mutations {
+            match mutation {
+                // Adding after line 0 means at the start, which `add_line_after` can't do.
+                Mutation::Add { after: 0, text } => {
+                    modifier.replace_line(1, &format!("{}\n{}", text, lines[0]))?;
+                    lines.insert(0, text.clone());
+                }
+                Mutation::Add { after, text } => {
+                    modifier.add_line_after(*after, text)?;
+                    lines.insert(*after, text.clone());
+                }
+                Mutation::Delete(line) => {
+                    modifier.delete_line(*line)?;
+                    lines.remove(line - 1);
+                }
+                Mutation::Replace { line, text } => {
+                    modifier.replace_line(*line, text)?;
+                    lines[line - 1] = text.clone();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the base and mutated files into `dir`. Returns their paths and the lines only
+    /// in either, as the script implies.
+    fn prepare(&self, dir: &Path) -> Result<(PathBuf, PathBuf, (Expected, Expected)), IoError> {
+        std::fs::create_dir_all(dir)?;
+        let (path_a, path_b) = (dir.join("base.txt"), dir.join("mutated.txt"));
+        let base = self.write_base(&path_a)?;
+        std::fs::copy(&path_a, &path_b)?;
+        let mut mutated = base.clone();
+        self.apply(&path_b, &mut mutated)?;
+
+        let only_in = |lines: &[String], other: &[String]| -> Expected {
+            let other: std::collections::HashSet<&String> = other.iter().collect();
+            lines.iter().enumerate().filter(|(_, line)| !other.contains(line)).map(|(i, line)| (i + 1, line.clone())).collect()
+        };
+        let expected = (only_in(&base, &mutated), only_in(&mutated, &base));
+        Ok((path_a, path_b, expected))
+    }
+
+    /// Runs the scenario in `dir` and compares what the engine reports with the script.
+    pub fn run(&self, dir: &Path) -> Result<ScenarioOutcome, IoError> {
+        let (path_a, path_b, expected) = self.prepare(dir)?;
+        let job = JobRegistry::default().create(scenario_config(false)?);
+        let diff = direct_diff(&job, path_str(&path_a)?, path_str(&path_b)?, &job.config, None)?;
+
+        let reported = |lines: Vec<CollectedLine>| -> Expected {
+            let mut found: Expected = lines.into_iter().map(|line| (line.line_number, line.text)).collect();
+            found.sort();
+            found
+        };
+        let hunk_lines = diff.hunks.as_ref().map(|hunks| {
+            let removed = hunks.iter().flat_map(|hunk| &hunk.removed).map(|line| (line.line_number, line.text.clone())).collect();
+            let added = hunks.iter().flat_map(|hunk| &hunk.added).map(|line| (line.line_number, line.text.clone())).collect();
+            (removed, added)
+        });
+        Ok(ScenarioOutcome {
+            expected,
+            reported: (reported(diff.unique_to_a), reported(diff.unique_to_b)),
+            hunk_lines,
+        })
+    }
+
+    /// Runs the scenario through the full engine, with `overrides` of the config choosing
+    /// which one, and compares the `unique_line` events it reports with the script.
+    pub fn run_engine(&self, dir: &Path, overrides: &serde_json::Value) -> Result<ScenarioOutcome, IoError> {
+        let (path_a, path_b, expected) = self.prepare(dir)?;
+        let config = scenario_config(false)?.with_overrides(overrides).map_err(IoError::other)?;
+        let job = JobRegistry::default().create(config.clone());
+        let sink = CollectingSink::default();
+        run_engine(sink.clone(), job, path_str(&path_a)?.to_string(), path_str(&path_b)?.to_string(), config, None)?;
+
+        let reported = |file: &str| -> Expected {
+            let mut found: Expected = sink
+                .payloads("unique_line")
+                .iter()
+                .filter(|line| line["file"] == file)
+                .map(|line| (line["line_number"].as_u64().unwrap_or(0) as usize, line["text"].as_str().unwrap_or("").to_string()))
+                .collect();
+            found.sort();
+            found
+        };
+        Ok(ScenarioOutcome { expected, reported: (reported("A"), reported("B")), hunk_lines: None })
+    }
+}
+
+fn scenario_config(use_external_sort: bool) -> Result<crate::config::CompareConfig, IoError> {
+    serde_json::from_value(serde_json::json!({
+        "useExternalSort": use_external_sort,
+        "ignoreOccurences": false,
+        "useSingleThread": false,
+        "ignoreLineNumber": false,
+    }))
+    .map_err(IoError::other)
+}
+
+fn path_str(path: &Path) -> Result<&str, IoError> {
+    path.to_str().ok_or_else(|| IoError::other(format!("Non UTF-8 path: {}", path.display())))
+}
+
+/// The lines only in the base file and only in the mutated file, as the script implies
+/// and as the engine reported them, unordered and in its ordered hunks.
+#[derive(Debug)]
+pub struct ScenarioOutcome {
+    pub expected: (Expected, Expected),
+    pub reported: (Expected, Expected),
+    pub hunk_lines: Option<(Expected, Expected)>,
+}
+
+impl ScenarioOutcome {
+    pub fn assert_exact(&self) {
+        assert_eq!(self.reported, self.expected, "unordered results differ from the mutations");
+        if let Some(hunk_lines) = &self.hunk_lines {
+            assert_eq!(hunk_lines, &self.expected, "ordered hunks differ from the mutations");
+        }
+    }
+}
+
+fn scenario_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("scenario_{}_{}", name, std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(name: &str, scenario: Scenario) {
+        let dir = scenario_dir(name);
+        let outcome = scenario.run(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        outcome.assert_exact();
+    }
+
+    #[test]
+    fn test_scripted_mutations_are_reported_exactly() {
+        let text = |s: &str| s.to_string();
+        run(
+            "scripted",
+            Scenario {
+                base_lines: 200,
+                mutations: vec![
+                    Mutation::Add { after: 0, text: text("new first line") },
+                    Mutation::Delete(50),
+                    Mutation::Replace { line: 120, text: text("replaced line") },
+                    Mutation::Add { after: 199, text: text("new last line") },
+                    Mutation::Delete(2),
+                ],
+            },
+        );
+    }
+
+    #[test]
+    fn test_engines_report_the_mutations_as_events() {
+        // Without `trivialDiffLimit: 0` inputs this small would all take the direct diff.
+        let engines = [
+            ("in_memory", serde_json::json!({ "trivialDiffLimit": 0 })),
+            ("external", serde_json::json!({ "useExternalSort": true, "trivialDiffLimit": 0 })),
+            ("streaming", serde_json::json!({ "streaming": true })),
+        ];
+        for (engine, overrides) in engines {
+            let dir = scenario_dir(&format!("engine_{}", engine));
+            let outcome = Scenario::random(500, 10, 42).run_engine(&dir, &overrides).unwrap();
+            std::fs::remove_dir_all(&dir).unwrap();
+            outcome.assert_exact();
+        }
+    }
+
+    #[test]
+    fn test_set_operations_report_one_side_or_the_common_lines() {
+        let engines = [
+            ("trivial", serde_json::json!({})),
+            ("in_memory", serde_json::json!({ "trivialDiffLimit": 0 })),
+            ("external", serde_json::json!({ "useExternalSort": true, "trivialDiffLimit": 0 })),
+        ];
+        for (engine, mut overrides) in engines {
+            let dir = scenario_dir(&format!("set_operation_{}", engine));
+            let scenario = Scenario::random(500, 10, 7);
+            overrides["setOperation"] = serde_json::json!("b_minus_a");
+            let outcome = scenario.run_engine(&dir, &overrides).unwrap();
+            assert_eq!(outcome.reported, (Vec::new(), outcome.expected.1.clone()), "{}", engine);
+
+            // Common lines aren't differences, so the limit on differences doesn't cut them short.
+            overrides["setOperation"] = serde_json::json!("intersection");
+            overrides["stopAfterDifferences"] = serde_json::json!(1);
+            let outcome = scenario.run_engine(&dir, &overrides).unwrap();
+            std::fs::remove_dir_all(&dir).unwrap();
+            let common = outcome.reported.0.iter().filter(|line| !outcome.expected.0.contains(line)).count();
+            assert_eq!((common, outcome.reported.0.len(), outcome.reported.1.len()), (500 - outcome.expected.0.len(), common, 0), "{}", engine);
+        }
+    }
+
+    #[test]
+    fn test_random_mutation_scripts_are_reported_exactly() {
+        for seed in 0..8 {
+            run(&format!("random_{}", seed), Scenario::random(1000, 25, seed));
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use crate::config::CompareConfig;
+use crate::engine::run_engine;
+use crate::jobs::JobRegistry;
+use crate::payloads::ScheduledRun;
+use crate::prefs::STORE_FILE;
+use crate::results::database::persist_job;
+use crate::results::export::export_report;
+use crate::results::export_writer::ExportOptions;
+use crate::results::store::flush_partial_results;
+use crate::tauri_sink::TauriSink;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_store::StoreExt;
+
+const SCHEDULES_KEY: &str = "schedules";
+/// How often the scheduler looks for schedules that came due.
+const TICK: Duration = Duration::from_secs(20);
+
+/// A comparison run at the times given by a cron expression, persisted in the app store.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    /// Assigned by `save_schedule` when empty.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// `minute hour day-of-month month day-of-week`, optionally preceded by seconds.
+    /// Days of the week count from 1 = Sunday, or are given by name (`Mon-Fri`).
+    pub cron: String,
+    pub file_a_path: String,
+    pub file_b_path: String,
+    pub compare_config: CompareConfig,
+    /// Directory the results are exported to after each run.
+    pub output_dir: String,
+    /// Export settings; `None` uses those of the preferences.
+    #[serde(default)]
+    pub export: Option<ExportOptions>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub last_run_ms: Option<u64>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Parses a cron expression, accepting the usual five fields as well as the six or seven
+/// (with seconds and years) the cron crate expects.
+fn parse_cron(expression: &str) -> Result<cron::Schedule, String> {
+    let expression = match expression.split_whitespace().count() {
+        5 => format!("0 {}", expression),
+        _ => expression.to_string(),
+    };
+    cron::Schedule::from_str(&expression).map_err(|err| format!("Invalid cron expression: {}", err))
+}
+
+/// Whether `schedule` should have run after `since`, up to and including `now`.
+fn is_due(schedule: &Schedule, since: &DateTime<Local>, now: &DateTime<Local>) -> bool {
+    schedule.enabled
+        && parse_cron(&schedule.cron).is_ok_and(|cron| cron.after(since).next().is_some_and(|time| time <= *now))
+}
+
+fn load(app: &AppHandle) -> Result<Vec<Schedule>, String> {
+    let store = app.store(STORE_FILE).map_err(|err| err.to_string())?;
+    Ok(store
+        .get(SCHEDULES_KEY)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default())
+}
+
+fn save(app: &AppHandle, schedules: &[Schedule]) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|err| err.to_string())?;
+    store.set(SCHEDULES_KEY, serde_json::to_value(schedules).map_err(|err| err.to_string())?);
+    store.save().map_err(|err| err.to_string())
+}
+
+/// `name` reduced to characters that are safe in a file name.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if stem.is_empty() { "comparison".to_string() } else { stem }
+}
+
+/// Runs the comparison of a schedule on the calling thread and exports its results.
+fn run_schedule(app: &AppHandle, schedule: &Schedule) -> ScheduledRun {
+    let mut run = ScheduledRun { schedule_id: schedule.id.clone(), job_id: None, outputs: Vec::new(), error: None };
+    let (file_a_path, file_b_path) = (schedule.file_a_path.clone(), schedule.file_b_path.clone());
+    let mut compare_config = schedule.compare_config.clone();
+    crate::calibration::apply_defaults(app, &mut compare_config);
+    crate::external::checkpoint::adopt_seed(&mut compare_config, &file_a_path, &file_b_path);
+    crate::external::file_cache::adopt_seed(&mut compare_config, &file_a_path, &file_b_path);
+    compare_config.seed_hashes();
+    let job = match crate::commands::create_job(&app.state::<JobRegistry>(), &file_a_path, &file_b_path, &compare_config) {
+        Ok(job) => job,
+        Err(e) => {
+            run.error = Some(e);
+            return run;
+        }
+    };
+    run.job_id = Some(job.id.clone());
+
+    let guard = job.start_run();
+    let sink = TauriSink(app.clone());
+    let result = run_engine(sink.clone(), job.clone(), file_a_path, file_b_path, compare_config, None);
+    if let Err(e) = &result {
+        flush_partial_results(&sink, &job, &e.to_string());
+    }
+    persist_job(app, &job);
+    drop(guard);
+    if let Err(e) = result {
+        run.error = Some(e.to_string());
+        return run;
+    }
+
+    let options = schedule.export.clone().unwrap_or_else(|| crate::prefs::load(app).export);
+    let file_name = format!(
+        "{}_{}.{}",
+        file_stem(&schedule.name),
+        Local::now().format("%Y%m%d-%H%M%S"),
+        options.format.extension()
+    );
+    match export_report(&job, &Path::new(&schedule.output_dir).join(file_name), &options) {
+        Ok(artifacts) => run.outputs = artifacts.iter().map(|path| path.to_string_lossy().into_owned()).collect(),
+        Err(e) => run.error = Some(format!("Export to {} failed: {}", schedule.output_dir, e)),
+    }
+    run
+}
+
+/// Stores when a schedule last ran and how that went.
+fn record_run(app: &AppHandle, run: &ScheduledRun, ran_at: &DateTime<Local>) -> Result<(), String> {
+    let mut schedules = load(app)?;
+    if let Some(schedule) = schedules.iter_mut().find(|schedule| schedule.id == run.schedule_id) {
+        schedule.last_run_ms = Some(ran_at.timestamp_millis() as u64);
+        schedule.last_error = run.error.clone();
+    }
+    save(app, &schedules)
+}
+
+/// Starts the thread running schedules as they come due. Runs missed while the app was
+/// closed are skipped, not caught up on.
+pub fn start_scheduler(app: AppHandle) {
+    thread::spawn(move || {
+        let mut checked = Local::now();
+        loop {
+            thread::sleep(TICK);
+            let now = Local::now();
+            let due: Vec<Schedule> = match load(&app) {
+                Ok(schedules) => schedules.into_iter().filter(|schedule| is_due(schedule, &checked, &now)).collect(),
+                Err(e) => {
+                    eprintln!("Failed to load schedules: {}", e);
+                    Vec::new()
+                }
+            };
+            // Times passing while these run are checked on the next tick.
+            checked = now;
+            for schedule in due {
+                let run = run_schedule(&app, &schedule);
+                if let Some(e) = &run.error {
+                    eprintln!("Scheduled comparison {} failed: {}", schedule.name, e);
+                }
+                if let Err(e) = record_run(&app, &run, &now) {
+                    eprintln!("Failed to record scheduled run: {}", e);
+                }
+                if let Err(e) = app.emit("scheduled_run_finished", run) {
+                    eprintln!("Failed to emit scheduled_run_finished event: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_schedules(app: AppHandle) -> Result<Vec<Schedule>, String> {
+    load(&app)
+}
+
+/// Adds a schedule, or replaces the one with the same id. Returns the stored schedule.
+#[tauri::command]
+pub fn save_schedule(app: AppHandle, mut schedule: Schedule) -> Result<Schedule, String> {
+    parse_cron(&schedule.cron)?;
+    crate::external::multi_file::check_writable(Path::new(&schedule.output_dir))
+        .map_err(|err| format!("Output directory {} is not usable: {}", schedule.output_dir, err))?;
+    let mut schedules = load(&app)?;
+    if schedule.id.is_empty() {
+        schedule.id = format!("schedule-{}", Local::now().timestamp_millis());
+    }
+    match schedules.iter_mut().find(|existing| existing.id == schedule.id) {
+        Some(existing) => *existing = schedule.clone(),
+        None => schedules.push(schedule.clone()),
+    }
+    save(&app, &schedules)?;
+    Ok(schedule)
+}
+
+#[tauri::command]
+pub fn remove_schedule(app: AppHandle, id: String) -> Result<(), String> {
+    let mut schedules = load(&app)?;
+    schedules.retain(|schedule| schedule.id != id);
+    save(&app, &schedules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn schedule(cron: &str) -> Schedule {
+        serde_json::from_value(json!({
+            "name": "nightly",
+            "cron": cron,
+            "fileAPath": "a.txt",
+            "fileBPath": "b.txt",
+            "compareConfig": {"useExternalSort":false,"ignoreOccurences":false,"useSingleThread":false,"ignoreLineNumber":false},
+            "outputDir": "out",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_five_field_expression_comes_due_once() {
+        let nightly = schedule("30 2 * * *");
+        let at = |hour, minute| Local.with_ymd_and_hms(2024, 3, 5, hour, minute, 0).unwrap();
+        assert!(is_due(&nightly, &at(2, 29), &at(2, 30)));
+        assert!(!is_due(&nightly, &at(2, 30), &at(2, 31)));
+        assert!(!is_due(&nightly, &at(1, 0), &at(2, 0)));
+        assert!(!is_due(&Schedule { enabled: false, ..nightly }, &at(2, 29), &at(2, 30)));
+
+        assert!(parse_cron("0 9 * * Mon-Fri").is_ok());
+        assert!(parse_cron("every day").is_err());
+    }
+
+    #[test]
+    fn test_file_stem_replaces_unsafe_characters() {
+        assert_eq!(file_stem("logs/app: nightly"), "logs_app__nightly");
+        assert_eq!(file_stem(""), "comparison");
+    }
+}
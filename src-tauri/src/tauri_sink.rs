@@ -0,0 +1,15 @@
+use large_file_compare_core::sink::ProgressSink;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+
+/// Forwards what the engines report to the frontend as Tauri events.
+#[derive(Clone)]
+pub struct TauriSink(pub AppHandle);
+
+impl ProgressSink for TauriSink {
+    fn on_event(&self, event: &str, payload: Value) {
+        if let Err(e) = self.0.emit(event, payload) {
+            eprintln!("Failed to emit {} event: {}", event, e);
+        }
+    }
+}
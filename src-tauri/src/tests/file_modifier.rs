@@ -76,13 +76,6 @@ impl FileModifier {
 
 }
 
-fn main() {
-    let path = PathBuf::from("F:\\testscript\\file1_original.txt");
-    let modifier = FileModifier::new(&path);
-
-    modifier.add_line_after(1, "line 2").unwrap();
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,3 +1,3 @@
 pub mod file_generator;
 pub mod large_file_generator;
-pub mod file_modifier;
\ No newline at end of file
+pub mod file_modifier;
@@ -0,0 +1,80 @@
+use semver::Version;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// GitHub API endpoint of the newest published release.
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/whisp1830/large-file-compare/releases/latest";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+    body: Option<String>,
+    published_at: Option<String>,
+}
+
+/// Result of `check_for_updates`.
+#[derive(Clone, serde::Serialize)]
+pub struct UpdateInfo {
+    pub current_version: String,
+    pub latest_version: String,
+    pub update_available: bool,
+    /// Release notes of the latest release, as Markdown.
+    pub changelog: String,
+    pub release_url: String,
+    pub published_at: Option<String>,
+}
+
+/// Parses a release tag such as `v1.2.0` or `app-v1.2.0`.
+fn parse_tag(tag: &str) -> Option<Version> {
+    let start = tag.find(|c: char| c.is_ascii_digit())?;
+    Version::parse(&tag[start..]).ok()
+}
+
+fn update_info(current: &str, release: Release) -> Result<UpdateInfo, String> {
+    let latest = parse_tag(&release.tag_name)
+        .ok_or_else(|| format!("Latest release has no version in its tag: {}", release.tag_name))?;
+    let current_version = Version::parse(current).map_err(|err| err.to_string())?;
+    Ok(UpdateInfo {
+        current_version: current.to_string(),
+        latest_version: latest.to_string(),
+        update_available: latest > current_version,
+        changelog: release.body.unwrap_or_default(),
+        release_url: release.html_url,
+        published_at: release.published_at,
+    })
+}
+
+/// Looks up the latest release and whether it is newer than the running build. Installing
+/// it is left to the user, or to the Tauri updater where that is configured.
+#[tauri::command]
+pub async fn check_for_updates() -> Result<UpdateInfo, String> {
+    let release: Release = ureq::get(LATEST_RELEASE_URL)
+        .timeout(REQUEST_TIMEOUT)
+        .set("Accept", "application/vnd.github+json")
+        .set("User-Agent", concat!("large-file-compare/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|err| format!("Update check failed: {}", err))?
+        .into_json()
+        .map_err(|err| format!("Unexpected release data: {}", err))?;
+    update_info(env!("CARGO_PKG_VERSION"), release)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newer_release_is_reported() {
+        let release = |tag: &str| Release {
+            tag_name: tag.to_string(),
+            html_url: String::new(),
+            body: Some("- fixes".to_string()),
+            published_at: None,
+        };
+        assert!(update_info("0.1.0", release("v0.2.0")).unwrap().update_available);
+        assert!(!update_info("0.1.0", release("app-v0.1.0")).unwrap().update_available);
+        assert!(update_info("0.1.0", release("nightly")).is_err());
+    }
+}
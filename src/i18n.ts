@@ -22,6 +22,7 @@ export const translations = {
         comparing: "Comparing...",
         fileAProgress: "File A Progress:",
         fileBProgress: "File B Progress:",
+        overallProgress: "Overall Progress:",
         comparisonTime: "Comparison Time:",
         details: "Details",
         detailsLog: "Details Log:",
@@ -64,6 +65,7 @@ export const translations = {
         comparing: "比较中...",
         fileAProgress: "文件A进度:",
         fileBProgress: "文件B进度:",
+        overallProgress: "总体进度:",
         comparisonTime: "比较用时:",
         details: "详情",
         detailsLog: "详细日志:",
@@ -106,6 +108,7 @@ export const translations = {
         comparing: "比較中...",
         fileAProgress: "ファイルAの進捗:",
         fileBProgress: "ファイルBの進捗:",
+        overallProgress: "全体の進捗:",
         comparisonTime: "比較時間:",
         details: "詳細",
         detailsLog: "詳細ログ:",
@@ -148,6 +151,7 @@ export const translations = {
         comparing: "비교 중...",
         fileAProgress: "파일 A 진행률:",
         fileBProgress: "파일 B 진행률:",
+        overallProgress: "전체 진행률:",
         comparisonTime: "비교 시간:",
         details: "세부 정보",
         detailsLog: "세부 로그:",